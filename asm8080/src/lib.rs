@@ -0,0 +1,214 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Macro asm8080! - Simulación CPU
+    Descripción:    Ensambla código 8080 escrito dentro del fuente Rust en tiempo de compilación
+                    y lo expande a un Vec<u8> (asm8080!) o a un [u8; N] (asm8080_array!). Los
+                    errores de ensamblado son errores de compilación sobre el token culpable.
+    Referencias:    https://doc.rust-lang.org/reference/procedural-macros.html
+
+***************************************************************************************/
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+// El ensamblador es el mismo módulo que usa el simulador
+#[path = "../../src/proyectos/sim_cpu_tablas.rs"]
+mod sim_cpu_tablas;
+#[path = "../../src/proyectos/sim_cpu_ensamblador.rs"]
+mod sim_cpu_ensamblador;
+
+use sim_cpu_ensamblador::{ensambla, ErrorEnsamblado};
+
+//***************************************************************************** Notas
+/* Uso
+    use asm8080::asm8080;
+
+    cpu.cargar_programa(&asm8080! {
+        inicio: lxi sp, 400h
+                mvi a, 4            // Los comentarios son los de Rust
+        bucle   inr a               // Etiqueta sin ':' en la columna más a la izquierda
+                cpi 10; jnz bucle   // ';' separa instrucciones en la misma línea
+                hlt
+    });
+
+    let rom: [u8; 3] = asm8080_array!("  JMP 0100H  ; También se admite una cadena con la fuente");
+
+La entrada se reconstruye como texto respetando las líneas y columnas del fuente Rust (la columna
+más a la izquierda hace de columna 1), así que las reglas son las de sim_cpu_ensamblador. Como los
+tokens son de Rust, las cadenas de DB van entre comillas dobles y sin escapes ("Hola"); un literal
+de carácter ('A') sirve como valor. El resultado es la imagen desde la dirección 0 (imagen()).
+Las líneas y columnas salen de proc_macro::Span::line() y column(), estables desde Rust 1.88
+(rust-version del Cargo.toml); proc_macro2 no las da en stable dentro de una macro.
+*/
+
+#[proc_macro]
+pub fn asm8080(entrada: TokenStream) -> TokenStream {
+    expande(entrada, false)
+}
+
+#[proc_macro]
+pub fn asm8080_array(entrada: TokenStream) -> TokenStream {
+    expande(entrada, true)
+}
+
+fn expande(entrada: TokenStream, array: bool) -> TokenStream {
+    let tokens: Vec<TokenTree> = entrada.into_iter().collect();
+    // Fuente en una cadena: el error señala la cadena completa e indica línea y columna
+    if let [TokenTree::Literal(literal)] = tokens.as_slice() {
+        if let Some(texto) = contenido_cadena(&literal.to_string()) {
+            return match ensambla(&texto) {
+                Ok(programa) => bytes_a_tokens(&programa.imagen(), array),
+                Err(error) => error_compilacion(&error.to_string(), literal.span()),
+            };
+        }
+    }
+    let mut fuente = Fuente::new(&tokens);
+    fuente.agrega(tokens);
+    match ensambla(&fuente.texto) {
+        Ok(programa) => bytes_a_tokens(&programa.imagen(), array),
+        Err(error) => error_compilacion(&error.mensaje, fuente.span_de(&error)),
+    }
+}
+
+//***************************************************************************** Reconstrucción del texto
+// Posición en el texto generado de un token de la entrada
+struct Posicion {
+    linea: usize,
+    columna: usize,
+    longitud: usize,
+    span: Span,
+}
+
+struct Fuente {
+    texto: String,
+    linea: usize,                       // Línea actual del texto generado (desde 1)
+    columna: usize,                     // Caracteres ya escritos en la línea actual
+    linea_origen: Option<usize>,
+    base: usize,                        // Columna del fuente Rust que pasa a ser la columna 1
+    posiciones: Vec<Posicion>,
+}
+
+impl Fuente {
+    fn new(tokens: &[TokenTree]) -> Fuente {
+        let base = tokens.iter().map(|t| t.span().column()).min().unwrap_or(1);
+        Fuente { texto: String::new(), linea: 1, columna: 0, linea_origen: None, base, posiciones: Vec::new() }
+    }
+
+    fn nueva_linea(&mut self) {
+        self.texto.push('\n');
+        self.linea += 1;
+        self.columna = 0;
+    }
+
+    fn agrega(&mut self, tokens: impl IntoIterator<Item = TokenTree>) {
+        for token in tokens {
+            match token {
+                TokenTree::Group(grupo) => {
+                    let (abre, cierra) = match grupo.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::None => ("", ""),
+                    };
+                    if !abre.is_empty() { self.agrega_texto(abre, grupo.span_open()); }
+                    self.agrega(grupo.stream());
+                    if !cierra.is_empty() { self.agrega_texto(cierra, grupo.span_close()); }
+                }
+                TokenTree::Punct(punto) if punto.as_char() == ';' => {
+                    self.linea_origen = Some(punto.span().line());
+                    self.nueva_linea();
+                }
+                otro => self.agrega_texto(&otro.to_string(), otro.span()),
+            }
+        }
+    }
+
+    fn agrega_texto(&mut self, texto: &str, span: Span) {
+        let linea = span.line();
+        if self.linea_origen.is_some_and(|l| l != linea) {
+            for _ in self.linea_origen.unwrap_or(linea)..linea { self.nueva_linea(); }
+        }
+        self.linea_origen = Some(linea);
+        let objetivo = span.column().saturating_sub(self.base) + 1;
+        if self.columna + 1 < objetivo {
+            self.texto.push_str(&" ".repeat(objetivo - 1 - self.columna));
+            self.columna = objetivo - 1;
+        } else if self.columna + 1 > objetivo && self.columna > 0 {
+            self.texto.push(' ');           // Token desplazado: nunca se pega al anterior
+            self.columna += 1;
+        }
+        let longitud = texto.chars().count();
+        self.posiciones.push(Posicion { linea: self.linea, columna: self.columna + 1, longitud, span });
+        self.texto.push_str(texto);
+        self.columna += longitud;
+    }
+
+    // Token que contiene la columna del error; si no hay ninguno, el último anterior de la línea
+    fn span_de(&self, error: &ErrorEnsamblado) -> Span {
+        let en_linea: Vec<&Posicion> = self.posiciones.iter().filter(|p| p.linea == error.linea).collect();
+        en_linea.iter().find(|p| p.columna <= error.columna && error.columna < p.columna + p.longitud.max(1))
+            .or_else(|| en_linea.iter().rev().find(|p| p.columna <= error.columna))
+            .or_else(|| en_linea.first())
+            .map(|p| p.span)
+            .unwrap_or_else(Span::call_site)
+    }
+}
+
+//***************************************************************************** Literales de cadena
+// Contenido de un literal de cadena de Rust ("..." o r#"..."#); None si no es una cadena
+fn contenido_cadena(literal: &str) -> Option<String> {
+    if let Some(resto) = literal.strip_prefix('r') {
+        let almohadillas = resto.chars().take_while(|c| *c == '#').count();
+        let cierre = format!("\"{}", "#".repeat(almohadillas));
+        return resto[almohadillas..].strip_prefix('"')?.strip_suffix(cierre.as_str()).map(|t| t.to_string());
+    }
+    let interior = literal.strip_prefix('"')?.strip_suffix('"')?;
+    let mut texto = String::new();
+    let mut caracteres = interior.chars().peekable();
+    while let Some(c) = caracteres.next() {
+        if c != '\\' { texto.push(c); continue; }
+        match caracteres.next()? {
+            'n' => texto.push('\n'),
+            'r' => texto.push('\r'),
+            't' => texto.push('\t'),
+            '0' => texto.push('\0'),
+            'x' => {
+                let hex: String = caracteres.by_ref().take(2).collect();
+                texto.push(u8::from_str_radix(&hex, 16).ok()? as char);
+            }
+            'u' => {
+                let codigo: String = caracteres.by_ref().skip(1).take_while(|c| *c != '}').collect();
+                texto.push(char::from_u32(u32::from_str_radix(&codigo, 16).ok()?)?);
+            }
+            '\n' => { while caracteres.peek().is_some_and(|c| c.is_whitespace()) { caracteres.next(); } }
+            otro => texto.push(otro),       // \\ \" \'
+        }
+    }
+    Some(texto)
+}
+
+//***************************************************************************** Expansión
+fn bytes_a_tokens(bytes: &[u8], array: bool) -> TokenStream {
+    let lista = bytes.iter().map(|b| format!("0x{:02X}u8", b)).collect::<Vec<String>>().join(", ");
+    let codigo = match (array, bytes.is_empty()) {
+        (true, true) => String::from("[0u8; 0]"),
+        (true, false) => format!("[{}]", lista),
+        (false, true) => String::from("::std::vec::Vec::<u8>::new()"),
+        (false, false) => format!("::std::vec![{}]", lista),
+    };
+    codigo.parse().unwrap_or_else(|_| error_compilacion("asm8080: expansión no válida", Span::call_site()))
+}
+
+// compile_error!("mensaje") con todos los tokens en 'span' para que el error se muestre ahí
+fn error_compilacion(mensaje: &str, span: Span) -> TokenStream {
+    let mut literal = Literal::string(mensaje);
+    literal.set_span(span);
+    let mut grupo = Group::new(Delimiter::Parenthesis, TokenStream::from(TokenTree::Literal(literal)));
+    grupo.set_span(span);
+    let mut exclamacion = Punct::new('!', Spacing::Alone);
+    exclamacion.set_span(span);
+    TokenStream::from_iter([
+        TokenTree::Ident(Ident::new("compile_error", span)),
+        TokenTree::Punct(exclamacion),
+        TokenTree::Group(grupo),
+    ])
+}
@@ -0,0 +1,47 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Pruebas de la macro asm8080! - Simulación CPU
+    Descripción:    Las macros procedimentales solo se pueden usar desde otro crate, así que
+                    sus pruebas van en tests/ en lugar de en un módulo tests de lib.rs.
+    Referencias:
+
+***************************************************************************************/
+use asm8080::{asm8080, asm8080_array};
+
+#[test]
+fn test_macro_asm8080() {
+    // Misma fuente con tokens de Rust y en una cadena con comentarios del ensamblador
+    let tokens = asm8080_array! {
+        bucle:  mvi a, 'A'; inr a
+                cpi ('Z' + 1) & 0FFh
+                jnz bucle
+    };
+    let cadena = asm8080!("BUCLE:  MVI A,'A'      ; Letra inicial
+    INR A
+    CPI 'Z'+1
+    JNZ BUCLE");
+    assert_eq!(tokens, [0x3E, 0x41, 0x3C, 0xFE, 0x5B, 0xC2, 0x00, 0x00]);
+    assert_eq!(cadena, tokens.to_vec());
+}
+
+#[test]
+fn test_etiquetas_locales_y_columnas() {
+    // La etiqueta sin ':' en la columna más a la izquierda; las locales cuelgan de la global
+    let programa = asm8080! {
+                lxi sp, 400h
+                mvi a, 4
+                call sub1
+                mvi b, 1
+                db 0ffh, 0ffh       // Fin de programa
+        sub1    inr a               // 0x000C
+                call .sub2
+                ret
+        .sub2:  inr a               // 0x0011
+                ret
+    };
+    assert_eq!(programa, vec![
+        0x31, 0x00, 0x04, 0x3E, 0x04, 0xCD, 0x0C, 0x00, 0x06, 0x01, 0xFF, 0xFF,
+        0x3C, 0xCD, 0x11, 0x00, 0xC9, 0x3C, 0xC9,
+    ]);
+}
@@ -86,6 +86,9 @@ fn ventana_opcode(cpu: &CPU, aux: &Aux) -> gtk::Window {
     etiqueta_hex.set_halign(Start);
     let etiqueta_pc = Label::new(Some("Contador PC: "));
     etiqueta_pc.set_halign(Start);
+    let etiqueta_fuente = Label::new(Some("Fuente: "));
+    etiqueta_fuente.set_halign(Start);
+    etiqueta_fuente.set_valign(Start);
 
     let text_view_inst = TextView::new();
     text_view_inst.set_monospace(true);
@@ -107,6 +110,13 @@ fn ventana_opcode(cpu: &CPU, aux: &Aux) -> gtk::Window {
     text_view_pc.set_editable(false);
     text_view_pc.set_cursor_visible(false);
     let bufer_pc = text_view_pc.buffer().unwrap();
+
+    let text_view_fuente = TextView::new();
+    text_view_fuente.set_monospace(true);
+    text_view_fuente.set_halign(Start);
+    text_view_fuente.set_editable(false);
+    text_view_fuente.set_cursor_visible(false);
+    let bufer_fuente = text_view_fuente.buffer().unwrap();
     
     let caja_opcode_0 = Box::new(gtk::Orientation::Vertical, 8);
     caja_opcode_0.set_margin(8);
@@ -124,7 +134,9 @@ fn ventana_opcode(cpu: &CPU, aux: &Aux) -> gtk::Window {
     grid_opcode.attach(&text_view_inst, 1, 0, 1, 1);
     grid_opcode.attach(&text_view_hex, 1, 1, 1, 1);
     grid_opcode.attach(&text_view_pc, 1, 2, 1, 1);
-    grid_opcode.attach(&etiqueta_opcode, 1, 3, 1, 1);
+    grid_opcode.attach(&etiqueta_fuente, 0, 3, 1, 1);
+    grid_opcode.attach(&text_view_fuente, 1, 3, 1, 1);
+    grid_opcode.attach(&etiqueta_opcode, 1, 4, 1, 1);
 
     ventana_opcode.add(&caja_opcode_0);
     caja_opcode_0.pack_start(&grid_opcode, true, true, 4);
@@ -139,8 +151,16 @@ fn ventana_opcode(cpu: &CPU, aux: &Aux) -> gtk::Window {
         text_view_inst.set_buffer(Some(&bufer_inst));
         bufer_hex.set_text(&format!("{}", cpu.memoria.leer_memoria(cpu.contador_de_programa)));
         text_view_hex.set_buffer(Some(&bufer_hex));
-        bufer_pc.set_text(&format!("0x{:04X}", cpu.contador_de_programa));
+        // Con tabla de símbolos el PC se muestra también como etiqueta+desplazamiento
+        bufer_pc.set_text(&match cpu.nombre_direccion(cpu.contador_de_programa) {
+            Some(nombre) => format!("0x{:04X} <{}>", cpu.contador_de_programa, nombre),
+            None => format!("0x{:04X}", cpu.contador_de_programa),
+        });
         text_view_pc.set_buffer(Some(&bufer_pc));
+        // Líneas del listado .PRN alrededor del PC ('>' en la actual)
+        let contexto = cpu.contexto_fuente(3, 3);
+        bufer_fuente.set_text(&if contexto.is_empty() { String::from("Sin listado") } else { contexto.join("\n") });
+        text_view_fuente.set_buffer(Some(&bufer_fuente));
 
         //bufer_opcode.set_text(&format!("0x{:02X}", cpu.get_a()));
         //etiqueta_pc.set_text(&format!("0x{:04X}", cpu.contador_de_programa));
@@ -256,6 +256,16 @@ fn main() {
 
     proyectos::sim_cpu_pruebas::cpu_sim_0();
     proyectos::sim_cpu_generica::cpu_generica_0();
+    proyectos::sim_cpu_isa::cpu_isa_0();
+    proyectos::sim_cpu_compara_trazas::compara_trazas_cmd(&std::env::args().skip(1).collect::<Vec<String>>());
+    proyectos::sim_cpu_monitor::monitor_cmd();
+    proyectos::sim_cpu_gdb::gdb_cmd();
+    proyectos::sim_cpu_dap::dap_cmd();
+    proyectos::sim_cpu_ensamblador::ensamblador_cmd();
+    proyectos::sim_cpu_flujo::flujo_cmd();
+    proyectos::sim_cpu_rel::enlaza_cmd(&std::env::args().skip(1).collect::<Vec<String>>());
+    proyectos::sim_cpu_cpm::cpm_cmd(&std::env::args().skip(1).collect::<Vec<String>>());
+    proyectos::sim_cpu_cpm_bios::cpm22_cmd(&std::env::args().skip(1).collect::<Vec<String>>());
 
     proyectos::pruebas_plotters::pru_ploter2();
 */
@@ -13,5 +13,31 @@ pub mod sim_cpu_pruebas;
 pub mod sim_cpu_registros;
 pub mod sim_cpu_memoria;
 pub mod sim_cpu_opcode;
+pub mod sim_cpu_tablas;
 pub mod sim_cpu_generica;
+pub mod sim_cpu_isa;
+pub mod sim_cpu_estado;
+pub mod sim_cpu_historial;
+pub mod sim_cpu_traza;
+pub mod sim_cpu_compara_trazas;
+pub mod sim_cpu_perfilador;
+pub mod sim_cpu_llamadas;
+pub mod sim_cpu_cobertura;
+pub mod sim_cpu_vigilancia;
+pub mod sim_cpu_expresion;
+pub mod sim_cpu_puntos_ruptura;
+pub mod sim_cpu_simbolos;
+pub mod sim_cpu_listado;
+pub mod sim_cpu_monitor;
+pub mod sim_cpu_gdb;
 
+pub mod sim_cpu_dap;
+pub mod sim_cpu_ensamblador;
+pub mod sim_cpu_desensamblador;
+pub mod sim_cpu_flujo;
+pub mod sim_cpu_hex;
+pub mod sim_cpu_cargador;
+pub mod sim_cpu_rel;
+pub mod sim_cpu_cpm;
+pub mod sim_cpu_cpm_ficheros;
+pub mod sim_cpu_cpm_bios;
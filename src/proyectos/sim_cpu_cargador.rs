@@ -0,0 +1,244 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Cargadores S-record y binario - Simulación CPU
+    Descripción:    Carga archivos Motorola S-record (S19, S28, S37) e imágenes binarias en
+                    BancosMemoria con dirección de carga, banco y punto de entrada opcionales.
+                    Las cargas que se solapan o no caben en el banco son errores y no escriben
+                    nada en memoria.
+    Referencias:    Motorola M68000 Family Programmer's Reference Manual, apéndice C (S-records)
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_memoria::BancosMemoria;
+use super::sim_cpu_registros::CPU;
+use super::sim_cpu_hex::{analiza_intel_hex, ImagenCarga};
+
+//***************************************************************************** Notas
+/* Uso
+    let mut opciones = OpcionesCarga::new();
+    opciones.direccion = Some(0x0100);          // Binario: dónde empieza. S-record: se reubica ahí
+    opciones.banco = Some(1);                   // None: banco actual (binario) o el del archivo
+    opciones.entrada = Some(0x0100);            // Fija el PC en 'banco' o, sin él, en el banco actual
+    cpu.cargar_archivo("programa.s19", &opciones)?;
+    cpu.cargar_binario(&std::fs::read("rom.bin").unwrap(), &opciones)?;
+
+cargar_archivo elige el formato por la extensión: .hex/.ihx Intel HEX, .s19/.s28/.s37/.srec/.mot
+S-record y cualquier otra binario. Sin 'entrada' se usa la dirección de inicio del archivo (S7/S8/S9
+o registros 03/05 de Intel HEX) si la trae, y si no el PC no cambia.
+
+En S-record la dirección de los registros S2/S3 por encima de 16 bits es el banco, igual que en
+Intel HEX. Con 'direccion' toda la imagen se desplaza para que su dirección más baja quede en ella
+(también la dirección de inicio) y con 'banco' todos los datos van a ese banco.
+*/
+
+pub struct OpcionesCarga {
+    pub direccion: Option<u16>,
+    pub banco: Option<u8>,
+    pub entrada: Option<u16>,
+}
+
+impl OpcionesCarga {
+    pub fn new() -> OpcionesCarga {
+        OpcionesCarga { direccion: None, banco: None, entrada: None }
+    }
+}
+
+impl Default for OpcionesCarga {
+    fn default() -> Self { OpcionesCarga::new() }
+}
+
+//***************************************************************************** S-record
+pub fn analiza_srec(texto: &str) -> Result<ImagenCarga, String> {
+    let mut imagen = ImagenCarga { bloques: Vec::new(), inicio: None };
+    let mut registros_datos: u32 = 0;
+    for (i, contenido) in texto.lines().enumerate() {
+        let linea = i + 1;
+        let contenido = contenido.trim();
+        if contenido.is_empty() { continue; }
+        if !contenido.is_ascii() { return Err(format!("Línea {}: caracteres no válidos", linea)); }
+        let tipo = match contenido.strip_prefix('S').and_then(|r| r.chars().next()) {
+            Some(t) if t.is_ascii_digit() => t.to_digit(10).unwrap_or(0),
+            _ => return Err(format!("Línea {}: el registro debe empezar por 'S' y un dígito", linea)),
+        };
+        let resto = &contenido[2..];
+        if resto.len() % 2 != 0 { return Err(format!("Línea {}: número impar de dígitos hexadecimales", linea)); }
+        let bytes = (0..resto.len()).step_by(2).map(|i| {
+            u8::from_str_radix(&resto[i..i + 2], 16).map_err(|_| format!("Línea {}: '{}' no es un byte hexadecimal", linea, &resto[i..i + 2]))
+        }).collect::<Result<Vec<u8>, String>>()?;
+        if bytes.is_empty() || bytes.len() != bytes[0] as usize + 1 {
+            return Err(format!("Línea {}: la longitud del registro no coincide con los datos", linea));
+        }
+        let suma = !bytes[..bytes.len() - 1].iter().fold(0u8, |s, b| s.wrapping_add(*b));
+        if suma != bytes[bytes.len() - 1] {
+            return Err(format!("Línea {}: suma de comprobación incorrecta (0x{:02X}, se esperaba 0x{:02X})", linea, bytes[bytes.len() - 1], suma));
+        }
+        let ancho = match tipo {
+            0 | 1 | 5 | 9 => 2,
+            2 | 6 | 8 => 3,
+            3 | 7 => 4,
+            _ => return Err(format!("Línea {}: tipo de registro S{} no soportado", linea, tipo)),
+        };
+        if bytes.len() < ancho + 2 { return Err(format!("Línea {}: registro demasiado corto", linea)); }
+        let direccion = bytes[1..1 + ancho].iter().fold(0u32, |d, b| (d << 8) | *b as u32);
+        let datos = &bytes[1 + ancho..bytes.len() - 1];
+        match tipo {
+            0 => {}                                     // Cabecera
+            1..=3 => {
+                registros_datos += 1;
+                if !datos.is_empty() { imagen.bloques.push((direccion, datos.to_vec())); }
+            }
+            5 | 6 => if direccion != registros_datos {
+                return Err(format!("Línea {}: el registro S{} cuenta {} registros de datos y hay {}", linea, tipo, direccion, registros_datos));
+            },
+            _ => { imagen.inicio = Some(direccion); break; }
+        }
+    }
+    Ok(imagen)
+}
+
+//***************************************************************************** Opciones
+// Aplica la reubicación y el banco a la imagen; devuelve la dirección lineal de entrada. Una
+// 'entrada' sin 'banco' es del banco actual, igual que en carga_binario.
+fn ajusta(imagen: &mut ImagenCarga, opciones: &OpcionesCarga, banco_actual: u8) -> Result<Option<u32>, String> {
+    let ajusta_dir = |lineal: u32, desplazamiento: i64| -> Result<u32, String> {
+        let baja = (lineal & 0xFFFF) as i64 + desplazamiento;
+        if !(0..=0xFFFF).contains(&baja) { return Err(format!("Dirección 0x{:06X}: fuera del banco al reubicar", lineal)); }
+        let banco = opciones.banco.map(|b| b as u32).unwrap_or(lineal >> 16);
+        Ok((banco << 16) | baja as u32)
+    };
+    let desplazamiento = match (opciones.direccion, imagen.bloques.iter().map(|(d, _)| d & 0xFFFF).min()) {
+        (Some(destino), Some(minima)) => destino as i64 - minima as i64,
+        _ => 0,
+    };
+    for bloque in imagen.bloques.iter_mut() { bloque.0 = ajusta_dir(bloque.0, desplazamiento)?; }
+    match (opciones.entrada, imagen.inicio) {
+        (Some(entrada), _) => Ok(Some(((opciones.banco.unwrap_or(banco_actual) as u32) << 16) | entrada as u32)),
+        (None, Some(inicio)) => Ok(Some(ajusta_dir(inicio, desplazamiento)?)),
+        (None, None) => Ok(None),
+    }
+}
+
+//*****************************************************************************
+impl BancosMemoria {
+    // Devuelven la dirección lineal de entrada (banco en los 16 bits altos) si la hay
+    pub fn carga_srec(&mut self, texto: &str, opciones: &OpcionesCarga) -> Result<Option<u32>, String> {
+        let mut imagen = analiza_srec(texto)?;
+        let entrada = ajusta(&mut imagen, opciones, self.banco_actual)?;
+        self.carga_imagen(&imagen)?;
+        Ok(entrada)
+    }
+
+    pub fn carga_binario(&mut self, datos: &[u8], opciones: &OpcionesCarga) -> Result<Option<u32>, String> {
+        let banco = opciones.banco.unwrap_or(self.banco_actual) as u32;
+        let inicio = opciones.direccion.unwrap_or(0) as u32;
+        let imagen = ImagenCarga { bloques: vec![((banco << 16) | inicio, datos.to_vec())], inicio: None };
+        self.carga_imagen(&imagen)?;
+        Ok(opciones.entrada.map(|e| (banco << 16) | e as u32))
+    }
+}
+
+impl CPU {
+    fn fija_entrada(&mut self, entrada: Option<u32>) -> Result<(), String> {
+        if let Some(entrada) = entrada {
+            let banco = (entrada >> 16) as usize;
+            if banco >= self.memoria.segmento_memoria.len() { return Err(format!("Punto de entrada 0x{:06X}: no existe el banco {}", entrada, banco)); }
+            self.memoria.set_banco_activo(banco as u8);
+            self.contador_de_programa = entrada as u16;
+        }
+        Ok(())
+    }
+
+    pub fn cargar_srec(&mut self, texto: &str, opciones: &OpcionesCarga) -> Result<(), String> {
+        let entrada = self.memoria.carga_srec(texto, opciones)?;
+        self.fija_entrada(entrada)
+    }
+
+    pub fn cargar_binario(&mut self, datos: &[u8], opciones: &OpcionesCarga) -> Result<(), String> {
+        let entrada = self.memoria.carga_binario(datos, opciones)?;
+        self.fija_entrada(entrada)
+    }
+
+    // Formato según la extensión del archivo
+    pub fn cargar_archivo(&mut self, ruta: &str, opciones: &OpcionesCarga) -> Result<(), String> {
+        let extension = ruta.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase()).unwrap_or_default();
+        let lee_texto = || std::fs::read_to_string(ruta).map_err(|e| format!("No se puede leer '{}': {}", ruta, e));
+        let resultado = match extension.as_str() {
+            "hex" | "ihx" => {
+                let mut imagen = analiza_intel_hex(&lee_texto()?)?;
+                ajusta(&mut imagen, opciones, self.memoria.banco_actual).and_then(|entrada| {
+                    self.memoria.carga_imagen(&imagen)?;
+                    self.fija_entrada(entrada)
+                })
+            }
+            "s19" | "s28" | "s37" | "srec" | "mot" => self.cargar_srec(&lee_texto()?, opciones),
+            _ => {
+                let datos = std::fs::read(ruta).map_err(|e| format!("No se puede leer '{}': {}", ruta, e))?;
+                self.cargar_binario(&datos, opciones)
+            }
+        };
+        resultado.map_err(|e| format!("{}: {}", ruta, e))
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srec_s19_y_s28() {
+        let mut cpu = CPU::new();
+        cpu.memoria.crear_segmento(0x100);
+        cpu.cargar_srec("
+S00600004844521B
+S1060010310004B4
+S2060100203EAAF0
+S5030002FA
+S9030010EC", &OpcionesCarga::new()).unwrap();
+        assert_eq!(&cpu.memoria.segmento_memoria[0][0x10..0x13], &[0x31, 0x00, 0x04]);
+        assert_eq!(&cpu.memoria.segmento_memoria[1][0x20..0x22], &[0x3E, 0xAA]);
+        assert_eq!(cpu.contador_de_programa, 0x0010);
+
+        // Reubicado a 0x0040 del banco 1: la entrada se desplaza con los datos
+        let mut opciones = OpcionesCarga::new();
+        opciones.direccion = Some(0x0040);
+        opciones.banco = Some(1);
+        cpu.cargar_srec("S1060010310004B4\nS9030010EC", &opciones).unwrap();
+        assert_eq!(&cpu.memoria.segmento_memoria[1][0x40..0x43], &[0x31, 0x00, 0x04]);
+        assert_eq!((cpu.memoria.get_banco_activo(), cpu.contador_de_programa), (1, 0x0040));
+
+        // Entrada sin banco: se queda en el banco activo aunque los datos vayan al 0
+        let opciones = OpcionesCarga { entrada: Some(0x0012), ..Default::default() };
+        cpu.cargar_srec("S1060010310004B4", &opciones).unwrap();
+        assert_eq!((cpu.memoria.get_banco_activo(), cpu.contador_de_programa), (1, 0x0012));
+
+        assert_eq!(analiza_srec("S1060010310004B5").err().unwrap(), "Línea 1: suma de comprobación incorrecta (0xB5, se esperaba 0xB4)");
+        assert_eq!(analiza_srec("S1060010310004B4\nS5030002FA").err().unwrap(), "Línea 2: el registro S5 cuenta 2 registros de datos y hay 1");
+    }
+
+    #[test]
+    fn test_binario_solapado_y_fuera_de_rango() {
+        let mut cpu = CPU::new();
+        let mut opciones = OpcionesCarga::new();
+        opciones.direccion = Some(0x0100);
+        opciones.entrada = Some(0x0102);
+        cpu.cargar_binario(&[0xC3, 0x00, 0x00], &opciones).unwrap();
+        assert_eq!(&cpu.memoria.segmento_memoria[0][0x100..0x103], &[0xC3, 0x00, 0x00]);
+        assert_eq!(cpu.contador_de_programa, 0x0102);
+
+        // CPU::new() tiene 1024 bytes: el error se devuelve y la memoria no cambia
+        opciones.direccion = Some(0x03FE);
+        assert_eq!(cpu.cargar_binario(&[1, 2, 3], &opciones).err().unwrap(), "Dirección 0x0003FE: 3 bytes no caben en el banco 0 (1024 bytes)");
+        assert_eq!(cpu.memoria.segmento_memoria[0][0x3FE], 0);
+        opciones.banco = Some(3);
+        assert!(cpu.cargar_binario(&[1], &opciones).err().unwrap().contains("no existe el banco 3"));
+
+        assert_eq!(cpu.cargar_srec("S10500103100B9\nS1050011BB002E", &OpcionesCarga::new()).err().unwrap()
+            , "Los datos en 0x000011 se solapan con los de 0x000010");
+    }
+}
+
+//*****************************************************************************
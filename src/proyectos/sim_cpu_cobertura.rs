@@ -0,0 +1,251 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Mapa de cobertura de memoria - Simulación CPU
+    Descripción:    Marca para cada byte de cada banco de memoria el tipo de acceso que ha
+                    recibido (buscado como opcode, buscado como operando, leído o escrito).
+                    Se exporta como listado hexadecimal anotado y como resumen de las
+                    regiones del programa que no se han tocado.
+    Referencias:
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::{sim_cpu_memoria::BancosMemoria, sim_cpu_registros::CPU, sim_cpu_opcode::LONGITUD_8080};
+
+//***************************************************************************** Notas
+/* Uso
+    cpu.cargar_programa(&programa);
+    cpu.habilitar_cobertura();          // Después de cargar: la carga no cuenta como escritura
+    cpu.run_no_win(&mut aux);
+    println!("{}", cpu.informe_cobertura(0, programa.len() as u16 - 1)?);
+
+Cada byte guarda una máscara de bits con los accesos. En el listado se muestra un carácter por
+byte (el de mayor prioridad):
+    '!' ejecutado y escrito (código automodificable)
+    'X' buscado como opcode
+    'o' buscado como operando
+    'M' leído y escrito
+    'W' escrito
+    'R' leído
+    '.' sin acceso
+
+Solo cuentan los accesos del programa: las escrituras de los depuradores (fill, edit y load del
+monitor, 'M' de GDB, writeMemory de DAP) usan escribir_memoria_depurador y no se marcan. Un
+rango con el final antes del inicio es un error.
+*/
+
+pub const ACCESO_OPCODE: u8 = 0b0001;
+pub const ACCESO_OPERANDO: u8 = 0b0010;
+pub const ACCESO_LECTURA: u8 = 0b0100;
+pub const ACCESO_ESCRITURA: u8 = 0b1000;
+
+pub struct Cobertura {
+    pub marcas: Vec<Vec<u8>>,           // Una máscara de accesos por byte y banco
+}
+
+impl Cobertura {
+    pub fn new(bancos: &BancosMemoria) -> Cobertura {
+        Cobertura { marcas: bancos.segmento_memoria.iter().map(|s| vec![0; s.len()]).collect() }
+    }
+
+    pub fn marca(&mut self, banco: u8, direccion: u16, acceso: u8) {
+        // Los bancos creados después de habilitar la cobertura se añaden al usarlos
+        while self.marcas.len() <= banco as usize { self.marcas.push(Vec::new()); }
+        let marcas = &mut self.marcas[banco as usize];
+        if marcas.len() <= direccion as usize { marcas.resize(direccion as usize + 1, 0); }
+        marcas[direccion as usize] |= acceso;
+    }
+
+    pub fn get(&self, banco: u8, direccion: u16) -> u8 {
+        self.marcas.get(banco as usize).and_then(|m| m.get(direccion as usize)).copied().unwrap_or(0)
+    }
+
+    pub fn simbolo(marca: u8) -> char {
+        let ejecutado = marca & (ACCESO_OPCODE | ACCESO_OPERANDO) != 0;
+        if ejecutado && marca & ACCESO_ESCRITURA != 0 { '!' }
+        else if marca & ACCESO_OPCODE != 0 { 'X' }
+        else if marca & ACCESO_OPERANDO != 0 { 'o' }
+        else if marca & (ACCESO_LECTURA | ACCESO_ESCRITURA) == (ACCESO_LECTURA | ACCESO_ESCRITURA) { 'M' }
+        else if marca & ACCESO_ESCRITURA != 0 { 'W' }
+        else if marca & ACCESO_LECTURA != 0 { 'R' }
+        else { '.' }
+    }
+
+    // Tramos consecutivos [inicio, fin] dentro del rango cuyos bytes no tienen ningún acceso
+    pub fn regiones_sin_acceso(&self, banco: u8, inicio: u16, fin: u16) -> Vec<(u16, u16)> {
+        let mut regiones = Vec::new();
+        let mut abierta: Option<u16> = None;
+        for direccion in inicio..=fin {
+            match (self.get(banco, direccion) == 0, abierta) {
+                (true, None) => abierta = Some(direccion),
+                (false, Some(desde)) => { regiones.push((desde, direccion - 1)); abierta = None; }
+                _ => {}
+            }
+        }
+        if let Some(desde) = abierta { regiones.push((desde, fin)); }
+        regiones
+    }
+
+    pub fn cuenta(&self, banco: u8, inicio: u16, fin: u16, acceso: u8) -> usize {
+        (inicio..=fin).filter(|&d| self.get(banco, d) & acceso != 0).count()
+    }
+
+    // Listado hexadecimal de 16 bytes por línea con el carácter de acceso de cada byte
+    pub fn listado_hex(&self, bancos: &BancosMemoria, banco: u8, inicio: u16, fin: u16) -> String {
+        let mut salida = String::new();
+        let segmento = &bancos.segmento_memoria[banco as usize];
+        let mut linea = inicio & 0xFFF0;
+        loop {
+            let mut hex = String::new();
+            let mut accesos = String::new();
+            for direccion in linea..=linea.saturating_add(15) {
+                if direccion < inicio || direccion > fin || direccion as usize >= segmento.len() {
+                    hex.push_str("   ");
+                    accesos.push(' ');
+                } else {
+                    hex.push_str(&format!("{:02X} ", segmento[direccion as usize]));
+                    accesos.push(Cobertura::simbolo(self.get(banco, direccion)));
+                }
+            }
+            salida.push_str(&format!("{:04X}  {} |{}|\n", linea, hex, accesos));
+            match linea.checked_add(16) {
+                Some(siguiente) if siguiente <= fin => linea = siguiente,
+                _ => break,
+            }
+        }
+        salida
+    }
+
+    pub fn resumen(&self, banco: u8, inicio: u16, fin: u16) -> Result<String, String> {
+        if fin < inicio { return Err(format!("Rango vacío: 0x{:04X} es menor que 0x{:04X}", fin, inicio)); }
+        let total = fin as usize - inicio as usize + 1;
+        let regiones = self.regiones_sin_acceso(banco, inicio, fin);
+        let sin_acceso: usize = regiones.iter().map(|(d, h)| (*h - *d) as usize + 1).sum();
+        let mut salida = format!("Banco {}, rango 0x{:04X}-0x{:04X} ({} bytes)\n", banco, inicio, fin, total);
+        salida.push_str(&format!("  Opcodes ejecutados: {}, operandos: {}, leídos: {}, escritos: {}\n"
+            , self.cuenta(banco, inicio, fin, ACCESO_OPCODE), self.cuenta(banco, inicio, fin, ACCESO_OPERANDO)
+            , self.cuenta(banco, inicio, fin, ACCESO_LECTURA), self.cuenta(banco, inicio, fin, ACCESO_ESCRITURA)));
+        salida.push_str(&format!("  Sin acceso: {} bytes ({:.1}%) en {} regiones\n"
+            , sin_acceso, sin_acceso as f64 * 100.0 / total as f64, regiones.len()));
+        for (desde, hasta) in regiones {
+            salida.push_str(&format!("    0x{:04X}-0x{:04X} ({} bytes)\n", desde, hasta, hasta - desde + 1));
+        }
+        Ok(salida)
+    }
+}
+
+//*****************************************************************************
+impl BancosMemoria {
+    pub fn marca_acceso(&mut self, direccion: u16, acceso: u8) {
+        let banco = self.banco_actual;
+        if let Some(cobertura) = self.cobertura.as_mut() { cobertura.marca(banco, direccion, acceso); }
+    }
+}
+
+impl CPU {
+    pub fn habilitar_cobertura(&mut self) {
+        self.memoria.cobertura = Some(Cobertura::new(&self.memoria));
+    }
+
+    pub fn deshabilitar_cobertura(&mut self) -> Option<Cobertura> {
+        self.memoria.cobertura.take()
+    }
+
+    // Marca el opcode y sus operandos según la longitud de la instrucción
+    pub fn marca_busqueda(&mut self, direccion: u16, opcode: u8) {
+        if self.memoria.cobertura.is_none() { return; }
+        self.memoria.marca_acceso(direccion, ACCESO_OPCODE);
+        for i in 1..LONGITUD_8080[opcode as usize] as u16 {
+            self.memoria.marca_acceso(direccion.wrapping_add(i), ACCESO_OPERANDO);
+        }
+    }
+
+    // Listado anotado y resumen del rango en el banco activo
+    pub fn informe_cobertura(&self, inicio: u16, fin: u16) -> Result<String, String> {
+        match self.memoria.cobertura.as_ref() {
+            Some(cobertura) => {
+                let banco = self.memoria.banco_actual;
+                let resumen = cobertura.resumen(banco, inicio, fin)?;
+                Ok(format!("{}\n{}", cobertura.listado_hex(&self.memoria, banco, inicio, fin), resumen))
+            }
+            None => Ok(String::from("Cobertura no habilitada\n")),
+        }
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proyectos::sim_cpu_pruebas::Aux;
+    use asm8080::asm8080;
+
+    fn ejecuta(programa: &Vec<u8>, pasos: usize) -> CPU {
+        let mut cpu = CPU::new();
+        let mut aux = Aux { imp_contador_programa: 0, imp_instruccion: 0, imp_mnemonico: String::new() };
+        cpu.cargar_programa(programa);
+        cpu.habilitar_cobertura();
+        for _ in 0..pasos { cpu.step_no_win(&mut aux); }
+        cpu
+    }
+
+    #[test]
+    fn test_tipos_de_acceso() {
+        let cpu = ejecuta(&asm8080! {
+                    lda dato
+            escribe: sta escribe + 2    // Escribe sobre su propio operando (0x0005)
+                    jmp sigue
+                    db 0, 0             // No se ejecuta
+            sigue:  inr a               // 0x000B
+            dato:   db 42h              // 0x000C
+        }, 4);
+        let cobertura = cpu.memoria.cobertura.as_ref().unwrap();
+        assert_eq!(cobertura.get(0, 0x0000), ACCESO_OPCODE);
+        assert_eq!(cobertura.get(0, 0x0001), ACCESO_OPERANDO);
+        assert_eq!(cobertura.get(0, 0x0005), ACCESO_OPERANDO | ACCESO_ESCRITURA);
+        assert_eq!(cobertura.get(0, 0x000C), ACCESO_LECTURA);
+        assert_eq!(cobertura.get(0, 0x0009), 0);
+        assert_eq!(cobertura.regiones_sin_acceso(0, 0, 0x0C), vec![(0x0009, 0x000A)]);
+
+        let informe = cpu.informe_cobertura(0, 0x0C).unwrap();
+        assert!(informe.contains("0000  3A 0C 00 32 05 42 C3 0B 00 00 00 3C 42  "));
+        assert!(informe.contains("|XooXo!Xoo..XR   |"));
+        assert!(informe.contains("Sin acceso: 2 bytes"));
+        assert!(informe.contains("0x0009-0x000A (2 bytes)"));
+    }
+
+    #[test]
+    fn test_cobertura_deshabilitada_y_bancos() {
+        let mut cpu = ejecuta(&asm8080! { nop }, 0);
+        cpu.memoria.crear_segmento(256);
+        cpu.memoria.set_banco_activo(1);
+        cpu.memoria.escribir_memoria(0x0010, 1);
+        let cobertura = cpu.deshabilitar_cobertura().unwrap();
+        assert_eq!(cobertura.get(1, 0x0010), ACCESO_ESCRITURA);
+        assert_eq!(cobertura.get(0, 0x0010), 0);
+        cpu.memoria.escribir_memoria(0x0011, 1);
+        assert!(cpu.memoria.cobertura.is_none());
+    }
+
+    #[test]
+    fn test_rango_vacio_y_escrituras_del_depurador() {
+        use crate::proyectos::sim_cpu_monitor::Monitor;
+        let cpu = ejecuta(&asm8080! { nop }, 1);
+        assert!(cpu.informe_cobertura(5, 4).is_err());
+        assert!(cpu.memoria.cobertura.as_ref().unwrap().resumen(0, 0x0010, 0x000F).is_err());
+        assert!(cpu.informe_cobertura(0, 0).unwrap().contains("(1 bytes)"));
+
+        // fill y edit del monitor no marcan escrituras; STA sí
+        let mut monitor = Monitor::new(ejecuta(&asm8080! { mvi a, 7; sta 20h }, 0));
+        let mut salida = Vec::new();
+        monitor.ejecuta(std::io::Cursor::new(b"f 0x10 0x13 0xAA\ne 0x18 1 2\ns 2\n".to_vec()), &mut salida).unwrap();
+        let cobertura = monitor.cpu.memoria.cobertura.as_ref().unwrap();
+        assert_eq!(monitor.cpu.memoria.leer_memoria(0x0011), 0xAA);
+        assert_eq!(cobertura.cuenta(0, 0x0010, 0x001F, ACCESO_ESCRITURA), 0);
+        assert_eq!(cobertura.get(0, 0x0020), ACCESO_ESCRITURA);
+    }
+}
+
+//*****************************************************************************
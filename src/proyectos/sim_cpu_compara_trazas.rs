@@ -0,0 +1,318 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Comparación de trazas de ejecución - Simulación CPU
+    Descripción:    Alinea dos trazas por número de instrucción e informa del primer
+                    registro en el que difiere algún registro, flag, SP o PC, con N
+                    registros de contexto y la diferencia campo a campo.
+    Referencias:
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_traza::{lee_traza, FormatoTraza, RegistroTraza};
+use colored::*;
+
+//***************************************************************************** Notas
+/* Campos comparados
+    pc, sp, a, b, c, d, e, h, l, ciclos, bytes
+    flag_s, flag_z, flag_5, flag_h, flag_3, flag_p, flag_1, flag_c   (bits 7..0 del byte de flags)
+El desensamblado no se compara: cada emulador lo escribe a su manera.
+
+Por defecto solo se comparan los registros, SP, PC y los flags: ciclos y bytes dependen de cómo
+cuente y escriba cada emulador, así que hay que pedirlos con comparar("ciclos") o --comparar.
+Los campos se pueden ignorar por nombre. "no_documentados" equivale a flag_5, flag_3 y flag_1,
+los bits que el 8080 fija a 0/1/0 y que otros emuladores no siempre reproducen igual. "flags"
+ignora los ocho bits.
+*/
+
+const NOMBRES_FLAGS: [(&str, u8); 8] = [
+    ("flag_s", 7), ("flag_z", 6), ("flag_5", 5), ("flag_h", 4),
+    ("flag_3", 3), ("flag_p", 2), ("flag_1", 1), ("flag_c", 0),
+];
+
+pub const CAMPOS: [&str; 11] = ["pc", "sp", "a", "b", "c", "d", "e", "h", "l", "ciclos", "bytes"];
+pub const CAMPOS_OPCIONALES: [&str; 2] = ["ciclos", "bytes"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diferencia {
+    pub campo: String,
+    pub valor_a: String,
+    pub valor_b: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResultadoComparacion {
+    Iguales(usize),                         // Número de registros comparados
+    Divergencia {
+        indice: usize,                      // Número de instrucción (desde 0)
+        diferencias: Vec<Diferencia>,
+    },
+    LongitudDistinta {                      // Una traza termina antes sin diferencias previas
+        longitud_a: usize,
+        longitud_b: usize,
+    },
+}
+
+pub struct OpcionesComparacion {
+    pub contexto: usize,
+    pub ignorados: Vec<String>,
+}
+
+impl OpcionesComparacion {
+    pub fn new() -> OpcionesComparacion {
+        OpcionesComparacion { contexto: 3, ignorados: CAMPOS_OPCIONALES.iter().map(|c| c.to_string()).collect() }
+    }
+
+    // Vuelve a comparar un campo ignorado (ciclos y bytes lo están por defecto)
+    pub fn comparar(&mut self, campo: &str) -> Result<(), String> {
+        let campo = campo.trim().to_lowercase();
+        if !CAMPOS.contains(&campo.as_str()) && !NOMBRES_FLAGS.iter().any(|(f, _)| *f == campo) {
+            return Err(format!("Campo desconocido '{}'", campo));
+        }
+        self.ignorados.retain(|i| *i != campo);
+        Ok(())
+    }
+
+    pub fn ignorar(&mut self, campo: &str) -> Result<(), String> {
+        let campo = campo.trim().to_lowercase();
+        match campo.as_str() {
+            "no_documentados" => {
+                for f in ["flag_5", "flag_3", "flag_1"] { self.ignorados.push(f.to_string()); }
+            }
+            "flags" => {
+                for (f, _) in NOMBRES_FLAGS.iter() { self.ignorados.push(f.to_string()); }
+            }
+            c if CAMPOS.contains(&c) || NOMBRES_FLAGS.iter().any(|(f, _)| *f == c) => self.ignorados.push(campo),
+            _ => return Err(format!("Campo desconocido '{}'", campo)),
+        }
+        Ok(())
+    }
+
+    fn compara(&self, campo: &str) -> bool {
+        !self.ignorados.iter().any(|i| i == campo)
+    }
+}
+
+impl Default for OpcionesComparacion {
+    fn default() -> Self { OpcionesComparacion::new() }
+}
+
+//*****************************************************************************
+pub fn diferencias(a: &RegistroTraza, b: &RegistroTraza, opciones: &OpcionesComparacion) -> Vec<Diferencia> {
+    let mut salida = Vec::new();
+    let mut anota = |campo: &str, valor_a: String, valor_b: String| {
+        if valor_a != valor_b && opciones.compara(campo) {
+            salida.push(Diferencia { campo: campo.to_string(), valor_a, valor_b });
+        }
+    };
+
+    anota("pc", format!("{:04X}", a.pc), format!("{:04X}", b.pc));
+    anota("sp", format!("{:04X}", a.sp), format!("{:04X}", b.sp));
+    for (i, nombre) in ["a", "b", "c", "d", "e", "h", "l"].iter().enumerate() {
+        anota(nombre, format!("{:02X}", a.registros[i]), format!("{:02X}", b.registros[i]));
+    }
+    for (nombre, bit) in NOMBRES_FLAGS.iter() {
+        anota(nombre, ((a.flags >> bit) & 1).to_string(), ((b.flags >> bit) & 1).to_string());
+    }
+    anota("ciclos", a.ciclos.to_string(), b.ciclos.to_string());
+    anota("bytes", format!("{:02X?}", a.bytes), format!("{:02X?}", b.bytes));
+    salida
+}
+
+pub fn compara_registros(traza_a: &[RegistroTraza], traza_b: &[RegistroTraza], opciones: &OpcionesComparacion) -> ResultadoComparacion {
+    for (indice, (a, b)) in traza_a.iter().zip(traza_b.iter()).enumerate() {
+        let diferencias = diferencias(a, b, opciones);
+        if !diferencias.is_empty() {
+            return ResultadoComparacion::Divergencia { indice, diferencias };
+        }
+    }
+    if traza_a.len() != traza_b.len() {
+        return ResultadoComparacion::LongitudDistinta { longitud_a: traza_a.len(), longitud_b: traza_b.len() };
+    }
+    ResultadoComparacion::Iguales(traza_a.len())
+}
+
+// Informe de texto con los registros de contexto de ambas trazas y la diferencia por campos
+pub fn informe(traza_a: &[RegistroTraza], traza_b: &[RegistroTraza], opciones: &OpcionesComparacion) -> String {
+    let mut salida = String::new();
+    match compara_registros(traza_a, traza_b, opciones) {
+        ResultadoComparacion::Iguales(total) => {
+            salida.push_str(&format!("Las trazas coinciden ({} registros comparados)\n", total));
+        }
+        ResultadoComparacion::LongitudDistinta { longitud_a, longitud_b } => {
+            salida.push_str(&format!("Sin diferencias en los {} primeros registros, pero la traza A tiene {} y la traza B {}\n"
+                , longitud_a.min(longitud_b), longitud_a, longitud_b));
+        }
+        ResultadoComparacion::Divergencia { indice, diferencias } => {
+            let inicio = indice.saturating_sub(opciones.contexto);
+            salida.push_str(&format!("Primera divergencia en la instrucción {} (PC A: {:04X}, PC B: {:04X})\n"
+                , indice, traza_a[indice].pc, traza_b[indice].pc));
+            for (etiqueta, traza) in [("A", traza_a), ("B", traza_b)] {
+                salida.push_str(&format!("--- Traza {}\n", etiqueta));
+                for (i, registro) in traza.iter().enumerate().take(indice + 1).skip(inicio) {
+                    let marca = if i == indice { ">>" } else { "  " };
+                    salida.push_str(&format!("{} {:>6} {}\n", marca, i, registro.a_texto(FormatoTraza::TextoFijo)));
+                }
+            }
+            salida.push_str("--- Diferencias\n");
+            for d in diferencias.iter() {
+                salida.push_str(&format!("   {:<8} A: {:<12} B: {}\n", d.campo, d.valor_a, d.valor_b));
+            }
+        }
+    }
+    salida
+}
+
+pub fn compara_archivos(ruta_a: &str, ruta_b: &str, opciones: &OpcionesComparacion) -> Result<String, String> {
+    let lee = |ruta: &str| -> Result<Vec<RegistroTraza>, String> {
+        let texto = std::fs::read_to_string(ruta).map_err(|e| format!("No se puede leer '{}': {}", ruta, e))?;
+        lee_traza(&texto).map_err(|e| format!("{}: {}", ruta, e))
+    };
+    Ok(informe(&lee(ruta_a)?, &lee(ruta_b)?, opciones))
+}
+
+//***************************************************************************** Comando
+/* Uso:
+    compara_trazas <traza_a> <traza_b> [-n <contexto>] [--ignorar campo,...] [--comparar ciclos,bytes]
+Ejemplo desde main:
+    let argumentos: Vec<String> = std::env::args().skip(1).collect();
+    proyectos::sim_cpu_compara_trazas::compara_trazas_cmd(&argumentos);
+*/
+pub fn compara_trazas_cmd(argumentos: &[String]) {
+    match ejecuta_comando(argumentos) {
+        Ok(informe) => print!("{}", informe),
+        Err(e) => {
+            println!("{}", e.red());
+            println!("Uso: compara_trazas <traza_a> <traza_b> [-n <contexto>] [--ignorar campo,...] [--comparar ciclos,bytes]");
+        }
+    }
+}
+
+fn ejecuta_comando(argumentos: &[String]) -> Result<String, String> {
+    let mut opciones = OpcionesComparacion::new();
+    let mut rutas = Vec::new();
+    let mut i = 0;
+    while i < argumentos.len() {
+        match argumentos[i].as_str() {
+            "-n" | "--contexto" => {
+                i += 1;
+                let valor = argumentos.get(i).ok_or(String::from("Falta el valor de -n"))?;
+                opciones.contexto = valor.parse().map_err(|_| format!("Contexto no válido '{}'", valor))?;
+            }
+            "--ignorar" => {
+                i += 1;
+                let valor = argumentos.get(i).ok_or(String::from("Falta la lista de --ignorar"))?;
+                for campo in valor.split(',') { opciones.ignorar(campo)?; }
+            }
+            "--comparar" => {
+                i += 1;
+                let valor = argumentos.get(i).ok_or(String::from("Falta la lista de --comparar"))?;
+                for campo in valor.split(',') { opciones.comparar(campo)?; }
+            }
+            ruta => rutas.push(ruta.to_string()),
+        }
+        i += 1;
+    }
+    if rutas.len() != 2 {
+        return Err(String::from("Se necesitan exactamente dos trazas"));
+    }
+    compara_archivos(&rutas[0], &rutas[1], &opciones)
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registro(pc: u16, a: u8, flags: u8) -> RegistroTraza {
+        RegistroTraza {
+            ciclos: pc as u64 * 4,
+            pc,
+            bytes: vec![0x00],
+            desensamblado: String::from("NOP"),
+            registros: [a, 0, 0, 0, 0, 0, 0],
+            sp: 0,
+            flags,
+            etiqueta: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_primera_divergencia() {
+        let traza_a: Vec<RegistroTraza> = (0..10).map(|i| registro(i, i as u8, 0x02)).collect();
+        let mut traza_b = traza_a.clone();
+        traza_b[6].registros[0] = 0x99;
+        traza_b[6].flags = 0x03;
+        traza_b[8].pc = 0x1234;
+
+        let resultado = compara_registros(&traza_a, &traza_b, &OpcionesComparacion::new());
+        assert_eq!(resultado, ResultadoComparacion::Divergencia {
+            indice: 6,
+            diferencias: vec![
+                Diferencia { campo: String::from("a"), valor_a: String::from("06"), valor_b: String::from("99") },
+                Diferencia { campo: String::from("flag_c"), valor_a: String::from("0"), valor_b: String::from("1") },
+            ],
+        });
+
+        let opciones = OpcionesComparacion { contexto: 2, ..Default::default() };
+        let texto = informe(&traza_a, &traza_b, &opciones);
+        assert!(texto.starts_with("Primera divergencia en la instrucción 6"));
+        assert_eq!(texto.lines().filter(|l| l.starts_with(">>")).count(), 2);
+        assert!(texto.contains("      4 "));
+        assert!(!texto.contains("      3 "));
+    }
+
+    #[test]
+    fn test_ignorar_campos() {
+        let traza_a = vec![registro(0, 1, 0x02), registro(1, 1, 0x02)];
+        let traza_b = vec![registro(0, 1, 0x00), registro(1, 1, 0x2A)];
+        let mut opciones = OpcionesComparacion::new();
+        assert!(matches!(compara_registros(&traza_a, &traza_b, &opciones), ResultadoComparacion::Divergencia { indice: 0, .. }));
+
+        opciones.ignorar("no_documentados").unwrap();
+        assert_eq!(compara_registros(&traza_a, &traza_b, &opciones), ResultadoComparacion::Iguales(2));
+        assert!(opciones.ignorar("flag_x").is_err());
+
+        // Ciclos y bytes solo se comparan si se piden
+        let mut traza_b = traza_a.clone();
+        traza_b[1].ciclos += 1;
+        traza_b[1].bytes = vec![0x40];
+        let mut opciones = OpcionesComparacion::new();
+        assert_eq!(compara_registros(&traza_a, &traza_b, &opciones), ResultadoComparacion::Iguales(2));
+        opciones.comparar("ciclos").unwrap();
+        let ResultadoComparacion::Divergencia { indice: 1, diferencias } = compara_registros(&traza_a, &traza_b, &opciones) else { panic!() };
+        assert_eq!(diferencias.iter().map(|d| d.campo.as_str()).collect::<Vec<_>>(), vec!["ciclos"]);
+        assert!(opciones.comparar("flag_x").is_err());
+    }
+
+    #[test]
+    fn test_longitud_distinta() {
+        let traza_a = vec![registro(0, 1, 0x02), registro(1, 1, 0x02)];
+        let traza_b = vec![registro(0, 1, 0x02)];
+        assert_eq!(compara_registros(&traza_a, &traza_b, &OpcionesComparacion::new()),
+            ResultadoComparacion::LongitudDistinta { longitud_a: 2, longitud_b: 1 });
+    }
+
+    #[test]
+    fn test_comando_con_archivos() {
+        let directorio = std::env::temp_dir();
+        let ruta_a = directorio.join(format!("traza_a_{}.txt", std::process::id()));
+        let ruta_b = directorio.join(format!("traza_b_{}.csv", std::process::id()));
+        std::fs::write(&ruta_a, "0000000000 0000 3E 04    MVI A,04H         00 00 00 00 00 00 00 0000 02\n").unwrap();
+        std::fs::write(&ruta_b, "0,0000,3E 04,\"MVI A,04H\",00,00,00,00,00,00,00,0000,03\n").unwrap();
+
+        let argumentos = vec![ruta_a.to_str().unwrap().to_string(), ruta_b.to_str().unwrap().to_string()];
+        let texto = ejecuta_comando(&argumentos).unwrap();
+        assert!(texto.contains("flag_c"));
+        let mut con_ignorar = argumentos.clone();
+        con_ignorar.extend([String::from("--ignorar"), String::from("flag_c")]);
+        assert!(ejecuta_comando(&con_ignorar).unwrap().starts_with("Las trazas coinciden"));
+
+        std::fs::remove_file(ruta_a).unwrap();
+        std::fs::remove_file(ruta_b).unwrap();
+    }
+}
+
+//*****************************************************************************
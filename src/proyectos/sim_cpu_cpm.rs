@@ -0,0 +1,245 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Ejecución de programas CP/M (.COM) - Simulación CPU
+    Descripción:    Carga un programa .COM en 0x0100 con la página cero de CP/M, intercepta
+                    las llamadas al BDOS (CALL 0005) y las atiende en Rust: las funciones de
+                    consola escriben en la salida dada (stdout). Un salto a 0x0000 (arranque
+                    en caliente) termina la ejecución. El núcleo solo implementa unos 20
+                    opcodes (ver sim_cpu_opcode), así que los programas de diagnóstico del
+                    8080 (TST8080, 8080PRE, CPUTEST, 8080EXM) todavía no se pueden pasar: se
+                    detienen con error en el primer opcode que falta.
+    Referencias:    CP/M 2.2 Operating System Manual, Digital Research (1979), sección 5
+                    https://altairclone.com/downloads/cpu_tests/
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+use super::sim_cpu_cargador::OpcionesCarga;
+use std::io::Write;
+use std::path::PathBuf;
+
+//***************************************************************************** Notas
+/* Uso
+    let mut maquina = MaquinaCpm::new(std::io::stdout());
+    maquina.carga_archivo_com("TST8080.COM")?;
+    match maquina.ejecuta(1_000_000_000)? {
+        FinCpm::Terminado => println!("\nFin del programa"),
+        FinCpm::Limite => println!("\nLímite de instrucciones"),
+    }
+
+Memoria de 64K en un banco. Página cero:
+    0x0000  JMP 0xFE03      Arranque en caliente: si el PC llega aquí termina la ejecución
+    0x0005  JMP 0xFE00      Entrada del BDOS: si el PC llega aquí se atiende la función C
+    0xFE00  RET             (no se ejecuta: el BDOS devuelve con un RET emulado)
+La pila empieza en 0xFE00 con 0x0000 apilado, así que un RET final también termina. 0x0006-0x0007
+contienen 0xFE00, la cima de la TPA que leen muchos programas para saber la memoria libre.
+
+Funciones BDOS: 0 (reinicio), 2 (carácter de E), 6 (E != 0xFF: carácter de E), 9 (cadena en DE
+terminada en '$'), 11 (estado de consola: nada pendiente), 12 (versión 2.2) y las de ficheros
+de sim_cpu_cpm_ficheros (13-22, 25, 26) si hay un directorio montado como A:. Una función no
+soportada, o un opcode que el simulador no implementa (el PC no avanza), termina con error
+indicando qué falta.
+
+Limitación: el núcleo solo ejecuta NOP, INR/DCR B y A, MVI B/A, LDAX B, LXI SP, STA, LDA, ADD B,
+ADC B, JMP, CALL, RET, RST, DI y EI. Cualquier .COM real (TST8080 incluido) usa otros opcodes y
+termina con "Opcode 0x.. no implementado"; cpm_cmd lo recuerda al arrancar.
+*/
+
+pub const DIRECCION_TPA: u16 = 0x0100;
+pub const ENTRADA_BDOS: u16 = 0x0005;
+pub const DIRECCION_BDOS: u16 = 0xFE00;
+pub const ARRANQUE_CALIENTE: u16 = 0xFE03;
+pub const AVISO_OPCODES: &str = "Aviso: el simulador solo implementa unos 20 opcodes del 8080; los programas de diagnóstico (TST8080, CPUTEST...) se detendrán en el primer opcode que falte";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FinCpm {
+    Terminado,                          // Salto a 0x0000 o función BDOS 0
+    Limite,                             // Se alcanzó el máximo de instrucciones
+}
+
+pub struct MaquinaCpm<W: Write> {
+    pub cpu: CPU,
+    pub salida: W,
+    pub instrucciones: u64,
+    pub directorio: Option<PathBuf>,    // Directorio del anfitrión que hace de unidad A:
+    pub dma: u16,                       // Dirección de transferencia de los registros de 128 bytes
+    pub busqueda: Vec<[u8; 11]>,        // Entradas pendientes de 'search next'
+}
+
+impl<W: Write> MaquinaCpm<W> {
+    pub fn new(salida: W) -> MaquinaCpm<W> {
+        let mut cpu = CPU::new();
+        cpu.memoria.segmento_memoria = vec![vec![0; 0x10000]];
+        cpu.memoria.banco_actual = 0;
+        let mut maquina = MaquinaCpm { cpu, salida, instrucciones: 0, directorio: None, dma: 0x0080, busqueda: Vec::new() };
+        maquina.prepara_pagina_cero();
+        maquina
+    }
+
+    fn prepara_pagina_cero(&mut self) {
+        let memoria = &mut self.cpu.memoria.segmento_memoria[0];
+        memoria[0x0000..0x0003].copy_from_slice(&[0xC3, ARRANQUE_CALIENTE as u8, (ARRANQUE_CALIENTE >> 8) as u8]);
+        memoria[0x0005..0x0008].copy_from_slice(&[0xC3, DIRECCION_BDOS as u8, (DIRECCION_BDOS >> 8) as u8]);
+        memoria[DIRECCION_BDOS as usize] = 0xC9;
+        memoria[ARRANQUE_CALIENTE as usize] = 0xC9;
+        self.cpu.puntero_de_pila = DIRECCION_BDOS;
+        self.cpu.apila(0x0000);
+        self.cpu.contador_de_programa = DIRECCION_TPA;
+    }
+
+    pub fn carga_com(&mut self, programa: &[u8]) -> Result<(), String> {
+        if programa.len() > (DIRECCION_BDOS - DIRECCION_TPA) as usize {
+            return Err(format!("El programa ({} bytes) no cabe en la TPA (0x{:04X}-0x{:04X})", programa.len(), DIRECCION_TPA, DIRECCION_BDOS - 1));
+        }
+        let mut opciones = OpcionesCarga::new();
+        opciones.direccion = Some(DIRECCION_TPA);
+        opciones.entrada = Some(DIRECCION_TPA);
+        self.cpu.cargar_binario(programa, &opciones)
+    }
+
+    pub fn carga_archivo_com(&mut self, ruta: &str) -> Result<(), String> {
+        let datos = std::fs::read(ruta).map_err(|e| format!("No se puede leer '{}': {}", ruta, e))?;
+        self.carga_com(&datos).map_err(|e| format!("{}: {}", ruta, e))
+    }
+
+    //************************************* Ejecución
+    pub fn ejecuta(&mut self, max_instrucciones: u64) -> Result<FinCpm, String> {
+        for _ in 0..max_instrucciones {
+            match self.cpu.contador_de_programa {
+                0x0000 => return Ok(FinCpm::Terminado),
+                ENTRADA_BDOS => {
+                    if self.bdos()? { return Ok(FinCpm::Terminado); }
+                    self.cpu.contador_de_programa = self.cpu.desapila();
+                    continue;
+                }
+                _ => {}
+            }
+            let contador = self.cpu.contador_de_programa;
+            let (opcode, _) = self.cpu.ejecuta_paso();
+            self.instrucciones += 1;
+            // Las instrucciones no implementadas no mueven el PC; JMP a sí mismo sí es válido
+            if self.cpu.contador_de_programa == contador && opcode != 0xC3 {
+                return Err(format!("Opcode 0x{:02X} no implementado en 0x{:04X} (tras {} instrucciones)", opcode, contador, self.instrucciones));
+            }
+        }
+        Ok(FinCpm::Limite)
+    }
+
+    // Atiende la función del registro C; el resultado va en A y en HL (L = A, H = B = 0).
+    // Devuelve true si el programa termina.
+    fn bdos(&mut self) -> Result<bool, String> {
+        let funcion = self.cpu.reg_c;
+        let resultado: u8 = match funcion {
+            0 => return Ok(true),
+            2 => { self.escribe(&[self.cpu.reg_e])?; 0 }
+            6 if self.cpu.reg_e != 0xFF => { self.escribe(&[self.cpu.reg_e])?; 0 }
+            6 => 0,                     // Entrada directa: no hay tecla
+            9 => {
+                let mut direccion = self.cpu.get_de();
+                let mut texto = Vec::new();
+                loop {
+                    let c = self.cpu.memoria.leer_memoria(direccion);
+                    if c == b'$' { break; }
+                    texto.push(c);
+                    direccion = direccion.wrapping_add(1);
+                    if direccion == self.cpu.get_de() { return Err(String::from("BDOS 9: cadena sin '$'")); }
+                }
+                self.escribe(&texto)?;
+                0
+            }
+            11 => 0,
+            12 => 0x22,
+            13..=22 | 25 | 26 => self.bdos_ficheros(funcion)?,
+            _ => {
+                // El CALL que llamó al BDOS está 3 bytes antes de la dirección de retorno apilada
+                let sp = self.cpu.puntero_de_pila;
+                let retorno = u16::from_le_bytes([self.cpu.memoria.leer_memoria(sp), self.cpu.memoria.leer_memoria(sp.wrapping_add(1))]);
+                return Err(format!("Función BDOS {} no soportada (llamada desde 0x{:04X})", funcion, retorno.wrapping_sub(3)));
+            }
+        };
+        self.cpu.reg_a = resultado;
+        self.cpu.reg_b = 0;
+        self.cpu.set_hl(resultado as u16);
+        Ok(false)
+    }
+
+    // Llamada al BDOS desde Rust (pruebas y herramientas): devuelve A
+    pub fn llama_bdos(&mut self, funcion: u8, de: u16) -> Result<u8, String> {
+        self.cpu.reg_c = funcion;
+        self.cpu.set_de(de);
+        self.bdos()?;
+        Ok(self.cpu.reg_a)
+    }
+
+    fn escribe(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.salida.write_all(bytes).and_then(|_| self.salida.flush()).map_err(|e| format!("Error de salida: {}", e))
+    }
+}
+
+//*****************************************************************************
+// Ejecuta el .COM indicado como primer argumento con el directorio actual como unidad A:; el
+// resto de argumentos es la línea de órdenes del programa
+pub fn cpm_cmd(argumentos: &[String]) {
+    let Some(ruta) = argumentos.first() else {
+        println!("Uso: cpm <programa.com> [argumentos]");
+        return;
+    };
+    println!("{}", AVISO_OPCODES);
+    let mut maquina = MaquinaCpm::new(std::io::stdout());
+    maquina.monta_directorio(".");
+    maquina.pone_argumentos(&argumentos[1..].join(" "));
+    let resultado = maquina.carga_archivo_com(ruta).and_then(|_| maquina.ejecuta(u64::MAX));
+    match resultado {
+        Ok(FinCpm::Terminado) => println!("\nFin del programa ({} instrucciones, {} ciclos)", maquina.instrucciones, maquina.cpu.ciclos),
+        Ok(FinCpm::Limite) => println!("\nLímite de instrucciones alcanzado"),
+        Err(error) => println!("\nError: {}", error),
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asm8080::asm8080;
+
+    #[test]
+    fn test_cadena_y_arranque_en_caliente() {
+        let mut maquina = MaquinaCpm::new(Vec::new());
+        let mut programa = asm8080! {
+            call 5              // C = 9, DE = 0x0110
+            mvi a, 'A'
+            ret                 // Vuelve a 0x0000
+        };
+        programa.resize(0x10, 0);
+        programa.extend_from_slice(b"Hola CP/M\r\n$");
+        maquina.carga_com(&programa).unwrap();
+        maquina.cpu.reg_c = 9;
+        maquina.cpu.set_de(0x0110);
+        assert_eq!(maquina.ejecuta(100), Ok(FinCpm::Terminado));
+        assert_eq!(String::from_utf8(maquina.salida.clone()).unwrap(), "Hola CP/M\r\n");
+        assert_eq!(maquina.instrucciones, 3);
+        assert_eq!(maquina.cpu.reg_a, 0x41);
+        assert_eq!(maquina.cpu.memoria.leer_memoria(0x0007), 0xFE);
+    }
+
+    #[test]
+    fn test_caracter_y_errores() {
+        let mut maquina = MaquinaCpm::new(Vec::new());
+        maquina.carga_com(&asm8080! { call 5; hlt }).unwrap();
+        maquina.cpu.reg_c = 2;
+        maquina.cpu.reg_e = b'*';
+        assert_eq!(maquina.ejecuta(100).err().unwrap(), "Opcode 0x76 no implementado en 0x0103 (tras 2 instrucciones)");
+        assert_eq!(maquina.salida, b"*");
+
+        let mut maquina = MaquinaCpm::new(Vec::new());
+        maquina.carga_com(&asm8080! { call 5 }).unwrap();
+        maquina.cpu.reg_c = 99;
+        assert_eq!(maquina.ejecuta(100).err().unwrap(), "Función BDOS 99 no soportada (llamada desde 0x0100)");
+        assert!(maquina.carga_com(&vec![0; 0xFE00]).is_err());
+    }
+}
+
+//*****************************************************************************
@@ -0,0 +1,498 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Máquina CP/M 2.2 con BIOS emulada - Simulación CPU
+    Descripción:    Emula la BIOS de CP/M 2.2: consola sobre el terminal del anfitrión y hasta
+                    cuatro unidades de disquete de 8" con formato IBM 3740 guardadas en
+                    archivos de imagen (SELDSK, SETTRK, SETSEC, SETDMA, READ, WRITE, SECTRAN).
+                    El arranque lee las pistas de sistema (CCP y BDOS) en BancosMemoria y
+                    salta al CCP, que se ejecuta en el simulador. Como el núcleo solo
+                    implementa unos 20 opcodes, el CCP de Digital Research todavía no arranca:
+                    se detiene con error en su primer PUSH. Lo que sí funciona es la BIOS.
+    Referencias:    CP/M 2.2 Alteration Guide, Digital Research (1979), secciones 6 y 10
+                    IBM 3740: 77 pistas, 26 sectores de 128 bytes, 2 pistas de sistema
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+use super::sim_cpu_cpm::{FinCpm, AVISO_OPCODES};
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{BufRead, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+//***************************************************************************** Notas
+/* Uso
+    let mut configuracion = ConfiguracionCpm::new();        // 64K: CCP E400, BDOS EC00, BIOS FA00
+    configuracion.discos[0] = Some(Disco::desde_archivo("cpm22.img")?);
+    configuracion.discos[1] = Some(Disco::formateado(GEOMETRIA_IBM_3740));
+    let entrada = std::io::stdin().lock();
+    let mut sistema = SistemaCpm::new(configuracion, entrada, std::io::stdout());
+    sistema.arranca()?;                                     // Lee las pistas de sistema
+    sistema.ejecuta(u64::MAX)?;                             // Termina al agotarse la entrada
+
+La BIOS ocupa la tabla de saltos estándar en 'bios' (17 entradas de 3 bytes: BOOT, WBOOT, CONST,
+CONIN, CONOUT, LIST, PUNCH, READER, HOME, SELDSK, SETTRK, SETSEC, SETDMA, READ, WRITE, LISTST,
+SECTRAN). Cuando el PC llega a una entrada se atiende en Rust y se vuelve con un RET emulado.
+Detrás de la tabla están la tabla de traducción de sectores (salto 6), el DPB común y para cada
+unidad su DPH, su vector de comprobación y su mapa de asignación.
+
+Arranque: se leen 'sectores_sistema' sectores seguidos desde la pista 0, sector 2 (el sector 1 es
+el cargador de la máquina real) en la dirección del CCP. La página cero queda con JMP WBOOT en
+0x0000 y JMP BDOS+6 en 0x0005, y se entra en el CCP con C = unidad actual.
+
+Consola: CONIN lee una línea completa de la entrada cuando no hay nada pendiente ('\n' pasa a ser
+'\r') y CONST indica si queda algo pendiente. Al agotarse la entrada la ejecución termina. LIST y
+PUNCH se descartan y READER devuelve 1AH (fin de texto).
+
+Limitación: el CCP y el BDOS reales usan opcodes que el simulador no implementa (PUSH, LXI H,
+MOV...), así que con un disco de CP/M 2.2 la ejecución termina con "Opcode 0x.. no implementado"
+poco después de entrar en el CCP. Las pruebas usan un "sistema" escrito solo con los opcodes
+implementados para comprobar el arranque y la BIOS.
+
+Las imágenes de disco son la concatenación de las pistas y sectores físicos, sin cabeceras (el
+formato .IMG habitual de los emuladores). Un disco con archivo escribe cada sector en él.
+*/
+
+//***************************************************************************** Discos
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeometriaDisco {
+    pub pistas: u16,
+    pub sectores: u16,                  // Por pista, numerados desde 1
+    pub tamano_sector: usize,
+    pub pistas_reservadas: u16,
+    pub salto: u16,                     // Factor de entrelazado de la tabla de traducción
+}
+
+pub const GEOMETRIA_IBM_3740: GeometriaDisco = GeometriaDisco { pistas: 77, sectores: 26, tamano_sector: 128, pistas_reservadas: 2, salto: 6 };
+
+impl GeometriaDisco {
+    pub fn tamano(&self) -> usize {
+        self.pistas as usize * self.sectores as usize * self.tamano_sector
+    }
+
+    // Sector físico (desde 1) de cada sector lógico (desde 0)
+    pub fn tabla_traduccion(&self) -> Vec<u8> {
+        let mut tabla = Vec::new();
+        let mut usados = vec![false; self.sectores as usize];
+        let mut sector = 0usize;
+        for _ in 0..self.sectores {
+            while usados[sector] { sector = (sector + 1) % self.sectores as usize; }
+            usados[sector] = true;
+            tabla.push(sector as u8 + 1);
+            sector = (sector + self.salto as usize) % self.sectores as usize;
+        }
+        tabla
+    }
+}
+
+pub struct Disco {
+    pub geometria: GeometriaDisco,
+    pub datos: Vec<u8>,
+    pub ruta: Option<PathBuf>,          // Si hay archivo, cada escritura se guarda en él
+}
+
+impl Disco {
+    // Disco recién formateado: todos los bytes a E5H (directorio vacío)
+    pub fn formateado(geometria: GeometriaDisco) -> Disco {
+        Disco { geometria, datos: vec![0xE5; geometria.tamano()], ruta: None }
+    }
+
+    pub fn desde_archivo<P: AsRef<Path>>(ruta: P) -> Result<Disco, String> {
+        let ruta = ruta.as_ref();
+        let mut datos = std::fs::read(ruta).map_err(|e| format!("No se puede leer '{}': {}", ruta.display(), e))?;
+        let geometria = GEOMETRIA_IBM_3740;
+        if datos.len() > geometria.tamano() {
+            return Err(format!("'{}': {} bytes, una imagen IBM 3740 tiene {}", ruta.display(), datos.len(), geometria.tamano()));
+        }
+        datos.resize(geometria.tamano(), 0xE5);         // Imágenes recortadas al último sector usado
+        Ok(Disco { geometria, datos, ruta: Some(ruta.to_path_buf()) })
+    }
+
+    fn posicion(&self, pista: u16, sector: u16) -> Option<usize> {
+        if pista >= self.geometria.pistas || sector == 0 || sector > self.geometria.sectores { return None; }
+        Some((pista as usize * self.geometria.sectores as usize + sector as usize - 1) * self.geometria.tamano_sector)
+    }
+
+    pub fn lee_sector(&self, pista: u16, sector: u16) -> Option<&[u8]> {
+        let inicio = self.posicion(pista, sector)?;
+        Some(&self.datos[inicio..inicio + self.geometria.tamano_sector])
+    }
+
+    pub fn escribe_sector(&mut self, pista: u16, sector: u16, datos: &[u8]) -> Result<(), String> {
+        let inicio = self.posicion(pista, sector).ok_or(format!("Pista {} sector {} fuera del disco", pista, sector))?;
+        let tamano = self.geometria.tamano_sector;
+        self.datos[inicio..inicio + tamano].copy_from_slice(&datos[..tamano]);
+        if let Some(ruta) = self.ruta.as_ref() {
+            OpenOptions::new().write(true).create(true).truncate(false).open(ruta)
+                .and_then(|mut f| { f.seek(SeekFrom::Start(inicio as u64))?; f.write_all(&datos[..tamano]) })
+                .map_err(|e| format!("'{}': {}", ruta.display(), e))?;
+        }
+        Ok(())
+    }
+
+    // Copia el CCP y el BDOS a las pistas reservadas desde la pista 0, sector 2
+    pub fn instala_sistema(&mut self, sistema: &[u8]) -> Result<(), String> {
+        let tamano = self.geometria.tamano_sector;
+        let mut sector = vec![0u8; tamano];
+        for (i, trozo) in sistema.chunks(tamano).enumerate() {
+            let (pista, numero) = posicion_sistema(&self.geometria, i);
+            if pista >= self.geometria.pistas_reservadas { return Err(format!("El sistema ({} bytes) no cabe en las pistas reservadas", sistema.len())); }
+            sector.fill(0);
+            sector[..trozo.len()].copy_from_slice(trozo);
+            self.escribe_sector(pista, numero, &sector)?;
+        }
+        Ok(())
+    }
+}
+
+// Pista y sector físico del sector i-ésimo del sistema (el primero es la pista 0, sector 2)
+fn posicion_sistema(geometria: &GeometriaDisco, i: usize) -> (u16, u16) {
+    let lineal = i + 1;
+    ((lineal / geometria.sectores as usize) as u16, (lineal % geometria.sectores as usize) as u16 + 1)
+}
+
+//***************************************************************************** Configuración
+pub struct ConfiguracionCpm {
+    pub ccp: u16,
+    pub bdos: u16,
+    pub bios: u16,
+    pub sectores_sistema: usize,        // Sectores de CCP + BDOS que se cargan al arrancar
+    pub discos: [Option<Disco>; 4],     // A: B: C: D:
+}
+
+impl ConfiguracionCpm {
+    // Sistema de 64K estándar
+    pub fn new() -> ConfiguracionCpm {
+        ConfiguracionCpm { ccp: 0xE400, bdos: 0xEC00, bios: 0xFA00, sectores_sistema: 44, discos: [None, None, None, None] }
+    }
+}
+
+impl Default for ConfiguracionCpm {
+    fn default() -> Self { ConfiguracionCpm::new() }
+}
+
+//***************************************************************************** BIOS
+pub const FUNCIONES_BIOS: u16 = 17;
+const DESPLAZAMIENTO_DATOS: u16 = 0x40;         // Tablas de la BIOS detrás de la tabla de saltos
+
+pub struct SistemaCpm<R: BufRead, W: Write> {
+    pub cpu: CPU,
+    pub configuracion: ConfiguracionCpm,
+    pub entrada: R,
+    pub salida: W,
+    pub instrucciones: u64,
+    pub pendiente: VecDeque<u8>,        // Entrada de consola leída y aún no consumida
+    pub disco: u8,
+    pub pista: u16,
+    pub sector: u16,
+    pub dma: u16,
+}
+
+impl<R: BufRead, W: Write> SistemaCpm<R, W> {
+    pub fn new(configuracion: ConfiguracionCpm, entrada: R, salida: W) -> SistemaCpm<R, W> {
+        let mut cpu = CPU::new();
+        cpu.memoria.segmento_memoria = vec![vec![0; 0x10000]];
+        cpu.memoria.banco_actual = 0;
+        let mut sistema = SistemaCpm { cpu, configuracion, entrada, salida, instrucciones: 0, pendiente: VecDeque::new(), disco: 0, pista: 0, sector: 1, dma: 0x0080 };
+        sistema.prepara_bios();
+        sistema
+    }
+
+    //************************************* Tablas en memoria
+    fn direccion_xlt(&self) -> u16 { self.configuracion.bios + DESPLAZAMIENTO_DATOS }
+    fn direccion_dpb(&self) -> u16 { self.direccion_xlt() + 32 }
+    fn direccion_dirbuf(&self) -> u16 { self.direccion_dpb() + 16 }
+    fn direccion_dph(&self, unidad: u8) -> u16 { self.direccion_dirbuf() + 128 + unidad as u16 * 64 }
+
+    fn escribe(&mut self, direccion: u16, datos: &[u8]) {
+        let inicio = direccion as usize;
+        self.cpu.memoria.segmento_memoria[0][inicio..inicio + datos.len()].copy_from_slice(datos);
+    }
+
+    // Tabla de saltos (un RET por entrada: nunca se ejecuta), XLT, DPB y un DPH por unidad
+    fn prepara_bios(&mut self) {
+        let bios = self.configuracion.bios;
+        for i in 0..FUNCIONES_BIOS { self.escribe(bios + i * 3, &[0xC9, 0x00, 0x00]); }
+        let geometria = GEOMETRIA_IBM_3740;
+        self.escribe(self.direccion_xlt(), &geometria.tabla_traduccion());
+        // SPT 26, BSH 3, BLM 7, EXM 0, DSM 242, DRM 63, AL0 C0H, AL1 0, CKS 16, OFF 2
+        let mut dpb = Vec::new();
+        dpb.extend_from_slice(&geometria.sectores.to_le_bytes());
+        dpb.extend_from_slice(&[3, 7, 0]);
+        dpb.extend_from_slice(&242u16.to_le_bytes());
+        dpb.extend_from_slice(&63u16.to_le_bytes());
+        dpb.extend_from_slice(&[0xC0, 0x00]);
+        dpb.extend_from_slice(&16u16.to_le_bytes());
+        dpb.extend_from_slice(&geometria.pistas_reservadas.to_le_bytes());
+        self.escribe(self.direccion_dpb(), &dpb);
+        for unidad in 0..4 {
+            // DPH: XLT, 3 palabras del BDOS, DIRBUF, DPB, CSV, ALV
+            let dph = self.direccion_dph(unidad);
+            let (csv, alv) = (dph + 16, dph + 32);
+            let mut datos = Vec::new();
+            for palabra in [self.direccion_xlt(), 0, 0, 0, self.direccion_dirbuf(), self.direccion_dpb(), csv, alv] {
+                datos.extend_from_slice(&palabra.to_le_bytes());
+            }
+            self.escribe(dph, &datos);
+        }
+    }
+
+    //************************************* Arranque
+    // Carga CCP y BDOS desde las pistas de sistema del disco A: y prepara la página cero
+    fn carga_sistema(&mut self) -> Result<(), String> {
+        let disco = self.configuracion.discos[0].as_ref().ok_or(String::from("No hay disco en A: para arrancar"))?;
+        let tamano = disco.geometria.tamano_sector;
+        let mut sistema = Vec::new();
+        for i in 0..self.configuracion.sectores_sistema {
+            let (pista, sector) = posicion_sistema(&disco.geometria, i);
+            let datos = disco.lee_sector(pista, sector).ok_or(format!("Pista {} sector {}: fuera del disco", pista, sector))?;
+            sistema.extend_from_slice(datos);
+        }
+        if self.configuracion.ccp as usize + sistema.len() > self.configuracion.bios as usize {
+            return Err(format!("El sistema ({} bytes desde 0x{:04X}) pisa la BIOS en 0x{:04X}", sistema.len(), self.configuracion.ccp, self.configuracion.bios));
+        }
+        self.escribe(self.configuracion.ccp, &sistema);
+        let (wboot, bdos) = (self.configuracion.bios + 3, self.configuracion.bdos + 6);
+        self.escribe(0x0000, &[0xC3, wboot as u8, (wboot >> 8) as u8]);
+        self.escribe(0x0005, &[0xC3, bdos as u8, (bdos >> 8) as u8]);
+        self.dma = 0x0080;
+        self.cpu.puntero_de_pila = 0x0100;
+        self.cpu.reg_c = self.cpu.memoria.leer_memoria(0x0004);
+        Ok(())
+    }
+
+    // Arranque en frío: unidad A:, usuario 0 y entrada en el CCP
+    pub fn arranca(&mut self) -> Result<(), String> {
+        self.escribe(0x0003, &[0x00, 0x00]);            // IOBYTE y unidad actual
+        self.carga_sistema()?;
+        self.cpu.contador_de_programa = self.configuracion.ccp;
+        Ok(())
+    }
+
+    //************************************* Ejecución
+    pub fn ejecuta(&mut self, max_instrucciones: u64) -> Result<FinCpm, String> {
+        let bios = self.configuracion.bios;
+        for _ in 0..max_instrucciones {
+            let contador = self.cpu.contador_de_programa;
+            if contador >= bios && contador < bios + FUNCIONES_BIOS * 3 && (contador - bios).is_multiple_of(3) {
+                if self.bios(((contador - bios) / 3) as u8)? { return Ok(FinCpm::Terminado); }
+                continue;
+            }
+            let (opcode, _) = self.cpu.ejecuta_paso();
+            self.instrucciones += 1;
+            if self.cpu.contador_de_programa == contador && opcode != 0xC3 {
+                return Err(format!("Opcode 0x{:02X} no implementado en 0x{:04X} (tras {} instrucciones)", opcode, contador, self.instrucciones));
+            }
+        }
+        Ok(FinCpm::Limite)
+    }
+
+    // Atiende la función n de la BIOS. Devuelve true si la consola se ha quedado sin entrada.
+    fn bios(&mut self, funcion: u8) -> Result<bool, String> {
+        let bc = self.cpu.get_bc();
+        match funcion {
+            0 => { return self.arranca().map(|_| false); }
+            1 => {
+                self.carga_sistema()?;
+                self.cpu.contador_de_programa = self.configuracion.ccp + 3;
+                return Ok(false);
+            }
+            2 => self.cpu.reg_a = if self.pendiente.is_empty() { 0x00 } else { 0xFF },
+            3 => match self.lee_consola()? {
+                Some(c) => self.cpu.reg_a = c,
+                None => return Ok(true),
+            },
+            4 => self.salida.write_all(&[self.cpu.reg_c]).and_then(|_| self.salida.flush()).map_err(|e| format!("Error de consola: {}", e))?,
+            5 | 6 => {}
+            7 => self.cpu.reg_a = 0x1A,
+            8 => self.pista = 0,
+            9 => {
+                let unidad = self.cpu.reg_c;
+                let montado = self.configuracion.discos.get(unidad as usize).is_some_and(|d| d.is_some());
+                if montado { self.disco = unidad; }
+                self.cpu.set_hl(if montado { self.direccion_dph(unidad) } else { 0 });
+            }
+            10 => self.pista = bc,
+            11 => self.sector = bc,
+            12 => self.dma = bc,
+            13 => self.cpu.reg_a = self.lee_disco(),
+            14 => self.cpu.reg_a = self.escribe_disco(),
+            15 => self.cpu.reg_a = 0xFF,
+            16 => {
+                let xlt = self.cpu.get_de();
+                let fisico = if xlt == 0 { bc + 1 } else { self.cpu.memoria.leer_memoria(xlt.wrapping_add(bc)) as u16 };
+                self.cpu.set_hl(fisico);
+            }
+            _ => return Err(format!("Función de BIOS {} no existe", funcion)),
+        }
+        self.cpu.contador_de_programa = self.cpu.desapila();
+        Ok(false)
+    }
+
+    // Llamada a la BIOS desde Rust (pruebas y herramientas): devuelve A y HL
+    pub fn llama_bios(&mut self, funcion: u8, bc: u16, de: u16) -> Result<(u8, u16), String> {
+        self.cpu.set_bc(bc);
+        self.cpu.set_de(de);
+        self.cpu.apila(self.cpu.contador_de_programa);
+        self.bios(funcion)?;
+        Ok((self.cpu.reg_a, self.cpu.get_hl()))
+    }
+
+    fn lee_consola(&mut self) -> Result<Option<u8>, String> {
+        if self.pendiente.is_empty() {
+            let mut linea = String::new();
+            let leidos = self.entrada.read_line(&mut linea).map_err(|e| format!("Error de consola: {}", e))?;
+            if leidos == 0 { return Ok(None); }
+            self.pendiente.extend(linea.bytes().filter(|c| *c != b'\r').map(|c| if c == b'\n' { b'\r' } else { c }));
+        }
+        Ok(self.pendiente.pop_front())
+    }
+
+    // 0: correcto, 1: error (sin disco o pista/sector fuera de rango)
+    fn lee_disco(&mut self) -> u8 {
+        let Some(disco) = self.configuracion.discos[self.disco as usize].as_ref() else { return 1 };
+        let Some(datos) = disco.lee_sector(self.pista, self.sector).map(|d| d.to_vec()) else { return 1 };
+        if self.dma as usize + datos.len() > 0x10000 { return 1; }
+        self.escribe(self.dma, &datos);
+        0
+    }
+
+    fn escribe_disco(&mut self) -> u8 {
+        let (pista, sector, dma) = (self.pista, self.sector, self.dma as usize);
+        let Some(disco) = self.configuracion.discos[self.disco as usize].as_mut() else { return 1 };
+        let tamano = disco.geometria.tamano_sector;
+        if dma + tamano > 0x10000 { return 1; }
+        let datos = self.cpu.memoria.segmento_memoria[0][dma..dma + tamano].to_vec();
+        if disco.escribe_sector(pista, sector, &datos).is_ok() { 0 } else { 1 }
+    }
+}
+
+//*****************************************************************************
+// Arranca CP/M desde las imágenes de disco dadas como argumentos (A:, B:, ...)
+pub fn cpm22_cmd(argumentos: &[String]) {
+    if argumentos.is_empty() {
+        println!("Uso: cpm22 <disco_a.img> [disco_b.img ...]");
+        return;
+    }
+    println!("{}", AVISO_OPCODES);
+    let mut configuracion = ConfiguracionCpm::new();
+    for (unidad, ruta) in argumentos.iter().take(4).enumerate() {
+        match Disco::desde_archivo(ruta) {
+            Ok(disco) => configuracion.discos[unidad] = Some(disco),
+            Err(error) => { println!("Error: {}", error); return; }
+        }
+    }
+    let mut sistema = SistemaCpm::new(configuracion, std::io::stdin().lock(), std::io::stdout());
+    match sistema.arranca().and_then(|_| sistema.ejecuta(u64::MAX)) {
+        Ok(_) => println!("\nFin de la sesión ({} instrucciones)", sistema.instrucciones),
+        Err(error) => println!("\nError: {}", error),
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asm8080::asm8080;
+
+    fn sistema_de_prueba(entrada: &str) -> SistemaCpm<&[u8], Vec<u8>> {
+        // "Sistema" de 44 sectores: cada sector lleno con su número
+        let imagen: Vec<u8> = (0..44u8).flat_map(|i| [i; 128]).collect();
+        sistema_con_imagen(&imagen, entrada)
+    }
+
+    fn sistema_con_imagen<'a>(imagen: &[u8], entrada: &'a str) -> SistemaCpm<&'a [u8], Vec<u8>> {
+        let mut disco = Disco::formateado(GEOMETRIA_IBM_3740);
+        disco.instala_sistema(imagen).unwrap();
+        let mut configuracion = ConfiguracionCpm::new();
+        configuracion.discos[0] = Some(disco);
+        SistemaCpm::new(configuracion, entrada.as_bytes(), Vec::new())
+    }
+
+    #[test]
+    fn test_arranque_y_geometria() {
+        let tabla = GEOMETRIA_IBM_3740.tabla_traduccion();
+        assert_eq!(&tabla[..8], &[1, 7, 13, 19, 25, 5, 11, 17]);
+        assert_eq!(tabla.len(), 26);
+        assert_eq!(GEOMETRIA_IBM_3740.tamano(), 256_256);
+
+        let mut sistema = sistema_de_prueba("");
+        sistema.arranca().unwrap();
+        assert_eq!(sistema.cpu.contador_de_programa, 0xE400);
+        // El sector 25 del sistema es la pista 1, sector 1
+        assert_eq!(sistema.configuracion.discos[0].as_ref().unwrap().lee_sector(1, 1).unwrap()[0], 25);
+        assert_eq!(sistema.cpu.memoria.leer_memoria(0xE400 + 25 * 128), 25);
+        assert_eq!(sistema.cpu.memoria.leer_memoria(0xE400 + 44 * 128 - 1), 43);
+        assert_eq!(&sistema.cpu.memoria.segmento_memoria[0][0..3], &[0xC3, 0x03, 0xFA]);
+        assert_eq!(&sistema.cpu.memoria.segmento_memoria[0][5..8], &[0xC3, 0x06, 0xEC]);
+
+        // SELDSK: A: tiene DPH con la XLT y el DPB de IBM 3740; B: no está montada
+        let (_, dph) = sistema.llama_bios(9, 0, 0).unwrap();
+        assert_ne!(dph, 0);
+        let palabra = |s: &SistemaCpm<&[u8], Vec<u8>>, d: u16| u16::from_le_bytes([s.cpu.memoria.leer_memoria(d), s.cpu.memoria.leer_memoria(d + 1)]);
+        let (xlt, dpb) = (palabra(&sistema, dph), palabra(&sistema, dph + 10));
+        assert_eq!(palabra(&sistema, dpb), 26);
+        assert_eq!(palabra(&sistema, dpb + 5), 242);
+        assert_eq!(palabra(&sistema, dpb + 13), 2);
+        assert_eq!(sistema.llama_bios(16, 1, xlt).unwrap().1, 7);
+        assert_eq!(sistema.llama_bios(9, 1, 0).unwrap().1, 0);
+    }
+
+    #[test]
+    fn test_consola_y_sectores() {
+        let mut sistema = sistema_de_prueba("dir\n");
+        sistema.arranca().unwrap();
+        assert_eq!(sistema.llama_bios(2, 0, 0).unwrap().0, 0x00);
+        assert_eq!(sistema.llama_bios(3, 0, 0).unwrap().0, b'd');
+        assert_eq!(sistema.llama_bios(2, 0, 0).unwrap().0, 0xFF);
+        let resto: Vec<u8> = (0..3).map(|_| sistema.llama_bios(3, 0, 0).unwrap().0).collect();
+        assert_eq!(resto, b"ir\r");
+        sistema.llama_bios(4, b'A' as u16, 0).unwrap();
+        assert_eq!(sistema.salida, b"A");
+        // Sin más entrada la máquina termina
+        sistema.cpu.contador_de_programa = 0xFA00 + 3 * 3;
+        assert_eq!(sistema.ejecuta(10), Ok(FinCpm::Terminado));
+
+        // Escribe un sector desde 0x1000 y lo vuelve a leer en 0x2000
+        for i in 0..128u16 { sistema.cpu.memoria.segmento_memoria[0][0x1000 + i as usize] = i as u8; }
+        sistema.llama_bios(9, 0, 0).unwrap();
+        sistema.llama_bios(10, 40, 0).unwrap();
+        sistema.llama_bios(11, 26, 0).unwrap();
+        sistema.llama_bios(12, 0x1000, 0).unwrap();
+        assert_eq!(sistema.llama_bios(14, 0, 0).unwrap().0, 0);
+        sistema.llama_bios(12, 0x2000, 0).unwrap();
+        assert_eq!(sistema.llama_bios(13, 0, 0).unwrap().0, 0);
+        assert_eq!(sistema.cpu.memoria.leer_memoria(0x2000 + 127), 127);
+        sistema.llama_bios(11, 27, 0).unwrap();
+        assert_eq!(sistema.llama_bios(13, 0, 0).unwrap().0, 1);
+    }
+
+    #[test]
+    fn test_lo_que_se_ejecuta_al_arrancar() {
+        // Un CCP hecho solo con opcodes implementados: lee la consola por la BIOS hasta agotarla
+        let mut imagen = asm8080! {
+            call 0FA09h         // CONIN        <- 0xE400
+            sta 100h
+            jmp 0E400h
+        };
+        imagen.resize(44 * 128, 0);
+        let mut sistema = sistema_con_imagen(&imagen, "ab\n");
+        sistema.arranca().unwrap();
+        assert_eq!(sistema.ejecuta(100), Ok(FinCpm::Terminado));
+        assert_eq!(sistema.instrucciones, 3 * 3 + 1);
+        assert_eq!(sistema.cpu.memoria.leer_memoria(0x0100), b'\r');
+
+        // El CCP de CP/M 2.2 empieza con JMP a su inicio, LXI SP y PUSH B: se para en el PUSH
+        let mut imagen = vec![0u8; 44 * 128];
+        imagen[0..3].copy_from_slice(&[0xC3, 0x5C, 0xE7]);
+        imagen[0x35C..0x360].copy_from_slice(&[0x31, 0xAB, 0xE7, 0xC5]);
+        let mut sistema = sistema_con_imagen(&imagen, "");
+        sistema.arranca().unwrap();
+        assert_eq!(sistema.ejecuta(100).err().unwrap(), "Opcode 0xC5 no implementado en 0xE75F (tras 3 instrucciones)");
+    }
+}
+
+//*****************************************************************************
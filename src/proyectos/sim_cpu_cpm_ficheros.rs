@@ -0,0 +1,380 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Ficheros CP/M en un directorio del anfitrión - Simulación CPU
+    Descripción:    Funciones de ficheros del BDOS (abrir, cerrar, buscar, borrar, leer y
+                    escribir secuencial, crear, DMA) sobre los FCB del programa. Los ficheros
+                    son los de un directorio del anfitrión que hace de unidad A:.
+    Referencias:    CP/M 2.2 Operating System Manual, Digital Research (1979), secciones 5.2-5.3
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_cpm::MaquinaCpm;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+//***************************************************************************** Notas
+/* Uso
+    let mut maquina = MaquinaCpm::new(std::io::stdout());
+    maquina.monta_directorio("/tmp/cpm");       // A: = /tmp/cpm
+    maquina.pone_argumentos("ENTRADA.TXT SALIDA.TXT");  // Cola en 0x0080 y FCB en 0x005C y 0x006C
+
+FCB (36 bytes en DE):
+    0       unidad (0: actual, 1: A:)       12  EX  extensión (bloques de 16K)
+    1-8     nombre (espacios al final)      14  S2  módulo (bloques de 512K)
+    9-11    tipo (el bit 7 son atributos)   15  RC  registros de la extensión abierta
+    32      CR  registro actual en la extensión
+
+No se guarda estado por fichero abierto: cada lectura o escritura abre el fichero del anfitrión y
+se coloca en el registro (S2 * 32 + EX) * 128 + CR. Sólo son visibles los ficheros con nombre
+8.3 válido; los nombres se comparan en mayúsculas. Al leer, el último registro se rellena con 1AH
+(fin de texto de CP/M). 'search first/next' devuelve una entrada de directorio por fichero en el
+DMA (código 0) aunque ocupe varias extensiones. 'make' falla (0xFF) si el nombre del FCB no es 8.3
+o si el fichero ya existe: nunca crea fuera del directorio ni trunca un fichero.
+
+Valores de A: 0 correcto (o código de directorio), 0xFF fichero no encontrado o sin directorio,
+1 fin de fichero (lectura), 2 disco lleno (escritura fallida).
+*/
+
+const TAMANO_REGISTRO: usize = 128;
+const FIN_TEXTO: u8 = 0x1A;
+
+// Nombre 8.3 del anfitrión en el formato de 11 caracteres del FCB; None si no es válido
+fn nombre_cpm(nombre: &str) -> Option<[u8; 11]> {
+    let (base, tipo) = nombre.rsplit_once('.').unwrap_or((nombre, ""));
+    let valido = |c: char| c.is_ascii_graphic() && !"<>.,;:=?*[]/\\".contains(c);
+    if base.is_empty() || base.len() > 8 || tipo.len() > 3 || !base.chars().chain(tipo.chars()).all(valido) { return None; }
+    let mut resultado = [b' '; 11];
+    for (i, c) in base.bytes().enumerate() { resultado[i] = c.to_ascii_uppercase(); }
+    for (i, c) in tipo.bytes().enumerate() { resultado[8 + i] = c.to_ascii_uppercase(); }
+    Some(resultado)
+}
+
+fn nombre_anfitrion(nombre: &[u8; 11]) -> String {
+    let base = String::from_utf8_lossy(&nombre[..8]).trim_end().to_string();
+    let tipo = String::from_utf8_lossy(&nombre[8..]).trim_end().to_string();
+    if tipo.is_empty() { base } else { format!("{}.{}", base, tipo) }
+}
+
+// '?' en el patrón vale cualquier carácter
+fn coincide(patron: &[u8; 11], nombre: &[u8; 11]) -> bool {
+    patron.iter().zip(nombre.iter()).all(|(p, n)| *p == b'?' || p == n)
+}
+
+// Nombre y tipo de un texto "NOMBRE.TIP" (con '*' para el resto del campo) en formato FCB
+fn patron_desde_texto(texto: &str) -> [u8; 11] {
+    let mut resultado = [b' '; 11];
+    let (base, tipo) = texto.rsplit_once('.').unwrap_or((texto, ""));
+    for (campo, inicio, ancho) in [(base, 0, 8), (tipo, 8, 3)] {
+        for (i, c) in campo.bytes().take(ancho).enumerate() {
+            if c == b'*' {
+                for j in i..ancho { resultado[inicio + j] = b'?'; }
+                break;
+            }
+            resultado[inicio + i] = c.to_ascii_uppercase();
+        }
+    }
+    resultado
+}
+
+//*****************************************************************************
+impl<W: Write> MaquinaCpm<W> {
+    pub fn monta_directorio<P: AsRef<Path>>(&mut self, directorio: P) {
+        self.directorio = Some(directorio.as_ref().to_path_buf());
+    }
+
+    // Línea de órdenes: cola en 0x0080 (longitud y texto) y los dos primeros nombres en los FCB
+    // por defecto 0x005C y 0x006C, como hace el CCP
+    pub fn pone_argumentos(&mut self, argumentos: &str) {
+        let cola = if argumentos.is_empty() { String::new() } else { format!(" {}", argumentos.to_ascii_uppercase()) };
+        let cola: Vec<u8> = cola.bytes().take(127).collect();
+        let memoria = &mut self.cpu.memoria.segmento_memoria[0];
+        memoria[0x0080] = cola.len() as u8;
+        memoria[0x0081..0x0081 + cola.len()].copy_from_slice(&cola);
+        for (i, fcb) in [0x005Cusize, 0x006C].iter().enumerate() {
+            let campo = argumentos.split_whitespace().nth(i).unwrap_or("");
+            let (unidad, nombre) = match campo.split_once(':') {
+                Some((u, n)) if u.len() == 1 && u.as_bytes()[0].is_ascii_alphabetic() => (u.as_bytes()[0].to_ascii_uppercase() - b'A' + 1, n),
+                _ => (0, campo),
+            };
+            memoria[*fcb] = unidad;
+            memoria[fcb + 1..fcb + 12].copy_from_slice(&patron_desde_texto(nombre));
+            memoria[fcb + 12..fcb + 16].fill(0);
+        }
+    }
+
+    //************************************* FCB
+    fn lee_fcb(&self, fcb: u16, desplazamiento: u16) -> u8 {
+        self.cpu.memoria.leer_memoria(fcb.wrapping_add(desplazamiento))
+    }
+
+    fn escribe_fcb(&mut self, fcb: u16, desplazamiento: u16, valor: u8) {
+        self.cpu.memoria.segmento_memoria[0][fcb.wrapping_add(desplazamiento) as usize] = valor;
+    }
+
+    fn nombre_fcb(&self, fcb: u16) -> [u8; 11] {
+        let mut nombre = [0u8; 11];
+        for (i, c) in nombre.iter_mut().enumerate() { *c = (self.lee_fcb(fcb, 1 + i as u16) & 0x7F).to_ascii_uppercase(); }
+        nombre
+    }
+
+    // Directorio de la unidad del FCB (sólo existe A:)
+    fn directorio_fcb(&self, fcb: u16) -> Option<PathBuf> {
+        if self.lee_fcb(fcb, 0) > 1 { return None; }
+        self.directorio.clone()
+    }
+
+    // Ficheros del directorio con nombre 8.3, ordenados
+    fn entradas(&self, directorio: &Path) -> Vec<([u8; 11], PathBuf)> {
+        let mut entradas: Vec<([u8; 11], PathBuf)> = fs::read_dir(directorio).into_iter().flatten().flatten()
+            .filter(|e| e.path().is_file())
+            .filter_map(|e| nombre_cpm(&e.file_name().to_string_lossy()).map(|n| (n, e.path())))
+            .collect();
+        entradas.sort();
+        entradas
+    }
+
+    fn busca_fichero(&self, fcb: u16) -> Option<PathBuf> {
+        let directorio = self.directorio_fcb(fcb)?;
+        let nombre = self.nombre_fcb(fcb);
+        self.entradas(&directorio).into_iter().find(|(n, _)| *n == nombre).map(|(_, ruta)| ruta)
+    }
+
+    fn registro_actual(&self, fcb: u16) -> u64 {
+        (self.lee_fcb(fcb, 14) as u64 * 32 + self.lee_fcb(fcb, 12) as u64) * 128 + self.lee_fcb(fcb, 32) as u64
+    }
+
+    fn avanza_registro(&mut self, fcb: u16) {
+        let siguiente = self.registro_actual(fcb) + 1;
+        self.escribe_fcb(fcb, 32, (siguiente % 128) as u8);
+        self.escribe_fcb(fcb, 12, ((siguiente / 128) % 32) as u8);
+        self.escribe_fcb(fcb, 14, (siguiente / (128 * 32)) as u8);
+    }
+
+    // Registros de la extensión del FCB según el tamaño del fichero
+    fn actualiza_rc(&mut self, fcb: u16, longitud: u64) {
+        let registros = longitud.div_ceil(TAMANO_REGISTRO as u64);
+        let extension = self.lee_fcb(fcb, 14) as u64 * 32 + self.lee_fcb(fcb, 12) as u64;
+        self.escribe_fcb(fcb, 15, registros.saturating_sub(extension * 128).min(128) as u8);
+    }
+
+    // El registro de 128 bytes del DMA tiene que caber en la memoria
+    fn dma(&self) -> Result<usize, String> {
+        if self.dma as usize + TAMANO_REGISTRO > 0x10000 { return Err(format!("DMA 0x{:04X}: el registro se sale de la memoria", self.dma)); }
+        Ok(self.dma as usize)
+    }
+
+    fn entrada_directorio(&mut self, nombre: &[u8; 11], longitud: u64) -> Result<(), String> {
+        let dma = self.dma()?;
+        let registros = longitud.div_ceil(TAMANO_REGISTRO as u64);
+        let memoria = &mut self.cpu.memoria.segmento_memoria[0];
+        memoria[dma..dma + 32].fill(0);
+        memoria[dma + 1..dma + 12].copy_from_slice(nombre);
+        memoria[dma + 15] = registros.min(128) as u8;
+        Ok(())
+    }
+
+    //************************************* Funciones
+    pub(super) fn bdos_ficheros(&mut self, funcion: u8) -> Result<u8, String> {
+        let fcb = self.cpu.get_de();
+        let resultado = match funcion {
+            13 => { self.dma = 0x0080; self.busqueda.clear(); 0 }                   // Reiniciar discos
+            14 => if self.cpu.reg_e == 0 { 0 } else { 0xFF },                       // Seleccionar disco
+            25 => 0,                                                                // Disco actual: A:
+            26 => { self.dma = fcb; 0 }                                             // Fijar DMA
+            15 => match self.busca_fichero(fcb) {                                   // Abrir
+                Some(ruta) => {
+                    let longitud = fs::metadata(&ruta).map(|m| m.len()).unwrap_or(0);
+                    self.actualiza_rc(fcb, longitud);
+                    0
+                }
+                None => 0xFF,
+            },
+            16 => if self.busca_fichero(fcb).is_some() { 0 } else { 0xFF },         // Cerrar
+            17 | 18 => {                                                            // Buscar primero / siguiente
+                if funcion == 17 {
+                    let patron = self.nombre_fcb(fcb);
+                    self.busqueda = match self.directorio_fcb(fcb) {
+                        Some(directorio) => self.entradas(&directorio).into_iter().map(|(n, _)| n).filter(|n| coincide(&patron, n)).rev().collect(),
+                        None => Vec::new(),
+                    };
+                }
+                match self.busqueda.pop() {
+                    Some(nombre) => {
+                        let longitud = self.directorio.as_ref().and_then(|d| fs::metadata(d.join(nombre_anfitrion(&nombre))).ok()).map(|m| m.len()).unwrap_or(0);
+                        self.entrada_directorio(&nombre, longitud)?;
+                        0
+                    }
+                    None => 0xFF,
+                }
+            }
+            19 => {                                                                 // Borrar
+                let patron = self.nombre_fcb(fcb);
+                let directorio = self.directorio_fcb(fcb);
+                let borrados = directorio.map(|d| self.entradas(&d)).unwrap_or_default().into_iter()
+                    .filter(|(n, _)| coincide(&patron, n))
+                    .filter(|(_, ruta)| fs::remove_file(ruta).is_ok())
+                    .count();
+                if borrados > 0 { 0 } else { 0xFF }
+            }
+            20 => self.lee_secuencial(fcb)?,
+            21 => self.escribe_secuencial(fcb)?,
+            22 => {                                                                 // Crear
+                // El nombre del FCB lo pone el programa: sólo se crea si es un 8.3 válido (sin
+                // separadores ni '..') que aún no está en el directorio, y nunca se trunca otro
+                let Some(directorio) = self.directorio_fcb(fcb) else { return Ok(0xFF) };
+                let nombre = nombre_anfitrion(&self.nombre_fcb(fcb));
+                if nombre_cpm(&nombre).is_none() || self.busca_fichero(fcb).is_some() { return Ok(0xFF); }
+                match OpenOptions::new().write(true).create_new(true).open(directorio.join(&nombre)) {
+                    Ok(_) => {
+                        for desplazamiento in [12, 14, 15, 32] { self.escribe_fcb(fcb, desplazamiento, 0); }
+                        0
+                    }
+                    Err(_) => 0xFF,
+                }
+            }
+            _ => return Err(format!("Función BDOS {} no es de ficheros", funcion)),
+        };
+        Ok(resultado)
+    }
+
+    fn lee_secuencial(&mut self, fcb: u16) -> Result<u8, String> {
+        let Some(ruta) = self.busca_fichero(fcb) else { return Ok(0xFF) };
+        let mut fichero = fs::File::open(&ruta).map_err(|e| format!("{}: {}", ruta.display(), e))?;
+        let posicion = self.registro_actual(fcb) * TAMANO_REGISTRO as u64;
+        let mut registro = vec![FIN_TEXTO; TAMANO_REGISTRO];
+        fichero.seek(SeekFrom::Start(posicion)).map_err(|e| format!("{}: {}", ruta.display(), e))?;
+        let mut leidos = 0;
+        while leidos < TAMANO_REGISTRO {
+            match fichero.read(&mut registro[leidos..]) {
+                Ok(0) => break,
+                Ok(n) => leidos += n,
+                Err(e) => return Err(format!("{}: {}", ruta.display(), e)),
+            }
+        }
+        if leidos == 0 { return Ok(1); }
+        let dma = self.dma()?;
+        self.cpu.memoria.segmento_memoria[0][dma..dma + TAMANO_REGISTRO].copy_from_slice(&registro);
+        self.avanza_registro(fcb);
+        Ok(0)
+    }
+
+    fn escribe_secuencial(&mut self, fcb: u16) -> Result<u8, String> {
+        let Some(ruta) = self.busca_fichero(fcb) else { return Ok(0xFF) };
+        let posicion = self.registro_actual(fcb) * TAMANO_REGISTRO as u64;
+        let dma = self.dma()?;
+        let registro = self.cpu.memoria.segmento_memoria[0][dma..dma + TAMANO_REGISTRO].to_vec();
+        let escrito = OpenOptions::new().write(true).open(&ruta)
+            .and_then(|mut f| { f.seek(SeekFrom::Start(posicion))?; f.write_all(&registro) });
+        if escrito.is_err() { return Ok(2); }
+        self.avanza_registro(fcb);
+        let longitud = fs::metadata(&ruta).map(|m| m.len()).unwrap_or(0);
+        self.actualiza_rc(fcb, longitud);
+        Ok(0)
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Directorio temporal propio de cada prueba con los ficheros dados
+    fn directorio(nombre: &str, ficheros: &[(&str, &[u8])]) -> PathBuf {
+        let ruta = std::env::temp_dir().join(format!("cpm_{}_{}", nombre, std::process::id()));
+        let _ = fs::remove_dir_all(&ruta);
+        fs::create_dir_all(&ruta).unwrap();
+        for (fichero, datos) in ficheros { fs::write(ruta.join(fichero), datos).unwrap(); }
+        ruta
+    }
+
+    fn prepara_fcb(maquina: &mut MaquinaCpm<Vec<u8>>, fcb: u16, nombre: &str) {
+        let memoria = &mut maquina.cpu.memoria.segmento_memoria[0];
+        memoria[fcb as usize..fcb as usize + 36].fill(0);
+        memoria[fcb as usize + 1..fcb as usize + 12].copy_from_slice(&patron_desde_texto(nombre));
+    }
+
+    #[test]
+    fn test_leer_escribir_y_borrar() {
+        let ruta = directorio("lectura", &[("texto.txt", &[b'x'; 200])]);
+        let mut maquina = MaquinaCpm::new(Vec::new());
+        maquina.monta_directorio(&ruta);
+        let (fcb, dma) = (0x005C, 0x2000);
+        prepara_fcb(&mut maquina, fcb, "TEXTO.TXT");
+        assert_eq!(maquina.llama_bdos(26, dma).unwrap(), 0);
+        assert_eq!(maquina.llama_bdos(15, fcb).unwrap(), 0);
+        assert_eq!(maquina.cpu.memoria.leer_memoria(fcb + 15), 2);                  // RC: 2 registros
+        assert_eq!(maquina.llama_bdos(20, fcb).unwrap(), 0);
+        assert_eq!(maquina.llama_bdos(20, fcb).unwrap(), 0);
+        assert_eq!(maquina.cpu.memoria.leer_memoria(dma + 71), b'x');
+        assert_eq!(maquina.cpu.memoria.leer_memoria(dma + 72), FIN_TEXTO);          // 200 - 128 = 72
+        assert_eq!(maquina.llama_bdos(20, fcb).unwrap(), 1);                        // Fin de fichero
+        assert_eq!(maquina.llama_bdos(16, fcb).unwrap(), 0);
+
+        // Crear y escribir dos registros con lo que hay en el DMA
+        prepara_fcb(&mut maquina, 0x006C, "NUEVO.DAT");
+        assert_eq!(maquina.llama_bdos(21, 0x006C).unwrap(), 0xFF);                  // Aún no existe
+        assert_eq!(maquina.llama_bdos(22, 0x006C).unwrap(), 0);
+        assert_eq!(maquina.llama_bdos(21, 0x006C).unwrap(), 0);
+        assert_eq!(maquina.llama_bdos(21, 0x006C).unwrap(), 0);
+        assert_eq!(maquina.cpu.memoria.leer_memoria(0x006C + 32), 2);               // CR
+        let escrito = fs::read(ruta.join("NUEVO.DAT")).unwrap();
+        assert_eq!(escrito.len(), 256);
+        assert_eq!(&escrito[128..200], &[b'x'; 72][..]);
+
+        prepara_fcb(&mut maquina, fcb, "*.TXT");
+        assert_eq!(maquina.llama_bdos(19, fcb).unwrap(), 0);
+        assert!(!ruta.join("texto.txt").exists());
+        assert_eq!(maquina.llama_bdos(15, 0x005C).unwrap(), 0xFF);
+        fs::remove_dir_all(&ruta).unwrap();
+    }
+
+    #[test]
+    fn test_busqueda_y_argumentos() {
+        let ruta = directorio("busqueda", &[("B.COM", b"1"), ("A.COM", b"2"), ("C.TXT", b"3"), ("nombre_largo.com", b"4")]);
+        let mut maquina = MaquinaCpm::new(Vec::new());
+        maquina.monta_directorio(&ruta);
+        maquina.pone_argumentos("????????.COM b:otro.txt");
+        assert_eq!(maquina.cpu.memoria.leer_memoria(0x0080), 24);
+        assert_eq!(maquina.cpu.memoria.leer_memoria(0x006C), 2);                    // Unidad B:
+        let mut encontrados = Vec::new();
+        let mut codigo = maquina.llama_bdos(17, 0x005C).unwrap();
+        while codigo != 0xFF {
+            let entrada: Vec<u8> = (1..12).map(|i| maquina.cpu.memoria.leer_memoria(0x0080 + i)).collect();
+            encontrados.push(String::from_utf8(entrada).unwrap());
+            codigo = maquina.llama_bdos(18, 0x005C).unwrap();
+        }
+        assert_eq!(encontrados, vec!["A       COM", "B       COM"]);
+        // B: no existe
+        assert_eq!(maquina.llama_bdos(15, 0x006C).unwrap(), 0xFF);
+        fs::remove_dir_all(&ruta).unwrap();
+    }
+
+    #[test]
+    fn test_crear_sin_salir_del_directorio() {
+        let ruta = directorio("crear", &[("EXISTE.TXT", b"datos")]);
+        let mut maquina = MaquinaCpm::new(Vec::new());
+        maquina.monta_directorio(ruta.join("A"));
+        fs::create_dir_all(ruta.join("A")).unwrap();
+        fs::write(ruta.join("A").join("EXISTE.TXT"), b"datos").unwrap();
+        // Nombres que escaparían del directorio montado
+        for nombre in [b"../EXISTE.T".as_slice(), b"..      TXT", b"/tmp/X  TXT", b"A\\B     TXT"] {
+            let memoria = &mut maquina.cpu.memoria.segmento_memoria[0];
+            memoria[0x005C..0x005C + 36].fill(0);
+            memoria[0x005D..0x005D + 11].copy_from_slice(nombre);
+            assert_eq!(maquina.llama_bdos(22, 0x005C).unwrap(), 0xFF);
+        }
+        assert_eq!(fs::read(ruta.join("EXISTE.TXT")).unwrap(), b"datos");
+        assert_eq!(fs::read_dir(&ruta).unwrap().count(), 2);
+        // Crear un fichero que ya existe no lo trunca
+        prepara_fcb(&mut maquina, 0x005C, "EXISTE.TXT");
+        assert_eq!(maquina.llama_bdos(22, 0x005C).unwrap(), 0xFF);
+        assert_eq!(fs::read(ruta.join("A").join("EXISTE.TXT")).unwrap(), b"datos");
+        fs::remove_dir_all(&ruta).unwrap();
+    }
+}
+
+//*****************************************************************************
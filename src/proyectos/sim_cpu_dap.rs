@@ -0,0 +1,967 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Servidor Debug Adapter Protocol (DAP) - Simulación CPU
+    Descripción:    Expone el simulador a los editores que hablan DAP por stdin/stdout: lanzar un
+                    programa desde archivo, puntos de ruptura por dirección, por función (símbolo)
+                    y por línea de un listado, paso a paso, continuar, pausa, vista de registros y
+                    flags, evaluación de expresiones y lectura de memoria de BancosMemoria.
+    Referencias:    https://microsoft.github.io/debug-adapter-protocol/specification
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+use super::sim_cpu_pruebas::Aux;
+use super::sim_cpu_expresion::Expresion;
+use super::sim_cpu_monitor::{es_call, es_ret, es_salto};
+use super::sim_cpu_opcode::LONGITUD_8080;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+//***************************************************************************** Notas
+/* Uso
+    let mut servidor = ServidorDap::new(CPU::new());
+    servidor.ejecuta(stdin.lock(), &mut stdout)?;       // Hasta 'disconnect' o fin de la entrada
+
+Configuración de lanzamiento (argumentos de 'launch'):
+    "program":     archivo binario a cargar (obligatorio)
+    "address":     dirección de carga y de inicio del PC (número o texto "0x100"), 0 por defecto
+    "listing":     listado .PRN de ASM (ver sim_cpu_listado). Sus líneas son las que admite
+                   setBreakpoints y las que se muestran en la pila; las líneas 'dir = ...' (EQU) se
+                   ignoran.
+    "symbols":     archivo de símbolos .SYM o .MAP (ver sim_cpu_simbolos). Se usan en los
+                   puntos de ruptura por función y para nombrar el marco de pila (ETIQUETA+n).
+    "stopOnEntry": se detiene antes de la primera instrucción
+
+Peticiones: initialize, launch, setBreakpoints, setFunctionBreakpoints, setInstructionBreakpoints,
+configurationDone, threads, stackTrace, scopes, variables, evaluate, readMemory, continue, next,
+stepIn, stepOut, pause, terminate y disconnect. Los puntos de ruptura admiten 'condition' y
+'logMessage' con la sintaxis de sim_cpu_expresion y 'hitCondition' numérica; se guardan como
+PuntosRuptura de la CPU y su id es el id DAP.
+
+La entrada se lee en un hilo aparte para poder atender 'pause' mientras el programa corre; la
+ejecución avanza por tandas de INSTRUCCIONES_POR_TANDA comprobando entre tanda y tanda si ha
+llegado alguna petición. stdout queda reservado al protocolo.
+*/
+
+const HILO: i64 = 1;
+const REF_REGISTROS: i64 = 1;
+const REF_FLAGS: i64 = 2;
+const INSTRUCCIONES_POR_TANDA: usize = 10_000;
+
+//***************************************************************************** JSON
+// Subconjunto de JSON suficiente para los mensajes DAP (sin dependencias externas)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Nulo,
+    Logico(bool),
+    Numero(f64),
+    Texto(String),
+    Lista(Vec<Json>),
+    Objeto(Vec<(String, Json)>),
+}
+
+pub fn objeto(campos: Vec<(&str, Json)>) -> Json {
+    Json::Objeto(campos.into_iter().map(|(clave, valor)| (clave.to_string(), valor)).collect())
+}
+
+impl From<&str> for Json { fn from(texto: &str) -> Json { Json::Texto(texto.to_string()) } }
+impl From<String> for Json { fn from(texto: String) -> Json { Json::Texto(texto) } }
+impl From<bool> for Json { fn from(valor: bool) -> Json { Json::Logico(valor) } }
+impl From<i64> for Json { fn from(valor: i64) -> Json { Json::Numero(valor as f64) } }
+impl From<usize> for Json { fn from(valor: usize) -> Json { Json::Numero(valor as f64) } }
+
+impl Json {
+    pub fn analiza(texto: &str) -> Result<Json, String> {
+        let mut lector = LectorJson { car: texto.chars().collect(), pos: 0 };
+        let valor = lector.valor()?;
+        lector.espacios();
+        if lector.pos < lector.car.len() { return Err(format!("JSON: datos sobrantes en la posición {}", lector.pos)); }
+        Ok(valor)
+    }
+
+    pub fn get(&self, clave: &str) -> Option<&Json> {
+        match self {
+            Json::Objeto(campos) => campos.iter().find(|(c, _)| c == clave).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn texto(&self) -> Option<&str> { if let Json::Texto(t) = self { Some(t) } else { None } }
+    pub fn numero(&self) -> Option<i64> { if let Json::Numero(n) = self { Some(*n as i64) } else { None } }
+    pub fn logico(&self) -> Option<bool> { if let Json::Logico(b) = self { Some(*b) } else { None } }
+    pub fn lista(&self) -> &[Json] { if let Json::Lista(l) = self { l } else { &[] } }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Nulo => write!(f, "null"),
+            Json::Logico(b) => write!(f, "{}", b),
+            Json::Numero(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Json::Numero(n) => write!(f, "{}", n),
+            Json::Texto(t) => escribe_cadena(f, t),
+            Json::Lista(lista) => {
+                write!(f, "[")?;
+                for (i, valor) in lista.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    write!(f, "{}", valor)?;
+                }
+                write!(f, "]")
+            }
+            Json::Objeto(campos) => {
+                write!(f, "{{")?;
+                for (i, (clave, valor)) in campos.iter().enumerate() {
+                    if i > 0 { write!(f, ",")?; }
+                    escribe_cadena(f, clave)?;
+                    write!(f, ":{}", valor)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn escribe_cadena(f: &mut fmt::Formatter, texto: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in texto.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct LectorJson {
+    car: Vec<char>,
+    pos: usize,
+}
+
+impl LectorJson {
+    fn espacios(&mut self) {
+        while self.car.get(self.pos).is_some_and(|c| c.is_whitespace()) { self.pos += 1; }
+    }
+
+    fn espera(&mut self, c: char) -> Result<(), String> {
+        self.espacios();
+        if self.car.get(self.pos) != Some(&c) { return Err(format!("JSON: se esperaba '{}' en la posición {}", c, self.pos)); }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn valor(&mut self) -> Result<Json, String> {
+        self.espacios();
+        match self.car.get(self.pos) {
+            Some('{') => self.objeto(),
+            Some('[') => self.lista(),
+            Some('"') => Ok(Json::Texto(self.cadena()?)),
+            Some('t') => self.literal("true", Json::Logico(true)),
+            Some('f') => self.literal("false", Json::Logico(false)),
+            Some('n') => self.literal("null", Json::Nulo),
+            Some(_) => self.numero(),
+            None => Err(String::from("JSON: fin inesperado")),
+        }
+    }
+
+    fn literal(&mut self, palabra: &str, valor: Json) -> Result<Json, String> {
+        let fin = self.pos + palabra.len();
+        if fin > self.car.len() || self.car[self.pos..fin].iter().collect::<String>() != palabra {
+            return Err(format!("JSON: valor no válido en la posición {}", self.pos));
+        }
+        self.pos = fin;
+        Ok(valor)
+    }
+
+    fn numero(&mut self) -> Result<Json, String> {
+        let inicio = self.pos;
+        while self.car.get(self.pos).is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) { self.pos += 1; }
+        let texto: String = self.car[inicio..self.pos].iter().collect();
+        texto.parse::<f64>().map(Json::Numero).map_err(|_| format!("JSON: número no válido en la posición {}", inicio))
+    }
+
+    fn cadena(&mut self) -> Result<String, String> {
+        self.espera('"')?;
+        let mut texto = String::new();
+        loop {
+            let c = *self.car.get(self.pos).ok_or("JSON: cadena sin terminar")?;
+            self.pos += 1;
+            match c {
+                '"' => return Ok(texto),
+                '\\' => {
+                    let escape = *self.car.get(self.pos).ok_or("JSON: cadena sin terminar")?;
+                    self.pos += 1;
+                    match escape {
+                        'n' => texto.push('\n'),
+                        'r' => texto.push('\r'),
+                        't' => texto.push('\t'),
+                        'b' => texto.push('\u{8}'),
+                        'f' => texto.push('\u{c}'),
+                        'u' => {
+                            let hex: String = self.car.iter().skip(self.pos).take(4).collect();
+                            let codigo = u32::from_str_radix(&hex, 16).map_err(|_| format!("JSON: escape '\\u{}' no válido", hex))?;
+                            self.pos += 4;
+                            texto.push(char::from_u32(codigo).unwrap_or('\u{FFFD}'));
+                        }
+                        c => texto.push(c),         // \" \\ \/
+                    }
+                }
+                c => texto.push(c),
+            }
+        }
+    }
+
+    fn lista(&mut self) -> Result<Json, String> {
+        self.espera('[')?;
+        let mut lista = Vec::new();
+        self.espacios();
+        if self.car.get(self.pos) == Some(&']') { self.pos += 1; return Ok(Json::Lista(lista)); }
+        loop {
+            lista.push(self.valor()?);
+            self.espacios();
+            match self.car.get(self.pos) {
+                Some(',') => self.pos += 1,
+                Some(']') => { self.pos += 1; return Ok(Json::Lista(lista)); }
+                _ => return Err(format!("JSON: se esperaba ',' o ']' en la posición {}", self.pos)),
+            }
+        }
+    }
+
+    fn objeto(&mut self) -> Result<Json, String> {
+        self.espera('{')?;
+        let mut campos = Vec::new();
+        self.espacios();
+        if self.car.get(self.pos) == Some(&'}') { self.pos += 1; return Ok(Json::Objeto(campos)); }
+        loop {
+            self.espacios();
+            let clave = self.cadena()?;
+            self.espera(':')?;
+            campos.push((clave, self.valor()?));
+            self.espacios();
+            match self.car.get(self.pos) {
+                Some(',') => self.pos += 1,
+                Some('}') => { self.pos += 1; return Ok(Json::Objeto(campos)); }
+                _ => return Err(format!("JSON: se esperaba ',' o '}}' en la posición {}", self.pos)),
+            }
+        }
+    }
+}
+
+//***************************************************************************** Marco de mensajes
+// Lee un mensaje 'Content-Length: n\r\n\r\n<json>'. None al llegar al fin de la entrada.
+pub fn lee_mensaje<R: BufRead>(entrada: &mut R) -> Result<Option<String>, String> {
+    let mut longitud = None;
+    loop {
+        let mut linea = String::new();
+        if entrada.read_line(&mut linea).map_err(|e| e.to_string())? == 0 { return Ok(None); }
+        let linea = linea.trim();
+        if linea.is_empty() {
+            if longitud.is_some() { break; }
+            continue;
+        }
+        if let Some((nombre, valor)) = linea.split_once(':') {
+            if nombre.trim().eq_ignore_ascii_case("Content-Length") {
+                longitud = Some(valor.trim().parse::<usize>().map_err(|_| format!("Content-Length no válido '{}'", valor.trim()))?);
+            }
+        }
+    }
+    let mut cuerpo = vec![0u8; longitud.unwrap_or(0)];
+    entrada.read_exact(&mut cuerpo).map_err(|e| e.to_string())?;
+    String::from_utf8(cuerpo).map(Some).map_err(|_| String::from("El mensaje no es UTF-8"))
+}
+
+pub fn escribe_mensaje<W: Write>(salida: &mut W, mensaje: &Json) -> Result<(), String> {
+    let texto = mensaje.to_string();
+    write!(salida, "Content-Length: {}\r\n\r\n{}", texto.len(), texto).and_then(|_| salida.flush()).map_err(|e| e.to_string())
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const TABLA: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut texto = String::new();
+    for grupo in bytes.chunks(3) {
+        let n = (grupo[0] as u32) << 16 | (*grupo.get(1).unwrap_or(&0) as u32) << 8 | *grupo.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= grupo.len() { texto.push(TABLA[((n >> (18 - 6 * i)) & 0x3F) as usize] as char); } else { texto.push('='); }
+        }
+    }
+    texto
+}
+
+//***************************************************************************** Servidor
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Objetivo {
+    Continuar,
+    Paso,
+    Retorno(u16, u16),                  // Paso sobre CALL: (PC de retorno, SP)
+    Salida(u16),                        // Salir de la subrutina: RET con SP mayor que éste
+}
+
+pub struct ServidorDap {
+    pub cpu: CPU,
+    aux: Aux,
+    secuencia: i64,
+    rupturas_fuente: Vec<usize>,
+    rupturas_funcion: Vec<usize>,
+    rupturas_instruccion: Vec<usize>,
+    parar_al_inicio: bool,
+    ejecutando: Option<Objetivo>,
+    reanuda: bool,                      // La primera instrucción del objetivo no comprueba sus puntos
+    fin_sesion: bool,
+}
+
+fn direccion_json(valor: Option<&Json>, cpu: &CPU) -> Result<u16, String> {
+    let valor = valor.ok_or("Falta la dirección")?;
+    let numero = match valor {
+        Json::Numero(n) => *n as i64,
+        Json::Texto(texto) => Expresion::analiza_con_simbolos(texto, cpu.simbolos.as_ref())?.evalua(cpu)?,
+        _ => return Err(format!("Dirección no válida: {}", valor)),
+    };
+    u16::try_from(numero).map_err(|_| format!("Dirección fuera de rango: {}", numero))
+}
+
+impl ServidorDap {
+    pub fn new(cpu: CPU) -> ServidorDap {
+        ServidorDap {
+            cpu,
+            aux: Aux { imp_contador_programa: 0, imp_instruccion: 0, imp_mnemonico: String::new() },
+            secuencia: 0,
+            rupturas_fuente: Vec::new(),
+            rupturas_funcion: Vec::new(),
+            rupturas_instruccion: Vec::new(),
+            parar_al_inicio: false,
+            ejecutando: None,
+            reanuda: false,
+            fin_sesion: false,
+        }
+    }
+
+    // Atiende peticiones hasta 'disconnect'/'terminate' o el fin de la entrada
+    pub fn ejecuta<R: BufRead + Send + 'static, W: Write>(&mut self, entrada: R, salida: &mut W) -> Result<(), String> {
+        let (emisor, receptor) = mpsc::channel();
+        thread::spawn(move || {
+            let mut entrada = entrada;
+            loop {
+                let mensaje = lee_mensaje(&mut entrada);
+                let fin = !matches!(mensaje, Ok(Some(_)));
+                if emisor.send(mensaje).is_err() || fin { break; }
+            }
+        });
+        while !self.fin_sesion {
+            let mensaje = if self.ejecutando.is_some() {
+                match receptor.try_recv() {
+                    Ok(mensaje) => Some(mensaje),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            } else {
+                match receptor.recv() {
+                    Ok(mensaje) => Some(mensaje),
+                    Err(_) => return Ok(()),
+                }
+            };
+            let mensajes = match mensaje {
+                None => self.avanza(INSTRUCCIONES_POR_TANDA),
+                Some(Ok(Some(texto))) => match Json::analiza(&texto) {
+                    Ok(peticion) => self.procesa(&peticion),
+                    Err(error) => vec![self.salida_consola(&format!("Mensaje no válido: {}", error))],
+                },
+                Some(Ok(None)) => return Ok(()),
+                Some(Err(error)) => return Err(error),
+            };
+            for mensaje in mensajes.iter() { escribe_mensaje(salida, mensaje)?; }
+        }
+        Ok(())
+    }
+
+    // Procesa una petición y devuelve la respuesta seguida de los eventos que provoca
+    pub fn procesa(&mut self, peticion: &Json) -> Vec<Json> {
+        let comando = peticion.get("command").and_then(|c| c.texto()).unwrap_or("").to_string();
+        let nulo = Json::Nulo;
+        let argumentos = peticion.get("arguments").unwrap_or(&nulo);
+        let mut eventos = Vec::new();
+        let resultado = match comando.as_str() {
+            "initialize" => {
+                eventos.push(self.evento("initialized", Json::Nulo));
+                Ok(self.capacidades())
+            }
+            "launch" => self.lanza(argumentos),
+            "setBreakpoints" => self.rupturas_fuente(argumentos),
+            "setFunctionBreakpoints" => self.rupturas_funcion(argumentos),
+            "setInstructionBreakpoints" => self.rupturas_instruccion(argumentos),
+            "configurationDone" => {
+                if self.parar_al_inicio {
+                    eventos.push(self.parada("entry", None, None));
+                } else if let Some(id) = self.cpu.comprueba_puntos_ruptura() {
+                    eventos.push(self.parada("breakpoint", Some(id), None));
+                } else {
+                    self.inicia(Objetivo::Continuar);
+                }
+                Ok(Json::Nulo)
+            }
+            "threads" => Ok(objeto(vec![("threads", Json::Lista(vec![objeto(vec![("id", HILO.into()), ("name", "CPU 8080".into())])]))])),
+            "stackTrace" => Ok(self.pila()),
+            "scopes" => Ok(objeto(vec![("scopes", Json::Lista(vec![
+                objeto(vec![("name", "Registros".into()), ("variablesReference", REF_REGISTROS.into()), ("expensive", false.into())]),
+                objeto(vec![("name", "Flags".into()), ("variablesReference", REF_FLAGS.into()), ("expensive", false.into())]),
+            ]))])),
+            "variables" => Ok(self.variables(argumentos.get("variablesReference").and_then(|r| r.numero()).unwrap_or(0))),
+            "evaluate" => self.evalua(argumentos),
+            "readMemory" => self.lee_memoria(argumentos),
+            "continue" => {
+                self.inicia(Objetivo::Continuar);
+                Ok(objeto(vec![("allThreadsContinued", true.into())]))
+            }
+            "stepIn" => { self.inicia(Objetivo::Paso); Ok(Json::Nulo) }
+            "next" => {
+                let pc = self.cpu.contador_de_programa;
+                self.cpu.memoria.leer_memoria_comprobada(pc).map(|opcode| {
+                    self.inicia(if es_call(opcode) {
+                        Objetivo::Retorno(pc.wrapping_add(LONGITUD_8080[opcode as usize] as u16), self.cpu.puntero_de_pila)
+                    } else {
+                        Objetivo::Paso
+                    });
+                    Json::Nulo
+                })
+            }
+            "stepOut" => { self.inicia(Objetivo::Salida(self.cpu.puntero_de_pila)); Ok(Json::Nulo) }
+            "pause" => {
+                if self.ejecutando.take().is_some() { eventos.push(self.parada("pause", None, None)); }
+                Ok(Json::Nulo)
+            }
+            "terminate" => {
+                self.ejecutando = None;
+                eventos.push(self.evento("terminated", Json::Nulo));
+                Ok(Json::Nulo)
+            }
+            "disconnect" => { self.fin_sesion = true; Ok(Json::Nulo) }
+            _ => Err(format!("Petición no soportada '{}'", comando)),
+        };
+        let mut mensajes = vec![self.respuesta(peticion, &comando, resultado)];
+        mensajes.append(&mut eventos);
+        // Los pasos son cortos: se ejecutan ya para que el evento 'stopped' siga a la respuesta
+        if matches!(self.ejecutando, Some(Objetivo::Paso)) { mensajes.append(&mut self.avanza(1)); }
+        mensajes
+    }
+
+    fn capacidades(&self) -> Json {
+        objeto(vec![
+            ("supportsConfigurationDoneRequest", true.into()),
+            ("supportsConditionalBreakpoints", true.into()),
+            ("supportsHitConditionalBreakpoints", true.into()),
+            ("supportsLogPoints", true.into()),
+            ("supportsFunctionBreakpoints", true.into()),
+            ("supportsInstructionBreakpoints", true.into()),
+            ("supportsReadMemoryRequest", true.into()),
+            ("supportsEvaluateForHovers", true.into()),
+            ("supportsTerminateRequest", true.into()),
+        ])
+    }
+
+    //************************************* Mensajes
+    fn siguiente_secuencia(&mut self) -> i64 {
+        self.secuencia += 1;
+        self.secuencia
+    }
+
+    fn respuesta(&mut self, peticion: &Json, comando: &str, resultado: Result<Json, String>) -> Json {
+        let mut campos = vec![
+            ("seq", self.siguiente_secuencia().into()),
+            ("type", "response".into()),
+            ("request_seq", peticion.get("seq").cloned().unwrap_or(Json::Nulo)),
+            ("success", resultado.is_ok().into()),
+            ("command", comando.into()),
+        ];
+        match resultado {
+            Ok(Json::Nulo) => {}
+            Ok(cuerpo) => campos.push(("body", cuerpo)),
+            Err(error) => campos.push(("message", error.into())),
+        }
+        objeto(campos)
+    }
+
+    fn evento(&mut self, nombre: &str, cuerpo: Json) -> Json {
+        let mut campos = vec![("seq", self.siguiente_secuencia().into()), ("type", "event".into()), ("event", nombre.into())];
+        if cuerpo != Json::Nulo { campos.push(("body", cuerpo)); }
+        objeto(campos)
+    }
+
+    fn parada(&mut self, motivo: &str, ruptura: Option<usize>, texto: Option<String>) -> Json {
+        let mut campos = vec![("reason", motivo.into()), ("threadId", HILO.into()), ("allThreadsStopped", true.into())];
+        if let Some(id) = ruptura { campos.push(("hitBreakpointIds", Json::Lista(vec![id.into()]))); }
+        if let Some(texto) = texto { campos.push(("text", texto.into())); }
+        self.evento("stopped", objeto(campos))
+    }
+
+    fn salida_consola(&mut self, texto: &str) -> Json {
+        self.evento("output", objeto(vec![("category", "console".into()), ("output", format!("{}\n", texto).into())]))
+    }
+
+    //************************************* Lanzamiento
+    fn lanza(&mut self, argumentos: &Json) -> Result<Json, String> {
+        let programa = argumentos.get("program").and_then(|p| p.texto()).ok_or("Falta 'program' en la configuración de lanzamiento")?;
+        let inicio = match argumentos.get("address") { Some(dir) => direccion_json(Some(dir), &self.cpu)?, None => 0 };
+        let datos = std::fs::read(programa).map_err(|e| format!("No se puede leer '{}': {}", programa, e))?;
+        let tamano = self.cpu.memoria.segmento_memoria[self.cpu.memoria.banco_actual as usize].len();
+        if inicio as usize + datos.len() > tamano {
+            return Err(format!("El programa ({} bytes en 0x{:04X}) no cabe en el banco de {} bytes", datos.len(), inicio, tamano));
+        }
+        if let Some(ruta) = argumentos.get("listing").and_then(|l| l.texto()) {
+            self.cpu.cargar_listado(ruta)?;
+        }
+        if let Some(ruta) = argumentos.get("symbols").and_then(|s| s.texto()) {
+            self.cpu.cargar_simbolos(ruta)?;
+        }
+        for (i, b) in datos.iter().enumerate() { self.cpu.memoria.escribir_memoria_depurador(inicio + i as u16, *b); }
+        self.cpu.contador_de_programa = inicio;
+        self.parar_al_inicio = argumentos.get("stopOnEntry").and_then(|s| s.logico()).unwrap_or(false);
+        Ok(Json::Nulo)
+    }
+
+    //************************************* Puntos de ruptura
+    // Sustituye los puntos de un grupo: elimina los anteriores y crea los nuevos
+    fn reemplaza_rupturas(&mut self, anteriores: Vec<usize>) {
+        for id in anteriores { let _ = self.cpu.elimina_punto_ruptura(id); }
+    }
+
+    // Crea un punto con condition/hitCondition/logMessage y devuelve el Breakpoint DAP
+    fn crea_ruptura(&mut self, dir: u16, punto: &Json, linea: Option<usize>) -> Result<(usize, Json), String> {
+        let condicion = punto.get("condition").and_then(|c| c.texto()).filter(|c| !c.trim().is_empty());
+        let id = match punto.get("logMessage").and_then(|m| m.texto()) {
+            Some(mensaje) => self.cpu.agrega_registro_ruptura(dir, condicion, mensaje)?,
+            None => self.cpu.agrega_punto_ruptura(dir, condicion)?,
+        };
+        if let Some(cuenta) = punto.get("hitCondition").and_then(|h| h.texto()).filter(|h| !h.trim().is_empty()) {
+            let cuenta = cuenta.trim().trim_start_matches(">=").trim().parse::<u64>();
+            match cuenta {
+                Ok(cuenta) => self.cpu.punto_ruptura_mut(id)?.cuenta = Some(cuenta),
+                Err(_) => {
+                    self.cpu.elimina_punto_ruptura(id)?;
+                    return Err(String::from("hitCondition debe ser un número de impactos"));
+                }
+            }
+        }
+        let mut campos = vec![("id", id.into()), ("verified", true.into()), ("instructionReference", format!("0x{:04X}", dir).into())];
+        if let Some(linea) = linea { campos.push(("line", linea.into())); }
+        Ok((id, objeto(campos)))
+    }
+
+    fn no_verificado(mensaje: &str) -> Json {
+        objeto(vec![("verified", false.into()), ("message", mensaje.into())])
+    }
+
+    fn rupturas_fuente(&mut self, argumentos: &Json) -> Result<Json, String> {
+        let anteriores = std::mem::take(&mut self.rupturas_fuente);
+        self.reemplaza_rupturas(anteriores);
+        let ruta = argumentos.get("source").and_then(|s| s.get("path")).and_then(|p| p.texto()).unwrap_or("").to_string();
+        let mut resultado = Vec::new();
+        for punto in argumentos.get("breakpoints").map(|b| b.lista()).unwrap_or(&[]) {
+            let linea = punto.get("line").and_then(|l| l.numero()).unwrap_or(0).max(0) as usize;
+            let destino = match self.cpu.listado.as_ref() {
+                None => Err(String::from("Sin listado: indique 'listing' en la configuración de lanzamiento")),
+                Some(listado) if !listado.es_fuente(&ruta) => Err(format!("'{}' no es el listado cargado", ruta)),
+                Some(listado) => listado.direccion(linea).ok_or(format!("No hay código a partir de la línea {}", linea)),
+            };
+            resultado.push(match destino.and_then(|(linea, dir)| self.crea_ruptura(dir, punto, Some(linea))) {
+                Ok((id, json)) => { self.rupturas_fuente.push(id); json }
+                Err(error) => Self::no_verificado(&error),
+            });
+        }
+        Ok(objeto(vec![("breakpoints", Json::Lista(resultado))]))
+    }
+
+    fn rupturas_funcion(&mut self, argumentos: &Json) -> Result<Json, String> {
+        let anteriores = std::mem::take(&mut self.rupturas_funcion);
+        self.reemplaza_rupturas(anteriores);
+        let mut resultado = Vec::new();
+        for punto in argumentos.get("breakpoints").map(|b| b.lista()).unwrap_or(&[]) {
+            let nombre = punto.get("name").and_then(|n| n.texto()).unwrap_or("");
+            let destino = match self.cpu.simbolos.as_ref().and_then(|s| s.busca(nombre)) {
+                Some(dir) => Ok(dir),
+                None => direccion_json(punto.get("name"), &self.cpu).map_err(|_| format!("Símbolo desconocido '{}'", nombre)),
+            };
+            let linea = destino.as_ref().ok().and_then(|dir| self.cpu.listado.as_ref().and_then(|l| l.linea(*dir)));
+            resultado.push(match destino.and_then(|dir| self.crea_ruptura(dir, punto, linea)) {
+                Ok((id, json)) => { self.rupturas_funcion.push(id); json }
+                Err(error) => Self::no_verificado(&error),
+            });
+        }
+        Ok(objeto(vec![("breakpoints", Json::Lista(resultado))]))
+    }
+
+    fn rupturas_instruccion(&mut self, argumentos: &Json) -> Result<Json, String> {
+        let anteriores = std::mem::take(&mut self.rupturas_instruccion);
+        self.reemplaza_rupturas(anteriores);
+        let mut resultado = Vec::new();
+        for punto in argumentos.get("breakpoints").map(|b| b.lista()).unwrap_or(&[]) {
+            let desplazamiento = punto.get("offset").and_then(|o| o.numero()).unwrap_or(0);
+            let destino = direccion_json(punto.get("instructionReference"), &self.cpu)
+                .map(|dir| dir.wrapping_add(desplazamiento as u16));
+            let linea = destino.as_ref().ok().and_then(|dir| self.cpu.listado.as_ref().and_then(|l| l.linea(*dir)));
+            resultado.push(match destino.and_then(|dir| self.crea_ruptura(dir, punto, linea)) {
+                Ok((id, json)) => { self.rupturas_instruccion.push(id); json }
+                Err(error) => Self::no_verificado(&error),
+            });
+        }
+        Ok(objeto(vec![("breakpoints", Json::Lista(resultado))]))
+    }
+
+    //************************************* Ejecución
+    // La ejecución continúa desde el PC en que está detenida, sin volver a parar en él
+    fn inicia(&mut self, objetivo: Objetivo) {
+        self.ejecutando = Some(objetivo);
+        self.reanuda = true;
+        self.cpu.reanuda_en_ruptura();
+    }
+
+    // Ejecuta hasta 'limite' instrucciones del objetivo en curso y devuelve los eventos generados
+    pub fn avanza(&mut self, limite: usize) -> Vec<Json> {
+        let objetivo = match self.ejecutando {
+            Some(objetivo) => objetivo,
+            None => return Vec::new(),
+        };
+        let mut mensajes = Vec::new();
+        for _ in 0..limite {
+            let pc = self.cpu.contador_de_programa;
+            // Los puntos de ruptura se comprueban antes de ejecutar la instrucción del PC
+            if !std::mem::take(&mut self.reanuda) {
+                let ruptura = self.cpu.comprueba_puntos_ruptura();
+                for texto in self.cpu.toma_mensajes_ruptura() { mensajes.push(self.salida_consola(&texto)); }
+                if let Some(id) = ruptura {
+                    self.ejecutando = None;
+                    mensajes.push(self.parada("breakpoint", Some(id), None));
+                    return mensajes;
+                }
+            }
+            // La instrucción completa (opcode y operandos) tiene que estar dentro del banco
+            let instruccion = self.cpu.memoria.leer_memoria_comprobada(pc).and_then(|opcode| {
+                let ultimo = pc.wrapping_add(LONGITUD_8080[opcode as usize] as u16 - 1);
+                self.cpu.memoria.leer_memoria_comprobada(ultimo).map(|_| opcode)
+            });
+            let opcode = match instruccion {
+                Ok(opcode) => opcode,
+                Err(error) => {
+                    self.ejecutando = None;
+                    mensajes.push(self.parada("exception", None, Some(error)));
+                    return mensajes;
+                }
+            };
+            if opcode == 0xFF {
+                self.ejecutando = None;
+                mensajes.push(self.salida_consola(&format!("Fin de programa (0xFF) en 0x{:04X}", pc)));
+                mensajes.push(self.evento("exited", objeto(vec![("exitCode", 0i64.into())])));
+                mensajes.push(self.evento("terminated", Json::Nulo));
+                return mensajes;
+            }
+            self.cpu.step_no_win(&mut self.aux);
+            let opcode = self.aux.imp_instruccion;
+            let vigilancia = self.cpu.disparos_vigilancia().first().map(|d| d.descripcion());
+            let nuevo_pc = self.cpu.contador_de_programa;
+            let parada = if let Some(texto) = vigilancia {
+                Some(("data breakpoint", None, Some(texto)))
+            } else if nuevo_pc == pc && !es_salto(opcode) {
+                Some(("exception", None, Some(format!("Opcode 0x{:02X} no implementado en 0x{:04X}", opcode, pc))))
+            } else {
+                let fin = match objetivo {
+                    Objetivo::Continuar => false,
+                    Objetivo::Paso => true,
+                    Objetivo::Retorno(retorno, sp) => nuevo_pc == retorno && self.cpu.puntero_de_pila == sp,
+                    Objetivo::Salida(sp) => es_ret(opcode) && self.cpu.puntero_de_pila > sp,
+                };
+                if fin { Some(("step", None, None)) } else { None }
+            };
+            if let Some((motivo, id, texto)) = parada {
+                self.ejecutando = None;
+                mensajes.push(self.parada(motivo, id, texto));
+                return mensajes;
+            }
+        }
+        mensajes
+    }
+
+    //************************************* Inspección
+    // Nombre de una dirección con los símbolos: ETIQUETA o ETIQUETA+n con la etiqueta anterior más cercana
+    fn nombre_direccion(&self, dir: u16) -> String {
+        self.cpu.nombre_direccion(dir).unwrap_or(format!("0x{:04X}", dir))
+    }
+
+    fn pila(&self) -> Json {
+        let pc = self.cpu.contador_de_programa;
+        let mut marco = vec![
+            ("id", 0i64.into()),
+            ("name", self.nombre_direccion(pc).into()),
+            ("line", 0i64.into()),
+            ("column", 0i64.into()),
+            ("instructionPointerReference", format!("0x{:04X}", pc).into()),
+        ];
+        if let Some((listado, linea)) = self.cpu.listado.as_ref().and_then(|l| l.linea(pc).map(|n| (l, n))) {
+            marco[2] = ("line", linea.into());
+            marco[3] = ("column", 1i64.into());
+            let nombre = Path::new(&listado.ruta).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            marco.push(("source", objeto(vec![("name", nombre.into()), ("path", listado.ruta.clone().into())])));
+        }
+        objeto(vec![("stackFrames", Json::Lista(vec![objeto(marco)])), ("totalFrames", 1i64.into())])
+    }
+
+    fn variables(&self, referencia: i64) -> Json {
+        let cpu = &self.cpu;
+        let variable = |nombre: &str, valor: String, memoria: Option<u16>| {
+            let mut campos = vec![("name", nombre.into()), ("value", valor.into()), ("variablesReference", 0i64.into())];
+            if let Some(dir) = memoria { campos.push(("memoryReference", format!("0x{:04X}", dir).into())); }
+            objeto(campos)
+        };
+        let lista = match referencia {
+            REF_REGISTROS => {
+                let mut lista: Vec<Json> = [("A", cpu.reg_a), ("B", cpu.reg_b), ("C", cpu.reg_c), ("D", cpu.reg_d),
+                    ("E", cpu.reg_e), ("H", cpu.reg_h), ("L", cpu.reg_l)].iter()
+                    .map(|(nombre, valor)| variable(nombre, format!("0x{:02X}", valor), None)).collect();
+                for (nombre, valor) in [("BC", cpu.get_bc()), ("DE", cpu.get_de()), ("HL", cpu.get_hl()),
+                    ("SP", cpu.puntero_de_pila), ("PC", cpu.contador_de_programa)] {
+                    lista.push(variable(nombre, format!("0x{:04X}", valor), Some(valor)));
+                }
+                lista
+            }
+            REF_FLAGS => {
+                let flags = &cpu.flags;
+                let mut lista = vec![variable("F", format!("0x{:02X}", flags.get_flags()), None)];
+                for (nombre, valor) in [("S", flags.sign), ("Z", flags.zero), ("AC", flags.half_carry), ("P", flags.parity_overflow), ("CY", flags.carry)] {
+                    lista.push(variable(nombre, (valor as u8).to_string(), None));
+                }
+                lista
+            }
+            _ => Vec::new(),
+        };
+        objeto(vec![("variables", Json::Lista(lista))])
+    }
+
+    fn evalua(&self, argumentos: &Json) -> Result<Json, String> {
+        let texto = argumentos.get("expression").and_then(|e| e.texto()).ok_or("Falta 'expression'")?;
+        let expresion = Expresion::analiza_con_simbolos(texto, self.cpu.simbolos.as_ref())?;
+        let valor = expresion.evalua(&self.cpu)?;
+        let mut campos = if expresion.es_8_bits() {
+            vec![("result", format!("0x{:02X} ({})", valor as u8, valor).into())]
+        } else {
+            vec![("result", format!("0x{:04X} ({})", valor as u16, valor).into())]
+        };
+        campos.push(("variablesReference", 0i64.into()));
+        if !expresion.es_8_bits() { campos.push(("memoryReference", format!("0x{:04X}", valor as u16).into())); }
+        Ok(objeto(campos))
+    }
+
+    // Lee del banco actual; los bytes que quedan fuera del banco se informan como no legibles
+    fn lee_memoria(&self, argumentos: &Json) -> Result<Json, String> {
+        let base = direccion_json(argumentos.get("memoryReference"), &self.cpu)? as i64;
+        let inicio = base + argumentos.get("offset").and_then(|o| o.numero()).unwrap_or(0);
+        let cantidad = argumentos.get("count").and_then(|c| c.numero()).ok_or("Falta 'count'")?.max(0);
+        let memoria = &self.cpu.memoria;
+        let tamano = memoria.segmento_memoria[memoria.banco_actual as usize].len() as i64;
+        if inicio < 0 || inicio >= tamano {
+            return Ok(objeto(vec![("address", format!("0x{:04X}", inicio.max(0)).into()), ("unreadableBytes", cantidad.into())]));
+        }
+        let legibles = cantidad.min(tamano - inicio);
+        let bytes: Vec<u8> = (0..legibles).map(|i| memoria.leer_memoria((inicio + i) as u16)).collect();
+        Ok(objeto(vec![
+            ("address", format!("0x{:04X}", inicio).into()),
+            ("data", base64(&bytes).into()),
+            ("unreadableBytes", (cantidad - legibles).into()),
+        ]))
+    }
+}
+
+//*****************************************************************************
+// Servidor DAP sobre stdin/stdout; el programa llega con la petición 'launch'
+pub fn dap_cmd() {
+    let mut servidor = ServidorDap::new(CPU::new());
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    if let Err(error) = servidor.ejecuta(std::io::BufReader::new(stdin), &mut stdout) {
+        eprintln!("Error: {}", error);
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use asm8080::asm8080;
+
+    fn peticion(seq: i64, comando: &str, argumentos: Json) -> Json {
+        objeto(vec![("seq", seq.into()), ("type", "request".into()), ("command", comando.into()), ("arguments", argumentos)])
+    }
+
+    fn archivo_temporal(nombre: &str, datos: &[u8]) -> String {
+        let ruta = std::env::temp_dir().join(format!("sim_cpu_dap_{}_{}", std::process::id(), nombre));
+        std::fs::write(&ruta, datos).unwrap();
+        ruta.to_string_lossy().to_string()
+    }
+
+    fn eventos<'a>(mensajes: &'a [Json], nombre: &str) -> Vec<&'a Json> {
+        mensajes.iter().filter(|m| m.get("event").and_then(|e| e.texto()) == Some(nombre)).collect()
+    }
+
+    #[test]
+    fn test_json() {
+        let json = Json::analiza(r#" {"a": [1, -2.5e1, true, null], "b": "x\"ñ\n"} "#).unwrap();
+        assert_eq!(json.get("a").unwrap().lista()[1], Json::Numero(-25.0));
+        assert_eq!(json.get("b").unwrap().texto(), Some("x\"ñ\n"));
+        assert_eq!(json.to_string(), r#"{"a":[1,-25,true,null],"b":"x\"ñ\n"}"#);
+        assert!(Json::analiza(r#"{"a": }"#).is_err());
+        assert!(Json::analiza("[1, 2").is_err());
+        assert_eq!(base64(b"8080"), "ODA4MA==");
+        assert_eq!(base64(b"CPU"), "Q1BV");
+    }
+
+    #[test]
+    fn test_sesion_con_listado() {
+        let programa = archivo_temporal("prog.bin", &asm8080! {
+                    lxi sp, 400h
+                    mvi a, 4
+                    call sub
+                    inr b
+                    db 0ffh             // Fin de programa
+            sub:    inr a               // 0x000A
+                    ret
+        });
+        let listado = archivo_temporal("prog.prn", b" 0000 310004   lxi sp,400h\n 0003 3E04     mvi a,4\n\
+            \x20              ; llamada\n 0005 CD0A00   call sub\n 0008 04       inr b\n 0009 FF       db 0ffh\n\
+            \x20000A =        sub equ $\n 000A 3C       inr a\n 000B C9       ret\n");
+        let simbolos = archivo_temporal("prog.sym", b"000A SUB 0008 VUELTA\n");
+        let mut servidor = ServidorDap::new(CPU::new());
+        let mensajes = servidor.procesa(&peticion(1, "initialize", objeto(vec![("adapterID", "sim8080".into())])));
+        assert_eq!(mensajes[0].get("body").unwrap().get("supportsReadMemoryRequest"), Some(&Json::Logico(true)));
+        assert_eq!(eventos(&mensajes, "initialized").len(), 1);
+        let mensajes = servidor.procesa(&peticion(2, "launch", objeto(vec![
+            ("program", programa.as_str().into()), ("listing", listado.as_str().into()), ("symbols", simbolos.as_str().into()),
+        ])));
+        assert_eq!(mensajes[0].get("success"), Some(&Json::Logico(true)));
+        // La línea 3 es un comentario: el punto se mueve a la 4 (CALL en 0x0005)
+        let mensajes = servidor.procesa(&peticion(3, "setBreakpoints", objeto(vec![
+            ("source", objeto(vec![("path", listado.as_str().into())])),
+            ("breakpoints", Json::Lista(vec![objeto(vec![("line", 3i64.into()), ("condition", "A == 4".into())]), objeto(vec![("line", 20i64.into())])])),
+        ])));
+        let puntos = mensajes[0].get("body").unwrap().get("breakpoints").unwrap().lista().to_vec();
+        assert_eq!(puntos[0].get("line"), Some(&Json::Numero(4.0)));
+        assert_eq!(puntos[0].get("instructionReference").unwrap().texto(), Some("0x0005"));
+        assert_eq!(puntos[1].get("verified"), Some(&Json::Logico(false)));
+        servidor.procesa(&peticion(4, "setFunctionBreakpoints", objeto(vec![
+            ("breakpoints", Json::Lista(vec![objeto(vec![("name", "sub".into()), ("logMessage", "A={A}".into())])])),
+        ])));
+        servidor.procesa(&peticion(5, "configurationDone", Json::Nulo));
+        let mensajes = servidor.avanza(100);
+        let parada = eventos(&mensajes, "stopped")[0].get("body").unwrap();
+        assert_eq!(parada.get("reason").unwrap().texto(), Some("breakpoint"));
+        let pila = servidor.procesa(&peticion(6, "stackTrace", objeto(vec![("threadId", HILO.into())])));
+        let marco = &pila[0].get("body").unwrap().get("stackFrames").unwrap().lista()[0];
+        assert_eq!(marco.get("line"), Some(&Json::Numero(4.0)));
+        assert_eq!(marco.get("name").unwrap().texto(), Some("0x0005"));
+        // 'next' pasa sobre la subrutina; el punto de registro de SUB escribe en la consola
+        servidor.procesa(&peticion(7, "next", objeto(vec![("threadId", HILO.into())])));
+        let mensajes = servidor.avanza(100);
+        assert_eq!(eventos(&mensajes, "output")[0].get("body").unwrap().get("output").unwrap().texto(), Some("[#2 0x000A] A=0x04\n"));
+        assert_eq!(eventos(&mensajes, "stopped")[0].get("body").unwrap().get("reason").unwrap().texto(), Some("step"));
+        assert_eq!(servidor.cpu.contador_de_programa, 0x0008);
+        let pila = servidor.procesa(&peticion(8, "stackTrace", Json::Nulo));
+        assert_eq!(pila[0].get("body").unwrap().get("stackFrames").unwrap().lista()[0].get("name").unwrap().texto(), Some("VUELTA"));
+        let variables = servidor.procesa(&peticion(9, "variables", objeto(vec![("variablesReference", REF_REGISTROS.into())])));
+        let lista = variables[0].get("body").unwrap().get("variables").unwrap().lista().to_vec();
+        assert_eq!(lista[0].get("value").unwrap().texto(), Some("0x05"));
+        assert_eq!(lista[10].get("name").unwrap().texto(), Some("SP"));
+        assert_eq!(lista[10].get("value").unwrap().texto(), Some("0x0400"));
+        let memoria = servidor.procesa(&peticion(10, "readMemory", objeto(vec![("memoryReference", "0x0003".into()), ("count", 2i64.into())])));
+        assert_eq!(memoria[0].get("body").unwrap().get("data").unwrap().texto(), Some("PgQ="));
+        let memoria = servidor.procesa(&peticion(11, "readMemory", objeto(vec![("memoryReference", "0x03FF".into()), ("count", 4i64.into())])));
+        assert_eq!(memoria[0].get("body").unwrap().get("unreadableBytes"), Some(&Json::Numero(3.0)));
+        // stepIn responde y se detiene en el mismo lote de mensajes
+        let mensajes = servidor.procesa(&peticion(12, "stepIn", objeto(vec![("threadId", HILO.into())])));
+        assert_eq!(eventos(&mensajes, "stopped").len(), 1);
+        assert_eq!(servidor.cpu.reg_b, 1);
+        servidor.procesa(&peticion(13, "continue", Json::Nulo));
+        let mensajes = servidor.avanza(100);
+        assert_eq!(eventos(&mensajes, "terminated").len(), 1);
+        let error = servidor.procesa(&peticion(14, "evaluate", objeto(vec![("expression", "A +".into())])));
+        assert_eq!(error[0].get("success"), Some(&Json::Logico(false)));
+        for ruta in [programa, listado, simbolos] { std::fs::remove_file(ruta).unwrap(); }
+    }
+
+    #[test]
+    fn test_stdio_con_pausa() {
+        let programa = archivo_temporal("bucle.bin", &asm8080! {
+            bucle:  inr a
+                    jmp bucle
+        });
+        let mut entrada = Vec::new();
+        for mensaje in [
+            peticion(1, "initialize", Json::Nulo),
+            peticion(2, "launch", objeto(vec![("program", programa.as_str().into()), ("stopOnEntry", true.into())])),
+            peticion(3, "configurationDone", Json::Nulo),
+            peticion(4, "continue", Json::Nulo),
+            peticion(5, "pause", Json::Nulo),
+            peticion(6, "disconnect", Json::Nulo),
+        ] {
+            escribe_mensaje(&mut entrada, &mensaje).unwrap();
+        }
+        let mut servidor = ServidorDap::new(CPU::new());
+        let mut salida = Vec::new();
+        servidor.ejecuta(Cursor::new(entrada), &mut salida).unwrap();
+        let mut lector = Cursor::new(salida);
+        let mut mensajes = Vec::new();
+        while let Some(texto) = lee_mensaje(&mut lector).unwrap() { mensajes.push(Json::analiza(&texto).unwrap()); }
+        let motivos: Vec<&str> = eventos(&mensajes, "stopped").iter()
+            .map(|e| e.get("body").unwrap().get("reason").unwrap().texto().unwrap()).collect();
+        // La pausa llega con el bucle en marcha (o antes de la primera tanda) y siempre lo detiene
+        assert_eq!(motivos, vec!["entry", "pause"]);
+        assert_eq!(mensajes.iter().filter(|m| m.get("type").and_then(|t| t.texto()) == Some("response")).count(), 6);
+        assert_eq!(mensajes.last().unwrap().get("command").unwrap().texto(), Some("disconnect"));
+        std::fs::remove_file(programa).unwrap();
+    }
+
+    #[test]
+    fn test_bucle_sobre_si_mismo() {
+        // JMP $ no avanza el PC pero está implementado: no es una excepción
+        let mut cpu = CPU::new();
+        cpu.cargar_programa(&asm8080! { bucle: jmp bucle });
+        let mut servidor = ServidorDap::new(cpu);
+        servidor.inicia(Objetivo::Continuar);
+        assert!(servidor.avanza(100).is_empty());
+        assert!(servidor.ejecutando.is_some());
+        servidor.cpu.memoria.escribir_memoria(0x0000, 0x08);    // Opcode sin implementar
+        let mensajes = servidor.avanza(100);
+        assert_eq!(eventos(&mensajes, "stopped")[0].get("body").unwrap().get("reason").unwrap().texto(), Some("exception"));
+    }
+
+    #[test]
+    fn test_final_del_banco() {
+        // Un programa que se sale del banco de 1 KiB se para con una excepción en lugar de leer fuera
+        let mut servidor = ServidorDap::new(CPU::new());
+        servidor.cpu.contador_de_programa = 0x03FE;
+        servidor.inicia(Objetivo::Continuar);
+        let mensajes = servidor.avanza(100);
+        let parada = eventos(&mensajes, "stopped")[0].get("body").unwrap();
+        assert_eq!(parada.get("reason").unwrap().texto(), Some("exception"));
+        assert_eq!(parada.get("text").unwrap().texto(), Some("Dirección 0x0400 fuera del banco 0 (1024 bytes)"));
+        assert_eq!(servidor.cpu.contador_de_programa, 0x0400);
+        // 'next' con el PC fuera del banco es un error de la petición
+        let respuesta = servidor.procesa(&peticion(1, "next", Json::Nulo));
+        assert_eq!(respuesta[0].get("success"), Some(&Json::Logico(false)));
+        assert!(servidor.ejecutando.is_none());
+        // Los operandos también tienen que estar en el banco (JMP en 0x03FF)
+        servidor.cpu.memoria.escribir_memoria(0x03FF, 0xC3);
+        servidor.cpu.contador_de_programa = 0x03FF;
+        servidor.inicia(Objetivo::Paso);
+        let mensajes = servidor.avanza(100);
+        let parada = eventos(&mensajes, "stopped")[0].get("body").unwrap();
+        assert_eq!(parada.get("text").unwrap().texto(), Some("Dirección 0x0401 fuera del banco 0 (1024 bytes)"));
+    }
+}
+
+//*****************************************************************************
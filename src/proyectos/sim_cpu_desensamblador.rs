@@ -0,0 +1,252 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Desensamblador 8080 - Simulación CPU
+    Descripción:    Decodifica cualquier rango de direcciones de BancosMemoria en registros
+                    (dirección, bytes, mnemónico, operandos) con la longitud correcta de cada
+                    instrucción. Operandos en hexadecimal o decimal y sustitución de las
+                    direcciones conocidas por su símbolo.
+    Referencias:    Intel 8080 Assembly Language Programming Manual (1975)
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_memoria::BancosMemoria;
+use super::sim_cpu_tablas::{LONGITUD_8080, MNEMONICOS_8080};
+use std::collections::HashMap;
+
+//***************************************************************************** Notas
+/* Uso
+    let mut opciones = OpcionesDesensamblado::new();
+    opciones.formato = FormatoNumero::Decimal;
+    opciones.agrega_simbolos(programa.simbolos.iter().map(|(n, d)| (n.clone(), *d)));
+    for instruccion in desensambla_rango(&cpu.memoria, 0x0000, 0x0020, &opciones) {
+        println!("{}", instruccion.linea());        // 0005  CD 0C 00  CALL SUB1
+    }
+
+Los mnemónicos salen de MNEMONICOS_8080, la misma tabla que usa el ensamblador, así que el texto
+de 'texto()' se puede volver a ensamblar. En hexadecimal los números llevan sufijo H y un 0 delante
+si empiezan por letra (0FFH). Los símbolos sustituyen a los operandos de 16 bits (addr y d16)
+cuya dirección coincide exactamente; los de 8 bits se dejan como número.
+
+Los opcodes no documentados se marcan con '*' (documentada = false). Si una instrucción no cabe
+al final del banco, sus bytes se devuelven como 'DB' para no leer fuera de la memoria.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormatoNumero {
+    Hexadecimal,
+    Decimal,
+}
+
+pub struct OpcionesDesensamblado {
+    pub formato: FormatoNumero,
+    pub simbolos: HashMap<u16, String>,
+    pub banco: Option<u8>,              // None: banco actual
+}
+
+impl OpcionesDesensamblado {
+    pub fn new() -> OpcionesDesensamblado {
+        OpcionesDesensamblado { formato: FormatoNumero::Hexadecimal, simbolos: HashMap::new(), banco: None }
+    }
+
+    // Si una dirección tiene varios nombres se conserva el primero
+    pub fn agrega_simbolos<I: IntoIterator<Item = (String, u16)>>(&mut self, simbolos: I) {
+        for (nombre, dir) in simbolos { self.simbolos.entry(dir).or_insert(nombre); }
+    }
+
+    pub fn byte(&self, valor: u8) -> String {
+        match self.formato {
+            FormatoNumero::Hexadecimal => hex_intel(format!("{:02X}", valor)),
+            FormatoNumero::Decimal => valor.to_string(),
+        }
+    }
+
+    fn palabra(&self, valor: u16) -> String {
+        if let Some(nombre) = self.simbolos.get(&valor) { return nombre.clone(); }
+        match self.formato {
+            FormatoNumero::Hexadecimal => hex_intel(format!("{:04X}", valor)),
+            FormatoNumero::Decimal => valor.to_string(),
+        }
+    }
+}
+
+impl Default for OpcionesDesensamblado {
+    fn default() -> Self { OpcionesDesensamblado::new() }
+}
+
+// Número hexadecimal con la sintaxis de Intel: sufijo H y 0 inicial si empieza por letra
+fn hex_intel(digitos: String) -> String {
+    if digitos.starts_with(|c: char| c.is_ascii_alphabetic()) { format!("0{}H", digitos) } else { format!("{}H", digitos) }
+}
+
+//***************************************************************************** Instrucción
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstruccionDesensamblada {
+    pub direccion: u16,
+    pub bytes: Vec<u8>,
+    pub mnemonico: String,              // Sin el '*' de los no documentados
+    pub operandos: Vec<String>,
+    pub documentada: bool,
+    pub destino: Option<u16>,           // Dirección de JMP/CALL/Jcc/Ccc/RST
+}
+
+impl InstruccionDesensamblada {
+    pub fn texto(&self) -> String {
+        let marca = if self.documentada { "" } else { "*" };
+        if self.operandos.is_empty() { format!("{}{}", marca, self.mnemonico) }
+        else { format!("{}{} {}", marca, self.mnemonico, self.operandos.join(",")) }
+    }
+
+    // Línea de listado: dirección, bytes y texto
+    pub fn linea(&self) -> String {
+        let hex = self.bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+        format!("{:04X}  {:<9} {}", self.direccion, hex, self.texto())
+    }
+
+    pub fn longitud(&self) -> u16 {
+        self.bytes.len() as u16
+    }
+}
+
+//***************************************************************************** Desensamblado
+// Decodifica la instrucción que empieza en bytes[0]; 'bytes' debe traer los operandos que haya
+pub fn desensambla_bytes(direccion: u16, bytes: &[u8], opciones: &OpcionesDesensamblado) -> InstruccionDesensamblada {
+    let opcode = bytes[0];
+    let longitud = LONGITUD_8080[opcode as usize] as usize;
+    if bytes.len() < longitud {
+        // Instrucción cortada por el final de la memoria: se muestran los bytes sueltos
+        return InstruccionDesensamblada {
+            direccion,
+            bytes: bytes.to_vec(),
+            mnemonico: String::from("DB"),
+            operandos: bytes.iter().map(|b| opciones.byte(*b)).collect(),
+            documentada: true,
+            destino: None,
+        };
+    }
+    let entrada = MNEMONICOS_8080[opcode as usize];
+    let documentada = !entrada.starts_with('*');
+    let (mnemonico, patron) = entrada.trim_start_matches('*').split_once(' ').unwrap_or((entrada.trim_start_matches('*'), ""));
+    let inmediato16 = if longitud == 3 { Some(u16::from_le_bytes([bytes[1], bytes[2]])) } else { None };
+    let mut destino = None;
+    let operandos: Vec<String> = if patron.is_empty() { Vec::new() } else {
+        patron.split(',').map(|operando| match operando {
+            "d8" => opciones.byte(bytes[1]),
+            "d16" => opciones.palabra(inmediato16.unwrap_or(0)),
+            "addr" => {
+                // Las únicas 'addr' de J.. y C.. son saltos y llamadas (STA, LDA, SHLD y LHLD no lo son)
+                if mnemonico.starts_with('J') || mnemonico.starts_with('C') { destino = inmediato16; }
+                opciones.palabra(inmediato16.unwrap_or(0))
+            }
+            otro => otro.to_string(),
+        }).collect()
+    };
+    if mnemonico == "RST" { destino = Some((opcode & 0x38) as u16); }
+    InstruccionDesensamblada {
+        direccion,
+        bytes: bytes[..longitud].to_vec(),
+        mnemonico: mnemonico.to_string(),
+        operandos,
+        documentada,
+        destino,
+    }
+}
+
+fn segmento<'a>(memoria: &'a BancosMemoria, opciones: &OpcionesDesensamblado) -> &'a [u8] {
+    let banco = opciones.banco.unwrap_or(memoria.banco_actual) as usize;
+    memoria.segmento_memoria.get(banco).map(|s| s.as_slice()).unwrap_or(&[])
+}
+
+// Una instrucción. Si 'direccion' está fuera del banco devuelve un DB vacío.
+pub fn desensambla_instruccion(memoria: &BancosMemoria, direccion: u16, opciones: &OpcionesDesensamblado) -> InstruccionDesensamblada {
+    let datos = segmento(memoria, opciones);
+    let inicio = direccion as usize;
+    if inicio >= datos.len() {
+        return InstruccionDesensamblada { direccion, bytes: Vec::new(), mnemonico: String::from("DB"), operandos: Vec::new(), documentada: true, destino: None };
+    }
+    let fin = (inicio + 3).min(datos.len());
+    desensambla_bytes(direccion, &datos[inicio..fin], opciones)
+}
+
+// Instrucciones que empiezan entre 'inicio' y 'fin' (ambos incluidos) del banco elegido
+pub fn desensambla_rango(memoria: &BancosMemoria, inicio: u16, fin: u16, opciones: &OpcionesDesensamblado) -> Vec<InstruccionDesensamblada> {
+    let tamano = segmento(memoria, opciones).len() as u32;
+    let mut instrucciones = Vec::new();
+    let mut dir = inicio as u32;
+    while dir <= fin as u32 && dir < tamano {
+        let instruccion = desensambla_instruccion(memoria, dir as u16, opciones);
+        dir += instruccion.longitud() as u32;
+        instrucciones.push(instruccion);
+    }
+    instrucciones
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proyectos::sim_cpu_ensamblador::ensambla;
+
+    const FUENTE: &str = "
+INICIO: LXI SP,0400H
+        MVI A,0FFH
+        CALL SUB1
+        JNZ INICIO
+        RST 7
+        LXI H,TABLA
+        HLT
+SUB1:   ADI 10
+        RET
+TABLA:  DB 0, 1";
+
+    fn memoria_con_programa() -> (BancosMemoria, OpcionesDesensamblado) {
+        let programa = ensambla(FUENTE).unwrap();
+        let mut memoria = BancosMemoria::new();
+        for (i, b) in programa.imagen().iter().enumerate() { memoria.escribir_memoria(i as u16, *b); }
+        let mut opciones = OpcionesDesensamblado::new();
+        opciones.agrega_simbolos(programa.simbolos.iter().map(|(nombre, dir)| (nombre.clone(), *dir)));
+        (memoria, opciones)
+    }
+
+    #[test]
+    fn test_ida_y_vuelta_con_el_ensamblador() {
+        let (memoria, mut opciones) = memoria_con_programa();
+        let instrucciones = desensambla_rango(&memoria, 0x0000, 0x0012, &opciones);
+        let textos: Vec<String> = instrucciones.iter().map(|i| i.texto()).collect();
+        assert_eq!(textos, vec!["LXI SP,0400H", "MVI A,0FFH", "CALL SUB1", "JNZ INICIO", "RST 7", "LXI H,TABLA", "HLT", "ADI 0AH", "RET"]);
+        assert_eq!(instrucciones[2].destino, Some(0x0010));
+        assert_eq!(instrucciones[4].destino, Some(0x0038));
+        assert_eq!(instrucciones[7].destino, None);
+        assert_eq!(instrucciones[2].linea(), "0005  CD 10 00  CALL SUB1");
+        // El texto vuelve a ensamblarse en los mismos bytes
+        let fuente: String = instrucciones.iter().map(|i| format!("  {}\n", i.texto())).collect();
+        let simbolos: String = opciones.simbolos.iter().map(|(dir, nombre)| format!("{} EQU {}\n", nombre, dir)).collect();
+        let original: Vec<u8> = instrucciones.iter().flat_map(|i| i.bytes.clone()).collect();
+        assert_eq!(ensambla(&(simbolos + &fuente)).unwrap().bytes, original);
+        opciones.formato = FormatoNumero::Decimal;
+        opciones.simbolos.clear();
+        let decimal: Vec<String> = desensambla_rango(&memoria, 0x0000, 0x0004, &opciones).iter().map(|i| i.texto()).collect();
+        assert_eq!(decimal, vec!["LXI SP,1024", "MVI A,255"]);
+    }
+
+    #[test]
+    fn test_final_de_banco_y_no_documentados() {
+        let mut memoria = BancosMemoria::new();
+        memoria.crear_segmento(5);
+        memoria.set_banco_activo(1);
+        for (i, b) in [0x08u8, 0xCB, 0x34, 0x12, 0xC3].iter().enumerate() { memoria.escribir_memoria(i as u16, *b); }
+        let mut opciones = OpcionesDesensamblado::new();
+        let instrucciones = desensambla_rango(&memoria, 0x0000, 0xFFFF, &opciones);
+        assert_eq!(instrucciones.iter().map(|i| i.texto()).collect::<Vec<_>>(), vec!["*NOP", "*JMP 1234H", "DB 0C3H"]);
+        assert!(!instrucciones[1].documentada);
+        assert_eq!(instrucciones[1].destino, Some(0x1234));
+        // El banco 0 sigue accesible sin cambiar el banco actual
+        opciones.banco = Some(0);
+        assert_eq!(desensambla_rango(&memoria, 0x0000, 0x0000, &opciones)[0].texto(), "NOP");
+        assert_eq!(desensambla_instruccion(&memoria, 0x4000, &opciones).bytes.len(), 0);
+    }
+}
+
+//*****************************************************************************
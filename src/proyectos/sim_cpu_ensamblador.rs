@@ -0,0 +1,775 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Ensamblador 8080 de dos pasadas - Simulación CPU
+    Descripción:    Ensambla código con los mnemónicos estándar de Intel: etiquetas globales y
+                    locales, directivas ORG/DB/DW/DS/EQU/SET/END, expresiones con $ y aritmética,
+                    literales de carácter y de cadena. Genera los bytes para cargar_programa, un
+                    listado .PRN y la tabla de símbolos; los errores indican línea y columna.
+    Referencias:    Intel 8080 Assembly Language Programming Manual (1975)
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_tablas::{LONGITUD_8080, MNEMONICOS_8080};
+use std::collections::BTreeMap;
+use std::fmt;
+
+//***************************************************************************** Notas
+/* Uso
+    let programa = ensambla("
+            ORG 0
+    INICIO: LXI SP,400H
+            MVI A,'A'
+    .bucle: INR A
+            CPI 'Z'+1
+            JNZ .bucle          ; Etiqueta local: INICIO.BUCLE
+            HLT
+    ")?;
+    cpu.cargar_programa(&programa.imagen());     // Imagen desde 0x0000
+    println!("{}", programa.listado());
+    println!("{}", programa.tabla_simbolos());
+
+Sintaxis de línea:  [etiqueta[:]] [mnemónico [operando, ...]] [; comentario]
+    - Una etiqueta sin ':' debe empezar en la columna 1. Las que empiezan por '.' son locales a la
+      última etiqueta global y se guardan como GLOBAL.LOCAL. Los nombres no distinguen mayúsculas.
+    - NOMBRE EQU expr define una constante; debe definirse antes de usarse en ORG, DS o EQU.
+    - NOMBRE SET expr es igual pero se puede redefinir con otro SET; cada línea ve el último
+      valor asignado antes de ella (una referencia anterior al primer SET ve el último).
+    - DB admite expresiones de 8 bits y cadenas ('ABC' o "ABC", '' dentro de '...' es una comilla).
+    - END [expr] termina el ensamblado; la expresión opcional es la dirección de entrada.
+
+Expresiones (de menor a mayor precedencia):
+    OR XOR | ^      AND &      + -      * / MOD SHL SHR % << >>      unarios: - + NOT ~ HIGH LOW
+    Números: 123, 123D, 0FFH, 0x1F, 1010B, 0b1010, 17O, 17Q. '$' es la dirección de la línea actual.
+    Un literal de carácter vale su código ASCII ('AB' vale 0x4142).
+
+Las instrucciones se codifican buscando en MNEMONICOS_8080 (se ignoran las entradas no
+documentadas marcadas con '*'), así que el ensamblador y el desensamblador usan la misma tabla.
+
+El listado sigue las columnas del .PRN del ASM de CP/M que lee ListadoFuente (sim_cpu_listado):
+     DIR  BYTES     FUENTE              (hasta 4 bytes por línea)
+     DIR  BYTES                         (continuación de una línea con más de 4 bytes)
+     VALOR =        FUENTE              (EQU y SET)
+                    FUENTE              (líneas sin dirección: comentarios y líneas vacías)
+La columna 0 es la de la letra de error del ASM (siempre en blanco). Así el listado sirve como
+'listing' del servidor DAP y la tabla de símbolos como su 'symbols' (.SYM).
+*/
+
+//***************************************************************************** Errores
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorEnsamblado {
+    pub linea: usize,
+    pub columna: usize,
+    pub mensaje: String,
+}
+
+impl fmt::Display for ErrorEnsamblado {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Línea {}, columna {}: {}", self.linea, self.columna, self.mensaje)
+    }
+}
+
+fn error(linea: usize, columna: usize, mensaje: String) -> ErrorEnsamblado {
+    ErrorEnsamblado { linea, columna, mensaje }
+}
+
+//***************************************************************************** Analizador léxico
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),                      // En mayúsculas
+    Numero(i64),
+    Cadena(Vec<u8>),
+    Op(String),
+    Dolar,
+    Coma,
+    DosPuntos,
+    AbreP,
+    CierraP,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    tok: Tok,
+    col: usize,                         // Columna (desde 1) del primer carácter
+}
+
+fn es_letra_ident(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '?' | '@')
+}
+
+// Valor de un número con los sufijos H/B/O/Q/D o los prefijos 0x/0b
+fn valor_numero(texto: &str) -> Option<i64> {
+    let t = texto.to_ascii_uppercase();
+    let (digitos, base) = if let Some(d) = t.strip_suffix('H') { (d, 16) }
+        else if let Some(d) = t.strip_prefix("0X") { (d, 16) }
+        else if let Some(d) = t.strip_prefix("0B").filter(|d| !d.is_empty() && d.chars().all(|c| c == '0' || c == '1')) { (d, 2) }
+        else if let Some(d) = t.strip_suffix('B') { (d, 2) }
+        else if let Some(d) = t.strip_suffix('O').or_else(|| t.strip_suffix('Q')) { (d, 8) }
+        else if let Some(d) = t.strip_suffix('D') { (d, 10) }
+        else { (t.as_str(), 10) };
+    if digitos.is_empty() { return None; }
+    i64::from_str_radix(digitos, base).ok().filter(|v| *v <= 0xFFFF_FFFF)
+}
+
+fn analiza_lexico(texto: &str, linea: usize) -> Result<Vec<Token>, ErrorEnsamblado> {
+    let car: Vec<char> = texto.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < car.len() {
+        let c = car[i];
+        let col = i + 1;
+        if c.is_whitespace() { i += 1; continue; }
+        if c == ';' { break; }
+        if c.is_ascii_digit() {
+            let inicio = i;
+            while i < car.len() && car[i].is_ascii_alphanumeric() { i += 1; }
+            let palabra: String = car[inicio..i].iter().collect();
+            let valor = valor_numero(&palabra).ok_or(error(linea, col, format!("Número no válido '{}'", palabra)))?;
+            tokens.push(Token { tok: Tok::Numero(valor), col });
+            continue;
+        }
+        if es_letra_ident(c) {
+            let inicio = i;
+            while i < car.len() && es_letra_ident(car[i]) { i += 1; }
+            tokens.push(Token { tok: Tok::Ident(car[inicio..i].iter().collect::<String>().to_ascii_uppercase()), col });
+            continue;
+        }
+        if c == '\'' || c == '"' {
+            let mut bytes = Vec::new();
+            i += 1;
+            loop {
+                if i >= car.len() { return Err(error(linea, col, String::from("Cadena sin cerrar"))); }
+                if car[i] == c {
+                    if car.get(i + 1) == Some(&c) { bytes.push(c as u8); i += 2; continue; }     // '' dentro de '...'
+                    i += 1;
+                    break;
+                }
+                if !car[i].is_ascii() { return Err(error(linea, i + 1, format!("Carácter no ASCII '{}' en la cadena", car[i]))); }
+                bytes.push(car[i] as u8);
+                i += 1;
+            }
+            tokens.push(Token { tok: Tok::Cadena(bytes), col });
+            continue;
+        }
+        let doble: String = car[i..(i + 2).min(car.len())].iter().collect();
+        let tok = match c {
+            '<' | '>' if doble == "<<" || doble == ">>" => { i += 1; Tok::Op(doble) }
+            '+' | '-' | '*' | '/' | '%' | '&' | '|' | '^' | '~' => Tok::Op(c.to_string()),
+            '$' => Tok::Dolar,
+            ',' => Tok::Coma,
+            ':' => Tok::DosPuntos,
+            '(' => Tok::AbreP,
+            ')' => Tok::CierraP,
+            _ => return Err(error(linea, col, format!("Carácter inesperado '{}'", c))),
+        };
+        i += 1;
+        tokens.push(Token { tok, col });
+    }
+    Ok(tokens)
+}
+
+//***************************************************************************** Expresiones
+const PALABRAS_OPERADOR: [&str; 9] = ["MOD", "AND", "OR", "XOR", "NOT", "SHL", "SHR", "HIGH", "LOW"];
+
+struct Contexto<'a> {
+    simbolos: &'a BTreeMap<String, u16>,
+    dolar: u16,
+    global: &'a str,
+    linea: usize,
+}
+
+fn nombre_completo(nombre: &str, global: &str) -> String {
+    if nombre.starts_with('.') { format!("{}{}", global, nombre) } else { nombre.to_string() }
+}
+
+struct Evaluador<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    ctx: &'a Contexto<'a>,
+    col_fin: usize,
+}
+
+impl<'a> Evaluador<'a> {
+    fn error(&self, mensaje: String) -> ErrorEnsamblado {
+        let col = self.tokens.get(self.pos).map(|t| t.col).unwrap_or(self.col_fin);
+        error(self.ctx.linea, col, mensaje)
+    }
+
+    // Operador binario del nivel si el token actual es uno de 'ops'
+    fn operador(&mut self, ops: &[&str]) -> Option<String> {
+        let texto = match &self.tokens.get(self.pos)?.tok {
+            Tok::Op(op) => op.clone(),
+            Tok::Ident(nombre) => nombre.clone(),
+            _ => return None,
+        };
+        if !ops.contains(&texto.as_str()) { return None; }
+        self.pos += 1;
+        Some(texto)
+    }
+
+    fn nivel_o(&mut self) -> Result<i64, ErrorEnsamblado> {
+        let mut valor = self.nivel_y()?;
+        while let Some(op) = self.operador(&["OR", "|", "XOR", "^"]) {
+            let derecha = self.nivel_y()?;
+            valor = if op == "OR" || op == "|" { valor | derecha } else { valor ^ derecha };
+        }
+        Ok(valor)
+    }
+
+    fn nivel_y(&mut self) -> Result<i64, ErrorEnsamblado> {
+        let mut valor = self.nivel_suma()?;
+        while self.operador(&["AND", "&"]).is_some() { valor &= self.nivel_suma()?; }
+        Ok(valor)
+    }
+
+    fn nivel_suma(&mut self) -> Result<i64, ErrorEnsamblado> {
+        let mut valor = self.nivel_producto()?;
+        while let Some(op) = self.operador(&["+", "-"]) {
+            let derecha = self.nivel_producto()?;
+            valor = if op == "+" { valor.wrapping_add(derecha) } else { valor.wrapping_sub(derecha) };
+        }
+        Ok(valor)
+    }
+
+    fn nivel_producto(&mut self) -> Result<i64, ErrorEnsamblado> {
+        let mut valor = self.unario()?;
+        loop {
+            let posicion = self.pos;
+            let op = match self.operador(&["*", "/", "%", "MOD", "SHL", "SHR", "<<", ">>"]) {
+                Some(op) => op,
+                None => return Ok(valor),
+            };
+            let derecha = self.unario()?;
+            valor = match op.as_str() {
+                "*" => valor.wrapping_mul(derecha),
+                "/" | "%" | "MOD" if derecha == 0 => { self.pos = posicion; return Err(self.error(String::from("División por cero"))); }
+                "/" => valor / derecha,
+                "%" | "MOD" => valor % derecha,
+                "SHL" | "<<" => valor.wrapping_shl(derecha as u32),
+                _ => valor.wrapping_shr(derecha as u32),
+            };
+        }
+    }
+
+    fn unario(&mut self) -> Result<i64, ErrorEnsamblado> {
+        match self.operador(&["-", "+", "NOT", "~", "HIGH", "LOW"]).as_deref() {
+            Some("-") => Ok(self.unario()?.wrapping_neg()),
+            Some("+") => self.unario(),
+            Some("HIGH") => Ok((self.unario()? >> 8) & 0xFF),
+            Some("LOW") => Ok(self.unario()? & 0xFF),
+            Some(_) => Ok(!self.unario()? & 0xFFFF),
+            None => self.primario(),
+        }
+    }
+
+    fn primario(&mut self) -> Result<i64, ErrorEnsamblado> {
+        let token = self.tokens.get(self.pos).ok_or(self.error(String::from("Expresión incompleta")))?;
+        let valor = match &token.tok {
+            Tok::Numero(n) => *n,
+            Tok::Dolar => self.ctx.dolar as i64,
+            Tok::Cadena(bytes) if !bytes.is_empty() && bytes.len() <= 2 => bytes.iter().fold(0i64, |v, b| v << 8 | *b as i64),
+            Tok::Cadena(_) => return Err(self.error(String::from("Un literal de carácter en una expresión debe tener 1 o 2 caracteres"))),
+            Tok::Ident(nombre) if PALABRAS_OPERADOR.contains(&nombre.as_str()) => return Err(self.error(format!("Falta el operando antes de '{}'", nombre))),
+            Tok::Ident(nombre) => {
+                let completo = nombre_completo(nombre, self.ctx.global);
+                *self.ctx.simbolos.get(&completo).ok_or(self.error(format!("Símbolo no definido '{}'", completo)))? as i64
+            }
+            Tok::AbreP => {
+                self.pos += 1;
+                let valor = self.nivel_o()?;
+                if self.tokens.get(self.pos).map(|t| &t.tok) != Some(&Tok::CierraP) { return Err(self.error(String::from("Falta ')'"))); }
+                valor
+            }
+            _ => return Err(self.error(String::from("Se esperaba un valor"))),
+        };
+        self.pos += 1;
+        Ok(valor)
+    }
+}
+
+// Evalúa una expresión completa; 'col_fin' es la columna que se indica si la expresión está vacía
+fn evalua(tokens: &[Token], ctx: &Contexto, col_fin: usize) -> Result<i64, ErrorEnsamblado> {
+    let mut evaluador = Evaluador { tokens, pos: 0, ctx, col_fin };
+    let valor = evaluador.nivel_o()?;
+    if evaluador.pos < tokens.len() { return Err(evaluador.error(String::from("Símbolo inesperado en la expresión"))); }
+    Ok(valor)
+}
+
+//***************************************************************************** Líneas
+struct Operando {
+    tokens: Vec<Token>,
+    col: usize,
+}
+
+struct Linea {
+    numero: usize,
+    texto: String,
+    etiqueta: Option<(String, usize)>,  // Nombre completo y columna
+    instruccion: Option<(String, usize)>,
+    operandos: Vec<Operando>,
+    global: String,                     // Etiqueta global vigente para las locales
+    direccion: u16,
+    tamano: u16,
+}
+
+const DIRECTIVAS: [&str; 7] = ["ORG", "DB", "DW", "DS", "EQU", "SET", "END"];
+
+fn es_mnemonico(nombre: &str) -> bool {
+    DIRECTIVAS.contains(&nombre) || MNEMONICOS_8080.iter().any(|m| m.split_whitespace().next() == Some(nombre))
+}
+
+// Separa los operandos por comas (fuera de paréntesis)
+fn separa_operandos(tokens: &[Token], col_fin: usize, linea: usize) -> Result<Vec<Operando>, ErrorEnsamblado> {
+    if tokens.is_empty() { return Ok(Vec::new()); }
+    let mut operandos = Vec::new();
+    let mut actual: Vec<Token> = Vec::new();
+    let mut col = tokens[0].col;
+    let mut nivel = 0;
+    for token in tokens {
+        match token.tok {
+            Tok::AbreP => nivel += 1,
+            Tok::CierraP => nivel -= 1,
+            Tok::Coma if nivel == 0 => {
+                if actual.is_empty() { return Err(error(linea, token.col, String::from("Operando vacío"))); }
+                operandos.push(Operando { tokens: std::mem::take(&mut actual), col });
+                col = token.col + 1;
+                continue;
+            }
+            _ => {}
+        }
+        actual.push(token.clone());
+    }
+    if actual.is_empty() { return Err(error(linea, col_fin, String::from("Operando vacío"))); }
+    operandos.push(Operando { tokens: actual, col });
+    Ok(operandos)
+}
+
+fn analiza_linea(numero: usize, texto: &str, global: &mut String) -> Result<Linea, ErrorEnsamblado> {
+    let tokens = analiza_lexico(texto, numero)?;
+    let col_fin = texto.chars().take_while(|c| *c != ';').count() + 1;
+    let mut pos = 0;
+    let mut etiqueta = None;
+    if let Some(Token { tok: Tok::Ident(nombre), col }) = tokens.first() {
+        let con_dos_puntos = tokens.get(1).map(|t| &t.tok) == Some(&Tok::DosPuntos);
+        let antes_de_equ = matches!(tokens.get(1).map(|t| &t.tok), Some(Tok::Ident(s)) if s == "EQU" || s == "SET");
+        if con_dos_puntos || antes_de_equ || (*col == 1 && !es_mnemonico(nombre)) {
+            if PALABRAS_OPERADOR.contains(&nombre.as_str()) || nombre == "$" {
+                return Err(error(numero, *col, format!("'{}' es una palabra reservada", nombre)));
+            }
+            if !nombre.starts_with('.') && !antes_de_equ { *global = nombre.clone(); }
+            etiqueta = Some((nombre_completo(nombre, global), *col));
+            pos = if con_dos_puntos { 2 } else { 1 };
+        }
+    }
+    let mut instruccion = None;
+    match tokens.get(pos) {
+        Some(Token { tok: Tok::Ident(nombre), col }) => { instruccion = Some((nombre.clone(), *col)); pos += 1; }
+        Some(token) => return Err(error(numero, token.col, String::from("Se esperaba un mnemónico o una directiva"))),
+        None => {}
+    }
+    let operandos = separa_operandos(&tokens[pos..], col_fin, numero)?;
+    Ok(Linea { numero, texto: texto.to_string(), etiqueta, instruccion, operandos, global: global.clone(), direccion: 0, tamano: 0 })
+}
+
+//***************************************************************************** Codificación
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Inmediato {
+    Ninguno,
+    Byte,
+    Palabra,
+}
+
+// Registro u operando fijo de la tabla (B, SP, PSW, ...) o el número de RST
+fn es_registro(tokens: &[Token], nombre: &str) -> bool {
+    matches!(tokens, [Token { tok: Tok::Ident(r), .. }] if r == nombre)
+}
+
+// Busca en MNEMONICOS_8080 la entrada que encaja con el mnemónico y los operandos. Los números
+// de RST se comparan con el valor del operando solo si 'ctx' está disponible (segunda pasada).
+fn busca_opcode(mnemonico: &str, col: usize, operandos: &[Operando], ctx: Option<&Contexto>, linea: usize) -> Result<(u8, Inmediato), ErrorEnsamblado> {
+    let mut conocido = false;
+    for (opcode, entrada) in MNEMONICOS_8080.iter().enumerate() {
+        if entrada.starts_with('*') { continue; }
+        let (nombre, patron) = entrada.split_once(' ').unwrap_or((entrada, ""));
+        if nombre != mnemonico { continue; }
+        conocido = true;
+        let patrones: Vec<&str> = if patron.is_empty() { Vec::new() } else { patron.split(',').collect() };
+        if patrones.len() != operandos.len() { continue; }
+        let mut inmediato = Inmediato::Ninguno;
+        let mut encaja = true;
+        for (patron, operando) in patrones.iter().zip(operandos) {
+            match *patron {
+                "d8" => inmediato = Inmediato::Byte,
+                "d16" | "addr" => inmediato = Inmediato::Palabra,
+                p if p.chars().all(|c| c.is_ascii_digit()) => {
+                    if let Some(ctx) = ctx {
+                        encaja &= evalua(&operando.tokens, ctx, operando.col)? == p.parse::<i64>().unwrap_or(-1);
+                    }
+                }
+                p => encaja &= es_registro(&operando.tokens, p),
+            }
+        }
+        if encaja { return Ok((opcode as u8, inmediato)); }
+    }
+    let columna = operandos.first().map(|o| o.col).unwrap_or(col);
+    if conocido {
+        Err(error(linea, columna, format!("Operandos no válidos para {}", mnemonico)))
+    } else {
+        Err(error(linea, col, format!("Mnemónico desconocido '{}'", mnemonico)))
+    }
+}
+
+fn comprueba_rango(valor: i64, bits: u32, col: usize, linea: usize) -> Result<u16, ErrorEnsamblado> {
+    let maximo = (1i64 << bits) - 1;
+    if valor < -(maximo + 1) || valor > maximo {
+        return Err(error(linea, col, format!("Valor {} fuera de rango para {} bits", valor, bits)));
+    }
+    Ok((valor & maximo) as u16)
+}
+
+//***************************************************************************** Resultado
+pub struct LineaListado {
+    pub numero: usize,
+    pub direccion: Option<u16>,
+    pub valor_equ: Option<u16>,
+    pub bytes: Vec<u8>,
+    pub texto: String,
+}
+
+pub struct Ensamblado {
+    pub origen: u16,                    // Dirección del primer byte de 'bytes'
+    pub bytes: Vec<u8>,                 // Desde 'origen'; los huecos de ORG y DS quedan a 0
+    pub entrada: Option<u16>,           // Dirección de END expr
+    pub simbolos: BTreeMap<String, u16>,
+    pub lineas: Vec<LineaListado>,
+}
+
+impl Ensamblado {
+    // Imagen desde 0x0000 para cargar_programa
+    pub fn imagen(&self) -> Vec<u8> {
+        let mut imagen = vec![0u8; self.origen as usize];
+        imagen.extend_from_slice(&self.bytes);
+        imagen
+    }
+
+    pub fn listado(&self) -> String {
+        let mut salida = Vec::new();
+        for l in self.lineas.iter() {
+            let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02X}", b)).collect::<String>();
+            let linea = match (l.valor_equ, l.direccion) {
+                (Some(valor), _) => format!(" {:04X} = {:8}{}", valor, "", l.texto),
+                (None, Some(dir)) => format!(" {:04X} {:<8}  {}", dir, hex(&l.bytes[..l.bytes.len().min(4)]), l.texto),
+                (None, None) => format!("{:16}{}", "", l.texto),
+            };
+            salida.push(linea.trim_end().to_string());
+            if let Some(dir) = l.direccion {
+                for (i, resto) in l.bytes.chunks(4).enumerate().skip(1) {
+                    salida.push(format!(" {:04X} {}", dir.wrapping_add(4 * i as u16), hex(resto)));
+                }
+            }
+        }
+        salida.join("\n")
+    }
+
+    // Formato .SYM: 'dirección nombre' por línea, ordenado por dirección
+    pub fn tabla_simbolos(&self) -> String {
+        let mut simbolos: Vec<(&String, &u16)> = self.simbolos.iter().collect();
+        simbolos.sort_by_key(|(nombre, dir)| (**dir, (*nombre).clone()));
+        simbolos.iter().map(|(nombre, dir)| format!("{:04X} {}", dir, nombre)).collect::<Vec<String>>().join("\n")
+    }
+}
+
+//***************************************************************************** Ensamblador
+fn un_operando<'l>(linea: &'l Linea, instruccion: &(String, usize)) -> Result<&'l Operando, ErrorEnsamblado> {
+    match linea.operandos.as_slice() {
+        [operando] => Ok(operando),
+        _ => Err(error(linea.numero, instruccion.1, format!("{} necesita un operando", instruccion.0))),
+    }
+}
+
+pub fn ensambla(fuente: &str) -> Result<Ensamblado, ErrorEnsamblado> {
+    // Análisis de todas las líneas hasta END
+    let mut lineas = Vec::new();
+    let mut global = String::new();
+    for (i, texto) in fuente.lines().enumerate() {
+        let linea = analiza_linea(i + 1, texto, &mut global)?;
+        let fin = matches!(&linea.instruccion, Some((nombre, _)) if nombre == "END");
+        lineas.push(linea);
+        if fin { break; }
+    }
+
+    // Primera pasada: direcciones y valores de los símbolos
+    let mut simbolos: BTreeMap<String, u16> = BTreeMap::new();
+    let mut variables: Vec<String> = Vec::new();            // Símbolos definidos con SET
+    let mut dir: u32 = 0;
+    for linea in lineas.iter_mut() {
+        let numero = linea.numero;
+        let instruccion = linea.instruccion.clone().unwrap_or((String::new(), 1));
+        let ctx = Contexto { simbolos: &simbolos, dolar: dir as u16, global: &linea.global, linea: numero };
+        let mut valor_etiqueta = None;
+        let tamano: u32 = match instruccion.0.as_str() {
+            "" => 0,
+            "EQU" | "SET" => {
+                if linea.etiqueta.is_none() { return Err(error(numero, instruccion.1, format!("{} necesita un nombre", instruccion.0))); }
+                let operando = un_operando(linea, &instruccion)?;
+                valor_etiqueta = Some(comprueba_rango(evalua(&operando.tokens, &ctx, operando.col)?, 16, operando.col, numero)?);
+                0
+            }
+            "ORG" => {
+                let operando = un_operando(linea, &instruccion)?;
+                dir = comprueba_rango(evalua(&operando.tokens, &ctx, operando.col)?, 16, operando.col, numero)? as u32;
+                0
+            }
+            "DS" => {
+                let operando = un_operando(linea, &instruccion)?;
+                let valor = evalua(&operando.tokens, &ctx, operando.col)?;
+                if !(0..=0xFFFF).contains(&valor) { return Err(error(numero, operando.col, format!("Tamaño de DS no válido: {}", valor))); }
+                valor as u32
+            }
+            "DB" => {
+                if linea.operandos.is_empty() { return Err(error(numero, instruccion.1, String::from("DB necesita al menos un operando"))); }
+                linea.operandos.iter().map(|o| match o.tokens.as_slice() {
+                    [Token { tok: Tok::Cadena(bytes), .. }] => bytes.len() as u32,
+                    _ => 1,
+                }).sum()
+            }
+            "DW" => {
+                if linea.operandos.is_empty() { return Err(error(numero, instruccion.1, String::from("DW necesita al menos un operando"))); }
+                2 * linea.operandos.len() as u32
+            }
+            "END" => 0,
+            mnemonico => {
+                let (opcode, _) = busca_opcode(mnemonico, instruccion.1, &linea.operandos, None, numero)?;
+                LONGITUD_8080[opcode as usize] as u32
+            }
+        };
+        linea.direccion = dir as u16;
+        if let Some((nombre, col)) = &linea.etiqueta {
+            let es_set = instruccion.0 == "SET";
+            if simbolos.contains_key(nombre) && !(es_set && variables.contains(nombre)) {
+                return Err(error(numero, *col, format!("Símbolo '{}' definido dos veces", nombre)));
+            }
+            if es_set && !variables.contains(nombre) { variables.push(nombre.clone()); }
+            simbolos.insert(nombre.clone(), valor_etiqueta.unwrap_or(dir as u16));
+        }
+        if dir + tamano > 0x10000 {
+            return Err(error(numero, instruccion.1, String::from("El código sobrepasa la dirección 0xFFFF")));
+        }
+        linea.tamano = tamano as u16;
+        dir += tamano;
+    }
+
+    // Segunda pasada: generación de código. Los SET se vuelven a asignar en orden sobre una copia
+    // para que cada línea use el valor vigente
+    let mut valores = simbolos.clone();
+    let mut memoria: Vec<Option<u8>> = vec![None; 0x10000];
+    let mut listado = Vec::new();
+    let mut entrada = None;
+    for linea in lineas.iter() {
+        let numero = linea.numero;
+        let ctx = Contexto { simbolos: &valores, dolar: linea.direccion, global: &linea.global, linea: numero };
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut valor_equ = None;
+        let (instruccion, col) = linea.instruccion.clone().unwrap_or((String::new(), 1));
+        match instruccion.as_str() {
+            "EQU" => valor_equ = linea.etiqueta.as_ref().and_then(|(nombre, _)| simbolos.get(nombre).copied()),
+            "SET" => {
+                let operando = un_operando(linea, &(instruccion.clone(), col))?;
+                valor_equ = Some(comprueba_rango(evalua(&operando.tokens, &ctx, operando.col)?, 16, operando.col, numero)?);
+            }
+            "" | "ORG" | "DS" => {}
+            "END" => {
+                if let Some(operando) = linea.operandos.first() {
+                    entrada = Some(comprueba_rango(evalua(&operando.tokens, &ctx, operando.col)?, 16, operando.col, numero)?);
+                }
+            }
+            "DB" => {
+                for operando in linea.operandos.iter() {
+                    match operando.tokens.as_slice() {
+                        [Token { tok: Tok::Cadena(cadena), .. }] => bytes.extend_from_slice(cadena),
+                        tokens => bytes.push(comprueba_rango(evalua(tokens, &ctx, operando.col)?, 8, operando.col, numero)? as u8),
+                    }
+                }
+            }
+            "DW" => {
+                for operando in linea.operandos.iter() {
+                    let valor = comprueba_rango(evalua(&operando.tokens, &ctx, operando.col)?, 16, operando.col, numero)?;
+                    bytes.extend_from_slice(&valor.to_le_bytes());
+                }
+            }
+            mnemonico => {
+                let (opcode, inmediato) = busca_opcode(mnemonico, col, &linea.operandos, Some(&ctx), numero)?;
+                bytes.push(opcode);
+                if inmediato != Inmediato::Ninguno {
+                    let operando = linea.operandos.last().ok_or(error(numero, col, String::from("Falta el operando inmediato")))?;
+                    let bits = if inmediato == Inmediato::Byte { 8 } else { 16 };
+                    let valor = comprueba_rango(evalua(&operando.tokens, &ctx, operando.col)?, bits, operando.col, numero)?;
+                    if inmediato == Inmediato::Byte { bytes.push(valor as u8); } else { bytes.extend_from_slice(&valor.to_le_bytes()); }
+                }
+            }
+        }
+        if let (Some(valor), Some((nombre, _))) = (valor_equ, linea.etiqueta.as_ref()) {
+            if instruccion == "SET" { valores.insert(nombre.clone(), valor); }
+        }
+        for (i, b) in bytes.iter().enumerate() {
+            let destino = linea.direccion as usize + i;
+            if memoria[destino].is_some() {
+                return Err(error(numero, col, format!("La dirección 0x{:04X} ya tiene código (ORG solapado)", destino)));
+            }
+            memoria[destino] = Some(*b);
+        }
+        let con_direccion = linea.instruccion.is_some() || linea.etiqueta.is_some();
+        listado.push(LineaListado {
+            numero,
+            direccion: if con_direccion && instruccion != "END" { Some(linea.direccion) } else { None },
+            valor_equ,
+            bytes,
+            texto: linea.texto.clone(),
+        });
+    }
+
+    let primero = memoria.iter().position(|b| b.is_some());
+    let ultimo = memoria.iter().rposition(|b| b.is_some());
+    let (origen, bytes) = match (primero, ultimo) {
+        (Some(primero), Some(ultimo)) => (primero as u16, memoria[primero..=ultimo].iter().map(|b| b.unwrap_or(0)).collect()),
+        _ => (0, Vec::new()),
+    };
+    Ok(Ensamblado { origen, bytes, entrada, simbolos, lineas: listado })
+}
+
+//*****************************************************************************
+pub fn ensamblador_cmd() {
+    let fuente = "
+; Suma los bytes de TABLA en A
+        ORG 0
+N       EQU 4
+INICIO: LXI SP,400H
+        LXI H,TABLA
+        MVI B,N
+        XRA A
+.suma:  ADD M
+        INX H
+        DCR B
+        JNZ .suma
+        STA RESULTADO
+        HLT
+TABLA:  DB 1, 2, 3, 'A'-'@'
+RESULTADO: DS 1
+        END INICIO
+";
+    match ensambla(fuente) {
+        Ok(programa) => {
+            println!("{}\n", programa.listado());
+            println!("{}", programa.tabla_simbolos());
+        }
+        Err(error) => println!("Error: {}", error),
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_programa_del_monitor() {
+        // Mismo programa que el de las pruebas del monitor (sim_cpu_monitor::tests::monitor)
+        let programa = ensambla("
+        lxi sp, 400h
+        mvi a, 4
+        call sub1
+        mvi b, 01h
+        db 0ffh, 0FFH
+sub1:   inr a
+        call .otra
+        ret
+.otra:  inr a
+        ret
+").unwrap();
+        assert_eq!(programa.imagen(), vec![
+            0x31, 0x00, 0x04, 0x3E, 0x04, 0xCD, 0x0C, 0x00, 0x06, 0x01, 0xFF, 0xFF,
+            0x3C, 0xCD, 0x11, 0x00, 0xC9, 0x3C, 0xC9,
+        ]);
+        assert_eq!(programa.simbolos.get("SUB1.OTRA"), Some(&0x0011));
+        assert_eq!(programa.tabla_simbolos(), "000C SUB1\n0011 SUB1.OTRA");
+    }
+
+    #[test]
+    fn test_directivas_y_expresiones() {
+        let programa = ensambla("
+BDOS    EQU 5
+        ORG 100H
+INICIO: MVI C, LOW (FIN - $) + 1
+        LXI D, MENSAJE
+        DW INICIO, HIGH 1234H SHL 4, 0b101 * (2 + 3) MOD 7, NOT 0
+        RST BDOS + 2
+        DS 2
+MENSAJE: DB 'It''s', 0DH, \"\\n\"
+FIN     EQU $
+        END INICIO
+        NOP                 ; No se ensambla
+").unwrap();
+        assert_eq!(programa.origen, 0x100);
+        assert_eq!(programa.entrada, Some(0x100));
+        assert_eq!(programa.bytes, vec![
+            0x0E, 0x18, 0x11, 0x10, 0x01,
+            0x00, 0x01, 0x20, 0x01, 0x04, 0x00, 0xFF, 0xFF,
+            0xFF,                           // RST 7
+            0x00, 0x00,                     // DS 2
+            b'I', b't', b'\'', b's', 0x0D, b'\\', b'n',
+        ]);
+        assert_eq!(programa.simbolos.get("FIN"), Some(&0x0117));
+        let listado = programa.listado();
+        let lineas: Vec<&str> = listado.lines().collect();
+        assert_eq!(lineas[1], " 0005 =         BDOS    EQU 5");
+        assert_eq!(lineas[3], " 0100 0E18      INICIO: MVI C, LOW (FIN - $) + 1");
+        assert_eq!(lineas[5], " 0105 00012001          DW INICIO, HIGH 1234H SHL 4, 0b101 * (2 + 3) MOD 7, NOT 0");
+        assert_eq!(lineas[6], " 0109 0400FFFF");
+        assert_eq!(lineas.len(), 13);
+    }
+
+    #[test]
+    fn test_set_redefinible() {
+        let programa = ensambla("
+; Cuenta con SET
+CUENTA  SET 1
+        ORG 100H
+INICIO: MVI A,CUENTA
+CUENTA  SET CUENTA+1
+        MVI B,CUENTA
+TEXTO:  DB 'HOLA MUNDO'
+        END INICIO
+").unwrap();
+        assert_eq!(&programa.bytes[..4], &[0x3E, 0x01, 0x06, 0x02]);
+        assert_eq!(programa.simbolos.get("CUENTA"), Some(&2));
+        assert!(ensambla("X EQU 1\nX SET 2").is_err());
+        assert!(ensambla("X SET 1\nX EQU 2").is_err());
+        assert_eq!(programa.listado().lines().nth(5), Some(" 0002 =         CUENTA  SET CUENTA+1"));
+    }
+
+    #[test]
+    fn test_errores_con_linea_y_columna() {
+        let casos = [
+            ("  MOV A,M\n  MOV M,M", 2, 7, "Operandos no válidos para MOV"),
+            ("  JMP DESTINO", 1, 7, "Símbolo no definido 'DESTINO'"),
+            ("  MVI A,256", 1, 9, "Valor 256 fuera de rango para 8 bits"),
+            ("  LDAX H", 1, 8, "Operandos no válidos para LDAX"),
+            ("X: NOP\nX: NOP", 2, 1, "Símbolo 'X' definido dos veces"),
+            ("  FOO 1", 1, 3, "Mnemónico desconocido 'FOO'"),
+            ("  DB 'abc", 1, 6, "Cadena sin cerrar"),
+            ("  DB 1/(2-2)", 1, 7, "División por cero"),
+            ("  MVI A,", 1, 9, "Operando vacío"),
+            ("  ORG 10\n  NOP\n  ORG 10\n  NOP", 4, 3, "La dirección 0x000A ya tiene código (ORG solapado)"),
+            ("  DS N\nN EQU 3", 1, 6, "Símbolo no definido 'N'"),
+        ];
+        for (fuente, linea, columna, mensaje) in casos {
+            let resultado = ensambla(fuente).err().unwrap_or_else(|| panic!("'{}' debería fallar", fuente));
+            assert_eq!(resultado, error(linea, columna, mensaje.to_string()), "{}", fuente);
+        }
+        assert_eq!(ensambla("  HLT Z").err().unwrap().to_string(), "Línea 1, columna 7: Operandos no válidos para HLT");
+    }
+}
+
+//*****************************************************************************
@@ -0,0 +1,318 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Instantáneas del estado de la máquina - Simulación CPU
+    Descripción:    Guarda y restaura registros, flags, ciclos, estado de interrupciones
+                    y todos los bancos de memoria en un formato binario versionado.
+    Referencias:
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::{sim_cpu_memoria::Endianess, sim_cpu_registros::CPU, sim_cpu_historial::Historial};
+use std::io::{Read, Write};
+
+//***************************************************************************** Notas
+/* Formato de la instantánea (todos los valores multibyte en little-endian)
+    Cabecera    "SCPU" + versión (u8)
+    Secciones   etiqueta de 4 bytes + longitud (u32) + contenido
+
+    "REGS"  A B C D E H L (7 bytes), flags empaquetados (u8), IX, IY, PC, SP (u16),
+            registro de instrucciones (u8), ciclos (u64), interrupciones (u8),
+            longitud del mnemónico (u8) + texto
+    "MEMO"  endianess (u8: 0 little, 1 big), banco activo (u8), número de bancos (u16)
+    "BANK"  índice del banco (u16) + contenido del banco (resto de la sección)
+    "FIN "  sección vacía que marca el final
+
+Al cargar, las secciones con etiquetas desconocidas se saltan gracias a su longitud, de
+forma que versiones posteriores pueden añadir información sin romper las anteriores. Ninguna
+sección puede pasar de LONGITUD_MAXIMA_SECCION (un banco de 64 KiB y su índice): la longitud
+se comprueba antes de reservar memoria para leerla.
+
+La instantánea sólo guarda la máquina. Al cargarla se conservan las herramientas de depuración
+de la CPU actual (perfilador, grafo de llamadas, cobertura, puntos de ruptura, vigilancia,
+símbolos y listado fuente), que no forman parte del formato; el historial se vacía porque sus
+deltas no valen para el estado restaurado.
+*/
+
+pub const MAGIA_ESTADO: &[u8; 4] = b"SCPU";
+pub const VERSION_ESTADO: u8 = 1;
+pub const LONGITUD_MAXIMA_SECCION: usize = 2 + 0x10000;
+
+fn escribe_seccion<W: Write>(escritor: &mut W, etiqueta: &[u8; 4], contenido: &[u8]) -> Result<(), String> {
+    escritor.write_all(etiqueta).map_err(|e| e.to_string())?;
+    escritor.write_all(&(contenido.len() as u32).to_le_bytes()).map_err(|e| e.to_string())?;
+    escritor.write_all(contenido).map_err(|e| e.to_string())
+}
+
+fn lee_bytes<R: Read>(lector: &mut R, cantidad: usize) -> Result<Vec<u8>, String> {
+    let mut buffer = vec![0; cantidad];
+    lector.read_exact(&mut buffer).map_err(|e| format!("Instantánea incompleta: {}", e))?;
+    Ok(buffer)
+}
+
+// Lector secuencial sobre el contenido de una sección
+struct Cursor<'a> {
+    datos: &'a [u8],
+    posicion: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn toma(&mut self, cantidad: usize) -> Result<&'a [u8], String> {
+        if self.posicion + cantidad > self.datos.len() {
+            return Err(String::from("Sección de la instantánea demasiado corta"));
+        }
+        let bytes = &self.datos[self.posicion..self.posicion + cantidad];
+        self.posicion += cantidad;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> Result<u8, String> { Ok(self.toma(1)?[0]) }
+    fn u16(&mut self) -> Result<u16, String> { Ok(u16::from_le_bytes(self.toma(2)?.try_into().unwrap())) }
+    fn u64(&mut self) -> Result<u64, String> { Ok(u64::from_le_bytes(self.toma(8)?.try_into().unwrap())) }
+}
+
+//*****************************************************************************
+impl CPU {
+    pub fn save_state<W: Write>(&self, escritor: &mut W) -> Result<(), String> {
+        escritor.write_all(MAGIA_ESTADO).map_err(|e| e.to_string())?;
+        escritor.write_all(&[VERSION_ESTADO]).map_err(|e| e.to_string())?;
+
+        let mut regs = vec![self.reg_a, self.reg_b, self.reg_c, self.reg_d, self.reg_e, self.reg_h, self.reg_l];
+        regs.push(self.flags.get_flags());
+        regs.extend_from_slice(&self.reg_ix.to_le_bytes());
+        regs.extend_from_slice(&self.reg_iy.to_le_bytes());
+        regs.extend_from_slice(&self.contador_de_programa.to_le_bytes());
+        regs.extend_from_slice(&self.puntero_de_pila.to_le_bytes());
+        regs.push(self.registro_instrucciones);
+        regs.extend_from_slice(&self.ciclos.to_le_bytes());
+        regs.push(self.interrupciones as u8);
+        let mnemonic = self.mnemonic.as_bytes();
+        let longitud_mnemonic = mnemonic.len().min(255);
+        regs.push(longitud_mnemonic as u8);
+        regs.extend_from_slice(&mnemonic[..longitud_mnemonic]);
+        escribe_seccion(escritor, b"REGS", &regs)?;
+
+        let mut memo = vec![
+            match self.memoria.endianess { Endianess::LittleEndian => 0, Endianess::BigEndian => 1 },
+            self.memoria.banco_actual,
+        ];
+        memo.extend_from_slice(&(self.memoria.segmento_memoria.len() as u16).to_le_bytes());
+        escribe_seccion(escritor, b"MEMO", &memo)?;
+
+        for (indice, banco) in self.memoria.segmento_memoria.iter().enumerate() {
+            if banco.len() > 0x10000 {
+                return Err(format!("El banco {} tiene {} bytes: máximo 65536", indice, banco.len()));
+            }
+            let mut bank = (indice as u16).to_le_bytes().to_vec();
+            bank.extend_from_slice(banco);
+            escribe_seccion(escritor, b"BANK", &bank)?;
+        }
+
+        escribe_seccion(escritor, b"FIN ", &[])
+    }
+
+    pub fn load_state<R: Read>(&mut self, lector: &mut R) -> Result<(), String> {
+        let cabecera = lee_bytes(lector, 5)?;
+        if &cabecera[0..4] != MAGIA_ESTADO {
+            return Err(String::from("El archivo no es una instantánea del simulador"));
+        }
+        if cabecera[4] == 0 || cabecera[4] > VERSION_ESTADO {
+            return Err(format!("Versión de instantánea no soportada: {} (máxima {})", cabecera[4], VERSION_ESTADO));
+        }
+
+        // Se reconstruye el estado completo antes de tocar la CPU, así un error no la deja a medias
+        let mut nueva = CPU::new();
+        let mut num_bancos: Option<usize> = None;
+        let mut bancos: Vec<Option<Vec<u8>>> = Vec::new();
+        let mut hay_registros = false;
+
+        loop {
+            let etiqueta = lee_bytes(lector, 4)?;
+            let longitud = u32::from_le_bytes(lee_bytes(lector, 4)?.try_into().unwrap()) as usize;
+            if longitud > LONGITUD_MAXIMA_SECCION {
+                return Err(format!("Sección '{}' de {} bytes: máximo {}", String::from_utf8_lossy(&etiqueta), longitud, LONGITUD_MAXIMA_SECCION));
+            }
+            let contenido = lee_bytes(lector, longitud)?;
+            let mut cursor = Cursor { datos: &contenido, posicion: 0 };
+
+            match &etiqueta[..] {
+                b"REGS" => {
+                    let r = cursor.toma(7)?;
+                    nueva.reg_a = r[0]; nueva.reg_b = r[1]; nueva.reg_c = r[2]; nueva.reg_d = r[3];
+                    nueva.reg_e = r[4]; nueva.reg_h = r[5]; nueva.reg_l = r[6];
+                    nueva.flags.set_flags(cursor.u8()?);
+                    nueva.reg_ix = cursor.u16()?;
+                    nueva.reg_iy = cursor.u16()?;
+                    nueva.contador_de_programa = cursor.u16()?;
+                    nueva.puntero_de_pila = cursor.u16()?;
+                    nueva.registro_instrucciones = cursor.u8()?;
+                    nueva.ciclos = cursor.u64()?;
+                    nueva.interrupciones = cursor.u8()? != 0;
+                    let longitud_mnemonic = cursor.u8()? as usize;
+                    nueva.mnemonic = String::from_utf8_lossy(cursor.toma(longitud_mnemonic)?).to_string();
+                    hay_registros = true;
+                }
+                b"MEMO" => {
+                    nueva.memoria.endianess = match cursor.u8()? {
+                        0 => Endianess::LittleEndian,
+                        1 => Endianess::BigEndian,
+                        otro => return Err(format!("Endianess desconocido en la instantánea: {}", otro)),
+                    };
+                    nueva.memoria.banco_actual = cursor.u8()?;
+                    let total = cursor.u16()? as usize;
+                    num_bancos = Some(total);
+                    bancos = vec![None; total];
+                }
+                b"BANK" => {
+                    let total = num_bancos.ok_or(String::from("Sección BANK antes de MEMO"))?;
+                    let indice = cursor.u16()? as usize;
+                    if indice >= total {
+                        return Err(format!("Banco {} fuera de rango ({} bancos)", indice, total));
+                    }
+                    bancos[indice] = Some(contenido[cursor.posicion..].to_vec());
+                }
+                b"FIN " => break,
+                _ => {}     // Sección de una versión posterior: se ignora
+            }
+        }
+
+        if !hay_registros {
+            return Err(String::from("La instantánea no contiene la sección REGS"));
+        }
+        let total = num_bancos.ok_or(String::from("La instantánea no contiene la sección MEMO"))?;
+        if total == 0 || nueva.memoria.banco_actual as usize >= total {
+            return Err(String::from("Banco activo fuera de rango en la instantánea"));
+        }
+        let mut segmentos = Vec::with_capacity(total);
+        for (indice, banco) in bancos.into_iter().enumerate() {
+            segmentos.push(banco.ok_or(format!("Falta el banco {} en la instantánea", indice))?);
+        }
+        nueva.memoria.segmento_memoria = segmentos;
+
+        // El historial de deltas no es válido para el estado restaurado: se conserva vacío
+        nueva.historial = self.historial.take().map(|h| Historial::new(h.capacidad));
+        nueva.perfilador = self.perfilador.take();
+        nueva.grafo_llamadas = self.grafo_llamadas.take();
+        nueva.memoria.cobertura = self.memoria.cobertura.take();
+        nueva.vigilancia = self.vigilancia.take();
+        nueva.puntos_ruptura = self.puntos_ruptura.take();
+        nueva.simbolos = self.simbolos.take();
+        nueva.listado = self.listado.take();
+        *self = nueva;
+        Ok(())
+    }
+
+    pub fn guardar_estado_archivo(&self, ruta: &str) -> Result<(), String> {
+        let mut archivo = std::fs::File::create(ruta).map_err(|e| format!("No se puede crear '{}': {}", ruta, e))?;
+        self.save_state(&mut archivo)
+    }
+
+    pub fn cargar_estado_archivo(&mut self, ruta: &str) -> Result<(), String> {
+        let mut archivo = std::fs::File::open(ruta).map_err(|e| format!("No se puede abrir '{}': {}", ruta, e))?;
+        self.load_state(&mut archivo)
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proyectos::{sim_cpu_simbolos::TablaSimbolos, sim_cpu_listado::ListadoFuente, sim_cpu_llamadas::GrafoLlamadas};
+    use asm8080::asm8080;
+
+    fn cpu_de_prueba() -> CPU {
+        let mut cpu = CPU::new();
+        cpu.cargar_programa(&asm8080! {
+            mvi a, 3bh
+            mvi b, 0fh
+            add b
+            ei
+            sta 20h
+        });
+        for _ in 0..5 {
+            let instruccion = cpu.busca_instruccion();
+            let (opcode, operandos) = cpu.decodifica_instruccion(instruccion);
+            cpu.ejecuta_instruccion(opcode, operandos);
+        }
+        cpu.memoria.crear_segmento(256);
+        cpu.memoria.set_banco_activo(1);
+        cpu.memoria.escribir_memoria(0x10, 0xAA);
+        cpu.set_hl(0x1234);
+        cpu.puntero_de_pila = 0x03F0;
+        cpu
+    }
+
+    #[test]
+    fn test_guardar_y_restaurar() {
+        let cpu = cpu_de_prueba();
+        let mut buffer = Vec::new();
+        cpu.save_state(&mut buffer).unwrap();
+
+        let mut restaurada = CPU::new();
+        restaurada.load_state(&mut buffer.as_slice()).unwrap();
+        assert_eq!(restaurada.reg_a, 0x4A);
+        assert_eq!(restaurada.reg_b, 0x0f);
+        assert_eq!(restaurada.get_hl(), 0x1234);
+        assert_eq!(restaurada.flags.get_flags(), cpu.flags.get_flags());
+        assert_eq!(restaurada.contador_de_programa, 9);
+        assert_eq!(restaurada.puntero_de_pila, 0x03F0);
+        assert_eq!(restaurada.ciclos, 7 + 7 + 4 + 4 + 13);
+        assert!(restaurada.interrupciones);
+        assert_eq!(restaurada.mnemonic, "STA addr");
+        assert_eq!(restaurada.memoria.get_banco_activo(), 1);
+        assert_eq!(restaurada.memoria.segmento_memoria, cpu.memoria.segmento_memoria);
+        assert_eq!(restaurada.memoria.leer_memoria(0x10), 0xAA);
+        restaurada.memoria.set_banco_activo(0);
+        assert_eq!(restaurada.memoria.leer_memoria(0x20), 0x4A);
+    }
+
+    #[test]
+    fn test_seccion_desconocida_se_ignora() {
+        let cpu = cpu_de_prueba();
+        let mut buffer = Vec::new();
+        cpu.save_state(&mut buffer).unwrap();
+        // Inserta una sección de una versión futura justo después de la cabecera
+        let mut extra = b"XTRA".to_vec();
+        extra.extend_from_slice(&3u32.to_le_bytes());
+        extra.extend_from_slice(&[1, 2, 3]);
+        buffer.splice(5..5, extra);
+
+        let mut restaurada = CPU::new();
+        restaurada.load_state(&mut buffer.as_slice()).unwrap();
+        assert_eq!(restaurada.reg_a, 0x4A);
+    }
+
+    #[test]
+    fn test_errores_de_formato() {
+        let mut cpu = cpu_de_prueba();
+        assert!(cpu.load_state(&mut &b"NADA\x01"[..]).is_err());
+        assert!(cpu.load_state(&mut &b"SCPU\x09"[..]).unwrap_err().contains("Versión"));
+
+        let mut buffer = Vec::new();
+        cpu.save_state(&mut buffer).unwrap();
+        buffer.truncate(buffer.len() - 20);
+        assert!(cpu.load_state(&mut buffer.as_slice()).unwrap_err().contains("incompleta"));
+        // Una longitud de sección absurda se rechaza antes de reservar memoria
+        let mut enorme = b"SCPU\x01BANK".to_vec();
+        enorme.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(cpu.load_state(&mut enorme.as_slice()).unwrap_err().contains("máximo"));
+        // Un error no modifica el estado actual
+        assert_eq!(cpu.reg_a, 0x4A);
+    }
+
+    #[test]
+    fn test_se_conservan_simbolos_y_listado() {
+        let mut buffer = Vec::new();
+        cpu_de_prueba().save_state(&mut buffer).unwrap();
+        let mut cpu = CPU::new();
+        cpu.simbolos = Some(TablaSimbolos::analiza("0006 GUARDA\n").unwrap());
+        cpu.listado = Some(ListadoFuente::analiza("prueba.prn", " 0006 322000   sta 20h\n"));
+        cpu.grafo_llamadas = Some(Box::new(GrafoLlamadas::new()));
+        cpu.load_state(&mut buffer.as_slice()).unwrap();
+        assert_eq!(cpu.nombre_direccion(0x0006).as_deref(), Some("GUARDA"));
+        assert!(cpu.listado.is_some() && cpu.grafo_llamadas.is_some());
+    }
+}
+
+//*****************************************************************************
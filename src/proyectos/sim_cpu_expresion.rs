@@ -0,0 +1,410 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Evaluador de expresiones sobre el estado de la máquina - Simulación CPU
+    Descripción:    Pequeño lenguaje de expresiones para puntos de ruptura condicionales y
+                    para cualquier interfaz (pancurses, GTK, línea de comandos): registros,
+                    flags, memoria, aritmética, comparaciones y operadores lógicos.
+    Referencias:
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+use super::sim_cpu_simbolos::TablaSimbolos;
+
+//***************************************************************************** Notas
+/* Sintaxis (sin distinguir mayúsculas y minúsculas)
+    Números:        255, 0xFF, 0FFh, 0b1010
+    Registros:      A B C D E H L  (8 bits)    BC DE HL SP PC IX IY  (16 bits)
+    Flags:          Z S P CY AC  (0 ó 1; C es el registro, el acarreo es CY)
+    Memoria:        [HL]  [0x1234]  [SP+1]     (byte en la dirección, banco activo)
+    Símbolos:       BUCLE  SUB1.OTRA  [CONTADOR]  (con tabla de símbolos; los registros y flags
+                    tienen prioridad, así que una etiqueta 'C' no se puede usar)
+    Operadores (de menor a mayor precedencia):
+        ||   &&   == != < <= > >=   |   ^   &   + -   ! ~ - (unarios)
+    Los resultados lógicos valen 1 (verdadero) o 0 (falso); cualquier valor distinto de 0 es
+    verdadero.
+
+    let expresion = Expresion::analiza("PC == 0x0010 && (A > 0x3B || [HL] == 0)")?;
+    if expresion.es_verdadera(&cpu)? { ... }         // Err si lee memoria fuera del banco
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Registro { A, B, C, D, E, H, L, BC, DE, HL, SP, PC, IX, IY }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flag { Z, S, P, CY, AC }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OperadorBinario { O, Y, Igual, Distinto, Menor, MenorIgual, Mayor, MayorIgual, OBits, OxBits, YBits, Suma, Resta }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OperadorUnario { No, Complemento, Negativo }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expresion {
+    Numero(i64),
+    Registro(Registro),
+    Flag(Flag),
+    Memoria(Box<Expresion>),
+    Unaria(OperadorUnario, Box<Expresion>),
+    Binaria(OperadorBinario, Box<Expresion>, Box<Expresion>),
+}
+
+//***************************************************************************** Análisis léxico
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Numero(i64),
+    Nombre(String),
+    Simbolo(&'static str),
+}
+
+const SIMBOLOS: [&str; 19] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "|", "^", "&", "+", "-", "!", "~", "(", ")", "[", "]",
+];
+
+fn numero(texto: &str) -> Result<i64, String> {
+    let minusculas = texto.to_lowercase();
+    let resultado = if let Some(hex) = minusculas.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16)
+    } else if let Some(bin) = minusculas.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2)
+    } else if let Some(hex) = minusculas.strip_suffix('h') {
+        i64::from_str_radix(hex, 16)
+    } else {
+        minusculas.parse::<i64>()
+    };
+    resultado.map_err(|_| format!("Número no válido '{}'", texto))
+}
+
+fn tokens(texto: &str) -> Result<Vec<Token>, String> {
+    let caracteres: Vec<char> = texto.chars().collect();
+    let mut lista = Vec::new();
+    let mut i = 0;
+    'siguiente: while i < caracteres.len() {
+        let c = caracteres[i];
+        if c.is_whitespace() { i += 1; continue; }
+        if c.is_ascii_alphanumeric() || "_?@".contains(c) {
+            let inicio = i;
+            while i < caracteres.len() && (caracteres[i].is_ascii_alphanumeric() || "_?@.".contains(caracteres[i])) { i += 1; }
+            let palabra: String = caracteres[inicio..i].iter().collect();
+            // Los números hexadecimales con sufijo 'h' deben empezar por un dígito (0FFh)
+            if c.is_ascii_digit() { lista.push(Token::Numero(numero(&palabra)?)); }
+            else { lista.push(Token::Nombre(palabra.to_uppercase())); }
+            continue;
+        }
+        for simbolo in SIMBOLOS.iter() {
+            let longitud = simbolo.len();
+            if i + longitud <= caracteres.len() && caracteres[i..i + longitud].iter().collect::<String>() == *simbolo {
+                lista.push(Token::Simbolo(simbolo));
+                i += longitud;
+                continue 'siguiente;
+            }
+        }
+        return Err(format!("Carácter no válido '{}' en la posición {}", c, i));
+    }
+    Ok(lista)
+}
+
+//***************************************************************************** Análisis sintáctico
+struct Analizador<'a> {
+    tokens: Vec<Token>,
+    posicion: usize,
+    simbolos: Option<&'a TablaSimbolos>,
+}
+
+impl<'a> Analizador<'a> {
+    fn siguiente_es(&self, simbolo: &str) -> bool {
+        matches!(self.tokens.get(self.posicion), Some(Token::Simbolo(s)) if *s == simbolo)
+    }
+
+    fn consume(&mut self, simbolo: &str) -> bool {
+        if self.siguiente_es(simbolo) { self.posicion += 1; true } else { false }
+    }
+
+    fn espera(&mut self, simbolo: &str) -> Result<(), String> {
+        if self.consume(simbolo) { Ok(()) } else { Err(format!("Se esperaba '{}'", simbolo)) }
+    }
+
+    // Un nivel de operadores binarios asociativos por la izquierda
+    fn nivel(&mut self, operadores: &[(&str, OperadorBinario)], siguiente: fn(&mut Analizador<'a>) -> Result<Expresion, String>) -> Result<Expresion, String> {
+        let mut izquierda = siguiente(self)?;
+        'bucle: loop {
+            for (simbolo, operador) in operadores.iter() {
+                if self.consume(simbolo) {
+                    let derecha = siguiente(self)?;
+                    izquierda = Expresion::Binaria(*operador, Box::new(izquierda), Box::new(derecha));
+                    continue 'bucle;
+                }
+            }
+            return Ok(izquierda);
+        }
+    }
+
+    fn o(&mut self) -> Result<Expresion, String> { self.nivel(&[("||", OperadorBinario::O)], Analizador::y) }
+
+    fn y(&mut self) -> Result<Expresion, String> { self.nivel(&[("&&", OperadorBinario::Y)], Analizador::comparacion) }
+
+    fn comparacion(&mut self) -> Result<Expresion, String> {
+        self.nivel(&[("==", OperadorBinario::Igual), ("!=", OperadorBinario::Distinto), ("<=", OperadorBinario::MenorIgual)
+            , (">=", OperadorBinario::MayorIgual), ("<", OperadorBinario::Menor), (">", OperadorBinario::Mayor)], Analizador::o_bits)
+    }
+
+    fn o_bits(&mut self) -> Result<Expresion, String> { self.nivel(&[("|", OperadorBinario::OBits)], Analizador::ox_bits) }
+
+    fn ox_bits(&mut self) -> Result<Expresion, String> { self.nivel(&[("^", OperadorBinario::OxBits)], Analizador::y_bits) }
+
+    fn y_bits(&mut self) -> Result<Expresion, String> { self.nivel(&[("&", OperadorBinario::YBits)], Analizador::suma) }
+
+    fn suma(&mut self) -> Result<Expresion, String> {
+        self.nivel(&[("+", OperadorBinario::Suma), ("-", OperadorBinario::Resta)], Analizador::unaria)
+    }
+
+    fn unaria(&mut self) -> Result<Expresion, String> {
+        for (simbolo, operador) in [("!", OperadorUnario::No), ("~", OperadorUnario::Complemento), ("-", OperadorUnario::Negativo)] {
+            if self.consume(simbolo) { return Ok(Expresion::Unaria(operador, Box::new(self.unaria()?))); }
+        }
+        self.primaria()
+    }
+
+    fn primaria(&mut self) -> Result<Expresion, String> {
+        if self.consume("(") {
+            let expresion = self.o()?;
+            self.espera(")")?;
+            return Ok(expresion);
+        }
+        if self.consume("[") {
+            let direccion = self.o()?;
+            self.espera("]")?;
+            return Ok(Expresion::Memoria(Box::new(direccion)));
+        }
+        let token = self.tokens.get(self.posicion).cloned().ok_or("Expresión incompleta")?;
+        self.posicion += 1;
+        match token {
+            Token::Numero(valor) => Ok(Expresion::Numero(valor)),
+            Token::Nombre(nombre) => nombre_a_expresion(&nombre).or_else(|error| {
+                // Las etiquetas se sustituyen por su dirección al analizar
+                match self.simbolos.and_then(|s| s.busca(&nombre)) {
+                    Some(direccion) => Ok(Expresion::Numero(direccion as i64)),
+                    None if self.simbolos.is_some() => Err(format!("Registro, flag o símbolo desconocido '{}'", nombre)),
+                    None => Err(error),
+                }
+            }),
+            Token::Simbolo(simbolo) => Err(format!("Símbolo inesperado '{}'", simbolo)),
+        }
+    }
+}
+
+fn nombre_a_expresion(nombre: &str) -> Result<Expresion, String> {
+    let registro = match nombre {
+        "A" => Registro::A, "B" => Registro::B, "C" => Registro::C, "D" => Registro::D,
+        "E" => Registro::E, "H" => Registro::H, "L" => Registro::L,
+        "BC" => Registro::BC, "DE" => Registro::DE, "HL" => Registro::HL,
+        "SP" => Registro::SP, "PC" => Registro::PC, "IX" => Registro::IX, "IY" => Registro::IY,
+        _ => {
+            let flag = match nombre {
+                "Z" => Flag::Z, "S" => Flag::S, "P" => Flag::P, "CY" => Flag::CY, "AC" => Flag::AC,
+                _ => return Err(format!("Registro o flag desconocido '{}'", nombre)),
+            };
+            return Ok(Expresion::Flag(flag));
+        }
+    };
+    Ok(Expresion::Registro(registro))
+}
+
+//***************************************************************************** Evaluación
+impl Expresion {
+    pub fn analiza(texto: &str) -> Result<Expresion, String> {
+        Expresion::analiza_con_simbolos(texto, None)
+    }
+
+    pub fn analiza_con_simbolos(texto: &str, simbolos: Option<&TablaSimbolos>) -> Result<Expresion, String> {
+        let mut analizador = Analizador { tokens: tokens(texto)?, posicion: 0, simbolos };
+        if analizador.tokens.is_empty() { return Err(String::from("Expresión vacía")); }
+        let expresion = analizador.o()?;
+        if analizador.posicion != analizador.tokens.len() {
+            return Err(format!("Sobra texto al final de la expresión '{}'", texto));
+        }
+        Ok(expresion)
+    }
+
+    // Las direcciones de memoria fuera del banco activo son un error
+    pub fn evalua(&self, cpu: &CPU) -> Result<i64, String> {
+        let valor = match self {
+            Expresion::Numero(valor) => *valor,
+            Expresion::Registro(registro) => match registro {
+                Registro::A => cpu.reg_a as i64,
+                Registro::B => cpu.reg_b as i64,
+                Registro::C => cpu.reg_c as i64,
+                Registro::D => cpu.reg_d as i64,
+                Registro::E => cpu.reg_e as i64,
+                Registro::H => cpu.reg_h as i64,
+                Registro::L => cpu.reg_l as i64,
+                Registro::BC => cpu.get_bc() as i64,
+                Registro::DE => cpu.get_de() as i64,
+                Registro::HL => cpu.get_hl() as i64,
+                Registro::SP => cpu.puntero_de_pila as i64,
+                Registro::PC => cpu.contador_de_programa as i64,
+                Registro::IX => cpu.reg_ix as i64,
+                Registro::IY => cpu.reg_iy as i64,
+            },
+            Expresion::Flag(flag) => match flag {
+                Flag::Z => cpu.flags.zero as i64,
+                Flag::S => cpu.flags.sign as i64,
+                Flag::P => cpu.flags.parity_overflow as i64,
+                Flag::CY => cpu.flags.carry as i64,
+                Flag::AC => cpu.flags.half_carry as i64,
+            },
+            Expresion::Memoria(direccion) => {
+                let direccion = direccion.evalua(cpu)?;
+                let direccion = u16::try_from(direccion).map_err(|_| format!("Dirección {} fuera de rango", direccion))?;
+                cpu.memoria.leer_memoria_comprobada(direccion)? as i64
+            }
+            Expresion::Unaria(operador, valor) => {
+                let valor = valor.evalua(cpu)?;
+                match operador {
+                    OperadorUnario::No => (valor == 0) as i64,
+                    OperadorUnario::Complemento => !valor,
+                    OperadorUnario::Negativo => valor.wrapping_neg(),
+                }
+            }
+            Expresion::Binaria(operador, izquierda, derecha) => {
+                let a = izquierda.evalua(cpu)?;
+                // Evaluación en cortocircuito para los operadores lógicos
+                match operador {
+                    OperadorBinario::O => return Ok((a != 0 || derecha.evalua(cpu)? != 0) as i64),
+                    OperadorBinario::Y => return Ok((a != 0 && derecha.evalua(cpu)? != 0) as i64),
+                    _ => {}
+                }
+                let b = derecha.evalua(cpu)?;
+                match operador {
+                    OperadorBinario::Igual => (a == b) as i64,
+                    OperadorBinario::Distinto => (a != b) as i64,
+                    OperadorBinario::Menor => (a < b) as i64,
+                    OperadorBinario::MenorIgual => (a <= b) as i64,
+                    OperadorBinario::Mayor => (a > b) as i64,
+                    OperadorBinario::MayorIgual => (a >= b) as i64,
+                    OperadorBinario::OBits => a | b,
+                    OperadorBinario::OxBits => a ^ b,
+                    OperadorBinario::YBits => a & b,
+                    OperadorBinario::Suma => a.wrapping_add(b),
+                    OperadorBinario::Resta => a.wrapping_sub(b),
+                    OperadorBinario::O | OperadorBinario::Y => unreachable!(),
+                }
+            }
+        };
+        Ok(valor)
+    }
+
+    pub fn es_verdadera(&self, cpu: &CPU) -> Result<bool, String> {
+        Ok(self.evalua(cpu)? != 0)
+    }
+
+    // Indica si el resultado es de 8 bits (para mostrarlo con dos dígitos hexadecimales)
+    pub fn es_8_bits(&self) -> bool {
+        match self {
+            Expresion::Numero(valor) => (0..=0xFF).contains(valor),
+            Expresion::Registro(registro) => matches!(registro, Registro::A | Registro::B | Registro::C | Registro::D
+                | Registro::E | Registro::H | Registro::L),
+            Expresion::Flag(_) | Expresion::Memoria(_) => true,
+            Expresion::Unaria(_, valor) => valor.es_8_bits(),
+            Expresion::Binaria(operador, izquierda, derecha) => match operador {
+                OperadorBinario::OBits | OperadorBinario::OxBits | OperadorBinario::YBits
+                | OperadorBinario::Suma | OperadorBinario::Resta => izquierda.es_8_bits() && derecha.es_8_bits(),
+                _ => true,
+            },
+        }
+    }
+}
+
+// Sustituye cada {expresión} del texto por su valor en hexadecimal ({expresión:d} en decimal)
+pub fn formatea_mensaje(plantilla: &str, cpu: &CPU) -> Result<String, String> {
+    let mut salida = String::new();
+    let mut resto = plantilla;
+    while let Some(inicio) = resto.find('{') {
+        salida.push_str(&resto[..inicio]);
+        let fin = resto[inicio..].find('}').ok_or(format!("Falta '}}' en el mensaje '{}'", plantilla))? + inicio;
+        let contenido = &resto[inicio + 1..fin];
+        let (texto, decimal) = match contenido.strip_suffix(":d") {
+            Some(texto) => (texto, true),
+            None => (contenido, false),
+        };
+        let expresion = Expresion::analiza_con_simbolos(texto, cpu.simbolos.as_ref())?;
+        let valor = expresion.evalua(cpu)?;
+        salida.push_str(&if decimal { format!("{}", valor) }
+            else if expresion.es_8_bits() && (0..=0xFF).contains(&valor) { format!("0x{:02X}", valor) }
+            else { format!("0x{:04X}", valor) });
+        resto = &resto[fin + 1..];
+    }
+    salida.push_str(resto);
+    Ok(salida)
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpu_de_prueba() -> CPU {
+        let mut cpu = CPU::new();
+        cpu.reg_a = 0x3C;
+        cpu.reg_h = 0x00;
+        cpu.reg_l = 0x10;
+        cpu.reg_b = 0x12;
+        cpu.reg_c = 0x34;
+        cpu.flags.zero = true;
+        cpu.memoria.escribir_memoria(0x0010, 0xAA);
+        cpu.memoria.escribir_memoria(0x0011, 0x55);
+        cpu
+    }
+
+    #[test]
+    fn test_evaluacion() {
+        let cpu = cpu_de_prueba();
+        let casos = [
+            ("A", 0x3C), ("bc", 0x1234), ("0FFh + 1", 0x100), ("0b101", 5), ("[HL]", 0xAA), ("[hl + 1]", 0x55),
+            ("Z", 1), ("CY", 0), ("A == 0x3C && Z", 1), ("A > 0x3C || !Z", 0), ("1 + 2 == 3", 1),
+            ("B & 0xF0 | C & 0x0F", 0x14), ("~A & 0xFF", 0xC3), ("-1 < 0", 1), ("(1 || 0) && (PC != 5)", 1),
+        ];
+        for (texto, esperado) in casos {
+            assert_eq!(Expresion::analiza(texto).unwrap().evalua(&cpu), Ok(esperado), "{}", texto);
+        }
+    }
+
+    #[test]
+    fn test_errores_y_mensajes() {
+        assert!(Expresion::analiza("").is_err());
+        assert!(Expresion::analiza("A ==").is_err());
+        assert!(Expresion::analiza("[HL").is_err());
+        assert!(Expresion::analiza("X + 1").is_err());
+        assert!(Expresion::analiza("A $ 1").is_err());
+        assert!(Expresion::analiza("A 1").is_err());
+        let cpu = cpu_de_prueba();
+        assert_eq!(formatea_mensaje("A={A} HL={HL} [HL]={[HL]:d}", &cpu), Ok(String::from("A=0x3C HL=0x0010 [HL]=170")));
+        assert!(formatea_mensaje("A={A", &cpu).is_err());
+        // Memoria fuera del banco de 1 KiB de la CPU
+        assert_eq!(Expresion::analiza("[0x3FF]").unwrap().evalua(&cpu), Ok(0));
+        assert!(Expresion::analiza("[0x400]").unwrap().evalua(&cpu).unwrap_err().contains("fuera del banco"));
+        assert!(Expresion::analiza("[-1]").unwrap().evalua(&cpu).is_err());
+        assert!(formatea_mensaje("{[HL + 0x400]}", &cpu).is_err());
+    }
+
+    #[test]
+    fn test_simbolos() {
+        let mut tabla = TablaSimbolos::new();
+        tabla.agrega("TABLA", 0x0010);
+        tabla.agrega("SUB1.OTRA", 0x0200);
+        tabla.agrega("C", 0x0300);
+        let mut cpu = cpu_de_prueba();
+        assert_eq!(Expresion::analiza_con_simbolos("[tabla+1] + SUB1.OTRA", Some(&tabla)).unwrap().evalua(&cpu), Ok(0x255));
+        assert_eq!(Expresion::analiza_con_simbolos("C", Some(&tabla)).unwrap().evalua(&cpu), Ok(0x34));
+        assert_eq!(Expresion::analiza_con_simbolos("NADA", Some(&tabla)).err().unwrap(), "Registro, flag o símbolo desconocido 'NADA'");
+        assert!(Expresion::analiza("TABLA").is_err());
+        cpu.simbolos = Some(tabla);
+        assert_eq!(formatea_mensaje("{[TABLA]}", &cpu), Ok(String::from("0xAA")));
+    }
+}
+
+//*****************************************************************************
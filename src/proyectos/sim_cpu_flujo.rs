@@ -0,0 +1,353 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Análisis de flujo de control - Simulación CPU
+    Descripción:    Recorrido recursivo del código desde los puntos de entrada (reset, vectores
+                    RST y direcciones dadas) siguiendo saltos, llamadas y retornos. Separa el
+                    código de los datos, agrupa las instrucciones en bloques básicos y exporta
+                    el grafo de flujo de control en formato DOT de Graphviz.
+    Referencias:    https://graphviz.org/doc/info/lang.html
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_memoria::BancosMemoria;
+use super::sim_cpu_desensamblador::{desensambla_instruccion, InstruccionDesensamblada, OpcionesDesensamblado};
+use std::collections::{BTreeMap, BTreeSet};
+
+//***************************************************************************** Notas
+/* Uso
+    let opciones = OpcionesDesensamblado::new();
+    let grafo = analiza_flujo(&cpu.memoria, &puntos_entrada(false, &[0x0100]), &opciones);
+    println!("{}", grafo.listado(&cpu.memoria, 0x0000, 0x0024, &opciones));   // Código y DB
+    std::fs::write("cfg.dot", grafo.dot("programa")).unwrap();                // dot -Tsvg cfg.dot
+
+A diferencia del barrido lineal (desensambla_rango), sólo se decodifica lo que es alcanzable desde
+las entradas, así que los bytes de datos mezclados con el código salen como DB. Se supone que toda
+subrutina vuelve: después de CALL, Ccc y RST se sigue con la instrucción siguiente. PCHL es un salto
+indirecto que no se puede resolver sin ejecutar; se anota en 'indirectos' y el bloque termina ahí.
+
+Un bloque básico termina en cualquier transferencia de control (también CALL y RST, para que la
+llamada aparezca como arista) o justo antes de una dirección a la que se salta. El DOT se escribe
+como texto, no hace falta el crate graphviz-rust.
+*/
+
+// Vectores de las instrucciones RST 0..7
+pub const VECTORES_RST: [u16; 8] = [0x0000, 0x0008, 0x0010, 0x0018, 0x0020, 0x0028, 0x0030, 0x0038];
+
+// Reset (0x0000), opcionalmente los vectores RST y las direcciones del usuario
+pub fn puntos_entrada(incluir_rst: bool, usuario: &[u16]) -> Vec<u16> {
+    let mut entradas = vec![0x0000];
+    if incluir_rst { entradas.extend_from_slice(&VECTORES_RST[1..]); }
+    entradas.extend_from_slice(usuario);
+    entradas
+}
+
+//***************************************************************************** Clasificación
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Flujo {
+    Sigue,                              // Instrucción normal
+    Salto,                              // JMP
+    SaltoCondicional,                   // Jcc
+    Llamada,                            // CALL, Ccc, RST
+    Retorno,                            // RET
+    RetornoCondicional,                 // Rcc
+    Indirecto,                          // PCHL
+    Parada,                             // HLT
+}
+
+fn flujo(instruccion: &InstruccionDesensamblada) -> Flujo {
+    let m = instruccion.mnemonico.as_str();
+    match m {
+        "JMP" => Flujo::Salto,
+        "RET" => Flujo::Retorno,
+        "PCHL" => Flujo::Indirecto,
+        "HLT" => Flujo::Parada,
+        "RST" | "CALL" => Flujo::Llamada,
+        "RNZ" | "RZ" | "RNC" | "RC" | "RPO" | "RPE" | "RP" | "RM" => Flujo::RetornoCondicional,
+        _ if instruccion.destino.is_some() && m.starts_with('J') => Flujo::SaltoCondicional,
+        _ if instruccion.destino.is_some() && m.starts_with('C') => Flujo::Llamada,
+        _ => Flujo::Sigue,
+    }
+}
+
+//***************************************************************************** Grafo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TipoArista {
+    Secuencial,                         // Paso al bloque siguiente sin salto
+    Salto,                              // JMP
+    Tomado,                             // Jcc cuando se cumple la condición
+    NoTomado,                           // Jcc cuando no se cumple
+    Llamada,                            // CALL, Ccc o RST hacia la subrutina
+    Continuacion,                       // Vuelta de la subrutina a la instrucción siguiente
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arista {
+    pub origen: u16,                    // Dirección de inicio de los bloques
+    pub destino: u16,
+    pub tipo: TipoArista,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloqueBasico {
+    pub inicio: u16,
+    pub instrucciones: Vec<InstruccionDesensamblada>,
+}
+
+impl BloqueBasico {
+    // Dirección siguiente al último byte del bloque
+    pub fn fin(&self) -> u32 {
+        self.instrucciones.last().map(|i| i.direccion as u32 + i.longitud() as u32).unwrap_or(self.inicio as u32)
+    }
+}
+
+pub struct GrafoFlujo {
+    pub entradas: Vec<u16>,
+    pub bloques: BTreeMap<u16, BloqueBasico>,
+    pub aristas: Vec<Arista>,
+    pub codigo: BTreeSet<u16>,          // Bytes que forman parte de alguna instrucción alcanzable
+    pub indirectos: Vec<u16>,           // Direcciones de PCHL
+    pub solapados: Vec<u16>,            // Destinos que caen en medio de otra instrucción
+}
+
+impl GrafoFlujo {
+    pub fn es_codigo(&self, direccion: u16) -> bool {
+        self.codigo.contains(&direccion)
+    }
+
+    // Bloque que contiene la dirección
+    pub fn bloque_de(&self, direccion: u16) -> Option<&BloqueBasico> {
+        self.bloques.range(..=direccion).next_back().map(|(_, b)| b).filter(|b| (direccion as u32) < b.fin())
+    }
+
+    pub fn sucesores(&self, bloque: u16) -> Vec<&Arista> {
+        self.aristas.iter().filter(|a| a.origen == bloque).collect()
+    }
+
+    pub fn predecesores(&self, bloque: u16) -> Vec<&Arista> {
+        self.aristas.iter().filter(|a| a.destino == bloque).collect()
+    }
+
+    // Listado del rango con las instrucciones alcanzables y el resto como DB (un byte por línea)
+    pub fn listado(&self, memoria: &BancosMemoria, inicio: u16, fin: u16, opciones: &OpcionesDesensamblado) -> String {
+        let mut salida = String::new();
+        let mut dir = inicio as u32;
+        while dir <= fin as u32 {
+            let instruccion = self.bloque_de(dir as u16).and_then(|b| b.instrucciones.iter().find(|i| i.direccion as u32 == dir));
+            let instruccion = match instruccion {
+                Some(i) => i.clone(),
+                None => {
+                    let byte = desensambla_instruccion(memoria, dir as u16, opciones).bytes.first().copied();
+                    let Some(byte) = byte else { break };
+                    InstruccionDesensamblada {
+                        direccion: dir as u16,
+                        bytes: vec![byte],
+                        mnemonico: String::from("DB"),
+                        operandos: vec![opciones.byte(byte)],
+                        documentada: true,
+                        destino: None,
+                    }
+                }
+            };
+            if self.bloques.contains_key(&(dir as u16)) {
+                let etiqueta = opciones.simbolos.get(&(dir as u16)).cloned().unwrap_or(format!("L{:04X}", dir));
+                salida.push_str(&format!("{}:\n", etiqueta));
+            }
+            salida.push_str(&format!("{}\n", instruccion.linea()));
+            dir += instruccion.longitud().max(1) as u32;
+        }
+        salida
+    }
+
+    // Grafo en formato DOT: un nodo por bloque con su desensamblado y aristas según el tipo
+    pub fn dot(&self, nombre: &str) -> String {
+        let mut salida = format!("digraph \"{}\" {{\n", escapa_dot(nombre));
+        salida.push_str("    node [shape=box, fontname=\"Courier\"];\n");
+        for bloque in self.bloques.values() {
+            let texto: String = bloque.instrucciones.iter().map(|i| format!("{:04X}  {}\\l", i.direccion, escapa_dot(&i.texto()))).collect();
+            let borde = if self.entradas.contains(&bloque.inicio) { ", penwidth=2" } else { "" };
+            salida.push_str(&format!("    \"{:04X}\" [label=\"{}\"{}];\n", bloque.inicio, texto, borde));
+        }
+        for arista in &self.aristas {
+            let estilo = match arista.tipo {
+                TipoArista::Secuencial => "",
+                TipoArista::Salto => " [color=blue]",
+                TipoArista::Tomado => " [color=darkgreen, label=\"T\"]",
+                TipoArista::NoTomado => " [color=red, label=\"F\"]",
+                TipoArista::Llamada => " [style=dashed, label=\"call\"]",
+                TipoArista::Continuacion => " [style=dotted]",
+            };
+            salida.push_str(&format!("    \"{:04X}\" -> \"{:04X}\"{};\n", arista.origen, arista.destino, estilo));
+        }
+        salida.push_str("}\n");
+        salida
+    }
+}
+
+fn escapa_dot(texto: &str) -> String {
+    texto.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+//***************************************************************************** Análisis
+pub fn analiza_flujo(memoria: &BancosMemoria, entradas: &[u16], opciones: &OpcionesDesensamblado) -> GrafoFlujo {
+    let mut instrucciones: BTreeMap<u16, InstruccionDesensamblada> = BTreeMap::new();
+    let mut codigo = BTreeSet::new();
+    let mut lideres: BTreeSet<u16> = entradas.iter().copied().collect();
+    let mut indirectos = Vec::new();
+    let mut solapados = Vec::new();
+    let mut pendientes: Vec<u16> = entradas.iter().rev().copied().collect();
+
+    // 1. Recorrido: cada dirección pendiente se sigue hasta una transferencia incondicional
+    while let Some(inicio) = pendientes.pop() {
+        let mut dir = inicio;
+        loop {
+            if instrucciones.contains_key(&dir) { break; }
+            if codigo.contains(&dir) { solapados.push(dir); break; }
+            let instruccion = desensambla_instruccion(memoria, dir, opciones);
+            // Fuera del banco o instrucción cortada al final
+            if instruccion.mnemonico == "DB" { break; }
+            let siguiente = dir.wrapping_add(instruccion.longitud());
+            let tipo = flujo(&instruccion);
+            if let Some(destino) = instruccion.destino {
+                lideres.insert(destino);
+                pendientes.push(destino);
+            }
+            for i in 0..instruccion.longitud() { codigo.insert(dir.wrapping_add(i)); }
+            if tipo == Flujo::Indirecto { indirectos.push(dir); }
+            instrucciones.insert(dir, instruccion);
+            match tipo {
+                Flujo::Sigue => dir = siguiente,
+                Flujo::Salto | Flujo::Retorno | Flujo::Indirecto | Flujo::Parada => break,
+                _ => { lideres.insert(siguiente); dir = siguiente; }
+            }
+        }
+    }
+
+    // 2. Bloques: se corta en los líderes, tras cada transferencia y donde el código no es contiguo
+    let mut bloques: BTreeMap<u16, BloqueBasico> = BTreeMap::new();
+    let mut actual: Option<BloqueBasico> = None;
+    for (dir, instruccion) in instrucciones {
+        let continua = actual.as_ref().is_some_and(|b| {
+            let ultima = b.instrucciones.last().unwrap();
+            b.fin() == dir as u32 && flujo(ultima) == Flujo::Sigue && !lideres.contains(&dir)
+        });
+        if !continua {
+            if let Some(bloque) = actual.take() { bloques.insert(bloque.inicio, bloque); }
+            actual = Some(BloqueBasico { inicio: dir, instrucciones: Vec::new() });
+        }
+        if let Some(bloque) = actual.as_mut() { bloque.instrucciones.push(instruccion); }
+    }
+    if let Some(bloque) = actual { bloques.insert(bloque.inicio, bloque); }
+
+    // 3. Aristas desde la última instrucción de cada bloque
+    let mut aristas = Vec::new();
+    for bloque in bloques.values() {
+        let ultima = bloque.instrucciones.last().unwrap();
+        let siguiente = ultima.direccion.wrapping_add(ultima.longitud());
+        let mut agrega = |destino: u16, tipo: TipoArista| {
+            if bloques.contains_key(&destino) { aristas.push(Arista { origen: bloque.inicio, destino, tipo }); }
+        };
+        match (flujo(ultima), ultima.destino) {
+            (Flujo::Salto, Some(destino)) => agrega(destino, TipoArista::Salto),
+            (Flujo::SaltoCondicional, Some(destino)) => {
+                agrega(destino, TipoArista::Tomado);
+                agrega(siguiente, TipoArista::NoTomado);
+            }
+            (Flujo::Llamada, Some(destino)) => {
+                agrega(destino, TipoArista::Llamada);
+                agrega(siguiente, TipoArista::Continuacion);
+            }
+            (Flujo::RetornoCondicional, _) => agrega(siguiente, TipoArista::NoTomado),
+            (Flujo::Sigue, _) => agrega(siguiente, TipoArista::Secuencial),
+            _ => {}
+        }
+    }
+
+    GrafoFlujo { entradas: entradas.to_vec(), bloques, aristas, codigo, indirectos, solapados }
+}
+
+//*****************************************************************************
+// Programa de cpu_generica_0: código y datos mezclados (0x0023 son datos tras el JMP)
+pub fn flujo_cmd() {
+    let mut memoria = BancosMemoria::new();
+    let programa: [u8; 37] = [
+        0x00, 0x3E, 0x04, 0x06, 0x0a, 0x04, 0x80, 0x00, 0x3E, 0xf0, 0x06, 0x0f, 0x80, 0x00,
+        0x3E, 0x3b, 0x3C, 0x32, 0x15, 0x00, 0x00, 0x00, 0x3A, 0x0b, 0x00, 0x00, 0x00,
+        0x06, 0xff, 0x80, 0x00, 0x00, 0xC3, 0x00, 0x00, 0xFF, 0xFF,
+    ];
+    for (i, b) in programa.iter().enumerate() { memoria.escribir_memoria(i as u16, *b); }
+    let opciones = OpcionesDesensamblado::new();
+    let grafo = analiza_flujo(&memoria, &puntos_entrada(false, &[]), &opciones);
+    println!("{}", grafo.listado(&memoria, 0x0000, programa.len() as u16 - 1, &opciones));
+    println!("{}", grafo.dot("cpu_generica_0"));
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proyectos::sim_cpu_ensamblador::ensambla;
+
+    fn carga(fuente: &str) -> BancosMemoria {
+        let mut memoria = BancosMemoria::new();
+        for (i, b) in ensambla(fuente).unwrap().imagen().iter().enumerate() { memoria.escribir_memoria(i as u16, *b); }
+        memoria
+    }
+
+    #[test]
+    fn test_bloques_y_aristas() {
+        let memoria = carga("
+        MVI B,3         ; 0000
+BUCLE:  CALL SUB        ; 0002
+        DCR B           ; 0005
+        JNZ BUCLE       ; 0006
+        JMP FIN         ; 0009
+        DB 0CDH, 34H    ; 000C  datos que parecen un CALL
+FIN:    HLT             ; 000E
+SUB:    RZ              ; 000F
+        PCHL            ; 0010");
+        let grafo = analiza_flujo(&memoria, &puntos_entrada(false, &[]), &OpcionesDesensamblado::new());
+        assert_eq!(grafo.bloques.keys().copied().collect::<Vec<u16>>(), vec![0x0000, 0x0002, 0x0005, 0x0009, 0x000E, 0x000F, 0x0010]);
+        assert!(!grafo.es_codigo(0x000C) && !grafo.es_codigo(0x000D));
+        assert_eq!(grafo.indirectos, vec![0x0010]);
+        let de = |origen: u16| grafo.sucesores(origen).iter().map(|a| (a.destino, a.tipo)).collect::<Vec<_>>();
+        assert_eq!(de(0x0000), vec![(0x0002, TipoArista::Secuencial)]);
+        assert_eq!(de(0x0002), vec![(0x000F, TipoArista::Llamada), (0x0005, TipoArista::Continuacion)]);
+        assert_eq!(de(0x0005), vec![(0x0002, TipoArista::Tomado), (0x0009, TipoArista::NoTomado)]);
+        assert_eq!(de(0x0009), vec![(0x000E, TipoArista::Salto)]);
+        assert_eq!(de(0x000F), vec![(0x0010, TipoArista::NoTomado)]);
+        assert!(de(0x000E).is_empty());
+        assert_eq!(grafo.bloque_de(0x0007).unwrap().inicio, 0x0005);
+
+        let dot = grafo.dot("prueba");
+        assert!(dot.starts_with("digraph \"prueba\" {"));
+        assert!(dot.contains("\"0005\" -> \"0002\" [color=darkgreen, label=\"T\"];"));
+        assert!(dot.contains("\"0000\" [label=\"0000  MVI B,03H\\l\", penwidth=2];"));
+    }
+
+    #[test]
+    fn test_datos_mezclados_y_vectores_rst() {
+        let memoria = carga("
+        JMP 0040H       ; 0000
+        DB 0FFH         ; 0003
+        ORG 0040H
+        RST 1           ; 0040
+        HLT             ; 0041
+        ORG 0008H
+        RET             ; 0008");
+        let opciones = OpcionesDesensamblado::new();
+        let grafo = analiza_flujo(&memoria, &puntos_entrada(false, &[]), &opciones);
+        assert!(grafo.es_codigo(0x0008));
+        assert!(!grafo.es_codigo(0x0003));
+        let listado = grafo.listado(&memoria, 0x0000, 0x0004, &opciones);
+        assert_eq!(listado, "L0000:\n0000  C3 40 00  JMP 0040H\n0003  FF        DB 0FFH\n0004  00        DB 00H\n");
+        // Con los vectores RST como entradas también se analiza 0x0010 (NOP...) aunque nadie lo llame
+        let grafo = analiza_flujo(&memoria, &puntos_entrada(true, &[]), &opciones);
+        assert!(grafo.es_codigo(0x0010));
+        assert_eq!(grafo.entradas.len(), 8);
+    }
+}
+
+//*****************************************************************************
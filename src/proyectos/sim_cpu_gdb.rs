@@ -0,0 +1,512 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Servidor del protocolo remoto de GDB (RSP) - Simulación CPU
+    Descripción:    Permite conectar un depurador estándar al simulador por TCP en localhost:
+                    lectura y escritura de registros y memoria, paso a paso, continuar,
+                    puntos de ruptura software/hardware, puntos de vigilancia e informe del
+                    motivo de parada.
+    Referencias:    https://sourceware.org/gdb/current/onlinedocs/gdb.html/Remote-Protocol.html
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+use super::sim_cpu_pruebas::Aux;
+use super::sim_cpu_vigilancia::ModoVigilancia;
+use super::sim_cpu_opcode::LONGITUD_8080;
+use super::sim_cpu_monitor::es_salto;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use asm8080::asm8080;
+
+//***************************************************************************** Notas
+/* Uso
+    let mut servidor = ServidorGdb::new(cpu);
+    servidor.escucha(1234)?;            // Espera una conexión en 127.0.0.1:1234
+
+    (gdb) set architecture z80          // o cualquier cliente RSP
+    (gdb) target remote localhost:1234
+
+Registros ('g', 'G', 'p', 'P'): el 8080 no tiene descripción propia en GDB, así que se usa la
+disposición del Z80: 8 registros de 16 bits en little-endian
+    0 AF (A alto, flags bajo)   1 BC   2 DE   3 HL   4 SP   5 PC   6 IX   7 IY
+
+Motivos de parada:
+    S05                     paso terminado o interrupción del cliente (S02 con Ctrl-C)
+    T05swbreak:;            punto de ruptura (hwbreak con Z1)
+    T05watch:dir;           punto de vigilancia (rwatch / awatch para lectura / acceso)
+    S04                     opcode no implementado (la ejecución no avanza)
+    S0b                     la instrucción del PC queda fuera del banco de memoria
+    W00                     fin de programa (0xFF en el PC)
+*/
+
+const NUM_REGISTROS: usize = 8;
+
+// Motivo de la última parada, para responder a '?'
+#[derive(Debug, Clone, PartialEq)]
+enum Parada {
+    Señal(u8),
+    Ruptura(bool),                      // true: punto hardware (Z1)
+    Vigilancia(ModoVigilancia, u16),
+    Fin,
+}
+
+pub struct ServidorGdb {
+    pub cpu: CPU,
+    aux: Aux,
+    puntos: HashMap<(u8, u16, u16), usize>,     // (tipo Z, dirección, longitud) -> id en la CPU
+    rupturas_hardware: Vec<u16>,
+    sin_ack: bool,
+    ultima_parada: Parada,
+}
+
+fn checksum(datos: &str) -> u8 {
+    datos.bytes().fold(0u8, |suma, b| suma.wrapping_add(b))
+}
+
+pub fn empaqueta(datos: &str) -> String {
+    format!("${}#{:02x}", datos, checksum(datos))
+}
+
+fn hex_a_bytes(texto: &str) -> Result<Vec<u8>, String> {
+    if !texto.len().is_multiple_of(2) { return Err(format!("Hexadecimal de longitud impar '{}'", texto)); }
+    (0..texto.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&texto[i..i + 2], 16).map_err(|_| format!("Hexadecimal no válido '{}'", texto)))
+        .collect()
+}
+
+fn bytes_a_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn numero_hex(texto: &str) -> Result<u32, String> {
+    u32::from_str_radix(texto, 16).map_err(|_| format!("Número hexadecimal no válido '{}'", texto))
+}
+
+// "dir,longitud" de los paquetes m, M y Z
+fn direccion_longitud(texto: &str) -> Result<(u16, u16), String> {
+    let (dir, longitud) = texto.split_once(',').ok_or(format!("Se esperaba 'dir,longitud' en '{}'", texto))?;
+    let dir = numero_hex(dir)?;
+    let longitud = numero_hex(longitud)?;
+    if dir > 0xFFFF || longitud > 0x10000 || dir + longitud > 0x10000 {
+        return Err(format!("Rango fuera del espacio de direcciones: {},{}", dir, longitud));
+    }
+    Ok((dir as u16, longitud as u16))
+}
+
+//*****************************************************************************
+impl ServidorGdb {
+    pub fn new(cpu: CPU) -> ServidorGdb {
+        ServidorGdb {
+            cpu,
+            aux: Aux { imp_contador_programa: 0, imp_instruccion: 0, imp_mnemonico: String::new() },
+            puntos: HashMap::new(),
+            rupturas_hardware: Vec::new(),
+            sin_ack: false,
+            ultima_parada: Parada::Señal(5),
+        }
+    }
+
+    // Espera una conexión en 127.0.0.1:puerto y la atiende hasta que el cliente se desconecta
+    pub fn escucha(&mut self, puerto: u16) -> Result<(), String> {
+        let escucha = TcpListener::bind(("127.0.0.1", puerto)).map_err(|e| format!("No se puede escuchar en el puerto {}: {}", puerto, e))?;
+        self.escucha_en(&escucha)
+    }
+
+    pub fn escucha_en(&mut self, escucha: &TcpListener) -> Result<(), String> {
+        let (flujo, _) = escucha.accept().map_err(|e| e.to_string())?;
+        self.atiende(flujo)
+    }
+
+    pub fn atiende(&mut self, mut flujo: TcpStream) -> Result<(), String> {
+        flujo.set_nodelay(true).map_err(|e| e.to_string())?;
+        let mut pendiente: Vec<u8> = Vec::new();
+        let mut bufer = [0u8; 1024];
+        loop {
+            // Se extraen todos los paquetes completos del búfer antes de leer más
+            loop {
+                let (paquete, consumidos) = match extrae_paquete(&pendiente) {
+                    Ok(Some(extraido)) => extraido,
+                    Ok(None) => break,
+                    Err(_) => {                     // Checksum incorrecto: se pide la retransmisión
+                        pendiente.clear();          // (sin acks no hay retransmisión: se descarta)
+                        if !self.sin_ack { flujo.write_all(b"-").map_err(|e| e.to_string())?; }
+                        break;
+                    }
+                };
+                pendiente.drain(..consumidos);
+                let paquete = match paquete {
+                    Some(paquete) => paquete,
+                    None => continue,               // Ack, nack o Ctrl-C sin ejecución en curso
+                };
+                if !self.sin_ack { flujo.write_all(b"+").map_err(|e| e.to_string())?; }
+                let copia = flujo.try_clone().map_err(|e| e.to_string())?;
+                let mut interrumpir = || hay_interrupcion(&copia);
+                let respuesta = self.procesa_paquete(&paquete, &mut interrumpir);
+                match respuesta {
+                    Some(respuesta) => flujo.write_all(empaqueta(&respuesta).as_bytes()).map_err(|e| e.to_string())?,
+                    None => return Ok(()),          // 'k' o 'D': fin de la sesión
+                }
+            }
+            let leidos = flujo.read(&mut bufer).map_err(|e| e.to_string())?;
+            if leidos == 0 { return Ok(()); }
+            pendiente.extend_from_slice(&bufer[..leidos]);
+        }
+    }
+
+    // Procesa el contenido de un paquete y devuelve la respuesta (None: cerrar la conexión).
+    // 'interrumpir' se consulta periódicamente durante 'c' para atender el Ctrl-C del cliente.
+    pub fn procesa_paquete(&mut self, paquete: &str, interrumpir: &mut dyn FnMut() -> bool) -> Option<String> {
+        let respuesta = match paquete.chars().next() {
+            Some('?') => Ok(self.motivo_parada()),
+            Some('g') => Ok(bytes_a_hex(&self.lee_registros())),
+            Some('G') => self.escribe_registros(&paquete[1..]),
+            Some('p') => numero_hex(&paquete[1..]).and_then(|n| self.registro(n as usize)).map(|v| bytes_a_hex(&v.to_le_bytes())),
+            Some('P') => self.escribe_registro(&paquete[1..]),
+            Some('m') => self.lee_memoria(&paquete[1..]),
+            Some('M') => self.escribe_memoria(&paquete[1..]),
+            Some('s') => self.reanuda(&paquete[1..], true, interrumpir),
+            Some('c') => self.reanuda(&paquete[1..], false, interrumpir),
+            Some('Z') => self.agrega_punto(&paquete[1..]),
+            Some('z') => self.elimina_punto(&paquete[1..]),
+            Some('H') => Ok(String::from("OK")),
+            Some('k') => return None,
+            Some('D') => return None,
+            Some('q') | Some('Q') => Ok(self.consulta(paquete)),
+            _ => Ok(String::new()),             // Paquete no soportado: respuesta vacía
+        };
+        Some(respuesta.unwrap_or_else(|_| String::from("E01")))
+    }
+
+    fn consulta(&mut self, paquete: &str) -> String {
+        if paquete.starts_with("qSupported") { return String::from("PacketSize=1000;swbreak+;hwbreak+;QStartNoAckMode+"); }
+        match paquete {
+            "QStartNoAckMode" => { self.sin_ack = true; String::from("OK") }
+            "qAttached" => String::from("1"),
+            "qC" => String::from("QC1"),
+            "qfThreadInfo" => String::from("m1"),
+            "qsThreadInfo" => String::from("l"),
+            _ => String::new(),
+        }
+    }
+
+    fn motivo_parada(&self) -> String {
+        match &self.ultima_parada {
+            Parada::Señal(señal) => format!("S{:02x}", señal),
+            Parada::Ruptura(false) => String::from("T05swbreak:;"),
+            Parada::Ruptura(true) => String::from("T05hwbreak:;"),
+            Parada::Vigilancia(modo, dir) => {
+                let tipo = match modo {
+                    ModoVigilancia::Escritura => "watch",
+                    ModoVigilancia::Lectura => "rwatch",
+                    ModoVigilancia::Acceso => "awatch",
+                };
+                format!("T05{}:{:04x};", tipo, dir)
+            }
+            Parada::Fin => String::from("W00"),
+        }
+    }
+
+    //************************************* Registros
+    fn registro(&self, numero: usize) -> Result<u16, String> {
+        let cpu = &self.cpu;
+        Ok(match numero {
+            0 => u16::from_be_bytes([cpu.reg_a, cpu.flags.get_flags()]),
+            1 => cpu.get_bc(),
+            2 => cpu.get_de(),
+            3 => cpu.get_hl(),
+            4 => cpu.puntero_de_pila,
+            5 => cpu.contador_de_programa,
+            6 => cpu.reg_ix,
+            7 => cpu.reg_iy,
+            _ => return Err(format!("Registro {} no válido", numero)),
+        })
+    }
+
+    fn asigna_registro(&mut self, numero: usize, valor: u16) -> Result<(), String> {
+        let cpu = &mut self.cpu;
+        let [alto, bajo] = valor.to_be_bytes();
+        match numero {
+            0 => { cpu.reg_a = alto; cpu.flags.set_flags(bajo); }
+            1 => { cpu.reg_b = alto; cpu.reg_c = bajo; }
+            2 => { cpu.reg_d = alto; cpu.reg_e = bajo; }
+            3 => { cpu.reg_h = alto; cpu.reg_l = bajo; }
+            4 => cpu.puntero_de_pila = valor,
+            5 => cpu.contador_de_programa = valor,
+            6 => cpu.reg_ix = valor,
+            7 => cpu.reg_iy = valor,
+            _ => return Err(format!("Registro {} no válido", numero)),
+        }
+        Ok(())
+    }
+
+    fn lee_registros(&self) -> Vec<u8> {
+        (0..NUM_REGISTROS).flat_map(|n| self.registro(n).unwrap_or(0).to_le_bytes()).collect()
+    }
+
+    fn escribe_registros(&mut self, texto: &str) -> Result<String, String> {
+        let bytes = hex_a_bytes(texto)?;
+        if bytes.len() != NUM_REGISTROS * 2 { return Err(format!("Se esperaban {} bytes de registros", NUM_REGISTROS * 2)); }
+        for n in 0..NUM_REGISTROS {
+            self.asigna_registro(n, u16::from_le_bytes([bytes[2 * n], bytes[2 * n + 1]]))?;
+        }
+        Ok(String::from("OK"))
+    }
+
+    fn escribe_registro(&mut self, texto: &str) -> Result<String, String> {
+        let (numero, valor) = texto.split_once('=').ok_or("Se esperaba 'n=valor'")?;
+        let bytes = hex_a_bytes(valor)?;
+        if bytes.len() != 2 { return Err(String::from("Los registros son de 16 bits")); }
+        self.asigna_registro(numero_hex(numero)? as usize, u16::from_le_bytes([bytes[0], bytes[1]]))?;
+        Ok(String::from("OK"))
+    }
+
+    //************************************* Memoria
+    fn lee_memoria(&self, texto: &str) -> Result<String, String> {
+        // Como en el protocolo, una lectura que pasa del final del banco devuelve solo los bytes
+        // que existen; si no existe ninguno es un error
+        let (dir, longitud) = direccion_longitud(texto)?;
+        self.cpu.memoria.leer_memoria_comprobada(dir)?;
+        let fin = (dir as usize + longitud as usize).min(self.cpu.memoria.longitud_banco());
+        let bytes: Vec<u8> = (dir as usize..fin).map(|d| self.cpu.memoria.leer_memoria(d as u16)).collect();
+        Ok(bytes_a_hex(&bytes))
+    }
+
+    fn escribe_memoria(&mut self, texto: &str) -> Result<String, String> {
+        let (rango, datos) = texto.split_once(':').ok_or("Se esperaba 'dir,longitud:datos'")?;
+        let (dir, longitud) = direccion_longitud(rango)?;
+        let bytes = hex_a_bytes(datos)?;
+        let tamano = self.cpu.memoria.segmento_memoria[self.cpu.memoria.banco_actual as usize].len();
+        if bytes.len() != longitud as usize || dir as usize + bytes.len() > tamano {
+            return Err(String::from("Escritura fuera del banco de memoria"));
+        }
+        for (i, b) in bytes.iter().enumerate() { self.cpu.memoria.escribir_memoria_depurador(dir + i as u16, *b); }
+        Ok(String::from("OK"))
+    }
+
+    //************************************* Puntos de ruptura y vigilancia
+    fn agrega_punto(&mut self, texto: &str) -> Result<String, String> {
+        let (tipo, rango) = texto.split_once(',').ok_or("Se esperaba 'tipo,dir,longitud'")?;
+        let tipo: u8 = tipo.parse().map_err(|_| format!("Tipo de punto no válido '{}'", tipo))?;
+        let (dir, longitud) = direccion_longitud(rango)?;
+        if self.puntos.contains_key(&(tipo, dir, longitud)) { return Ok(String::from("OK")); }
+        let id = match tipo {
+            0 | 1 => self.cpu.agrega_punto_ruptura(dir, None)?,
+            2..=4 => {
+                let modo = match tipo { 2 => ModoVigilancia::Escritura, 3 => ModoVigilancia::Lectura, _ => ModoVigilancia::Acceso };
+                self.cpu.agrega_vigilancia(dir, dir.wrapping_add(longitud.max(1) - 1), modo, None)?
+            }
+            _ => return Ok(String::new()),
+        };
+        if tipo == 1 { self.rupturas_hardware.push(dir); }
+        self.puntos.insert((tipo, dir, longitud), id);
+        Ok(String::from("OK"))
+    }
+
+    fn elimina_punto(&mut self, texto: &str) -> Result<String, String> {
+        let (tipo, rango) = texto.split_once(',').ok_or("Se esperaba 'tipo,dir,longitud'")?;
+        let tipo: u8 = tipo.parse().map_err(|_| format!("Tipo de punto no válido '{}'", tipo))?;
+        let (dir, longitud) = direccion_longitud(rango)?;
+        let id = self.puntos.remove(&(tipo, dir, longitud)).ok_or("Punto no encontrado")?;
+        match tipo {
+            0 | 1 => self.cpu.elimina_punto_ruptura(id)?,
+            _ => self.cpu.elimina_vigilancia(id)?,
+        }
+        if tipo == 1 { self.rupturas_hardware.retain(|d| *d != dir); }
+        Ok(String::from("OK"))
+    }
+
+    //************************************* Ejecución
+    fn reanuda(&mut self, direccion: &str, un_paso: bool, interrumpir: &mut dyn FnMut() -> bool) -> Result<String, String> {
+        if !direccion.is_empty() { self.cpu.contador_de_programa = numero_hex(direccion)? as u16; }
+        let mut ejecutadas: u64 = 0;
+        // GDB continúa desde el PC en que está detenido: esa instrucción no comprueba sus puntos
+        self.cpu.reanuda_en_ruptura();
+        self.ultima_parada = loop {
+            let pc = self.cpu.contador_de_programa;
+            if ejecutadas > 0 && self.cpu.comprueba_puntos_ruptura().is_some() {
+                break Parada::Ruptura(self.rupturas_hardware.contains(&pc));
+            }
+            self.cpu.toma_mensajes_ruptura();
+            // La instrucción completa (opcode y operandos) tiene que estar dentro del banco
+            let opcode = match self.cpu.memoria.leer_memoria_comprobada(pc) {
+                Ok(opcode) => opcode,
+                Err(_) => break Parada::Señal(11),
+            };
+            if opcode == 0xFF { break Parada::Fin; }
+            let ultimo = pc.wrapping_add(LONGITUD_8080[opcode as usize] as u16 - 1);
+            if self.cpu.memoria.leer_memoria_comprobada(ultimo).is_err() { break Parada::Señal(11); }
+            self.cpu.step_no_win(&mut self.aux);
+            ejecutadas += 1;
+            if let Some(disparo) = self.cpu.disparos_vigilancia().first() {
+                let modo = self.cpu.vigilancia.as_ref()
+                    .and_then(|v| v.puntos.iter().find(|p| p.id == disparo.id)).map(|p| p.modo)
+                    .unwrap_or(ModoVigilancia::Escritura);
+                break Parada::Vigilancia(modo, disparo.acceso.direccion);
+            }
+            if self.cpu.contador_de_programa == pc && !es_salto(self.aux.imp_instruccion) {
+                break Parada::Señal(4);
+            }
+            if un_paso { break Parada::Señal(5); }
+            if ejecutadas.is_multiple_of(1024) && interrumpir() { break Parada::Señal(2); }
+        };
+        Ok(self.motivo_parada())
+    }
+}
+
+// Busca el siguiente paquete en el búfer. Devuelve (Some(contenido) o None si solo hay un ack o
+// Ctrl-C, bytes consumidos), o None si el paquete aún no está completo.
+fn extrae_paquete(datos: &[u8]) -> Result<Option<(Option<String>, usize)>, String> {
+    let inicio = match datos.iter().position(|&b| b == b'$') {
+        Some(inicio) => inicio,
+        None => return Ok(if datos.is_empty() { None } else { Some((None, datos.len())) }),
+    };
+    if inicio > 0 { return Ok(Some((None, inicio))); }
+    let fin = match datos.iter().position(|&b| b == b'#') {
+        Some(fin) if fin + 2 < datos.len() => fin,
+        _ => return Ok(None),
+    };
+    let contenido = String::from_utf8_lossy(&datos[1..fin]).to_string();
+    let esperado = u8::from_str_radix(&String::from_utf8_lossy(&datos[fin + 1..fin + 3]), 16)
+        .map_err(|_| String::from("Checksum no válido"))?;
+    if esperado != checksum(&contenido) {
+        return Err(format!("Checksum incorrecto en el paquete '{}'", contenido));
+    }
+    Ok(Some((Some(contenido), fin + 3)))
+}
+
+// Comprueba sin bloquear si el cliente ha enviado Ctrl-C (0x03)
+fn hay_interrupcion(flujo: &TcpStream) -> bool {
+    let mut byte = [0u8; 1];
+    if flujo.set_nonblocking(true).is_err() { return false; }
+    let interrupcion = matches!(flujo.peek(&mut byte), Ok(1) if byte[0] == 0x03);
+    if interrupcion { let _ = (&*flujo).read(&mut byte); }
+    let _ = flujo.set_nonblocking(false);
+    interrupcion
+}
+
+//*****************************************************************************
+pub fn gdb_cmd() {
+    let mut cpu = CPU::new();
+    cpu.cargar_programa(&asm8080! {
+                lxi sp, 400h
+                mvi a, 3bh
+        bucle:  inr a
+                sta 20h
+                jmp bucle
+    });
+    let mut servidor = ServidorGdb::new(cpu);
+    println!("Esperando a GDB en 127.0.0.1:1234 (target remote localhost:1234)");
+    if let Err(error) = servidor.escucha(1234) { println!("Error: {}", error); }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn cpu_de_prueba() -> CPU {
+        let mut cpu = CPU::new();
+        cpu.cargar_programa(&asm8080! {
+                    lxi sp, 400h
+                    mvi a, 3bh
+                    inr a               // 0x0005
+                    sta 20h
+                    db 0ffh
+        });
+        cpu
+    }
+
+    // Cliente mínimo: envía un paquete y devuelve el contenido de la respuesta
+    fn envia(flujo: &mut TcpStream, datos: &str) -> String {
+        flujo.write_all(empaqueta(datos).as_bytes()).unwrap();
+        let mut recibido = Vec::new();
+        let mut byte = [0u8; 1];
+        loop {
+            flujo.read_exact(&mut byte).unwrap();
+            if recibido.is_empty() && byte[0] == b'+' { continue; }    // Ack del servidor
+            recibido.push(byte[0]);
+            if let Some((Some(contenido), _)) = extrae_paquete(&recibido).unwrap() { return contenido; }
+        }
+    }
+
+    #[test]
+    fn test_sesion_tcp() {
+        let escucha = TcpListener::bind("127.0.0.1:0").unwrap();
+        let puerto = escucha.local_addr().unwrap().port();
+        let hilo = thread::spawn(move || {
+            let mut servidor = ServidorGdb::new(cpu_de_prueba());
+            servidor.escucha_en(&escucha).unwrap();
+            servidor.cpu
+        });
+        let mut cliente = TcpStream::connect(("127.0.0.1", puerto)).unwrap();
+        assert!(envia(&mut cliente, "qSupported:swbreak+").contains("swbreak+"));
+        assert_eq!(envia(&mut cliente, "?"), "S05");
+        assert_eq!(envia(&mut cliente, "m0,3"), "310004");
+        assert_eq!(envia(&mut cliente, "Z0,5,1"), "OK");
+        assert_eq!(envia(&mut cliente, "c"), "T05swbreak:;");
+        // g: AF BC DE HL SP PC IX IY en little-endian
+        assert_eq!(envia(&mut cliente, "g"), "023b0000000000000004050000000000");
+        assert_eq!(envia(&mut cliente, "z0,5,1"), "OK");
+        assert_eq!(envia(&mut cliente, "Z2,20,1"), "OK");
+        assert_eq!(envia(&mut cliente, "c"), "T05watch:0020;");
+        assert_eq!(envia(&mut cliente, "m20,1"), "3c");
+        assert_eq!(envia(&mut cliente, "c"), "W00");
+        assert_eq!(envia(&mut cliente, "P0=0212"), "OK");
+        assert_eq!(envia(&mut cliente, "M30,2:aabb"), "OK");
+        assert_eq!(envia(&mut cliente, "m40000,1"), "E01");
+        // Sin acks un paquete con checksum incorrecto se descarta sin responder '-'
+        assert_eq!(envia(&mut cliente, "QStartNoAckMode"), "OK");
+        cliente.write_all(b"$g#00").unwrap();
+        cliente.write_all(empaqueta("?").as_bytes()).unwrap();
+        let mut respuesta = vec![0u8; empaqueta("W00").len()];
+        cliente.read_exact(&mut respuesta).unwrap();
+        assert_eq!(respuesta, empaqueta("W00").as_bytes());   // Respuesta a "?" tras terminar
+        assert_eq!(envia(&mut cliente, "m3ff,1"), "00");
+        cliente.write_all(empaqueta("k").as_bytes()).unwrap();
+        let cpu = hilo.join().unwrap();
+        assert_eq!(cpu.reg_a, 0x12);
+        assert_eq!(cpu.memoria.leer_memoria(0x31), 0xBB);
+    }
+
+    #[test]
+    fn test_paquetes_sin_red() {
+        let mut servidor = ServidorGdb::new(cpu_de_prueba());
+        let mut nunca = || false;
+        assert_eq!(servidor.procesa_paquete("s", &mut nunca), Some(String::from("S05")));
+        assert_eq!(servidor.procesa_paquete("p5", &mut nunca), Some(String::from("0300")));
+        assert_eq!(servidor.procesa_paquete("G0000000000000000000000000500000000000000", &mut nunca), Some(String::from("E01")));
+        assert_eq!(servidor.procesa_paquete("G00000000000000000000050000000000", &mut nunca), Some(String::from("OK")));
+        assert_eq!(servidor.cpu.contador_de_programa, 0x0005);
+        assert_eq!(servidor.procesa_paquete("vMustReplyEmpty", &mut nunca), Some(String::new()));
+        // Lecturas en el final del banco de 1 KiB: se recortan o fallan, nunca leen fuera
+        assert_eq!(servidor.procesa_paquete("m3fe,4", &mut nunca), Some(String::from("0000")));
+        assert_eq!(servidor.procesa_paquete("m400,1", &mut nunca), Some(String::from("E01")));
+        // PC en el final del banco (P5=0004 es 0x0400) o un programa que se sale de él: SIGSEGV
+        assert_eq!(servidor.procesa_paquete("P5=0004", &mut nunca), Some(String::from("OK")));
+        assert_eq!(servidor.procesa_paquete("s", &mut nunca), Some(String::from("S0b")));
+        assert_eq!(servidor.procesa_paquete("P5=fe03", &mut nunca), Some(String::from("OK")));
+        assert_eq!(servidor.procesa_paquete("c", &mut nunca), Some(String::from("S0b")));
+        assert_eq!(servidor.cpu.contador_de_programa, 0x0400);
+        // JZ $ y CALL $ no avanzan el PC, pero no son opcodes sin implementar
+        let mut siempre = || true;
+        for paquete in ["M100,3:ca0001", "P0=4000", "P4=0004", "P5=0001"] {
+            assert_eq!(servidor.procesa_paquete(paquete, &mut nunca), Some(String::from("OK")));
+        }
+        assert_eq!(servidor.procesa_paquete("s", &mut nunca), Some(String::from("S05")));
+        assert_eq!(servidor.procesa_paquete("c", &mut siempre), Some(String::from("S02")));
+        assert_eq!(servidor.procesa_paquete("M100,1:cd", &mut nunca), Some(String::from("OK")));
+        assert_eq!(servidor.procesa_paquete("s", &mut nunca), Some(String::from("S05")));
+        assert_eq!((servidor.cpu.contador_de_programa, servidor.cpu.puntero_de_pila), (0x0100, 0x03FE));
+        assert_eq!(servidor.procesa_paquete("k", &mut nunca), None);
+        assert!(extrae_paquete(b"$g#00").is_err());
+        assert_eq!(extrae_paquete(b"+$g#67").unwrap(), Some((None, 1)));
+        assert_eq!(extrae_paquete(b"$g#6").unwrap(), None);
+    }
+}
+
+//*****************************************************************************
@@ -17,6 +17,8 @@
 
 // use super::{sim_cpu_memoria::BancosMemoria, sim_cpu_memoria::Endianess};
 use super::{sim_cpu_registros::CPU, /*sim_cpu_registros::Flags */};
+use super::sim_cpu_desensamblador::desensambla_bytes;
+use super::sim_cpu_simbolos::TablaSimbolos;
 use pancurses::*;
 
 fn imprime_titulo(ventana: &Window, titulo: &str) {
@@ -74,9 +76,7 @@ Métodos parte de una implementación de una máquina virtual o un emulador de u
 
 impl CPU {    
     fn step(&mut self) {
-        let instruccion = self.busca_instruccion();
-        let (opcode, operandos) = self.decodifica_instruccion(instruccion);
-        self.ejecuta_instruccion(opcode, operandos);
+        let (opcode, operandos) = self.ejecuta_paso();
 
         /* (&self).info_registros()
         El paréntesis es necesario para asegurar que se tome la referencia de self antes de llamar al método
@@ -84,6 +84,7 @@ impl CPU {
         */
         (&self).info_opcode(opcode, operandos);
         (&self).info_registros();
+        (&self).info_fuente();
         (&self).info_pruebas();
 
         //info_pruebas();
@@ -92,7 +93,21 @@ impl CPU {
     fn run(&mut self, window: &Window) {
         //************************************** Ventana principal
         let mut pos_y = 3;
+        let mut reanuda = self.reanuda_en_ruptura();
         loop {
+            // Los puntos de ruptura se comprueban antes de ejecutar la instrucción del PC
+            if !reanuda {
+                let ruptura = self.comprueba_puntos_ruptura();
+                if let Some(mensaje) = self.toma_mensajes_ruptura().last() {
+                    window.mvprintw(27, 2, mensaje);
+                }
+                if let Some(id) = ruptura {
+                    window.mvprintw(28, 2, format!("Punto de ruptura #{} en 0x{:04X}", id, self.contador_de_programa));
+                    window.getch();
+                    break;
+                }
+            }
+            reanuda = false;
             window.mv(pos_y, 2);
             window.printw(format!( "Contador: 0x{:04X}, Instruccion: {:02x}"
                 , self.contador_de_programa
@@ -120,6 +135,12 @@ impl CPU {
             if self.memoria.leer_memoria(self.contador_de_programa) == 0xFF {
                 break;
             }
+
+            if let Some(disparo) = self.disparos_vigilancia().first() {
+                window.mvprintw(28, 2, disparo.descripcion());
+                window.getch();
+                break;
+            }
         }
     }
 }
@@ -254,7 +275,7 @@ impl CPU {                                   // Funciones de manejo de ventanas
         
         //let var_a_array: [u8; 8] = [1, 2, 4, 8, 16, 32, 64, 128];
         let mut var_a_array = &self.memoria.segmento_memoria[*(&self.memoria.get_banco_activo()) as usize];
-        muestra_mem(&comentarios_window, 9, 2, var_a_array);
+        muestra_mem(&comentarios_window, 9, 2, var_a_array, self.simbolos.as_ref());
 
         /*
         comentarios_window.mvprintw(pos_y,  pos_x, format!("", ));
@@ -302,6 +323,28 @@ impl CPU {                                   // Funciones de manejo de ventanas
         reg_window.refresh();
     }
 
+    // Función manejo ventana del código fuente (.PRN cargado con cargar_listado)
+    fn info_fuente(&self) {
+        let titulo_ventana_fuente = String::from(" Fuente ");
+        let fuente_window = newwin(17, 31, 12, 59);
+        fuente_window.border('|', '|', '-', '-', '+', '+', '+', '+');
+        imprime_titulo(&fuente_window, &titulo_ventana_fuente);
+
+        fuente_window.mvprintw(2, 2, format!("PC: {:04X} {:<17.17}", self.contador_de_programa
+            , self.nombre_direccion(self.contador_de_programa).unwrap_or_default()));
+        let contexto = self.contexto_fuente(5, 6);
+        if contexto.is_empty() {
+            fuente_window.mvprintw(4, 2, if self.listado.is_some() { "Sin línea de fuente" } else { "Sin listado" });
+        }
+        // Línea actual resaltada; el texto se recorta al ancho de la ventana
+        for (i, linea) in contexto.iter().enumerate() {
+            if linea.starts_with('>') { fuente_window.attrset(ColorPair(3)); }
+            fuente_window.mvprintw(4 + i as i32, 1, format!("{:<29.29}", linea));
+            fuente_window.attrset(Attribute::Normal);
+        }
+        fuente_window.refresh();
+    }
+
     // Función manejo ventana de los OP Code
     fn info_opcode(&self, opcode: u8, operandos: [u8; 2]) {
         let titulo_ventana_opcode = String::from(" OP Code ");
@@ -311,8 +354,12 @@ impl CPU {                                   // Funciones de manejo de ventanas
         imprime_titulo(&opcode_window, &titulo_ventana_opcode);
         let pos_y = opcode_window.get_cur_y();
 
-        opcode_window.mvprintw(2, 2, format!("{}", self.mnemonic));
+        // Texto desensamblado de los bytes de la instrucción, no solo el mnemónico que deja la CPU
+        let instruccion = desensambla_bytes(0, &[opcode, operandos[0], operandos[1]], &self.opciones_desensamblado());
+        opcode_window.mvprintw(2, 2, format!("{:<12}", instruccion.texto()));
         opcode_window.mvprintw(3, 2, format!("Hex: 0x{:02X}", opcode));
+        // Etiqueta+desplazamiento del PC si hay tabla de símbolos (recortada al ancho de la ventana)
+        opcode_window.mvprintw(4, 2, format!("{:<12.12}", self.nombre_direccion(self.contador_de_programa).unwrap_or_default()));
         opcode_window.mvprintw(5, 2, format!(" PC : {:04x}", self.contador_de_programa));
         opcode_window.mvprintw(6, 2, format!(" Operandos"));
         opcode_window.mvprintw(7, 2, format!(" Cero: 0x{:02X}", operandos[0]));
@@ -322,7 +369,7 @@ impl CPU {                                   // Funciones de manejo de ventanas
 }
 
 //***************************************************************************** Muestra memoria en formato tabla
-fn muestra_mem(comentarios_window: &Window, mut pos_y: i32, mut pos_x: i32, vec: &[u8]) {
+fn muestra_mem(comentarios_window: &Window, mut pos_y: i32, mut pos_x: i32, vec: &[u8], simbolos: Option<&TablaSimbolos>) {
 
     comentarios_window.mv(pos_y, pos_x);
     comentarios_window.mvprintw( pos_y, pos_x,format!(" Dir. Memoria  || 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F"));
@@ -375,7 +422,11 @@ fn muestra_mem(comentarios_window: &Window, mut pos_y: i32, mut pos_x: i32, vec:
 
             buffer.push_str(&format!("{:02X} ", byte));
         }
-        comentarios_window.mvprintw(pos_y as i32, 2, format!("       {:04x}    || ", direccion));
+        // Con símbolos la columna de dirección lleva también la etiqueta+desplazamiento de la fila
+        match simbolos.and_then(|s| s.nombre(direccion)) {
+            Some(nombre) => comentarios_window.mvprintw(pos_y, 2, format!(" {:04x} {:<8.8} || ", direccion, nombre)),
+            None => comentarios_window.mvprintw(pos_y, 2, format!("       {:04x}    || ", direccion)),
+        };
         comentarios_window.mvprintw(pos_y as i32, pos_x, &buffer);
         pos_y += 1;
         direccion += 0x10;
@@ -0,0 +1,248 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Formato Intel HEX - Simulación CPU
+    Descripción:    Carga archivos Intel HEX (registros 00, 01, 02, 03, 04 y 05) en las
+                    direcciones y bancos de BancosMemoria validando la suma de comprobación,
+                    y vuelca rangos de memoria a Intel HEX para intercambiar programas con
+                    otras herramientas.
+    Referencias:    Intel Hexadecimal Object File Format Specification, Rev. A (1988)
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_memoria::BancosMemoria;
+use super::sim_cpu_registros::CPU;
+
+//***************************************************************************** Notas
+/* Uso
+    cpu.cargar_archivo_hex("programa.hex")?;    // Coloca los datos y, si hay registro 03/05, el PC
+    let texto = cpu.memoria.vuelca_intel_hex(0, 0x0000, 0x00FF, 16)?;
+
+Cada registro es ':LLDDDDTT...CC' (longitud, dirección, tipo, datos y suma de comprobación).
+La dirección lineal de un dato es base + dirección, donde la base la fijan los registros 02
+(segmento * 16) y 04 (16 bits altos). Los 16 bits bajos son la dirección dentro del banco y los
+altos el número de banco: 0x0001_2000 es la dirección 0x2000 del banco 1. El banco tiene que
+existir (crear_segmento) y ser lo bastante grande, y dos registros no pueden escribir en la misma
+dirección; si no, la carga falla sin escribir nada.
+
+La dirección de inicio (03: CS*16+IP, 05: EIP) se traduce igual: PC con los 16 bits bajos y banco
+activo con los altos. El volcado de un banco distinto del 0 empieza con un registro 04.
+*/
+
+// Datos de un archivo de carga ya validado (Intel HEX o S-record)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImagenCarga {
+    pub bloques: Vec<(u32, Vec<u8>)>,   // (dirección lineal, datos) en el orden del archivo
+    pub inicio: Option<u32>,            // Dirección lineal de inicio (registros 03 y 05, S7-S9)
+}
+
+//***************************************************************************** Lectura
+fn hex_a_bytes(texto: &str, linea: usize) -> Result<Vec<u8>, String> {
+    if !texto.len().is_multiple_of(2) { return Err(format!("Línea {}: número impar de dígitos hexadecimales", linea)); }
+    (0..texto.len()).step_by(2).map(|i| {
+        u8::from_str_radix(&texto[i..i + 2], 16).map_err(|_| format!("Línea {}: '{}' no es un byte hexadecimal", linea, &texto[i..i + 2]))
+    }).collect()
+}
+
+pub fn analiza_intel_hex(texto: &str) -> Result<ImagenCarga, String> {
+    let mut imagen = ImagenCarga { bloques: Vec::new(), inicio: None };
+    let mut base: u32 = 0;
+    for (i, contenido) in texto.lines().enumerate() {
+        let linea = i + 1;
+        let contenido = contenido.trim();
+        if contenido.is_empty() { continue; }
+        let Some(registro) = contenido.strip_prefix(':') else {
+            return Err(format!("Línea {}: el registro debe empezar por ':'", linea));
+        };
+        if !registro.is_ascii() { return Err(format!("Línea {}: caracteres no válidos", linea)); }
+        let bytes = hex_a_bytes(registro, linea)?;
+        if bytes.len() < 5 { return Err(format!("Línea {}: registro demasiado corto", linea)); }
+        let longitud = bytes[0] as usize;
+        if bytes.len() != longitud + 5 {
+            return Err(format!("Línea {}: la longitud indica {} bytes de datos y hay {}", linea, longitud, bytes.len() - 5));
+        }
+        let suma = bytes.iter().fold(0u8, |s, b| s.wrapping_add(*b));
+        if suma != 0 {
+            let esperada = bytes[..bytes.len() - 1].iter().fold(0u8, |s, b| s.wrapping_add(*b)).wrapping_neg();
+            return Err(format!("Línea {}: suma de comprobación incorrecta (0x{:02X}, se esperaba 0x{:02X})", linea, bytes[bytes.len() - 1], esperada));
+        }
+        let direccion = u16::from_be_bytes([bytes[1], bytes[2]]);
+        let datos = &bytes[4..4 + longitud];
+        let exige = |n: usize| -> Result<(), String> {
+            if longitud == n { Ok(()) } else { Err(format!("Línea {}: el registro {:02X} debe tener {} bytes de datos", linea, bytes[3], n)) }
+        };
+        match bytes[3] {
+            0x00 => if !datos.is_empty() { imagen.bloques.push((base + direccion as u32, datos.to_vec())); },
+            0x01 => return Ok(imagen),
+            0x02 => { exige(2)?; base = (u16::from_be_bytes([datos[0], datos[1]]) as u32) << 4; }
+            0x03 => { exige(4)?; imagen.inicio = Some(((u16::from_be_bytes([datos[0], datos[1]]) as u32) << 4) + u16::from_be_bytes([datos[2], datos[3]]) as u32); }
+            0x04 => { exige(2)?; base = (u16::from_be_bytes([datos[0], datos[1]]) as u32) << 16; }
+            0x05 => { exige(4)?; imagen.inicio = Some(u32::from_be_bytes([datos[0], datos[1], datos[2], datos[3]])); }
+            tipo => return Err(format!("Línea {}: tipo de registro {:02X} no soportado", linea, tipo)),
+        }
+    }
+    Err(String::from("Falta el registro de fin de archivo (tipo 01)"))
+}
+
+//***************************************************************************** Escritura
+fn registro(tipo: u8, direccion: u16, datos: &[u8]) -> String {
+    let mut bytes = vec![datos.len() as u8, (direccion >> 8) as u8, direccion as u8, tipo];
+    bytes.extend_from_slice(datos);
+    let suma = bytes.iter().fold(0u8, |s, b| s.wrapping_add(*b)).wrapping_neg();
+    bytes.push(suma);
+    format!(":{}\n", bytes.iter().map(|b| format!("{:02X}", b)).collect::<String>())
+}
+
+// Texto Intel HEX de los bloques; se emite un registro 04 cada vez que cambian los 16 bits altos
+pub fn genera_intel_hex(bloques: &[(u32, Vec<u8>)], inicio: Option<u32>, bytes_por_linea: usize) -> String {
+    let bytes_por_linea = bytes_por_linea.clamp(1, 255);
+    let mut salida = String::new();
+    let mut alta_actual: u32 = 0;
+    for (direccion, datos) in bloques {
+        let mut dir = *direccion;
+        let mut resto = datos.as_slice();
+        while !resto.is_empty() {
+            if dir >> 16 != alta_actual {
+                alta_actual = dir >> 16;
+                salida.push_str(&registro(0x04, 0, &(alta_actual as u16).to_be_bytes()));
+            }
+            // Un registro no puede cruzar un límite de 64K
+            let hasta_limite = 0x10000 - (dir & 0xFFFF) as usize;
+            let n = resto.len().min(bytes_por_linea).min(hasta_limite);
+            salida.push_str(&registro(0x00, dir as u16, &resto[..n]));
+            resto = &resto[n..];
+            dir += n as u32;
+        }
+    }
+    if let Some(inicio) = inicio { salida.push_str(&registro(0x05, 0, &inicio.to_be_bytes())); }
+    salida.push_str(&registro(0x01, 0, &[]));
+    salida
+}
+
+//*****************************************************************************
+impl BancosMemoria {
+    // Comprueba todos los bloques antes de escribir para no dejar la memoria a medias. Dos bloques
+    // que escriben en la misma dirección son un error.
+    pub fn carga_imagen(&mut self, imagen: &ImagenCarga) -> Result<(), String> {
+        let mut ordenados: Vec<(u32, usize)> = imagen.bloques.iter().map(|(d, datos)| (*d, datos.len())).collect();
+        ordenados.sort();
+        for par in ordenados.windows(2) {
+            if par[0].0 + par[0].1 as u32 > par[1].0 {
+                return Err(format!("Los datos en 0x{:06X} se solapan con los de 0x{:06X}", par[1].0, par[0].0));
+            }
+        }
+        for (direccion, datos) in &imagen.bloques {
+            let banco = (direccion >> 16) as usize;
+            let Some(segmento) = self.segmento_memoria.get(banco) else {
+                return Err(format!("Dirección 0x{:06X}: no existe el banco {}", direccion, banco));
+            };
+            let fin = (direccion & 0xFFFF) as usize + datos.len();
+            if fin > segmento.len() {
+                return Err(format!("Dirección 0x{:06X}: {} bytes no caben en el banco {} ({} bytes)", direccion, datos.len(), banco, segmento.len()));
+            }
+        }
+        for (direccion, datos) in &imagen.bloques {
+            let inicio = (direccion & 0xFFFF) as usize;
+            self.segmento_memoria[(direccion >> 16) as usize][inicio..inicio + datos.len()].copy_from_slice(datos);
+        }
+        Ok(())
+    }
+
+    pub fn carga_intel_hex(&mut self, texto: &str) -> Result<Option<u32>, String> {
+        let imagen = analiza_intel_hex(texto)?;
+        self.carga_imagen(&imagen)?;
+        Ok(imagen.inicio)
+    }
+
+    // Rango [inicio, fin] de un banco en Intel HEX
+    pub fn vuelca_intel_hex(&self, banco: u8, inicio: u16, fin: u16, bytes_por_linea: usize) -> Result<String, String> {
+        let segmento = self.segmento_memoria.get(banco as usize).ok_or(format!("No existe el banco {}", banco))?;
+        if inicio > fin || fin as usize >= segmento.len() {
+            return Err(format!("Rango 0x{:04X}-0x{:04X} fuera del banco {} ({} bytes)", inicio, fin, banco, segmento.len()));
+        }
+        let datos = segmento[inicio as usize..=fin as usize].to_vec();
+        Ok(genera_intel_hex(&[(((banco as u32) << 16) | inicio as u32, datos)], None, bytes_por_linea))
+    }
+}
+
+impl CPU {
+    // Carga los datos y, si el archivo trae dirección de inicio, fija el banco activo y el PC
+    pub fn cargar_intel_hex(&mut self, texto: &str) -> Result<(), String> {
+        if let Some(inicio) = self.memoria.carga_intel_hex(texto)? {
+            let banco = (inicio >> 16) as usize;
+            if banco >= self.memoria.segmento_memoria.len() { return Err(format!("Dirección de inicio 0x{:06X}: no existe el banco {}", inicio, banco)); }
+            self.memoria.set_banco_activo(banco as u8);
+            self.contador_de_programa = inicio as u16;
+        }
+        Ok(())
+    }
+
+    pub fn cargar_archivo_hex(&mut self, ruta: &str) -> Result<(), String> {
+        let texto = std::fs::read_to_string(ruta).map_err(|e| format!("No se puede leer '{}': {}", ruta, e))?;
+        self.cargar_intel_hex(&texto).map_err(|e| format!("{}: {}", ruta, e))
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_carga_con_bancos_y_segmentos() {
+        let mut cpu = CPU::new();
+        cpu.memoria.crear_segmento(0x4000);
+        cpu.cargar_intel_hex("
+:03001000310004B8
+:020000040001F9
+:02200000C9C94C
+:020000020000FC
+:010020007669
+:0400000500012000D6
+:00000001FF
+:0100000000FF").unwrap();
+        assert_eq!(&cpu.memoria.segmento_memoria[0][0x10..0x13], &[0x31, 0x00, 0x04]);
+        assert_eq!(&cpu.memoria.segmento_memoria[1][0x2000..0x2002], &[0xC9, 0xC9]);
+        // El registro 02 vuelve a la base 0: el HLT va al banco 0
+        assert_eq!(cpu.memoria.segmento_memoria[0][0x20], 0x76);
+        assert_eq!(cpu.memoria.get_banco_activo(), 1);
+        assert_eq!(cpu.contador_de_programa, 0x2000);
+    }
+
+    #[test]
+    fn test_errores() {
+        let mut memoria = BancosMemoria::new();
+        assert_eq!(memoria.carga_intel_hex("\n:03001000310004B9\n:00000001FF").err().unwrap()
+            , "Línea 2: suma de comprobación incorrecta (0xB9, se esperaba 0xB8)");
+        assert_eq!(memoria.carga_intel_hex("0300100031000492").err().unwrap(), "Línea 1: el registro debe empezar por ':'");
+        assert_eq!(memoria.carga_intel_hex(":0400100031000492\n").err().unwrap(), "Línea 1: la longitud indica 4 bytes de datos y hay 3");
+        assert_eq!(memoria.carga_intel_hex(":03001000310004B8\n").err().unwrap(), "Falta el registro de fin de archivo (tipo 01)");
+        assert_eq!(memoria.carga_intel_hex(":00000006FA\n").err().unwrap(), "Línea 1: tipo de registro 06 no soportado");
+        // No hay banco 1: no se escribe nada, tampoco los datos del banco 0
+        assert!(memoria.carga_intel_hex(":01000000AA55\n:020000040001F9\n:01000000AA55\n:00000001FF").err().unwrap().contains("no existe el banco 1"));
+        assert_eq!(memoria.segmento_memoria[0][0], 0x00);
+    }
+
+    #[test]
+    fn test_volcado_y_vuelta() {
+        let mut memoria = BancosMemoria::new();
+        memoria.crear_segmento(0x100);
+        for i in 0..20u16 { memoria.segmento_memoria[1][0x10 + i as usize] = i as u8; }
+        let texto = memoria.vuelca_intel_hex(1, 0x0010, 0x0023, 16).unwrap();
+        assert_eq!(texto.lines().collect::<Vec<_>>(), vec![
+            ":020000040001F9",
+            ":10001000000102030405060708090A0B0C0D0E0F68",
+            ":040020001011121396",
+            ":00000001FF",
+        ]);
+        let mut copia = BancosMemoria::new();
+        copia.crear_segmento(0x100);
+        copia.carga_intel_hex(&texto).unwrap();
+        assert_eq!(copia.segmento_memoria[1], memoria.segmento_memoria[1]);
+        assert!(memoria.vuelca_intel_hex(1, 0x00F0, 0x0100, 16).is_err());
+    }
+}
+
+//*****************************************************************************
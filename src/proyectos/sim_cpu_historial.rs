@@ -0,0 +1,231 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Ejecución hacia atrás (depuración con viaje en el tiempo) - Simulación CPU
+    Descripción:    Antes de cada instrucción se guarda un delta con el estado de los
+                    registros y los bytes de memoria que la instrucción sobrescribe. Los
+                    deltas se guardan en un buffer circular acotado y permiten deshacer
+                    instrucciones de forma exacta con step_back() y run_back_to(pc).
+    Referencias:
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+use std::collections::VecDeque;
+
+//***************************************************************************** Deltas
+// Estado de los registros antes de ejecutar la instrucción (unos 30 bytes, más barato que
+// comparar campo a campo) y bytes de memoria sobrescritos durante la instrucción. El mnemónico
+// de la CPU (el de la instrucción anterior) se guarda igual que los registros, así que al
+// deshacer se ve el mismo texto que al avanzar.
+#[derive(Clone)]
+pub struct DeltaInstruccion {
+    pub registros: [u8; 7],             // A B C D E H L
+    pub flags: u8,
+    pub reg_ix: u16,
+    pub reg_iy: u16,
+    pub contador_de_programa: u16,
+    pub puntero_de_pila: u16,
+    pub registro_instrucciones: u8,
+    pub ciclos: u64,
+    pub interrupciones: bool,
+    pub banco_actual: u8,
+    pub mnemonic: String,
+    pub memoria: Vec<(u8, u16, u8)>,    // (banco, dirección, valor anterior)
+}
+
+pub struct Historial {
+    pub capacidad: usize,
+    deltas: VecDeque<DeltaInstruccion>,
+    pendiente: Option<DeltaInstruccion>,
+}
+
+impl Historial {
+    pub fn new(capacidad: usize) -> Historial {
+        Historial { capacidad, deltas: VecDeque::with_capacity(capacidad), pendiente: None }
+    }
+
+    pub fn len(&self) -> usize { self.deltas.len() }
+
+    pub fn is_empty(&self) -> bool { self.deltas.is_empty() }
+
+    pub fn limpiar(&mut self) {
+        self.deltas.clear();
+        self.pendiente = None;
+    }
+
+    fn guarda(&mut self, delta: DeltaInstruccion) {
+        if self.capacidad == 0 { return; }
+        if self.deltas.len() == self.capacidad {
+            self.deltas.pop_front();    // Se descarta la instrucción más antigua
+        }
+        self.deltas.push_back(delta);
+    }
+}
+
+//*****************************************************************************
+impl CPU {
+    pub fn habilitar_historial(&mut self, capacidad: usize) {
+        self.historial = Some(Historial::new(capacidad));
+    }
+
+    pub fn deshabilitar_historial(&mut self) {
+        self.historial = None;
+        self.memoria.diario_escrituras = None;
+    }
+
+    // Se llama antes de ejecutar una instrucción: guarda los registros y activa el diario de memoria
+    pub fn inicia_delta(&mut self) {
+        if self.historial.is_none() { return; }
+        let delta = DeltaInstruccion {
+            registros: [self.reg_a, self.reg_b, self.reg_c, self.reg_d, self.reg_e, self.reg_h, self.reg_l],
+            flags: self.flags.get_flags(),
+            reg_ix: self.reg_ix,
+            reg_iy: self.reg_iy,
+            contador_de_programa: self.contador_de_programa,
+            puntero_de_pila: self.puntero_de_pila,
+            registro_instrucciones: self.registro_instrucciones,
+            ciclos: self.ciclos,
+            interrupciones: self.interrupciones,
+            banco_actual: self.memoria.banco_actual,
+            mnemonic: self.mnemonic.clone(),
+            memoria: Vec::new(),
+        };
+        self.memoria.diario_escrituras = Some(Vec::new());
+        if let Some(historial) = self.historial.as_mut() { historial.pendiente = Some(delta); }
+    }
+
+    // Se llama después de ejecutar la instrucción: añade las escrituras y guarda el delta
+    pub fn registra_delta(&mut self) {
+        let escrituras = self.memoria.diario_escrituras.take().unwrap_or_default();
+        if let Some(historial) = self.historial.as_mut() {
+            if let Some(mut delta) = historial.pendiente.take() {
+                delta.memoria = escrituras;
+                historial.guarda(delta);
+            }
+        }
+    }
+
+    // Deshace la última instrucción registrada. Devuelve false si no queda historial.
+    pub fn step_back(&mut self) -> bool {
+        let delta = match self.historial.as_mut().and_then(|h| h.deltas.pop_back()) {
+            Some(delta) => delta,
+            None => return false,
+        };
+        // Las escrituras se deshacen en orden inverso por si la instrucción escribió dos veces
+        for &(banco, direccion, anterior) in delta.memoria.iter().rev() {
+            self.memoria.segmento_memoria[banco as usize][direccion as usize] = anterior;
+        }
+        let [a, b, c, d, e, h, l] = delta.registros;
+        self.reg_a = a; self.reg_b = b; self.reg_c = c; self.reg_d = d;
+        self.reg_e = e; self.reg_h = h; self.reg_l = l;
+        self.flags.set_flags(delta.flags);
+        self.reg_ix = delta.reg_ix;
+        self.reg_iy = delta.reg_iy;
+        self.contador_de_programa = delta.contador_de_programa;
+        self.puntero_de_pila = delta.puntero_de_pila;
+        self.registro_instrucciones = delta.registro_instrucciones;
+        self.ciclos = delta.ciclos;
+        self.interrupciones = delta.interrupciones;
+        self.memoria.banco_actual = delta.banco_actual;
+        self.mnemonic = delta.mnemonic;
+        true
+    }
+
+    // Retrocede hasta que el contador de programa vale 'pc'. Devuelve las instrucciones deshechas.
+    // Primero se busca en el historial: si 'pc' no está, la CPU no cambia.
+    pub fn run_back_to(&mut self, pc: u16) -> Result<usize, String> {
+        let deltas = self.historial.as_ref().map(|h| &h.deltas);
+        let deshechas = deltas.and_then(|d| d.iter().rev().position(|delta| delta.contador_de_programa == pc))
+            .ok_or(format!("0x{:04X} no está en el historial ({} instrucciones)", pc, deltas.map_or(0, |d| d.len())))? + 1;
+        for _ in 0..deshechas { self.step_back(); }
+        Ok(deshechas)
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proyectos::sim_cpu_pruebas::Aux;
+    use asm8080::asm8080;
+
+    fn cpu_con_programa() -> (CPU, Aux) {
+        let mut cpu = CPU::new();
+        cpu.cargar_programa(&asm8080! {
+                    mvi a, 3bh
+                    inr a
+                    sta destino
+                    mvi b, 0ffh
+            destino: nop                // 0x0008, se convierte en 3C
+                    add b
+                    db 0ffh
+        });
+        cpu.habilitar_historial(16);
+        let aux = Aux { imp_contador_programa: 0, imp_instruccion: 0, imp_mnemonico: String::new() };
+        (cpu, aux)
+    }
+
+    #[test]
+    fn test_step_back_restaura_registros_y_memoria() {
+        let (mut cpu, mut aux) = cpu_con_programa();
+        let memoria_inicial = cpu.memoria.segmento_memoria.clone();
+        let mut mnemonicos = vec![cpu.mnemonic.clone()];
+        for _ in 0..6 {
+            cpu.step_no_win(&mut aux);
+            mnemonicos.push(cpu.mnemonic.clone());
+        }
+        assert_eq!(cpu.memoria.leer_memoria(0x0008), 0x3C);
+        assert_eq!(cpu.reg_a, 0x3C);
+
+        // Se deshace el ADD A,B
+        assert!(cpu.step_back());
+        assert_eq!(cpu.contador_de_programa, 0x0009);
+        assert_eq!(cpu.reg_a, 0x3D);
+        assert_eq!(cpu.mnemonic, mnemonicos[5]);
+        // Se deshace todo el programa: al retroceder se ven los mismos mnemónicos que al avanzar
+        for i in (0..5).rev() {
+            assert!(cpu.step_back());
+            assert_eq!(cpu.mnemonic, mnemonicos[i]);
+        }
+        assert!(!cpu.step_back());
+        assert_eq!(cpu.contador_de_programa, 0);
+        assert_eq!(cpu.reg_a, 0);
+        assert_eq!(cpu.ciclos, 0);
+        assert_eq!(cpu.flags.get_flags(), 0x02);
+        assert_eq!(cpu.memoria.segmento_memoria, memoria_inicial);
+    }
+
+    #[test]
+    fn test_run_back_to() {
+        let (mut cpu, mut aux) = cpu_con_programa();
+        for _ in 0..6 { cpu.step_no_win(&mut aux); }
+        assert_eq!(cpu.run_back_to(0x0003), Ok(4));
+        assert_eq!(cpu.reg_a, 0x3C);
+        assert_eq!(cpu.mnemonic, "INR A");
+        assert_eq!(cpu.memoria.leer_memoria(0x0008), 0x00);
+        // Volver a ejecutar produce el mismo resultado
+        for _ in 0..4 { cpu.step_no_win(&mut aux); }
+        assert_eq!(cpu.memoria.leer_memoria(0x0008), 0x3C);
+        // Una dirección que no está en el historial no deshace nada
+        assert!(cpu.run_back_to(0x0100).is_err());
+        assert_eq!(cpu.contador_de_programa, 0x000A);
+        assert_eq!(cpu.historial.as_ref().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_buffer_circular_acotado() {
+        let (mut cpu, mut aux) = cpu_con_programa();
+        cpu.habilitar_historial(2);
+        for _ in 0..6 { cpu.step_no_win(&mut aux); }
+        assert_eq!(cpu.historial.as_ref().unwrap().len(), 2);
+        assert!(cpu.step_back());
+        assert!(cpu.step_back());
+        assert!(!cpu.step_back());
+        assert_eq!(cpu.contador_de_programa, 0x0008);
+    }
+}
+
+//*****************************************************************************
@@ -0,0 +1,723 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Descripción declarativa de juegos de instrucciones - Simulación CPU
+    Descripción:    Permite definir CPUs de juguete en un archivo de datos (registros, bits
+                    de flags, patrones de bits de los opcode y micro-operaciones) y ejecutar
+                    programas sobre los mismos bancos de memoria del simulador.
+    Referencias:
+    PanCurses       https://crates.io/crates/pancurses
+    Ejemplo         src/proyectos/isa/juguete.isa
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_memoria::BancosMemoria;
+use pancurses::*;
+
+fn imprime_titulo(ventana: &Window, titulo: &str) {
+    let max_x = ventana.get_max_x();
+    ventana.mv(1, (max_x - titulo.chars().count() as i32) / 2);
+    init_pair(1, COLOR_RED, COLOR_BLACK);
+    ventana.attrset(ColorPair(1));
+    ventana.printw(titulo);
+    ventana.attrset(Attribute::Normal);
+}
+
+//***************************************************************************** Notas
+/* Formato del archivo de descripción
+Cada línea no vacía que no empiece por '#' (comentario) es una directiva:
+    nombre      JUGUETE8
+    registros   A B C D
+    flags       Z=6 C=0
+    instr       MVI r,d : 001000rr dddddddd : cargar %r, #d
+
+El patrón de bits se escribe con un grupo de 8 caracteres por byte. Los '0' y '1' son bits
+fijos que identifican la instrucción y las letras forman los campos de operandos. Los bits
+de un campo dentro de un byte se leen de izquierda a derecha (bit más significativo primero)
+y, si el campo continúa en el byte siguiente, esos bits se colocan por encima (little-endian),
+igual que las direcciones de 16 bits del 8080.
+
+Micro-operaciones disponibles (separadas por ';'):
+    cargar DESTINO, FUENTE          DESTINO es un registro
+    almacenar [DIRECCION], FUENTE   escribe un byte en memoria
+    alu OP, DESTINO, FUENTE         OP: add adc sub sbb and or xor cmp inc dec
+    saltar DIRECCION
+    saltar_si FLAG=0|1, DIRECCION
+    parar
+
+La ALU actualiza solo los flags declarados cuyo nombre reconoce: Z, C, S, P y H.
+
+Un operando %r cuyo campo vale más que el número de registros declarados (un campo de 2 bits con
+solo 3 registros) detiene la ejecución con error en lugar de leer 0 o perder la escritura.
+Una instrucción con error no deja cambios a medias: se restauran los registros, los flags y la
+memoria que hubieran cambiado sus micro-operaciones anteriores.
+
+Vista: cpu_isa_0 abre una ventana de pancurses como cpu_generica_0 y run_win muestra una línea
+por instrucción con el PC, el mnemónico, los registros y los flags (q/Q para salir). run_no_win
+escribe las mismas líneas en la salida estándar.
+*/
+
+//***************************************************************************** Estructuras de la descripción
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operando {
+    Registro(usize),            // Registro por índice (nombre resuelto al cargar)
+    RegistroCampo(char),        // Registro seleccionado por el valor de un campo
+    Inmediato(char),            // Valor de un campo
+    Literal(u16),               // Valor fijo
+    Memoria(Box<Operando>),     // Contenido de memoria en la dirección indicada
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OperacionAlu { Add, Adc, Sub, Sbb, And, Or, Xor, Cmp, Inc, Dec }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MicroOp {
+    Cargar(Operando, Operando),
+    Almacenar(Operando, Operando),
+    Alu(OperacionAlu, Operando, Operando),
+    Saltar(Operando),
+    SaltarSi(usize, bool, Operando),    // (índice del flag, valor esperado, dirección)
+    Parar,
+}
+
+#[derive(Debug, Clone)]
+pub struct DefInstruccion {
+    pub mnemonico: String,
+    pub mascaras: Vec<u8>,              // Bits fijos de cada byte
+    pub valores: Vec<u8>,               // Valor de los bits fijos
+    pub campos: Vec<(char, Vec<u8>)>,   // (letra, máscara de cada byte)
+    pub micro_ops: Vec<MicroOp>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DescripcionIsa {
+    pub nombre: String,
+    pub registros: Vec<String>,
+    pub flags: Vec<(String, u8)>,       // (nombre, bit)
+    pub instrucciones: Vec<DefInstruccion>,
+}
+
+impl DefInstruccion {
+    pub fn longitud(&self) -> usize { self.mascaras.len() }
+
+    // Comprueba si los bytes coinciden con los bits fijos del patrón
+    pub fn coincide(&self, bytes: &[u8]) -> bool {
+        self.mascaras.iter().zip(self.valores.iter()).zip(bytes.iter())
+            .all(|((mascara, valor), byte)| byte & mascara == *valor)
+    }
+
+    // Extrae el valor de un campo de los bytes de la instrucción
+    pub fn valor_campo(&self, letra: char, bytes: &[u8]) -> u16 {
+        let mut valor: u16 = 0;
+        let mut desplazamiento = 0;
+        if let Some((_, mascaras)) = self.campos.iter().find(|(l, _)| *l == letra) {
+            for (mascara, byte) in mascaras.iter().zip(bytes.iter()) {
+                let mut parcial: u16 = 0;
+                let mut ancho = 0;
+                for bit in (0..8).rev() {
+                    if mascara & (1 << bit) != 0 {
+                        parcial = (parcial << 1) | ((byte >> bit) & 1) as u16;
+                        ancho += 1;
+                    }
+                }
+                valor |= parcial << desplazamiento;
+                desplazamiento += ancho;
+            }
+        }
+        valor
+    }
+}
+
+//***************************************************************************** Carga del archivo de descripción
+impl DescripcionIsa {
+    pub fn new() -> DescripcionIsa {
+        DescripcionIsa { nombre: String::new(), registros: Vec::new(), flags: Vec::new(), instrucciones: Vec::new() }
+    }
+
+    pub fn desde_archivo(ruta: &str) -> Result<DescripcionIsa, String> {
+        let texto = std::fs::read_to_string(ruta)
+            .map_err(|e| format!("No se puede leer '{}': {}", ruta, e))?;
+        DescripcionIsa::desde_texto(&texto)
+    }
+
+    pub fn desde_texto(texto: &str) -> Result<DescripcionIsa, String> {
+        let mut isa = DescripcionIsa::new();
+
+        for (num, linea) in texto.lines().enumerate() {
+            let linea = linea.trim();
+            if linea.is_empty() || linea.starts_with('#') { continue; }
+            let (directiva, resto) = match linea.split_once(char::is_whitespace) {
+                Some((d, r)) => (d, r.trim()),
+                None => (linea, ""),
+            };
+            let resultado = match directiva {
+                "nombre" => { isa.nombre = resto.to_string(); Ok(()) }
+                "registros" => {
+                    isa.registros = resto.split_whitespace().map(|r| r.to_uppercase()).collect();
+                    Ok(())
+                }
+                "flags" => isa.analiza_flags(resto),
+                "instr" => isa.analiza_instruccion(resto),
+                _ => Err(format!("Directiva desconocida '{}'", directiva)),
+            };
+            resultado.map_err(|e| format!("Línea {}: {}", num + 1, e))?;
+        }
+
+        if isa.registros.is_empty() {
+            return Err(String::from("La descripción no define registros"));
+        }
+        if isa.instrucciones.is_empty() {
+            return Err(String::from("La descripción no define instrucciones"));
+        }
+        Ok(isa)
+    }
+
+    fn analiza_flags(&mut self, resto: &str) -> Result<(), String> {
+        for def in resto.split_whitespace() {
+            let (nombre, bit) = def.split_once('=')
+                .ok_or(format!("Flag mal definido '{}', se espera NOMBRE=bit", def))?;
+            let bit: u8 = bit.parse().map_err(|_| format!("Bit de flag no válido '{}'", bit))?;
+            if bit > 7 { return Err(format!("Bit de flag fuera de rango: {}", bit)); }
+            self.flags.push((nombre.to_uppercase(), bit));
+        }
+        Ok(())
+    }
+
+    fn analiza_instruccion(&mut self, resto: &str) -> Result<(), String> {
+        let partes: Vec<&str> = resto.splitn(3, ':').collect();
+        if partes.len() < 2 {
+            return Err(String::from("Instrucción mal definida, se espera 'MNEMONICO : patrón : micro-operaciones'"));
+        }
+        let mnemonico = partes[0].trim().to_string();
+        let patron: String = partes[1].split_whitespace().collect();
+        let num_bytes = patron.len() / 8;
+        if num_bytes == 0 || num_bytes * 8 != patron.len() {
+            return Err(format!("El patrón de '{}' debe tener grupos de 8 bits", mnemonico));
+        }
+
+        let mut mascaras = Vec::new();
+        let mut valores = Vec::new();
+        let mut campos: Vec<(char, Vec<u8>)> = Vec::new();
+        for (i, caracter) in patron.chars().enumerate() {
+            let byte = i / 8;
+            let bit = 7 - (i % 8);
+            if mascaras.len() <= byte { mascaras.push(0u8); valores.push(0u8); }
+            match caracter {
+                '0' => { mascaras[byte] |= 1 << bit; }
+                '1' => { mascaras[byte] |= 1 << bit; valores[byte] |= 1 << bit; }
+                c if c.is_ascii_lowercase() => {
+                    let indice = match campos.iter().position(|(l, _)| *l == c) {
+                        Some(indice) => indice,
+                        None => { campos.push((c, vec![0; num_bytes])); campos.len() - 1 }
+                    };
+                    campos[indice].1[byte] |= 1 << bit;
+                }
+                c => return Err(format!("Carácter '{}' no válido en el patrón de '{}'", c, mnemonico)),
+            }
+        }
+
+        let mut micro_ops = Vec::new();
+        if let Some(texto_ops) = partes.get(2) {
+            for op in texto_ops.split(';').map(|o| o.trim()).filter(|o| !o.is_empty()) {
+                micro_ops.push(self.analiza_micro_op(op, &campos)?);
+            }
+        }
+
+        self.instrucciones.push(DefInstruccion { mnemonico, mascaras, valores, campos, micro_ops });
+        Ok(())
+    }
+
+    fn analiza_micro_op(&self, texto: &str, campos: &[(char, Vec<u8>)]) -> Result<MicroOp, String> {
+        let (nombre, resto) = match texto.split_once(char::is_whitespace) {
+            Some((n, r)) => (n, r.trim()),
+            None => (texto, ""),
+        };
+        let args: Vec<&str> = if resto.is_empty() { Vec::new() } else { resto.split(',').map(|a| a.trim()).collect() };
+        let num_args = |n: usize| -> Result<(), String> {
+            if args.len() != n { Err(format!("'{}' espera {} operandos", nombre, n)) } else { Ok(()) }
+        };
+
+        match nombre {
+            "cargar" => {
+                num_args(2)?;
+                let destino = self.analiza_operando(args[0], campos)?;
+                self.comprueba_registro(&destino)?;
+                Ok(MicroOp::Cargar(destino, self.analiza_operando(args[1], campos)?))
+            }
+            "almacenar" => {
+                num_args(2)?;
+                let destino = self.analiza_operando(args[0], campos)?;
+                if !matches!(destino, Operando::Memoria(_)) {
+                    return Err(String::from("'almacenar' necesita un destino en memoria [..]"));
+                }
+                Ok(MicroOp::Almacenar(destino, self.analiza_operando(args[1], campos)?))
+            }
+            "alu" => {
+                num_args(3)?;
+                let operacion = match args[0].to_lowercase().as_str() {
+                    "add" => OperacionAlu::Add, "adc" => OperacionAlu::Adc,
+                    "sub" => OperacionAlu::Sub, "sbb" => OperacionAlu::Sbb,
+                    "and" => OperacionAlu::And, "or" => OperacionAlu::Or,
+                    "xor" => OperacionAlu::Xor, "cmp" => OperacionAlu::Cmp,
+                    "inc" => OperacionAlu::Inc, "dec" => OperacionAlu::Dec,
+                    otra => return Err(format!("Operación de ALU desconocida '{}'", otra)),
+                };
+                let destino = self.analiza_operando(args[1], campos)?;
+                self.comprueba_registro(&destino)?;
+                Ok(MicroOp::Alu(operacion, destino, self.analiza_operando(args[2], campos)?))
+            }
+            "saltar" => {
+                num_args(1)?;
+                Ok(MicroOp::Saltar(self.analiza_operando(args[0], campos)?))
+            }
+            "saltar_si" => {
+                num_args(2)?;
+                let (flag, valor) = args[0].split_once('=')
+                    .ok_or(String::from("'saltar_si' espera FLAG=0|1"))?;
+                let indice = self.flags.iter().position(|(n, _)| n.eq_ignore_ascii_case(flag.trim()))
+                    .ok_or(format!("Flag desconocido '{}'", flag.trim()))?;
+                let valor = match valor.trim() {
+                    "0" => false,
+                    "1" => true,
+                    v => return Err(format!("Valor de flag no válido '{}'", v)),
+                };
+                Ok(MicroOp::SaltarSi(indice, valor, self.analiza_operando(args[1], campos)?))
+            }
+            "parar" => { num_args(0)?; Ok(MicroOp::Parar) }
+            _ => Err(format!("Micro-operación desconocida '{}'", nombre)),
+        }
+    }
+
+    fn analiza_operando(&self, texto: &str, campos: &[(char, Vec<u8>)]) -> Result<Operando, String> {
+        let campo = |letra: &str| -> Result<char, String> {
+            let mut caracteres = letra.chars();
+            match (caracteres.next(), caracteres.next()) {
+                (Some(c), None) if campos.iter().any(|(l, _)| *l == c) => Ok(c),
+                _ => Err(format!("Campo '{}' no definido en el patrón", letra)),
+            }
+        };
+
+        if let Some(interior) = texto.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            return Ok(Operando::Memoria(Box::new(self.analiza_operando(interior.trim(), campos)?)));
+        }
+        if let Some(letra) = texto.strip_prefix('%') {
+            return Ok(Operando::RegistroCampo(campo(letra)?));
+        }
+        if let Some(letra) = texto.strip_prefix('#') {
+            return Ok(Operando::Inmediato(campo(letra)?));
+        }
+        if let Some(indice) = self.registros.iter().position(|r| r.eq_ignore_ascii_case(texto)) {
+            return Ok(Operando::Registro(indice));
+        }
+        let literal = match texto.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16),
+            None => texto.parse::<u16>(),
+        };
+        literal.map(Operando::Literal).map_err(|_| format!("Operando no válido '{}'", texto))
+    }
+
+    fn comprueba_registro(&self, operando: &Operando) -> Result<(), String> {
+        match operando {
+            Operando::Registro(_) | Operando::RegistroCampo(_) => Ok(()),
+            _ => Err(String::from("El destino debe ser un registro")),
+        }
+    }
+}
+
+impl Default for DescripcionIsa {
+    fn default() -> Self { DescripcionIsa::new() }
+}
+
+//***************************************************************************** Máquina definida por la descripción
+pub struct MaquinaIsa {
+    pub isa: DescripcionIsa,
+    pub memoria: BancosMemoria,
+    pub registros: Vec<u8>,
+    pub flags: u8,
+    pub contador_de_programa: u16,
+    pub mnemonic: String,
+    pub parada: bool,
+}
+
+impl MaquinaIsa {
+    pub fn new(isa: DescripcionIsa) -> MaquinaIsa {
+        let num_registros = isa.registros.len();
+        MaquinaIsa {
+            isa,
+            memoria: BancosMemoria::new(),
+            registros: vec![0; num_registros],
+            flags: 0,
+            contador_de_programa: 0,
+            mnemonic: String::new(),
+            parada: false,
+        }
+    }
+
+    pub fn cargar_programa(&mut self, programa: &[u8]) {
+        for (i, &byte) in programa.iter().enumerate() {
+            self.memoria.escribir_memoria(i as u16, byte);
+        }
+    }
+
+    pub fn get_registro(&self, nombre: &str) -> Option<u8> {
+        let indice = self.isa.registros.iter().position(|r| r.eq_ignore_ascii_case(nombre))?;
+        Some(self.registros[indice])
+    }
+
+    pub fn get_flag(&self, nombre: &str) -> Option<bool> {
+        let (_, bit) = self.isa.flags.iter().find(|(n, _)| n.eq_ignore_ascii_case(nombre))?;
+        Some(self.flags & (1 << bit) != 0)
+    }
+
+    fn set_flag(&mut self, nombre: &str, valor: bool) {
+        if let Some((_, bit)) = self.isa.flags.iter().find(|(n, _)| n == nombre) {
+            if valor { self.flags |= 1 << bit } else { self.flags &= !(1 << bit) }
+        }
+    }
+
+    // Busca la definición cuyo patrón coincide con los bytes en el contador de programa
+    pub fn decodifica_instruccion(&self) -> Result<(usize, Vec<u8>), String> {
+        let max_longitud = self.isa.instrucciones.iter().map(|i| i.longitud()).max().unwrap_or(1);
+        let bytes: Vec<u8> = (0..max_longitud)
+            .map(|i| self.memoria.leer_memoria(self.contador_de_programa.wrapping_add(i as u16)))
+            .collect();
+        match self.isa.instrucciones.iter().position(|i| i.coincide(&bytes)) {
+            Some(indice) => {
+                let longitud = self.isa.instrucciones[indice].longitud();
+                Ok((indice, bytes[..longitud].to_vec()))
+            }
+            None => Err(format!("Opcode 0x{:02X} no definido en {} (PC: 0x{:04X})"
+                , bytes[0], self.isa.nombre, self.contador_de_programa)),
+        }
+    }
+
+    // Índice del registro que selecciona el campo; fuera de los declarados es un error
+    fn indice_registro(&self, letra: char, def: &DefInstruccion, bytes: &[u8]) -> Result<usize, String> {
+        let indice = def.valor_campo(letra, bytes) as usize;
+        if indice >= self.registros.len() {
+            return Err(format!("'{}': el campo {} selecciona el registro {} y {} solo declara {} (PC: 0x{:04X})"
+                , def.mnemonico, letra, indice, self.isa.nombre, self.registros.len(), self.contador_de_programa));
+        }
+        Ok(indice)
+    }
+
+    fn lee_operando(&self, operando: &Operando, def: &DefInstruccion, bytes: &[u8]) -> Result<u16, String> {
+        match operando {
+            Operando::Registro(indice) => Ok(self.registros[*indice] as u16),
+            Operando::RegistroCampo(letra) => Ok(self.registros[self.indice_registro(*letra, def, bytes)?] as u16),
+            Operando::Inmediato(letra) => Ok(def.valor_campo(*letra, bytes)),
+            Operando::Literal(valor) => Ok(*valor),
+            Operando::Memoria(direccion) => {
+                let direccion = self.lee_operando(direccion, def, bytes)?;
+                Ok(self.memoria.leer_memoria(direccion) as u16)
+            }
+        }
+    }
+
+    fn escribe_registro(&mut self, operando: &Operando, def: &DefInstruccion, bytes: &[u8], valor: u8) -> Result<(), String> {
+        let indice = match operando {
+            Operando::Registro(indice) => *indice,
+            Operando::RegistroCampo(letra) => self.indice_registro(*letra, def, bytes)?,
+            _ => return Err(format!("'{}': el destino debe ser un registro", def.mnemonico)),
+        };
+        self.registros[indice] = valor;
+        Ok(())
+    }
+
+    fn alu(&mut self, operacion: OperacionAlu, a: u8, b: u8) -> u8 {
+        let acarreo = self.get_flag("C").unwrap_or(false) as u8;
+        let (resultado, nuevo_acarreo, medio_acarreo) = match operacion {
+            OperacionAlu::Add => { let (r, c) = a.overflowing_add(b); (r, Some(c), (a & 0x0F) + (b & 0x0F) > 0x0F) }
+            OperacionAlu::Adc => {
+                let (r0, c0) = a.overflowing_add(b);
+                let (r, c1) = r0.overflowing_add(acarreo);
+                (r, Some(c0 || c1), (a & 0x0F) + (b & 0x0F) + acarreo > 0x0F)
+            }
+            OperacionAlu::Sub | OperacionAlu::Cmp => { let (r, c) = a.overflowing_sub(b); (r, Some(c), (a & 0x0F) < (b & 0x0F)) }
+            OperacionAlu::Sbb => {
+                let (r0, c0) = a.overflowing_sub(b);
+                let (r, c1) = r0.overflowing_sub(acarreo);
+                (r, Some(c0 || c1), (a & 0x0F) < (b & 0x0F) + acarreo)
+            }
+            OperacionAlu::And => (a & b, Some(false), false),
+            OperacionAlu::Or => (a | b, Some(false), false),
+            OperacionAlu::Xor => (a ^ b, Some(false), false),
+            // INC y DEC no modifican el acarreo, como INR y DCR del 8080
+            OperacionAlu::Inc => (b.wrapping_add(1), None, (b & 0x0F) == 0x0F),
+            OperacionAlu::Dec => (b.wrapping_sub(1), None, (b & 0x0F) == 0x00),
+        };
+        if let Some(c) = nuevo_acarreo { self.set_flag("C", c); }
+        self.set_flag("Z", resultado == 0);
+        self.set_flag("S", resultado & 0x80 != 0);
+        self.set_flag("P", resultado.count_ones() % 2 == 0);
+        self.set_flag("H", medio_acarreo);
+        resultado
+    }
+
+    pub fn step(&mut self) -> Result<(), String> {
+        if self.parada { return Ok(()); }
+        let (indice, bytes) = self.decodifica_instruccion()?;
+        let def = self.isa.instrucciones[indice].clone();
+        // La instrucción es atómica: si una micro-operación falla se deshacen las anteriores
+        let (registros, flags) = (self.registros.clone(), self.flags);
+        let mut escrituras = Vec::new();
+        match self.ejecuta_micro_ops(&def, &bytes, &mut escrituras) {
+            Ok((siguiente_pc, parada)) => {
+                self.mnemonic = def.mnemonico.clone();
+                self.contador_de_programa = siguiente_pc;
+                self.parada = parada;
+                Ok(())
+            }
+            Err(error) => {
+                self.registros = registros;
+                self.flags = flags;
+                for (direccion, anterior) in escrituras.into_iter().rev() {
+                    self.memoria.escribir_memoria(direccion, anterior);
+                }
+                Err(error)
+            }
+        }
+    }
+
+    // Devuelve el PC siguiente y si hay que parar; 'escrituras' guarda el valor anterior de cada
+    // byte escrito en memoria para poder deshacer la instrucción
+    fn ejecuta_micro_ops(&mut self, def: &DefInstruccion, bytes: &[u8], escrituras: &mut Vec<(u16, u8)>) -> Result<(u16, bool), String> {
+        let mut siguiente_pc = self.contador_de_programa.wrapping_add(def.longitud() as u16);
+        let mut parada = false;
+        for micro_op in def.micro_ops.iter() {
+            match micro_op {
+                MicroOp::Cargar(destino, fuente) => {
+                    let valor = self.lee_operando(fuente, def, bytes)? as u8;
+                    self.escribe_registro(destino, def, bytes, valor)?;
+                }
+                MicroOp::Almacenar(Operando::Memoria(direccion), fuente) => {
+                    let direccion = self.lee_operando(direccion, def, bytes)?;
+                    let valor = self.lee_operando(fuente, def, bytes)? as u8;
+                    escrituras.push((direccion, self.memoria.leer_memoria(direccion)));
+                    self.memoria.escribir_memoria(direccion, valor);
+                }
+                MicroOp::Almacenar(..) => return Err(format!("'{}': almacenar necesita un destino en memoria", def.mnemonico)),
+                MicroOp::Alu(operacion, destino, fuente) => {
+                    let a = self.lee_operando(destino, def, bytes)? as u8;
+                    let b = self.lee_operando(fuente, def, bytes)? as u8;
+                    let resultado = self.alu(*operacion, a, b);
+                    if *operacion != OperacionAlu::Cmp {
+                        self.escribe_registro(destino, def, bytes, resultado)?;
+                    }
+                }
+                MicroOp::Saltar(direccion) => {
+                    siguiente_pc = self.lee_operando(direccion, def, bytes)?;
+                }
+                MicroOp::SaltarSi(flag, valor, direccion) => {
+                    let bit = self.isa.flags[*flag].1;
+                    if (self.flags & (1 << bit) != 0) == *valor {
+                        siguiente_pc = self.lee_operando(direccion, def, bytes)?;
+                    }
+                }
+                MicroOp::Parar => { parada = true; }
+            }
+        }
+        Ok((siguiente_pc, parada))
+    }
+
+    // Ejecuta hasta una micro-operación 'parar' o un máximo de instrucciones
+    pub fn run(&mut self, max_instrucciones: usize) -> Result<usize, String> {
+        let mut ejecutadas = 0;
+        while !self.parada && ejecutadas < max_instrucciones {
+            self.step()?;
+            ejecutadas += 1;
+        }
+        Ok(ejecutadas)
+    }
+
+    //************************************* Vista de depuración (mismo formato que run_no_win)
+    pub fn info_registros(&self) -> String {
+        let mut salida = String::new();
+        for (nombre, valor) in self.isa.registros.iter().zip(self.registros.iter()) {
+            salida.push_str(&format!("Reg {}: {:02x}, ", nombre, valor));
+        }
+        for (nombre, bit) in self.isa.flags.iter() {
+            salida.push_str(&format!("{}={} ", nombre, (self.flags >> bit) & 1));
+        }
+        salida.trim_end().to_string()
+    }
+
+    // Ejecuta una instrucción y devuelve su línea de la vista
+    fn step_linea(&mut self) -> Result<String, String> {
+        let contador = self.contador_de_programa;
+        let instruccion = self.memoria.leer_memoria(contador);
+        self.step()?;
+        Ok(format!("Contador: 0x{:04X}, Instruccion: {:02x}, Mnemonic: {},\t{}",
+            contador, instruccion, self.mnemonic, self.info_registros()))
+    }
+
+    pub fn run_no_win(&mut self, max_instrucciones: usize) -> Result<(), String> {
+        for _ in 0..max_instrucciones {
+            if self.parada { break; }
+            println!("{}", self.step_linea()?);
+        }
+        Ok(())
+    }
+
+    // Igual que run de sim_cpu_generica: una línea por instrucción, una tecla para avanzar
+    pub fn run_win(&mut self, ventana: &Window, max_instrucciones: usize) -> Result<(), String> {
+        let mut pos_y = 3;
+        for _ in 0..max_instrucciones {
+            if self.parada { break; }
+            // La línea se rellena hasta el borde para borrar la de la vuelta anterior
+            let linea = self.step_linea()?.replace('\t', " ");
+            let ancho = (ventana.get_max_x() - 4).max(0) as usize;
+            ventana.mvprintw(pos_y, 2, format!("{:<ancho$.ancho$}", linea));
+            pos_y += 1;
+            if pos_y == ventana.get_max_y() - 2 { pos_y = 3; }
+            ventana.refresh();
+            if let Some(Input::Character('q' | 'Q')) = ventana.getch() { break; }
+        }
+        Ok(())
+    }
+}
+
+//***************************************************************************** Ejemplo de uso
+pub fn cpu_isa_0() {
+    let titulo = " Simulación CPU definida por descripción (JUGUETE8) ";
+    let isa = match DescripcionIsa::desde_texto(include_str!("isa/juguete.isa")) {
+        Ok(isa) => isa,
+        Err(e) => { println!("{}", e); return; }
+    };
+    let mut maquina = MaquinaIsa::new(isa);
+    let programa = vec![
+        0x20, 0x00,         // MVI A,0x00
+        0x21, 0x03,         // MVI B,0x03       Contador del bucle
+        0x22, 0x05,         // MVI C,0x05
+        0x42,               // ADD C            <- 0x0006
+        0x4D,               // DCR B
+        0x82, 0x06, 0x00,   // JNZ 0x0006
+        0x31, 0x20, 0x00,   // STA 0x0020       A = 0x0F
+        0xFF,               // HLT
+    ];
+    maquina.cargar_programa(&programa);
+
+    let mut ventana_principal = initscr();
+    let (val_y, val_x) = (30, 100);
+    // Verificación medidas de la terminal
+    if ventana_principal.get_max_y() < val_y || ventana_principal.get_max_x() < val_x {
+        endwin();
+        println!("La Terminal es menor del tamaño requerido: {} x {} \n", val_y, val_x);
+        return;
+    }
+    ventana_principal.resize(val_y, val_x);
+    ventana_principal.border('|', '|', '-', '-', '+', '+', '+', '+');
+    noecho();
+    start_color();
+    imprime_titulo(&ventana_principal, titulo);
+    ventana_principal.mvprintw(0, 2, " Salir: q/Q ");
+    ventana_principal.refresh();
+
+    let resultado = maquina.run_win(&ventana_principal, 100);
+    ventana_principal.mvprintw(val_y - 2, 2, match resultado.as_ref() {
+        Ok(_) if maquina.parada => String::from("Programa terminado, pulse una tecla"),
+        Ok(_) => String::from("Límite de instrucciones, pulse una tecla"),
+        Err(e) => e.clone(),
+    });
+    ventana_principal.getch();
+    echo();
+    endwin();
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn maquina_juguete(programa: Vec<u8>) -> MaquinaIsa {
+        let isa = DescripcionIsa::desde_texto(include_str!("isa/juguete.isa")).unwrap();
+        let mut maquina = MaquinaIsa::new(isa);
+        maquina.cargar_programa(&programa);
+        maquina
+    }
+
+    #[test]
+    fn test_carga_descripcion() {
+        let isa = DescripcionIsa::desde_texto(include_str!("isa/juguete.isa")).unwrap();
+        assert_eq!(isa.nombre, "JUGUETE8");
+        assert_eq!(isa.registros, vec!["A", "B", "C", "D"]);
+        assert_eq!(isa.flags[0], (String::from("Z"), 6));
+        let mvi = isa.instrucciones.iter().find(|i| i.mnemonico == "MVI r,d").unwrap();
+        assert_eq!(mvi.longitud(), 2);
+        assert_eq!(mvi.mascaras, vec![0b11111100, 0x00]);
+        assert_eq!(mvi.valor_campo('r', &[0x22, 0x7F]), 2);
+        assert_eq!(mvi.valor_campo('d', &[0x22, 0x7F]), 0x7F);
+    }
+
+    #[test]
+    fn test_campo_little_endian() {
+        let isa = DescripcionIsa::desde_texto(include_str!("isa/juguete.isa")).unwrap();
+        let jmp = isa.instrucciones.iter().find(|i| i.mnemonico == "JMP a").unwrap();
+        assert_eq!(jmp.valor_campo('a', &[0x80, 0x34, 0x12]), 0x1234);
+    }
+
+    #[test]
+    fn test_programa_bucle() {
+        let mut maquina = maquina_juguete(vec![
+            0x20, 0x00, 0x21, 0x03, 0x22, 0x05,
+            0x42, 0x4D, 0x82, 0x06, 0x00,
+            0x31, 0x20, 0x00, 0xFF,
+        ]);
+        maquina.run(100).unwrap();
+        assert!(maquina.parada);
+        assert_eq!(maquina.get_registro("A"), Some(0x0F));
+        assert_eq!(maquina.get_registro("B"), Some(0x00));
+        assert_eq!(maquina.get_flag("Z"), Some(true));
+        assert_eq!(maquina.memoria.leer_memoria(0x0020), 0x0F);
+    }
+
+    #[test]
+    fn test_acarreo_y_salto_condicional() {
+        let mut maquina = maquina_juguete(vec![
+            0x20, 0xF0,         // MVI A,0xF0
+            0x21, 0x20,         // MVI B,0x20
+            0x41,               // ADD B        -> 0x10 con acarreo
+            0x83, 0x0A, 0x00,   // JC 0x000A
+            0x20, 0x55,         // MVI A,0x55   (no se ejecuta)
+            0xFF,               // HLT          <- 0x000A
+        ]);
+        maquina.run(10).unwrap();
+        assert_eq!(maquina.get_registro("A"), Some(0x10));
+        assert_eq!(maquina.get_flag("C"), Some(true));
+    }
+
+    #[test]
+    fn test_errores() {
+        let error = DescripcionIsa::desde_texto("registros A\ninstr X : 0000 : parar").unwrap_err();
+        assert!(error.starts_with("Línea 2"));
+        let error = DescripcionIsa::desde_texto("registros A\ninstr X : 00000000 : cargar B, A").unwrap_err();
+        assert!(error.contains("Operando no válido"));
+
+        let mut maquina = maquina_juguete(vec![0xF0]);
+        assert!(maquina.step().unwrap_err().contains("no definido"));
+
+        // Campo de registro de 2 bits con solo 3 registros: r = 3 es un error al leer y al escribir
+        let isa = DescripcionIsa::desde_texto("nombre TRES\nregistros A B C\ninstr MOV r,s : 0000rrss : cargar %r, %s").unwrap();
+        let mut maquina = MaquinaIsa::new(isa.clone());
+        maquina.cargar_programa(&[0x03]);
+        assert_eq!(maquina.step().unwrap_err(), "'MOV r,s': el campo s selecciona el registro 3 y TRES solo declara 3 (PC: 0x0000)");
+        let mut maquina = MaquinaIsa::new(isa);
+        maquina.cargar_programa(&[0x0C]);
+        assert!(maquina.step().unwrap_err().contains("campo r selecciona el registro 3"));
+        assert_eq!(maquina.contador_de_programa, 0x0000);
+
+        // El error en la última micro-operación deshace las anteriores
+        let isa = DescripcionIsa::desde_texto("registros A B C\nflags Z=6\ninstr PON r : 000000rr : alu inc, A, A; almacenar [0x10], A; cargar %r, A").unwrap();
+        let mut maquina = MaquinaIsa::new(isa);
+        maquina.cargar_programa(&[0x03]);
+        maquina.flags = 1 << 6;
+        assert!(maquina.step().is_err());
+        assert_eq!((maquina.get_registro("A"), maquina.flags, maquina.memoria.leer_memoria(0x10)), (Some(0), 1 << 6, 0));
+        maquina.isa.instrucciones[0].micro_ops = vec![MicroOp::Almacenar(Operando::Registro(0), Operando::Literal(1))];
+        assert_eq!(maquina.step().unwrap_err(), "'PON r': almacenar necesita un destino en memoria");
+    }
+}
+
+//*****************************************************************************
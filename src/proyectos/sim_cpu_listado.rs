@@ -0,0 +1,237 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Listados de ensamblador (.PRN) - Simulación CPU
+    Descripción:    Lee los listados .PRN del ASM de CP/M (dirección, bytes y texto fuente) y
+                    construye el mapa dirección -> línea de código fuente que usan las vistas
+                    (pancurses, GTK, monitor) y el adaptador DAP para mostrar la línea actual y
+                    las que la rodean junto al contador de programa.
+    Referencias:    CP/M 2.2 Assembler (ASM) User's Guide, Digital Research (1978)
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+//***************************************************************************** Notas
+/* Uso
+    cpu.cargar_listado("PROG.PRN")?;
+    if let Some(linea) = cpu.linea_fuente() { println!("{}: {}", linea.numero, linea.texto); }
+    for texto in cpu.contexto_fuente(3, 3) { println!("{}", texto); }      // '>' en la actual
+
+Formato de cada línea del .PRN (columnas del ASM de CP/M):
+     0100 3E04     	MVI	A,4         dirección, hasta 4 bytes en hexadecimal y fuente
+     0108          BUCLE:              dirección sin bytes (etiqueta sola, ORG, DS)
+     0005 =        BDOS	EQU	5       EQU: el valor no es una dirección de código
+                   ; comentario        sin dirección
+     0110 48454C4C                     continuación de un DB largo (sin texto)
+Una letra de error en la columna 0 ("E0100 ...") se ignora. Los bytes de las líneas de
+continuación se asignan a la última línea con texto, de modo que cualquier byte de un DB largo
+lleva a su línea. Si dos líneas tienen la misma dirección (etiqueta sola y la instrucción de la
+siguiente) gana la que tiene bytes.
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineaFuente {
+    pub numero: usize,                  // Línea del archivo, desde 1
+    pub direccion: Option<u16>,
+    pub bytes: Vec<u8>,
+    pub texto: String,
+}
+
+pub struct ListadoFuente {
+    pub ruta: String,
+    pub lineas: Vec<LineaFuente>,
+    codigo: BTreeMap<u16, (usize, u16)>,    // Dirección -> (índice de la línea, bytes)
+}
+
+//***************************************************************************** Lectura
+fn es_hex(texto: &str) -> bool {
+    !texto.is_empty() && texto.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn analiza_linea(numero: usize, linea: &str) -> LineaFuente {
+    let sin_codigo = LineaFuente { numero, direccion: None, bytes: Vec::new(), texto: linea.trim().to_string() };
+    // Letra de error del ASM en la columna 0, pegada a la dirección o separada por un espacio
+    let resto = match linea.trim_start().split_once(' ') {
+        Some((error, resto)) if error.len() == 1 && error.chars().all(|c| c.is_ascii_alphabetic()) => resto,
+        _ if linea.starts_with(|c: char| c.is_ascii_alphabetic()) && linea.get(1..5).is_some_and(es_hex) => &linea[1..],
+        _ => linea,
+    };
+    let resto = resto.trim_start();
+    let Some(direccion) = resto.get(..4).filter(|d| es_hex(d)).and_then(|d| u16::from_str_radix(d, 16).ok()) else { return sin_codigo };
+    let resto = &resto[4..];
+    if !resto.is_empty() && !resto.starts_with(char::is_whitespace) { return sin_codigo; }
+    if resto.trim_start().starts_with('=') { return sin_codigo; }                    // EQU
+    // Los bytes van justo detrás de la dirección, separados por un solo espacio
+    let (bytes, texto) = match resto.strip_prefix(' ').filter(|r| !r.starts_with(char::is_whitespace)) {
+        Some(campos) => {
+            let (hex, texto) = campos.split_once(char::is_whitespace).unwrap_or((campos, ""));
+            if es_hex(hex) && hex.len() % 2 == 0 {
+                let bytes = (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0)).collect();
+                (bytes, texto)
+            } else {
+                (Vec::new(), campos)
+            }
+        }
+        None => (Vec::new(), resto),
+    };
+    LineaFuente { numero, direccion: Some(direccion), bytes, texto: texto.trim().to_string() }
+}
+
+impl ListadoFuente {
+    pub fn analiza(ruta: &str, texto: &str) -> ListadoFuente {
+        let lineas: Vec<LineaFuente> = texto.lines().enumerate().map(|(i, l)| analiza_linea(i + 1, l)).collect();
+        let mut codigo: BTreeMap<u16, (usize, u16)> = BTreeMap::new();
+        let mut ultima_con_texto: Option<usize> = None;
+        for (i, linea) in lineas.iter().enumerate() {
+            let Some(direccion) = linea.direccion else { continue };
+            let indice = match ultima_con_texto {
+                Some(anterior) if linea.texto.is_empty() && !linea.bytes.is_empty() => anterior,
+                _ => i,
+            };
+            if !linea.texto.is_empty() { ultima_con_texto = Some(i); }
+            let longitud = linea.bytes.len() as u16;
+            match codigo.get(&direccion) {
+                Some((_, anterior)) if *anterior > 0 || longitud == 0 => {}
+                _ => { codigo.insert(direccion, (indice, longitud)); }
+            }
+        }
+        ListadoFuente { ruta: ruta.to_string(), lineas, codigo }
+    }
+
+    pub fn carga_archivo(ruta: &str) -> Result<ListadoFuente, String> {
+        let texto = std::fs::read_to_string(ruta).map_err(|e| format!("No se puede leer '{}': {}", ruta, e))?;
+        Ok(ListadoFuente::analiza(ruta, &texto))
+    }
+
+    //************************************* Búsquedas
+    // Línea que contiene el byte de 'dir' (el primero o cualquiera de los siguientes)
+    pub fn linea_de(&self, dir: u16) -> Option<&LineaFuente> {
+        let (inicio, (indice, longitud)) = self.codigo.range(..=dir).next_back()?;
+        if (dir - inicio) < (*longitud).max(1) { self.lineas.get(*indice) } else { None }
+    }
+
+    pub fn linea(&self, dir: u16) -> Option<usize> {
+        self.linea_de(dir).map(|l| l.numero)
+    }
+
+    // Primera línea con dirección a partir de 'linea' (el editor ajusta el punto a esa línea)
+    pub fn direccion(&self, linea: usize) -> Option<(usize, u16)> {
+        self.lineas.iter().skip(linea.max(1) - 1).find_map(|l| l.direccion.map(|d| (l.numero, d)))
+    }
+
+    pub fn es_fuente(&self, ruta: &str) -> bool {
+        ruta == self.ruta || Path::new(ruta).file_name() == Path::new(&self.ruta).file_name()
+    }
+
+    // Líneas alrededor de la de 'dir', marcando la actual con '>'. Vacío si no está en el listado.
+    pub fn contexto(&self, dir: u16, antes: usize, despues: usize) -> Vec<String> {
+        let Some(actual) = self.linea_de(dir).map(|l| l.numero - 1) else { return Vec::new() };
+        let fin = (actual + despues + 1).min(self.lineas.len());
+        self.lineas[actual.saturating_sub(antes)..fin].iter().map(|l| {
+            format!("{}{:>5}  {}", if l.numero - 1 == actual { ">" } else { " " }, l.numero, l.texto)
+        }).collect()
+    }
+}
+
+//*****************************************************************************
+impl CPU {
+    // Devuelve el número de líneas con código
+    pub fn cargar_listado(&mut self, ruta: &str) -> Result<usize, String> {
+        let listado = ListadoFuente::carga_archivo(ruta)?;
+        let cantidad = listado.codigo.len();
+        self.listado = Some(listado);
+        Ok(cantidad)
+    }
+
+    pub fn linea_fuente(&self) -> Option<&LineaFuente> {
+        self.listado.as_ref().and_then(|l| l.linea_de(self.contador_de_programa))
+    }
+
+    pub fn contexto_fuente(&self, antes: usize, despues: usize) -> Vec<String> {
+        self.listado.as_ref().map(|l| l.contexto(self.contador_de_programa, antes, despues)).unwrap_or_default()
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proyectos::sim_cpu_ensamblador::ensambla;
+
+    const PRN: &str = "                ; Prueba
+ 0100                   ORG     100H
+ 0005 =         BDOS    EQU     5
+ 0100 3E04              MVI     A,4
+ 0102 CD0801            CALL    SUB
+ 0105 C30501    FIN:    JMP     FIN
+ 0108           SUB:
+ 0108 3C                INR     A
+ 0109 C9                RET
+ 010A 484F4C41  MSG:    DB      'HOLA$'
+ 010E 24
+E0110 00                NOP
+ 0111                   END
+";
+
+    #[test]
+    fn test_mapa_de_lineas() {
+        let listado = ListadoFuente::analiza("PRUEBA.PRN", PRN);
+        assert_eq!(listado.lineas.len(), 13);
+        assert_eq!(listado.lineas[2].direccion, None);
+        assert_eq!(listado.lineas[4], LineaFuente { numero: 5, direccion: Some(0x0102), bytes: vec![0xCD, 0x08, 0x01], texto: String::from("CALL    SUB") });
+        assert_eq!(listado.linea(0x0100), Some(4));
+        assert_eq!(listado.linea(0x0104), Some(5));
+        assert_eq!(listado.linea(0x0108), Some(8));
+        assert_eq!(listado.linea(0x010E), Some(10));
+        assert_eq!(listado.linea(0x0110), Some(12));
+        assert_eq!(listado.linea(0x0111), Some(13));
+        assert_eq!(listado.linea(0x0200), None);
+        assert_eq!(listado.direccion(3), Some((4, 0x0100)));
+        assert!(listado.es_fuente("/otro/directorio/PRUEBA.PRN"));
+    }
+
+    #[test]
+    fn test_contexto_en_la_cpu() {
+        let mut cpu = CPU::new();
+        assert!(cpu.contexto_fuente(1, 1).is_empty());
+        cpu.listado = Some(ListadoFuente::analiza("PRUEBA.PRN", PRN));
+        cpu.contador_de_programa = 0x0108;
+        assert_eq!(cpu.linea_fuente().map(|l| l.texto.as_str()), Some("INR     A"));
+        assert_eq!(cpu.contexto_fuente(1, 1), vec!["     7  SUB:", ">    8  INR     A", "     9  RET"]);
+        cpu.contador_de_programa = 0x0100;
+        assert_eq!(cpu.contexto_fuente(5, 0)[0], "     1  ; Prueba");
+        assert!(cpu.cargar_listado("no_existe.prn").is_err());
+    }
+
+    #[test]
+    fn test_listado_del_ensamblador() {
+        // El listado de sim_cpu_ensamblador se lee igual que un .PRN del ASM
+        let programa = ensambla("
+; Cuenta con SET
+CUENTA  SET 1
+        ORG 100H
+INICIO: MVI A,CUENTA
+CUENTA  SET CUENTA+1
+        MVI B,CUENTA
+TEXTO:  DB 'HOLA MUNDO'
+        END INICIO
+").unwrap();
+        let listado = ListadoFuente::analiza("prog.prn", &programa.listado());
+        let texto = |dir: u16| listado.linea_de(dir).map(|l| l.texto.as_str());
+        assert_eq!(texto(0x0100), Some("INICIO: MVI A,CUENTA"));
+        assert_eq!(texto(0x0102), Some("MVI B,CUENTA"));
+        assert_eq!(texto(0x0104), Some("TEXTO:  DB 'HOLA MUNDO'"));
+        assert_eq!(texto(0x010D), Some("TEXTO:  DB 'HOLA MUNDO'"));        // Continuación
+        assert_eq!(texto(0x010E), None);
+        // ORG, tres líneas de código y dos de continuación; los SET no tienen dirección
+        assert_eq!(listado.lineas.iter().filter(|l| l.direccion.is_some()).count(), 6);
+        assert_eq!(listado.lineas[1].texto, "; Cuenta con SET");
+    }
+}
+
+//*****************************************************************************
@@ -0,0 +1,305 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Grafo dinámico de llamadas - Simulación CPU
+    Descripción:    Registra durante la ejecución cada CALL/RST y RET, la profundidad de la
+                    pila de llamadas y los ciclos de cada subrutina (inclusivos, con lo que
+                    llama, y exclusivos, sólo su propio código). Se exporta como grafo DOT y
+                    como pilas plegadas (folded stacks) para generar un flame graph.
+    Referencias:    https://github.com/brendangregg/FlameGraph (formato de pilas plegadas)
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+use super::sim_cpu_monitor::{es_call, es_ret};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+//***************************************************************************** Notas
+/* Uso
+Como el perfilador, se guarda en CPU::grafo_llamadas como Option<Box<GrafoLlamadas>> y ejecuta_paso le
+pasa cada instrucción con el SP de antes y de después.
+
+    cpu.habilitar_grafo_llamadas();
+    cpu.nombra_rutina(0x0010, "SUB1");          // Opcional: sin nombre sale L0010
+    cpu.run_no_win(&mut aux);
+    let grafo = cpu.grafo_llamadas.as_ref().unwrap();
+    std::fs::write("llamadas.dot", grafo.dot()).unwrap();
+    std::fs::write("pilas.folded", grafo.pilas_plegadas()).unwrap();
+    // flamegraph.pl pilas.folded > llamadas.svg
+
+Una llamada cuenta cuando la instrucción es CALL/Ccc/RST y el SP baja 2 (la condicional se
+cumplió); un retorno cuando es RET/Rcc y el SP sube 2. La rutina raíz es la dirección de la primera
+instrucción registrada. Un RET sin llamada pendiente (RET usado como salto) se cuenta en
+'retornos_huerfanos' y no cambia la pila.
+
+Los ciclos del CALL se cargan al llamador y los del RET a la subrutina. En las llamadas recursivas
+los ciclos inclusivos sólo se suman en el marco más externo para no contarlos dos veces.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TipoEvento {
+    Llamada,
+    Retorno,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventoLlamada {
+    pub ciclo: u64,                     // Ciclos acumulados al terminar la instrucción
+    pub tipo: TipoEvento,
+    pub origen: u16,                    // Dirección del CALL/RST/RET
+    pub destino: u16,                   // Subrutina llamada o dirección de retorno
+    pub profundidad: usize,             // Profundidad después del evento
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EstadisticaRutina {
+    pub llamadas: u64,
+    pub ciclos_inclusivos: u64,
+    pub ciclos_exclusivos: u64,
+    pub profundidad_maxima: usize,
+}
+
+struct Marco {
+    rutina: u16,
+    ciclos_entrada: u64,                // Total de ciclos al entrar
+    ciclos_hijos: u64,                  // Ciclos inclusivos de las llamadas que ha hecho
+}
+
+pub struct GrafoLlamadas {
+    pub rutinas: BTreeMap<u16, EstadisticaRutina>,
+    pub aristas: BTreeMap<(u16, u16), u64>,         // (llamador, llamada) -> número de llamadas
+    pub eventos: VecDeque<EventoLlamada>,
+    pub limite_eventos: usize,                      // Se descartan los más antiguos
+    pub nombres: HashMap<u16, String>,
+    pub profundidad_maxima: usize,
+    pub retornos_huerfanos: u64,
+    pub total_ciclos: u64,
+    pila: Vec<Marco>,
+    plegadas: BTreeMap<Vec<u16>, u64>,              // Ciclos exclusivos por pila de llamadas
+}
+
+impl GrafoLlamadas {
+    pub fn new() -> GrafoLlamadas {
+        GrafoLlamadas {
+            rutinas: BTreeMap::new(),
+            aristas: BTreeMap::new(),
+            eventos: VecDeque::new(),
+            limite_eventos: 100_000,
+            nombres: HashMap::new(),
+            profundidad_maxima: 0,
+            retornos_huerfanos: 0,
+            total_ciclos: 0,
+            pila: Vec::new(),
+            plegadas: BTreeMap::new(),
+        }
+    }
+
+    pub fn nombre(&self, rutina: u16) -> String {
+        self.nombres.get(&rutina).cloned().unwrap_or(format!("L{:04X}", rutina))
+    }
+
+    // Profundidad actual (0: en la rutina raíz)
+    pub fn profundidad(&self) -> usize {
+        self.pila.len().saturating_sub(1)
+    }
+
+    pub fn registra(&mut self, direccion: u16, opcode: u8, ciclos: u64, pc_final: u16, sp_inicial: u16, sp_final: u16) {
+        if self.pila.is_empty() { self.entra(direccion); }
+        self.total_ciclos += ciclos;
+        let clave: Vec<u16> = self.pila.iter().map(|m| m.rutina).collect();
+        *self.plegadas.entry(clave).or_insert(0) += ciclos;
+
+        if es_call(opcode) && sp_final == sp_inicial.wrapping_sub(2) {
+            let llamador = self.pila.last().map(|m| m.rutina).unwrap_or(direccion);
+            *self.aristas.entry((llamador, pc_final)).or_insert(0) += 1;
+            self.entra(pc_final);
+            self.evento(TipoEvento::Llamada, direccion, pc_final);
+        } else if es_ret(opcode) && sp_final == sp_inicial.wrapping_add(2) {
+            if self.pila.len() > 1 {
+                self.sale();
+                self.evento(TipoEvento::Retorno, direccion, pc_final);
+            } else {
+                self.retornos_huerfanos += 1;
+            }
+        }
+    }
+
+    fn entra(&mut self, rutina: u16) {
+        self.pila.push(Marco { rutina, ciclos_entrada: self.total_ciclos, ciclos_hijos: 0 });
+        let profundidad = self.profundidad();
+        self.profundidad_maxima = self.profundidad_maxima.max(profundidad);
+        let estadistica = self.rutinas.entry(rutina).or_default();
+        estadistica.llamadas += 1;
+        estadistica.profundidad_maxima = estadistica.profundidad_maxima.max(profundidad);
+    }
+
+    fn sale(&mut self) {
+        let Some(marco) = self.pila.pop() else { return };
+        let inclusivos = self.total_ciclos - marco.ciclos_entrada;
+        let recursiva = self.pila.iter().any(|m| m.rutina == marco.rutina);
+        let estadistica = self.rutinas.entry(marco.rutina).or_default();
+        estadistica.ciclos_exclusivos += inclusivos - marco.ciclos_hijos;
+        if !recursiva { estadistica.ciclos_inclusivos += inclusivos; }
+        if let Some(padre) = self.pila.last_mut() { padre.ciclos_hijos += inclusivos; }
+    }
+
+    fn evento(&mut self, tipo: TipoEvento, origen: u16, destino: u16) {
+        if self.limite_eventos == 0 { return; }
+        if self.eventos.len() == self.limite_eventos { self.eventos.pop_front(); }
+        self.eventos.push_back(EventoLlamada { ciclo: self.total_ciclos, tipo, origen, destino, profundidad: self.profundidad() });
+    }
+
+    // Estadísticas incluyendo los marcos todavía abiertos (como si volvieran ahora)
+    pub fn estadisticas(&self) -> BTreeMap<u16, EstadisticaRutina> {
+        let mut rutinas = self.rutinas.clone();
+        // Un marco abierto todavía no ha sumado en su padre los ciclos del hijo abierto
+        let mut hijo_abierto = 0;
+        for (i, marco) in self.pila.iter().enumerate().rev() {
+            let inclusivos = self.total_ciclos - marco.ciclos_entrada;
+            let estadistica = rutinas.entry(marco.rutina).or_default();
+            estadistica.ciclos_exclusivos += inclusivos - marco.ciclos_hijos - hijo_abierto;
+            if !self.pila[..i].iter().any(|m| m.rutina == marco.rutina) { estadistica.ciclos_inclusivos += inclusivos; }
+            hijo_abierto = inclusivos;
+        }
+        rutinas
+    }
+
+    // Rutinas ordenadas por ciclos inclusivos (de mayor a menor)
+    pub fn informe(&self) -> String {
+        let estadisticas = self.estadisticas();
+        let mut lista: Vec<(&u16, &EstadisticaRutina)> = estadisticas.iter().collect();
+        lista.sort_by(|a, b| b.1.ciclos_inclusivos.cmp(&a.1.ciclos_inclusivos).then(a.0.cmp(b.0)));
+        let mut salida = format!("\n{:*^80}\n", " Grafo de llamadas ");
+        salida.push_str(&format!("Ciclos: {}, Profundidad máxima: {}, Retornos sin llamada: {}\n"
+            , self.total_ciclos, self.profundidad_maxima, self.retornos_huerfanos));
+        salida.push_str("  Rutina       Llamadas   Inclusivos   Exclusivos  % excl.  Prof.\n");
+        for (rutina, e) in lista {
+            let porcentaje = if self.total_ciclos == 0 { 0.0 } else { e.ciclos_exclusivos as f64 * 100.0 / self.total_ciclos as f64 };
+            salida.push_str(&format!("  {:<12} {:>8} {:>12} {:>12} {:>7.2}% {:>6}\n"
+                , self.nombre(*rutina), e.llamadas, e.ciclos_inclusivos, e.ciclos_exclusivos, porcentaje, e.profundidad_maxima));
+        }
+        salida
+    }
+
+    // Grafo DOT: un nodo por rutina con sus ciclos y una arista por pareja llamador -> llamada
+    pub fn dot(&self) -> String {
+        let mut salida = String::from("digraph llamadas {\n    node [shape=box, fontname=\"Courier\"];\n");
+        for (rutina, e) in self.estadisticas() {
+            salida.push_str(&format!("    \"{:04X}\" [label=\"{}\\nllamadas: {}\\ninclusivos: {}\\nexclusivos: {}\"];\n"
+                , rutina, self.nombre(rutina).replace('"', "\\\""), e.llamadas, e.ciclos_inclusivos, e.ciclos_exclusivos));
+        }
+        for ((llamador, llamada), veces) in &self.aristas {
+            salida.push_str(&format!("    \"{:04X}\" -> \"{:04X}\" [label=\"{}\"];\n", llamador, llamada, veces));
+        }
+        salida.push_str("}\n");
+        salida
+    }
+
+    // Una línea por pila distinta: "raiz;rutina;subrutina ciclos" (ciclos exclusivos de la última)
+    pub fn pilas_plegadas(&self) -> String {
+        let mut salida = String::new();
+        for (pila, ciclos) in &self.plegadas {
+            if *ciclos == 0 { continue; }
+            let nombres: Vec<String> = pila.iter().map(|r| self.nombre(*r).replace([';', ' '], "_")).collect();
+            salida.push_str(&format!("{} {}\n", nombres.join(";"), ciclos));
+        }
+        salida
+    }
+}
+
+impl Default for GrafoLlamadas {
+    fn default() -> Self { GrafoLlamadas::new() }
+}
+
+//*****************************************************************************
+impl CPU {
+    pub fn habilitar_grafo_llamadas(&mut self) {
+        self.grafo_llamadas = Some(Box::new(GrafoLlamadas::new()));
+    }
+
+    pub fn deshabilitar_grafo_llamadas(&mut self) -> Option<Box<GrafoLlamadas>> {
+        self.grafo_llamadas.take()
+    }
+
+    pub fn nombra_rutina(&mut self, direccion: u16, nombre: &str) {
+        if let Some(grafo) = self.grafo_llamadas.as_mut() { grafo.nombres.insert(direccion, nombre.to_string()); }
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proyectos::sim_cpu_pruebas::Aux;
+    use asm8080::asm8080;
+
+    fn ejecuta(programa: &Vec<u8>, pasos: usize) -> CPU {
+        let mut cpu = CPU::new();
+        let mut aux = Aux { imp_contador_programa: 0, imp_instruccion: 0, imp_mnemonico: String::new() };
+        cpu.cargar_programa(programa);
+        cpu.habilitar_grafo_llamadas();
+        cpu.nombra_rutina(0x0000, "inicio");
+        for _ in 0..pasos { cpu.step_no_win(&mut aux); }
+        cpu
+    }
+
+    #[test]
+    fn test_ciclos_inclusivos_y_exclusivos() {
+        let cpu = ejecuta(&asm8080! {
+                    lxi sp, 400h    // 0000  10
+                    call sub1       // 0003  17
+                    call sub2       // 0006  17
+                    hlt             // 0009
+            sub1:   call sub2       // 000A  17
+                    ret             // 000D  10
+            sub2:   nop             // 000E  4
+                    ret             // 000F  10
+        }, 9);              // El HLT no está implementado: se para antes
+        let grafo = cpu.grafo_llamadas.as_ref().unwrap();
+        assert_eq!(grafo.profundidad(), 0);
+        assert_eq!(grafo.profundidad_maxima, 2);
+        let sub1 = grafo.rutinas[&0x000A];
+        let sub2 = grafo.rutinas[&0x000E];
+        assert_eq!(sub2, EstadisticaRutina { llamadas: 2, ciclos_inclusivos: 28, ciclos_exclusivos: 28, profundidad_maxima: 2 });
+        assert_eq!(sub1, EstadisticaRutina { llamadas: 1, ciclos_inclusivos: 17 + 10 + 14, ciclos_exclusivos: 27, profundidad_maxima: 1 });
+        assert_eq!(grafo.aristas[&(0x0000, 0x000E)], 1);
+        assert_eq!(grafo.aristas[&(0x000A, 0x000E)], 1);
+        assert_eq!(grafo.eventos.len(), 6);
+        assert_eq!(grafo.eventos[1], EventoLlamada { ciclo: 10 + 17 + 17, tipo: TipoEvento::Llamada, origen: 0x000A, destino: 0x000E, profundidad: 2 });
+        assert_eq!(grafo.total_ciclos, cpu.ciclos);
+
+        let plegadas = grafo.pilas_plegadas();
+        assert_eq!(plegadas, "inicio 44\ninicio;L000A 27\ninicio;L000A;L000E 14\ninicio;L000E 14\n");
+        assert!(grafo.dot().contains("\"000A\" -> \"000E\" [label=\"1\"];"));
+        assert!(grafo.informe().contains("L000A               1           41           27"));
+    }
+
+    #[test]
+    fn test_recursion_y_retorno_huerfano() {
+        // Sin saltos condicionales en el simulador la recursión se alimenta a mano
+        let mut grafo = GrafoLlamadas::new();
+        grafo.registra(0x0000, 0xCD, 17, 0x0010, 0x0400, 0x03FE);   // CALL REC
+        grafo.registra(0x0010, 0x05, 5, 0x0011, 0x03FE, 0x03FE);    // DCR B
+        grafo.registra(0x0011, 0xCD, 17, 0x0010, 0x03FE, 0x03FC);   // CALL REC
+        grafo.registra(0x0010, 0x05, 5, 0x0011, 0x03FC, 0x03FC);    // DCR B
+        assert_eq!(grafo.profundidad(), 2);
+        // Con los marcos abiertos: la rutina raíz sólo ha ejecutado el primer CALL
+        let abiertas = grafo.estadisticas();
+        assert_eq!((abiertas[&0x0010].ciclos_inclusivos, abiertas[&0x0010].ciclos_exclusivos), (27, 27));
+        assert_eq!((abiertas[&0x0000].ciclos_inclusivos, abiertas[&0x0000].ciclos_exclusivos), (44, 17));
+
+        grafo.registra(0x0014, 0xC9, 10, 0x0014, 0x03FC, 0x03FE);   // RET
+        grafo.registra(0x0014, 0xC9, 10, 0x0003, 0x03FE, 0x0400);   // RET
+        grafo.registra(0x0003, 0xC9, 10, 0x1234, 0x0400, 0x0402);   // RET usado como salto
+        let rec = grafo.rutinas[&0x0010];
+        // Inclusivos sólo del marco externo: coinciden con el total de la rutina
+        assert_eq!(rec, EstadisticaRutina { llamadas: 2, ciclos_inclusivos: 47, ciclos_exclusivos: 47, profundidad_maxima: 2 });
+        assert_eq!(grafo.retornos_huerfanos, 1);
+        assert_eq!(grafo.profundidad(), 0);
+        assert_eq!(grafo.pilas_plegadas(), "L0000 27\nL0000;L0010 32\nL0000;L0010;L0010 15\n");
+    }
+}
+
+//*****************************************************************************
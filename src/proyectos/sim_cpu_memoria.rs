@@ -10,6 +10,9 @@
 #![allow(unused_variables)]
 #![allow(unused_assignments)]
 
+use super::sim_cpu_cobertura::{Cobertura, ACCESO_ESCRITURA, ACCESO_LECTURA};
+use super::sim_cpu_vigilancia::AccesoMemoria;
+
 /* Little-endian - Big-endian               
 u16::from_le_bytes y u16::from_be_bytes son dos métodos en el tipo u16 de Rust que te permiten
 convertir un arreglo de bytes en un valor u16. La diferencia entre ellos está en el orden de
@@ -39,6 +42,9 @@ pub struct BancosMemoria {
     pub segmento_memoria: Vec<Vec<u8>>,
     pub banco_actual: u8,
     pub endianess: Endianess,
+    pub diario_escrituras: Option<Vec<(u8, u16, u8)>>,  // (banco, dirección, valor anterior)
+    pub cobertura: Option<Cobertura>,                   // Mapa de accesos por byte
+    pub diario_accesos: Option<Vec<AccesoMemoria>>,     // Accesos de la instrucción en curso (vigilancia)
 }
 
 pub enum Endianess {
@@ -62,6 +68,9 @@ impl BancosMemoria {
             segmento_memoria: vec![vec![0; 16384]; 1],
             banco_actual: 0,
             endianess: Endianess::LittleEndian,
+            diario_escrituras: None,
+            cobertura: None,
+            diario_accesos: None,
         }
     }
 
@@ -88,21 +97,50 @@ impl BancosMemoria {
 
     pub fn set_banco_activo (&mut self, num_de_banco: u8) { self.banco_actual = num_de_banco; }
 
+    pub fn longitud_banco(&self) -> usize { self.segmento_memoria[self.banco_actual as usize].len() }
+
+    // Lectura para los depuradores (expresiones, monitor, GDB): fuera del banco es un error
+    pub fn leer_memoria_comprobada(&self, direccion: u16) -> Result<u8, String> {
+        if usize::from(direccion) >= self.longitud_banco() {
+            return Err(format!("Dirección 0x{:04X} fuera del banco {} ({} bytes)", direccion, self.banco_actual, self.longitud_banco()));
+        }
+        Ok(self.leer_memoria(direccion))
+    }
+
     pub fn escribir_memoria(&mut self, direccion: u16, val: u8) {
         let val = match self.endianess {
             Endianess::LittleEndian => val,
             Endianess::BigEndian => val.swap_bytes(),
         };
-        if usize::from(direccion) > self.segmento_memoria[self.banco_actual as usize].len() {
-            println!("Intento de almacenar fuera del rango del segmento de memoria")
+        // Los avisos van a stderr: stdout puede ser el canal de un protocolo (servidor DAP)
+        if usize::from(direccion) >= self.segmento_memoria[self.banco_actual as usize].len() {
+            eprintln!("Intento de almacenar fuera del rango del segmento de memoria")
         } else {
+            // Si el diario está activo se guarda el valor anterior para poder deshacer la escritura
+            if let Some(diario) = self.diario_escrituras.as_mut() {
+                let anterior = self.segmento_memoria[self.banco_actual as usize][direccion as usize];
+                diario.push((self.banco_actual, direccion, anterior));
+            }
+            let anterior = self.segmento_memoria[self.banco_actual as usize][direccion as usize];
             self.segmento_memoria[self.banco_actual as usize][direccion as usize] = val;
+            self.marca_acceso(direccion, ACCESO_ESCRITURA);
+            if let Some(accesos) = self.diario_accesos.as_mut() {
+                accesos.push(AccesoMemoria { banco: self.banco_actual, direccion, escritura: true, anterior, valor: val });
+            }
         }
     }
 
+    // Escritura hecha por un depurador (monitor, GDB, DAP): no cuenta como acceso del programa
+    // en la cobertura
+    pub fn escribir_memoria_depurador(&mut self, direccion: u16, val: u8) {
+        let cobertura = self.cobertura.take();
+        self.escribir_memoria(direccion, val);
+        self.cobertura = cobertura;
+    }
+
     pub fn leer_memoria(&self, direccion: u16) -> u8 {
-        let val = if usize::from(direccion) > self.segmento_memoria[self.banco_actual as usize].len() {
-            println!("Intento de leer fuera del rango del segmento de memoria");
+        let val = if usize::from(direccion) >= self.segmento_memoria[self.banco_actual as usize].len() {
+            eprintln!("Intento de leer fuera del rango del segmento de memoria");
             return 0;
         } else {
             self.segmento_memoria[self.banco_actual as usize][direccion as usize]
@@ -113,6 +151,16 @@ impl BancosMemoria {
         }
     }
 
+    // Lectura de datos hecha por una instrucción (LDA, LDAX...): queda anotada en la cobertura
+    pub fn leer_dato(&mut self, direccion: u16) -> u8 {
+        self.marca_acceso(direccion, ACCESO_LECTURA);
+        let valor = self.leer_memoria(direccion);
+        if let Some(accesos) = self.diario_accesos.as_mut() {
+            accesos.push(AccesoMemoria { banco: self.banco_actual, direccion, escritura: false, anterior: valor, valor });
+        }
+        valor
+    }
+
 }
 
 //*****************************************************************************  Test
@@ -0,0 +1,632 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Monitor / depurador de línea de comandos - Simulación CPU
+    Descripción:    Bucle interactivo (REPL) para terminal sin pancurses: ejecución paso a
+                    paso, continuar, saltar CALL, salir de subrutina, registros, memoria,
+                    desensamblado, puntos de ruptura y vigilancia, bancos e historial de
+                    comandos. Lee de cualquier BufRead, de modo que se puede usar por ssh o
+                    con los comandos canalizados por stdin en pruebas.
+    Referencias:
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+use super::sim_cpu_pruebas::Aux;
+use super::sim_cpu_opcode::LONGITUD_8080;
+use super::sim_cpu_desensamblador::desensambla_instruccion;
+use super::sim_cpu_expresion::Expresion;
+use super::sim_cpu_vigilancia::ModoVigilancia;
+use std::io::{BufRead, Write};
+use asm8080::asm8080;
+
+//***************************************************************************** Notas
+/* Uso
+    echo "b 0x0004 if B >= 3\nc\nr\nq" | cargo run    (con monitor_cmd() en main)
+
+Las direcciones y los valores se escriben como expresiones de sim_cpu_expresion ('d HL 8',
+'set A=[0x10]+1'). Con una tabla de símbolos ('sym PROG.SYM') las etiquetas valen como direcciones
+('b BUCLE+2', 'u SUB') y las direcciones se muestran como '0106 <BUCLE+2>'. Con un listado ('src PROG.PRN') 'r' añade la
+línea de fuente del PC y 'src' la muestra con su contexto. Una línea vacía repite el último comando, '!!' también, y '!n' repite el
+comando número n de 'hist'. El fin de la entrada equivale a 'q'.
+*/
+
+const AYUDA: &str = "\
+Comandos:
+  s|step [n]              Ejecuta n instrucciones (1 por defecto)
+  c|cont                  Continúa hasta un punto de ruptura, vigilancia o fin (0xFF)
+  n|next                  Ejecuta la instrucción saltando CALL y RST
+  o|out                   Continúa hasta salir de la subrutina actual
+  r|regs                  Muestra los registros y la instrucción siguiente
+  set <reg>=<valor>       Cambia un registro o flag (A..L, BC, DE, HL, SP, PC, Z, S, P, CY, AC)
+  d|dump [dir] [n]        Vuelca n bytes de memoria (128 por defecto)
+  f|fill <ini> <fin> <v>  Rellena un rango de memoria con un valor
+  e|edit <dir> <b1> ...   Escribe bytes a partir de una dirección
+  load <archivo> [dir]    Carga un archivo binario en memoria (en 0 por defecto)
+  u|dis [dir] [n]         Desensambla n instrucciones (8 por defecto, desde PC)
+  b|break <dir> [if <e>]  Punto de ruptura, con condición opcional
+  w|watch <ini> [fin] [r|w|rw]  Punto de vigilancia de memoria (escritura por defecto)
+  bl                      Lista puntos de ruptura y vigilancia
+  bd <id> | wd <id>       Elimina un punto de ruptura | de vigilancia
+  bank [n | crear <tam>]  Muestra, cambia o crea bancos de memoria
+  sym [archivo]           Carga una tabla de símbolos (.SYM / .MAP) o lista la actual
+  src [archivo]           Carga un listado .PRN o muestra las líneas de fuente alrededor del PC
+  hist                    Historial de comandos (!n repite, !! o línea vacía repite el último)
+  q|quit                  Sale del monitor";
+
+// Límite de instrucciones de 'c', 'n' y 'o' para no quedar atrapados en un bucle infinito
+const MAX_INSTRUCCIONES: usize = 1_000_000;
+
+pub struct Monitor {
+    pub cpu: CPU,
+    aux: Aux,
+    pub historial: Vec<String>,
+    pub max_instrucciones: usize,
+}
+
+pub fn es_call(opcode: u8) -> bool {
+    // CALL, CALL condicionales, CALL no documentados y RST
+    opcode == 0xCD || opcode & 0xC7 == 0xC4 || matches!(opcode, 0xDD | 0xED | 0xFD) || opcode & 0xC7 == 0xC7
+}
+
+pub fn es_ret(opcode: u8) -> bool {
+    matches!(opcode, 0xC9 | 0xD9) || opcode & 0xC7 == 0xC0
+}
+
+// Saltos, llamadas y retornos pueden dejar el PC donde estaba (JMP $). Si el PC no avanza con
+// cualquier otra instrucción es que su opcode no está implementado.
+pub fn es_salto(opcode: u8) -> bool {
+    opcode == 0xC3 || opcode & 0xC7 == 0xC2 || es_call(opcode) || es_ret(opcode)
+}
+
+fn valor(texto: &str, cpu: &CPU) -> Result<i64, String> {
+    Expresion::analiza_con_simbolos(texto, cpu.simbolos.as_ref())?.evalua(cpu)
+}
+
+// 0x0106, seguida de <BUCLE+2> si hay un símbolo cercano
+fn texto_direccion(cpu: &CPU, dir: u16) -> String {
+    match cpu.nombre_direccion(dir) {
+        Some(nombre) => format!("0x{:04X} <{}>", dir, nombre),
+        None => format!("0x{:04X}", dir),
+    }
+}
+
+fn direccion(texto: &str, cpu: &CPU) -> Result<u16, String> {
+    let valor = valor(texto, cpu)?;
+    u16::try_from(valor).map_err(|_| format!("Dirección fuera de rango: {}", valor))
+}
+
+fn byte(texto: &str, cpu: &CPU) -> Result<u8, String> {
+    let valor = valor(texto, cpu)?;
+    u8::try_from(valor).map_err(|_| format!("Byte fuera de rango: {}", valor))
+}
+
+// Desensambla la instrucción de 'dir'. Devuelve la línea de listado y la longitud.
+pub fn desensambla(cpu: &CPU, dir: u16) -> (String, u16) {
+    let instruccion = desensambla_instruccion(&cpu.memoria, dir, &cpu.opciones_desensamblado());
+    (instruccion.linea(), instruccion.longitud().max(1))
+}
+
+//*****************************************************************************
+impl Monitor {
+    pub fn new(cpu: CPU) -> Monitor {
+        Monitor {
+            cpu,
+            aux: Aux { imp_contador_programa: 0, imp_instruccion: 0, imp_mnemonico: String::new() },
+            historial: Vec::new(),
+            max_instrucciones: MAX_INSTRUCCIONES,
+        }
+    }
+
+    // Bucle principal: lee comandos hasta 'q' o el fin de la entrada
+    pub fn ejecuta<R: BufRead, W: Write>(&mut self, entrada: R, salida: &mut W) -> Result<(), String> {
+        let mut lineas = entrada.lines();
+        loop {
+            write!(salida, "8080> ").and_then(|_| salida.flush()).map_err(|e| e.to_string())?;
+            let linea = match lineas.next() {
+                Some(linea) => linea.map_err(|e| e.to_string())?,
+                None => { writeln!(salida).map_err(|e| e.to_string())?; return Ok(()); }
+            };
+            let linea = match self.resuelve_historial(linea.trim()) {
+                Ok(linea) => linea,
+                Err(error) => { writeln!(salida, "Error: {}", error).map_err(|e| e.to_string())?; continue; }
+            };
+            if linea.is_empty() { continue; }
+            if matches!(linea.as_str(), "q" | "quit" | "exit") { return Ok(()); }
+            self.historial.push(linea.clone());
+            let texto = match self.comando(&linea) {
+                Ok(texto) => texto,
+                Err(error) => format!("Error: {}", error),
+            };
+            if !texto.is_empty() { writeln!(salida, "{}", texto).map_err(|e| e.to_string())?; }
+        }
+    }
+
+    fn resuelve_historial(&self, linea: &str) -> Result<String, String> {
+        if linea.is_empty() || linea == "!!" {
+            return Ok(self.historial.last().cloned().unwrap_or_default());
+        }
+        match linea.strip_prefix('!') {
+            Some(numero) => {
+                let indice: usize = numero.parse().map_err(|_| format!("Referencia al historial no válida '{}'", linea))?;
+                self.historial.get(indice.wrapping_sub(1)).cloned().ok_or(format!("No existe el comando {} en el historial", indice))
+            }
+            None => Ok(linea.to_string()),
+        }
+    }
+
+    // Ejecuta un comando y devuelve el texto a mostrar
+    pub fn comando(&mut self, linea: &str) -> Result<String, String> {
+        let partes: Vec<&str> = linea.split_whitespace().collect();
+        let argumentos = &partes[1..];
+        match partes[0] {
+            "h" | "help" | "?" => Ok(AYUDA.to_string()),
+            "s" | "step" => {
+                let n = match argumentos.first() { Some(n) => valor(n, &self.cpu)?.max(1) as usize, None => 1 };
+                let mut salida = Vec::new();
+                for _ in 0..n {
+                    let pc = self.cpu.contador_de_programa;
+                    let texto = match self.comprueba_instruccion() {
+                        Ok(texto) => texto,
+                        Err(error) => { salida.push(format!("Error: {}", error)); break; }
+                    };
+                    self.cpu.step_no_win(&mut self.aux);
+                    salida.push(format!("{}  {}", self.cpu.formatea_direccion(pc), texto));
+                    if self.cpu.contador_de_programa == pc && !es_salto(self.aux.imp_instruccion) {
+                        salida.push(format!("La ejecución no avanza (opcode 0x{:02X} no implementado)", self.aux.imp_instruccion));
+                        break;
+                    }
+                }
+                salida.push(self.registros());
+                Ok(salida.join("\n"))
+            }
+            "c" | "cont" | "continue" => Ok(self.corre(|_, _, _| false)),
+            "n" | "next" => {
+                let pc = self.cpu.contador_de_programa;
+                let opcode = self.cpu.memoria.leer_memoria_comprobada(pc)?;
+                if !es_call(opcode) { return self.comando("s"); }
+                let retorno = pc.wrapping_add(LONGITUD_8080[opcode as usize] as u16);
+                let sp = self.cpu.puntero_de_pila;
+                Ok(self.corre(move |cpu, _, _| cpu.contador_de_programa == retorno && cpu.puntero_de_pila == sp))
+            }
+            "o" | "out" | "finish" => {
+                let sp = self.cpu.puntero_de_pila;
+                Ok(self.corre(move |cpu, opcode, _| es_ret(opcode) && cpu.puntero_de_pila > sp))
+            }
+            "r" | "regs" => Ok(self.registros()),
+            "set" => {
+                let texto = argumentos.join("");
+                let (registro, expresion) = texto.split_once('=').ok_or("Uso: set <reg>=<valor>")?;
+                let valor = valor(expresion, &self.cpu)?;
+                self.asigna_registro(registro, valor)?;
+                Ok(self.registros())
+            }
+            "d" | "dump" => {
+                let inicio = match argumentos.first() { Some(d) => direccion(d, &self.cpu)?, None => self.cpu.contador_de_programa };
+                let n = match argumentos.get(1) { Some(n) => valor(n, &self.cpu)?.max(1) as u32, None => 128 };
+                self.volcado(inicio, n)
+            }
+            "f" | "fill" => {
+                if argumentos.len() != 3 { return Err(String::from("Uso: fill <ini> <fin> <valor>")); }
+                let inicio = direccion(argumentos[0], &self.cpu)?;
+                let fin = direccion(argumentos[1], &self.cpu)?;
+                let valor = byte(argumentos[2], &self.cpu)?;
+                if fin < inicio { return Err(String::from("El final del rango es menor que el inicio")); }
+                self.comprueba_rango(fin as u32)?;
+                for dir in inicio..=fin { self.cpu.memoria.escribir_memoria_depurador(dir, valor); }
+                Ok(format!("{} bytes escritos", fin as u32 - inicio as u32 + 1))
+            }
+            "e" | "edit" => {
+                if argumentos.len() < 2 { return Err(String::from("Uso: edit <dir> <b1> [b2 ...]")); }
+                let inicio = direccion(argumentos[0], &self.cpu)?;
+                let bytes = argumentos[1..].iter().map(|b| byte(b, &self.cpu)).collect::<Result<Vec<u8>, String>>()?;
+                self.comprueba_rango(inicio as u32 + bytes.len() as u32 - 1)?;
+                for (i, b) in bytes.iter().enumerate() { self.cpu.memoria.escribir_memoria_depurador(inicio.wrapping_add(i as u16), *b); }
+                self.volcado(inicio, bytes.len() as u32)
+            }
+            "load" => {
+                let archivo = argumentos.first().ok_or("Uso: load <archivo> [dir]")?;
+                let inicio = match argumentos.get(1) { Some(d) => direccion(d, &self.cpu)?, None => 0 };
+                let datos = std::fs::read(archivo).map_err(|e| format!("No se puede leer '{}': {}", archivo, e))?;
+                if datos.is_empty() { return Err(format!("El archivo '{}' está vacío", archivo)); }
+                self.comprueba_rango(inicio as u32 + datos.len() as u32 - 1)?;
+                for (i, b) in datos.iter().enumerate() { self.cpu.memoria.escribir_memoria_depurador(inicio + i as u16, *b); }
+                Ok(format!("{} bytes cargados en 0x{:04X}", datos.len(), inicio))
+            }
+            "u" | "dis" => {
+                let mut dir = match argumentos.first() { Some(d) => direccion(d, &self.cpu)?, None => self.cpu.contador_de_programa };
+                let n = match argumentos.get(1) { Some(n) => valor(n, &self.cpu)?.max(1) as usize, None => 8 };
+                self.cpu.memoria.leer_memoria_comprobada(dir)?;
+                let mut salida = Vec::new();
+                for _ in 0..n {
+                    // Las instrucciones cortadas por el final del banco salen como DB
+                    if dir as usize >= self.cpu.memoria.longitud_banco() { break; }
+                    if let Some(etiqueta) = self.cpu.simbolos.as_ref().and_then(|s| s.etiqueta(dir)) { salida.push(format!("{}:", etiqueta)); }
+                    let (texto, longitud) = desensambla(&self.cpu, dir);
+                    salida.push(texto);
+                    dir = match dir.checked_add(longitud) { Some(siguiente) => siguiente, None => break };
+                }
+                Ok(salida.join("\n"))
+            }
+            "b" | "break" => {
+                let dir = direccion(argumentos.first().ok_or("Uso: break <dir> [if <expresión>]")?, &self.cpu)?;
+                let condicion = match argumentos.get(1) {
+                    Some(&"if") => Some(argumentos[2..].join(" ")),
+                    Some(otro) => return Err(format!("Se esperaba 'if' y hay '{}'", otro)),
+                    None => None,
+                };
+                let id = self.cpu.agrega_punto_ruptura(dir, condicion.as_deref())?;
+                Ok(format!("Punto de ruptura #{} en {}", id, texto_direccion(&self.cpu, dir)))
+            }
+            "w" | "watch" => {
+                let inicio = direccion(argumentos.first().ok_or("Uso: watch <ini> [fin] [r|w|rw]")?, &self.cpu)?;
+                let mut fin = inicio;
+                let mut modo = ModoVigilancia::Escritura;
+                for argumento in argumentos[1..].iter() {
+                    match *argumento {
+                        "r" => modo = ModoVigilancia::Lectura,
+                        "w" => modo = ModoVigilancia::Escritura,
+                        "rw" => modo = ModoVigilancia::Acceso,
+                        otro => fin = direccion(otro, &self.cpu)?,
+                    }
+                }
+                let id = self.cpu.agrega_vigilancia(inicio, fin, modo, None)?;
+                Ok(format!("Vigilancia #{} en {}-{} ({:?})", id, texto_direccion(&self.cpu, inicio), texto_direccion(&self.cpu, fin), modo))
+            }
+            "bl" => Ok(self.lista_puntos()),
+            "bd" => {
+                let id = valor(argumentos.first().ok_or("Uso: bd <id>")?, &self.cpu)? as usize;
+                self.cpu.elimina_punto_ruptura(id)?;
+                Ok(format!("Punto de ruptura #{} eliminado", id))
+            }
+            "wd" => {
+                let id = valor(argumentos.first().ok_or("Uso: wd <id>")?, &self.cpu)? as usize;
+                self.cpu.elimina_vigilancia(id)?;
+                Ok(format!("Vigilancia #{} eliminada", id))
+            }
+            "bank" => {
+                match argumentos {
+                    [] => {}
+                    ["crear", tamano] => {
+                        let tamano = valor(tamano, &self.cpu)?;
+                        if !(1..=0x10000).contains(&tamano) { return Err(format!("Tamaño de banco no válido: {}", tamano)); }
+                        self.cpu.memoria.crear_segmento(tamano as usize);
+                    }
+                    [numero] => {
+                        let numero = valor(numero, &self.cpu)?;
+                        if numero < 0 || numero as usize >= self.cpu.memoria.segmento_memoria.len() {
+                            return Err(format!("No existe el banco {}", numero));
+                        }
+                        self.cpu.memoria.set_banco_activo(numero as u8);
+                    }
+                    _ => return Err(String::from("Uso: bank [n | crear <tam>]")),
+                }
+                Ok(format!("Banco activo {} de {}", self.cpu.memoria.get_banco_activo(), self.cpu.memoria.segmento_memoria.len()))
+            }
+            "sym" => {
+                if let Some(archivo) = argumentos.first() {
+                    let cantidad = self.cpu.cargar_simbolos(archivo)?;
+                    return Ok(format!("{} símbolos cargados de '{}'", cantidad, archivo));
+                }
+                Ok(self.cpu.simbolos.as_ref().map(|s| s.tabla()).unwrap_or(String::from("No hay tabla de símbolos")))
+            }
+            "src" => {
+                if let Some(archivo) = argumentos.first() {
+                    let cantidad = self.cpu.cargar_listado(archivo)?;
+                    return Ok(format!("{} líneas de código cargadas de '{}'", cantidad, archivo));
+                }
+                if self.cpu.listado.is_none() { return Err(String::from("No hay listado: src <archivo.prn>")); }
+                let contexto = self.cpu.contexto_fuente(5, 5);
+                if contexto.is_empty() { return Ok(format!("PC={:04X} no está en el listado", self.cpu.contador_de_programa)); }
+                Ok(contexto.join("\n"))
+            }
+            "hist" => Ok(self.historial.iter().enumerate().map(|(i, c)| format!("{:4}  {}", i + 1, c)).collect::<Vec<_>>().join("\n")),
+            otro => Err(format!("Comando desconocido '{}' (h para ayuda)", otro)),
+        }
+    }
+
+    // Ejecuta hasta que 'fin' devuelve true o hasta un punto de ruptura, vigilancia o 0xFF.
+    // 'fin' recibe la CPU tras la instrucción, el opcode ejecutado y el PC donde empezó.
+    fn corre<F: Fn(&CPU, u8, u16) -> bool>(&mut self, fin: F) -> String {
+        let mut motivo = format!("Límite de {} instrucciones alcanzado", self.max_instrucciones);
+        // El monitor está detenido en el PC: la primera instrucción se ejecuta sin comprobar sus puntos
+        self.cpu.reanuda_en_ruptura();
+        let mut mensajes_ruptura = Vec::new();
+        for ejecutadas in 0..self.max_instrucciones {
+            if ejecutadas > 0 {
+                let ruptura = self.cpu.comprueba_puntos_ruptura();
+                mensajes_ruptura.append(&mut self.cpu.toma_mensajes_ruptura());
+                if let Some(id) = ruptura {
+                    mensajes_ruptura.push(format!("Punto de ruptura #{} en {}", id, texto_direccion(&self.cpu, self.cpu.contador_de_programa)));
+                    motivo = mensajes_ruptura.join("\n");
+                    break;
+                }
+            }
+            let pc = self.cpu.contador_de_programa;
+            if let Err(error) = self.comprueba_instruccion() {
+                mensajes_ruptura.push(format!("Error: {}", error));
+                motivo = mensajes_ruptura.join("\n");
+                break;
+            }
+            self.cpu.step_no_win(&mut self.aux);
+            let mut mensajes: Vec<String> = self.cpu.disparos_vigilancia().iter().map(|d| d.descripcion()).collect();
+            if !mensajes.is_empty() { motivo = mensajes.join("\n"); break; }
+            mensajes = std::mem::take(&mut mensajes_ruptura);
+            if fin(&self.cpu, self.aux.imp_instruccion, pc) { motivo = mensajes.join("\n"); break; }
+            if self.cpu.memoria.leer_memoria_comprobada(self.cpu.contador_de_programa) == Ok(0xFF) {
+                mensajes.push(format!("Fin de programa (0xFF) en {}", texto_direccion(&self.cpu, self.cpu.contador_de_programa)));
+                motivo = mensajes.join("\n");
+                break;
+            }
+            if self.cpu.contador_de_programa == pc && !es_call(self.aux.imp_instruccion) {
+                mensajes.push(if es_salto(self.aux.imp_instruccion) {
+                    format!("Bucle infinito: la instrucción de {} salta a sí misma", texto_direccion(&self.cpu, pc))
+                } else {
+                    format!("La ejecución no avanza (opcode 0x{:02X} no implementado)", self.aux.imp_instruccion)
+                });
+                motivo = mensajes.join("\n");
+                break;
+            }
+        }
+        if motivo.is_empty() { self.registros() } else { format!("{}\n{}", motivo, self.registros()) }
+    }
+
+    pub fn registros(&self) -> String {
+        let cpu = &self.cpu;
+        let fuente = cpu.linea_fuente().map(|l| format!("\n{:>5}  {}", l.numero, l.texto)).unwrap_or_default();
+        let flags = [(cpu.flags.sign, "S"), (cpu.flags.zero, "Z"), (cpu.flags.half_carry, "AC"), (cpu.flags.parity_overflow, "P"), (cpu.flags.carry, "CY")]
+            .iter().filter(|(activo, _)| *activo).map(|(_, nombre)| *nombre).collect::<Vec<_>>().join(" ");
+        format!("A={:02X} B={:02X} C={:02X} D={:02X} E={:02X} H={:02X} L={:02X} SP={:04X} PC={} F={:02X} [{}] ciclos={}\n{}{}"
+            , cpu.reg_a, cpu.reg_b, cpu.reg_c, cpu.reg_d, cpu.reg_e, cpu.reg_h, cpu.reg_l
+            , cpu.puntero_de_pila, cpu.formatea_direccion(cpu.contador_de_programa), cpu.flags.get_flags(), flags, cpu.ciclos
+            , desensambla(cpu, cpu.contador_de_programa).0, fuente)
+    }
+
+    fn asigna_registro(&mut self, registro: &str, valor: i64) -> Result<(), String> {
+        let cpu = &mut self.cpu;
+        let registro = registro.trim().to_uppercase();
+        let ocho_bits = matches!(registro.as_str(), "A" | "B" | "C" | "D" | "E" | "H" | "L");
+        let flag = matches!(registro.as_str(), "Z" | "S" | "P" | "CY" | "AC");
+        let maximo = if flag { 1 } else if ocho_bits { 0xFF } else { 0xFFFF };
+        if !(0..=maximo).contains(&valor) {
+            return Err(format!("Valor 0x{:X} fuera de rango para {}", valor, registro));
+        }
+        let [alto, bajo] = (valor as u16).to_be_bytes();
+        match registro.as_str() {
+            "A" => cpu.reg_a = bajo, "B" => cpu.reg_b = bajo, "C" => cpu.reg_c = bajo, "D" => cpu.reg_d = bajo,
+            "E" => cpu.reg_e = bajo, "H" => cpu.reg_h = bajo, "L" => cpu.reg_l = bajo,
+            "BC" => { cpu.reg_b = alto; cpu.reg_c = bajo; }
+            "DE" => { cpu.reg_d = alto; cpu.reg_e = bajo; }
+            "HL" => { cpu.reg_h = alto; cpu.reg_l = bajo; }
+            "SP" => cpu.puntero_de_pila = valor as u16,
+            "PC" => cpu.contador_de_programa = valor as u16,
+            "Z" => cpu.flags.zero = valor == 1,
+            "S" => cpu.flags.sign = valor == 1,
+            "P" => cpu.flags.parity_overflow = valor == 1,
+            "CY" => cpu.flags.carry = valor == 1,
+            "AC" => cpu.flags.half_carry = valor == 1,
+            otro => return Err(format!("Registro desconocido '{}'", otro)),
+        }
+        Ok(())
+    }
+
+    fn comprueba_rango(&self, fin: u32) -> Result<(), String> {
+        let longitud = self.cpu.memoria.segmento_memoria[self.cpu.memoria.banco_actual as usize].len();
+        if fin as usize >= longitud {
+            return Err(format!("La dirección 0x{:04X} está fuera del banco {} ({} bytes)", fin, self.cpu.memoria.banco_actual, longitud));
+        }
+        Ok(())
+    }
+
+    // La instrucción del PC debe caber entera en el banco antes de ejecutarla. Devuelve su texto.
+    fn comprueba_instruccion(&self) -> Result<String, String> {
+        let pc = self.cpu.contador_de_programa;
+        let opcode = self.cpu.memoria.leer_memoria_comprobada(pc)?;
+        self.comprueba_rango(pc as u32 + LONGITUD_8080[opcode as usize] as u32 - 1)?;
+        let opciones = self.cpu.opciones_desensamblado();
+        Ok(desensambla_instruccion(&self.cpu.memoria, pc, &opciones).texto())
+    }
+
+    // El volcado se corta al final del banco; empezar fuera de él es un error
+    fn volcado(&self, inicio: u16, n: u32) -> Result<String, String> {
+        self.cpu.memoria.leer_memoria_comprobada(inicio)?;
+        let mut salida = Vec::new();
+        let mut dir = inicio as u32;
+        let fin = (inicio as u32 + n).min(self.cpu.memoria.longitud_banco() as u32);
+        while dir < fin {
+            let bytes: Vec<u8> = (dir..(dir + 16).min(fin)).map(|d| self.cpu.memoria.leer_memoria(d as u16)).collect();
+            let hex = bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+            let ascii: String = bytes.iter().map(|&b| if (0x20..0x7F).contains(&b) { b as char } else { '.' }).collect();
+            let nombre = self.cpu.nombre_direccion(dir as u16).map(|n| format!(" <{}>", n)).unwrap_or_default();
+            salida.push(format!("{:04X}  {:<47}  |{}|{}", dir, hex, ascii, nombre));
+            dir += 16;
+        }
+        Ok(salida.join("\n"))
+    }
+
+    fn lista_puntos(&self) -> String {
+        let mut salida = Vec::new();
+        if let Some(puntos) = self.cpu.puntos_ruptura.as_ref() {
+            for p in puntos.puntos.iter() {
+                salida.push(format!("Ruptura #{} {}{} impactos={}", p.id, texto_direccion(&self.cpu, p.direccion)
+                    , p.texto_condicion.as_ref().map(|c| format!(" if {}", c)).unwrap_or_default(), p.impactos));
+            }
+        }
+        if let Some(vigilancia) = self.cpu.vigilancia.as_ref() {
+            for p in vigilancia.puntos.iter() {
+                salida.push(format!("Vigilancia #{} {}-{} {:?} disparos={}", p.id, texto_direccion(&self.cpu, p.inicio), texto_direccion(&self.cpu, p.fin), p.modo, p.disparos));
+            }
+        }
+        if salida.is_empty() { String::from("No hay puntos de ruptura ni de vigilancia") } else { salida.join("\n") }
+    }
+}
+
+//*****************************************************************************
+// Monitor sobre stdin/stdout con un programa de ejemplo con una subrutina
+pub fn monitor_cmd() {
+    let mut cpu = CPU::new();
+    cpu.cargar_programa(&asm8080! {
+                lxi sp, 400h
+                mvi a, 4
+                call suma
+                db 0ffh             // Fin de programa
+        suma:   inr a               // 0x0009
+                inr b
+                ret
+    });
+    let mut monitor = Monitor::new(cpu);
+    println!("Monitor 8080 (h para ayuda)");
+    let stdin = std::io::stdin();
+    if let Err(error) = monitor.ejecuta(stdin.lock(), &mut std::io::stdout()) {
+        println!("Error: {}", error);
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::sim_cpu_simbolos::TablaSimbolos;
+    use super::super::sim_cpu_listado::ListadoFuente;
+    use std::io::Cursor;
+
+    fn monitor() -> Monitor {
+        let mut cpu = CPU::new();
+        cpu.cargar_programa(&asm8080! {
+                    lxi sp, 400h
+                    mvi a, 4
+                    call sub1
+                    mvi b, 1
+                    db 0ffh, 0ffh       // Fin de programa
+            sub1:   inr a               // 0x000C
+                    call .sub2
+                    ret
+            .sub2:  inr a               // 0x0011
+                    ret
+        });
+        Monitor::new(cpu)
+    }
+
+    fn sesion(monitor: &mut Monitor, comandos: &str) -> String {
+        let mut salida = Vec::new();
+        monitor.ejecuta(Cursor::new(comandos.as_bytes()), &mut salida).unwrap();
+        String::from_utf8(salida).unwrap()
+    }
+
+    #[test]
+    fn test_step_next_y_out() {
+        let mut monitor = monitor();
+        let salida = sesion(&mut monitor, "s 2\nn\nr\nq\n");
+        // 'n' salta el CALL completo: A se incrementa dos veces
+        assert!(salida.contains("0000  LXI SP,0400H\n0003  MVI A,04H"));
+        assert_eq!(monitor.cpu.contador_de_programa, 0x0008);
+        assert_eq!(monitor.cpu.reg_a, 0x06);
+        assert_eq!(monitor.cpu.puntero_de_pila, 0x0400);
+        assert!(salida.contains("A=06 B=00"));
+        assert!(salida.contains("0008  06 01     MVI B,01H"));
+
+        // Dentro de la subrutina, 'o' vuelve justo detrás del CALL
+        let mut monitor = self::monitor();
+        sesion(&mut monitor, "s 4\no\n");
+        assert_eq!(monitor.cpu.contador_de_programa, 0x0008);
+        assert_eq!(monitor.cpu.reg_a, 0x06);
+    }
+
+    #[test]
+    fn test_ruptura_memoria_e_historial() {
+        let mut monitor = monitor();
+        let salida = sesion(&mut monitor, "b 0x0011 if A == 5\nc\nset HL=0x0100\ne HL 0x41 0x42\nd 0x100 2\nf 0x0200 0x0203 0xAA\nd 0x200 4\nhist\n!7\nbank\nbank 3\nxyz\n");
+        assert!(salida.contains("Punto de ruptura #1 en 0x0011"));
+        assert!(salida.contains("Punto de ruptura #1 en 0x0011\nA=05"));
+        assert_eq!(monitor.cpu.get_hl(), 0x0100);
+        assert!(salida.contains("0100  41 42"));
+        assert!(salida.contains("|AB|"));
+        assert!(salida.contains("0200  AA AA AA AA"));
+        assert!(salida.contains("   7  d 0x200 4"));
+        assert_eq!(salida.matches("0200  AA AA AA AA").count(), 2);
+        assert!(salida.contains("Banco activo 0 de 1"));
+        assert!(salida.contains("Error: No existe el banco 3"));
+        assert!(salida.contains("Error: Comando desconocido 'xyz'"));
+        // Continuar hasta el final
+        let salida = sesion(&mut monitor, "c\nu 0 3\n");
+        assert!(salida.contains("Fin de programa (0xFF) en 0x000A"));
+        assert!(salida.contains("0000  31 00 04  LXI SP,0400H\n0003  3E 04     MVI A,04H\n0005  CD 0C 00  CALL 000CH"));
+    }
+
+    #[test]
+    fn test_vigilancia_y_errores() {
+        let mut monitor = monitor();
+        let salida = sesion(&mut monitor, "w 0x03FE 0x03FF\nc\nbl\nset A=0x100\nset Q=1\nf 0 0x0500 0\n");
+        assert!(salida.contains("Vigilancia #1 en 0x03FE-0x03FF (Escritura)"));
+        assert!(salida.contains("por PC=0x0005 (CALL addr)"));
+        assert!(salida.contains("Vigilancia #1 0x03FE-0x03FF Escritura disparos=2"));
+        assert!(salida.contains("Error: Valor 0x100 fuera de rango para A"));
+        assert!(salida.contains("Error: Registro desconocido 'Q'"));
+        assert!(salida.contains("Error: La dirección 0x0500 está fuera del banco 0 (1024 bytes)"));
+    }
+
+    #[test]
+    fn test_final_del_banco_y_jmp_a_si_mismo() {
+        let mut monitor = monitor();
+        let salida = sesion(&mut monitor, "d 0x3F0 32\nd 0x400\nu 0x3FE 4\nset PC=0x3FE\ns 3\n");
+        assert!(salida.contains("03F0  00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00"));
+        assert!(!salida.contains("0400  00"));
+        assert!(salida.contains("Error: Dirección 0x0400 fuera del banco 0 (1024 bytes)"));
+        assert!(salida.contains("03FE  00        NOP\n03FF  00        NOP\n8080>"));
+        assert!(salida.contains("03FE  NOP\n03FF  NOP\nError: Dirección 0x0400 fuera del banco 0 (1024 bytes)"));
+        // 'c' también se para en el final del banco en lugar de buscar ahí la marca de fin
+        let mut monitor = self::monitor();
+        let salida = sesion(&mut monitor, "set PC=0x3FE\nc\n");
+        assert!(salida.contains("Error: Dirección 0x0400 fuera del banco 0 (1024 bytes)"));
+        assert_eq!(monitor.cpu.contador_de_programa, 0x0400);
+
+        let mut monitor = self::monitor();
+        let salida = sesion(&mut monitor, "e 0x100 0xC3 0x00 0x01\nset PC=0x100\ns\nc\n");
+        assert!(salida.contains("0100  JMP 0100H\nA="));
+        assert!(salida.contains("Bucle infinito: la instrucción de 0x0100 salta a sí misma"));
+        assert!(!salida.contains("no implementado"));
+    }
+
+    #[test]
+    fn test_etiquetas() {
+        let mut monitor = monitor();
+        let mut tabla = TablaSimbolos::new();
+        tabla.agrega_varios(vec![(String::from("INICIO"), 0x0000), (String::from("SUB1"), 0x000C), (String::from("SUB1.SUB2"), 0x0011)]);
+        monitor.cpu.simbolos = Some(tabla);
+        let salida = sesion(&mut monitor, "b sub1.sub2 if A == 5
+s 2
+c
+u SUB1 2
+d SUB1+1 2
+sym
+sym no_existe.sym
+");
+        assert!(salida.contains("Punto de ruptura #1 en 0x0011 <SUB1.SUB2>"));
+        assert!(salida.contains("0000 <INICIO>  LXI SP,0400H\n0003 <INICIO+3>  MVI A,04H"));
+        assert!(salida.contains("PC=0011 <SUB1.SUB2>"));
+        assert!(salida.contains("SUB1:\n000C  3C        INR A\n000D  CD 11 00  CALL SUB1.SUB2"));
+        assert!(salida.contains("000D  CD 11 ") && salida.contains("|..| <SUB1+1>"));
+        assert!(salida.contains("0000 INICIO\n000C SUB1\n0011 SUB1.SUB2"));
+        assert!(salida.contains("Error: No se puede leer 'no_existe.sym'"));
+    }
+
+    #[test]
+    fn test_listado_fuente() {
+        let mut monitor = monitor();
+        monitor.cpu.listado = Some(ListadoFuente::analiza("PRUEBA.PRN", " 0000 310004       lxi sp, 400h
+ 0003 3E04         mvi a, 4
+ 0005 CD0C00       call sub1
+ 0008 0601         mvi b, 1
+ 000A FFFF         db 0ffh, 0ffh
+ 000C 3C     sub1: inr a
+"));
+        let salida = sesion(&mut monitor, "s\nsrc\nn\nsrc no_existe.prn\n");
+        assert!(salida.contains("PC=0003 F=02 [] ciclos=10\n0003  3E 04     MVI A,04H\n    2  mvi a, 4"));
+        assert!(salida.contains("     1  lxi sp, 400h\n>    2  mvi a, 4\n     3  call sub1"));
+        assert!(salida.contains("Error: No se puede leer 'no_existe.prn'"));
+    }
+}
+
+//*****************************************************************************
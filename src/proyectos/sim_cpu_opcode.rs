@@ -19,6 +19,11 @@ fn imprime_titulo(titulo: &String) {
     println!("\n{:*^80}", titulo.blue());
 }
 
+//***************************************************************************** Tablas
+// Las tablas del 8080 están en sim_cpu_tablas para que el ensamblador pueda compartirlas con la
+// macro asm8080! (que no puede depender del resto del simulador)
+pub use super::sim_cpu_tablas::{CICLOS_8080, LONGITUD_8080};
+
 //***************************************************************************** 
 impl CPU {
     pub fn cargar_programa(&mut self, programa: &Vec<u8>) {
@@ -44,15 +49,56 @@ impl CPU {
         let opcode = instruccion;
         // Definición de la matriz 'operandos', que se compone de los dos bytes siguientes en la
         // memoria en las posiciones de memoria 'contador_de_programa +1' y 'contador_de_programa +2'.
-        let operandos = [
-            self.memoria.leer_memoria(self.contador_de_programa +1),
-            self.memoria.leer_memoria(self.contador_de_programa +2),
-        ];
+        // Solo se leen los que usa la instrucción (una de 1 byte puede estar al final del banco).
+        let longitud = LONGITUD_8080[opcode as usize] as u16;
+        let operando = |i: u16| if i < longitud { self.memoria.leer_memoria(self.contador_de_programa.wrapping_add(i)) } else { 0 };
+        let operandos = [operando(1), operando(2)];
         // Devuelve la tupla 'opcode' y 'operandos'.
         (opcode, operandos)
     }
 
+    // Ciclo completo de una instrucción (busca, decodifica y ejecuta) con los ganchos de
+    // depuración: historial para ejecución hacia atrás, perfilador y grafo de llamadas. Lo usan step y step_no_win.
+    pub fn ejecuta_paso(&mut self) -> (u8, [u8; 2]) {
+        let contador = self.contador_de_programa;
+        let ciclos_previos = self.ciclos;
+        let pila_previa = self.puntero_de_pila;
+        self.inicia_delta();
+        self.inicia_vigilancia();
+        let instruccion = self.busca_instruccion();
+        let (opcode, operandos) = self.decodifica_instruccion(instruccion);
+        self.marca_busqueda(contador, opcode);
+        self.ejecuta_instruccion(opcode, operandos);
+        self.registra_delta();
+        self.comprueba_vigilancia(contador);
+        if let Some(perfilador) = self.perfilador.as_mut() {
+            perfilador.registra(contador, opcode, self.ciclos - ciclos_previos);
+        }
+        if let Some(grafo) = self.grafo_llamadas.as_mut() {
+            grafo.registra(contador, opcode, self.ciclos - ciclos_previos, self.contador_de_programa, pila_previa, self.puntero_de_pila);
+        }
+        (opcode, operandos)
+    }
+
+    // Pila: el 8080 decrementa SP antes de guardar y guarda primero el byte alto
+    pub fn apila(&mut self, valor: u16) {
+        let [alto, bajo] = valor.to_be_bytes();
+        self.puntero_de_pila = self.puntero_de_pila.wrapping_sub(1);
+        self.memoria.escribir_memoria(self.puntero_de_pila, alto);
+        self.puntero_de_pila = self.puntero_de_pila.wrapping_sub(1);
+        self.memoria.escribir_memoria(self.puntero_de_pila, bajo);
+    }
+
+    pub fn desapila(&mut self) -> u16 {
+        let bajo = self.memoria.leer_dato(self.puntero_de_pila);
+        self.puntero_de_pila = self.puntero_de_pila.wrapping_add(1);
+        let alto = self.memoria.leer_dato(self.puntero_de_pila);
+        self.puntero_de_pila = self.puntero_de_pila.wrapping_add(1);
+        u16::from_le_bytes([bajo, alto])
+    }
+
     pub fn ejecuta_instruccion(&mut self, opcode: u8, operandos: [u8; 2]) {
+        self.ciclos += CICLOS_8080[opcode as usize] as u64;
         match opcode {
             0x00 => { // NOP: No hace nada
                 self.mnemonic = "NOP".to_string();
@@ -115,11 +161,17 @@ impl CPU {
 
                 let dire = self.get_bc().swap_bytes();
                 
-                self.reg_a = self.memoria.leer_memoria(direccion);
+                self.reg_a = self.memoria.leer_dato(direccion);
                 self.mnemonic = "LDAX B".to_string();
                 self.contador_de_programa += 1;
             }
 
+            0x31 => { // LXI SP,d16 carga el puntero de pila con los dos bytes siguientes
+                self.puntero_de_pila = u16::from_le_bytes([operandos[0], operandos[1]]);
+                self.mnemonic = "LXI SP,d16".to_string();
+                self.contador_de_programa += 3;
+            }
+
             0x32 => { // STA addr: carga el registro A en la dirección apuntada por HL
                 //self.reg_h = operandos[0];  // self.reg_h = self.memoria.leer_memoria(self.contador_de_programa + 1);
                 //self.reg_l = operandos[1];  // self.reg_l = self.memoria.leer_memoria(self.contador_de_programa + 2);
@@ -132,7 +184,7 @@ impl CPU {
         
             0x3A => { // LDA addr: carga el valor de la dirección apuntada por los dos siguientes bytes en el acumulador (A)
                 let direccion = u16::from_le_bytes([operandos[0], operandos[1]]);
-                self.reg_a = self.memoria.leer_memoria(direccion);
+                self.reg_a = self.memoria.leer_dato(direccion);
                 self.mnemonic = "LDA addr".to_string();
                 self.contador_de_programa += 3;
             },        
@@ -179,16 +231,103 @@ impl CPU {
                 self.mnemonic = "JMP nn".to_string();
             }
 
+            0xC9 => { // RET recupera de la pila la dirección de retorno
+                self.contador_de_programa = self.desapila();
+                self.mnemonic = "RET".to_string();
+            }
+
+            0xCD => { // CALL addr guarda en la pila la dirección de la instrucción siguiente y salta
+                self.apila(self.contador_de_programa.wrapping_add(3));
+                self.contador_de_programa = u16::from_le_bytes([operandos[0], operandos[1]]);
+                self.mnemonic = "CALL addr".to_string();
+            }
+
+            // 0xFF (RST 7) no se ejecuta: sigue siendo la marca de fin de programa de run_no_win
+            0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 => { // RST n: CALL a la dirección 8*n
+                self.apila(self.contador_de_programa.wrapping_add(1));
+                self.contador_de_programa = (opcode & 0x38) as u16;
+                self.mnemonic = format!("RST {}", (opcode >> 3) & 0x07);
+            }
+
+            0xF3 => { // DI deshabilita las interrupciones
+                self.interrupciones = false;
+                self.mnemonic = "DI".to_string();
+                self.contador_de_programa += 1;
+            }
+
+            0xFB => { // EI habilita las interrupciones
+                self.interrupciones = true;
+                self.mnemonic = "EI".to_string();
+                self.contador_de_programa += 1;
+            }
+
 // Revisar *********************************
-            _ => { print!("exit"); }
+            _ => { eprint!("exit"); }             // Por stderr: stdout puede transportar un protocolo (DAP)
         }
     }
 
 }
     
-//***************************************************************************** 
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asm8080::asm8080;
+
+    #[test]
+    fn test_apila_desapila() {
+        let mut cpu = CPU::new();
+        cpu.puntero_de_pila = 0x0400;
+        cpu.apila(0x1234);
+        // SP se decrementa antes de cada byte y el byte alto queda en la dirección más alta
+        assert_eq!(cpu.puntero_de_pila, 0x03FE);
+        assert_eq!((cpu.memoria.leer_memoria(0x03FF), cpu.memoria.leer_memoria(0x03FE)), (0x12, 0x34));
+        assert_eq!(cpu.desapila(), 0x1234);
+        assert_eq!(cpu.puntero_de_pila, 0x0400);
+    }
 
+    #[test]
+    fn test_lxi_sp_call_ret_rst() {
+        let mut cpu = CPU::new();
+        cpu.cargar_programa(&asm8080! {
+                    lxi sp, 400h        // 0000
+                    call subrutina      // 0003
+                    rst 2               // 0006
+                    nop                 // 0007
+            subrutina: ret              // 0008
+        });
+        cpu.ejecuta_paso();
+        assert_eq!((cpu.puntero_de_pila, cpu.contador_de_programa), (0x0400, 0x0003));
+        cpu.ejecuta_paso();
+        assert_eq!((cpu.puntero_de_pila, cpu.contador_de_programa), (0x03FE, 0x0008));
+        assert_eq!((cpu.memoria.leer_memoria(0x03FE), cpu.memoria.leer_memoria(0x03FF)), (0x06, 0x00));
+        cpu.ejecuta_paso();
+        assert_eq!((cpu.puntero_de_pila, cpu.contador_de_programa), (0x0400, 0x0006));
+        cpu.ejecuta_paso();
+        assert_eq!((cpu.puntero_de_pila, cpu.contador_de_programa), (0x03FE, 0x0010));
+        assert_eq!(cpu.mnemonic, "RST 2");
+        assert_eq!(cpu.desapila(), 0x0007);
+        assert_eq!(cpu.ciclos, 10 + 17 + 10 + 11);
+    }
 
+    #[test]
+    fn test_di_ei_y_ciclos() {
+        let mut cpu = CPU::new();
+        cpu.cargar_programa(&asm8080! { ei; di; mvi a, 1 });
+        cpu.ejecuta_paso();
+        assert!(cpu.interrupciones);
+        assert_eq!((cpu.mnemonic.as_str(), cpu.contador_de_programa), ("EI", 1));
+        cpu.ejecuta_paso();
+        assert!(!cpu.interrupciones);
+        assert_eq!((cpu.mnemonic.as_str(), cpu.contador_de_programa), ("DI", 2));
+        cpu.ejecuta_paso();
+        assert_eq!(cpu.ciclos, 4 + 4 + 7);
+        // Algunos valores de la tabla (los CALL y RET condicionales, sin saltar)
+        for (opcode, ciclos) in [(0x00, 4), (0x76, 7), (0xE3, 18), (0x22, 16), (0xC4, 11), (0xC0, 5), (0xCD, 17), (0xC9, 10)] {
+            assert_eq!(CICLOS_8080[opcode], ciclos, "opcode 0x{:02X}", opcode);
+        }
+    }
+}
 
 //*****************************************************************************
 /* Operaciones ALU pendientes de implementar
@@ -0,0 +1,229 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Perfilador de ejecución por opcode y por dirección - Simulación CPU
+    Descripción:    Cuenta ejecuciones y ciclos por opcode, por dirección del PC y por
+                    rangos de direcciones (por ejemplo una subrutina) y genera un informe
+                    ordenado con los puntos calientes y la mezcla de instrucciones.
+    Referencias:
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+use super::sim_cpu_tablas::MNEMONICOS_8080;
+use std::collections::HashMap;
+
+//***************************************************************************** Notas
+/* Uso
+El perfilador se guarda en CPU::perfilador como Option<Box<Perfilador>>. Con None (valor por
+defecto) el único coste en ejecuta_paso es comprobar la opción; al habilitarlo cada
+instrucción se anota con su dirección, opcode y ciclos consumidos.
+
+    cpu.habilitar_perfilador();
+    cpu.agrega_rango_perfil("bucle", 0x0006, 0x000A)?;     // Error si no está habilitado
+    cpu.run_no_win(&mut aux);           // Imprime el informe al terminar
+
+La mezcla de instrucciones se agrupa por opcode y el mnemónico del informe sale de
+MNEMONICOS_8080, no del último texto que dejó la CPU en 'mnemonic'.
+*/
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Contador {
+    pub ejecuciones: u64,
+    pub ciclos: u64,
+}
+
+impl Contador {
+    fn suma(&mut self, ciclos: u64) {
+        self.ejecuciones += 1;
+        self.ciclos += ciclos;
+    }
+
+    pub fn ciclos_por_instruccion(&self) -> f64 {
+        if self.ejecuciones == 0 { 0.0 } else { self.ciclos as f64 / self.ejecuciones as f64 }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RangoPerfil {
+    pub nombre: String,
+    pub inicio: u16,
+    pub fin: u16,                       // Incluido
+    pub contador: Contador,
+}
+
+pub struct Perfilador {
+    pub por_opcode: [Contador; 256],
+    pub por_direccion: HashMap<u16, Contador>,
+    pub rangos: Vec<RangoPerfil>,
+    pub total: Contador,
+}
+
+impl Perfilador {
+    pub fn new() -> Perfilador {
+        Perfilador {
+            por_opcode: [Contador::default(); 256],
+            por_direccion: HashMap::new(),
+            rangos: Vec::new(),
+            total: Contador::default(),
+        }
+    }
+
+    pub fn agrega_rango(&mut self, nombre: &str, inicio: u16, fin: u16) {
+        self.rangos.push(RangoPerfil { nombre: nombre.to_string(), inicio, fin, contador: Contador::default() });
+    }
+
+    pub fn registra(&mut self, direccion: u16, opcode: u8, ciclos: u64) {
+        self.total.suma(ciclos);
+        self.por_opcode[opcode as usize].suma(ciclos);
+        self.por_direccion.entry(direccion).or_default().suma(ciclos);
+        for rango in self.rangos.iter_mut().filter(|r| r.inicio <= direccion && direccion <= r.fin) {
+            rango.contador.suma(ciclos);
+        }
+    }
+
+    pub fn reiniciar(&mut self) {
+        let rangos: Vec<(String, u16, u16)> = self.rangos.iter().map(|r| (r.nombre.clone(), r.inicio, r.fin)).collect();
+        *self = Perfilador::new();
+        for (nombre, inicio, fin) in rangos { self.agrega_rango(&nombre, inicio, fin); }
+    }
+
+    // Direcciones ordenadas por ciclos consumidos (de mayor a menor)
+    pub fn puntos_calientes(&self) -> Vec<(u16, Contador)> {
+        let mut lista: Vec<(u16, Contador)> = self.por_direccion.iter().map(|(d, c)| (*d, *c)).collect();
+        lista.sort_by(|a, b| b.1.ciclos.cmp(&a.1.ciclos).then(a.0.cmp(&b.0)));
+        lista
+    }
+
+    // Opcodes ejecutados ordenados por número de ejecuciones (de mayor a menor)
+    pub fn mezcla_instrucciones(&self) -> Vec<(u8, Contador)> {
+        let mut lista: Vec<(u8, Contador)> = self.por_opcode.iter().enumerate()
+            .filter(|(_, c)| c.ejecuciones > 0)
+            .map(|(o, c)| (o as u8, *c))
+            .collect();
+        lista.sort_by(|a, b| b.1.ejecuciones.cmp(&a.1.ejecuciones).then(a.0.cmp(&b.0)));
+        lista
+    }
+
+    fn porcentaje(&self, parte: u64, total: u64) -> f64 {
+        if total == 0 { 0.0 } else { parte as f64 * 100.0 / total as f64 }
+    }
+
+    pub fn informe(&self, max_lineas: usize) -> String {
+        let mut salida = format!("\n{:*^80}\n", " Perfil de ejecución ");
+        salida.push_str(&format!("Instrucciones: {}, Ciclos: {}, Ciclos por instrucción (media): {:.2}\n"
+            , self.total.ejecuciones, self.total.ciclos, self.total.ciclos_por_instruccion()));
+
+        salida.push_str("\n--- Puntos calientes (por dirección)\n");
+        salida.push_str("  Dirección    Ejecuciones       Ciclos   % ciclos\n");
+        for (direccion, contador) in self.puntos_calientes().iter().take(max_lineas) {
+            salida.push_str(&format!("     0x{:04X}  {:>13} {:>12} {:>9.2}%\n"
+                , direccion, contador.ejecuciones, contador.ciclos
+                , self.porcentaje(contador.ciclos, self.total.ciclos)));
+        }
+
+        salida.push_str("\n--- Mezcla de instrucciones (por opcode)\n");
+        salida.push_str("  Opcode     Mnemónico      Ejecuciones   % instr.     CPI\n");
+        for (opcode, contador) in self.mezcla_instrucciones().iter().take(max_lineas) {
+            let mnemonico = MNEMONICOS_8080[*opcode as usize];
+            salida.push_str(&format!("  0x{:02X}       {:<12} {:>13} {:>9.2}% {:>7.2}\n"
+                , opcode, mnemonico, contador.ejecuciones
+                , self.porcentaje(contador.ejecuciones, self.total.ejecuciones)
+                , contador.ciclos_por_instruccion()));
+        }
+
+        if !self.rangos.is_empty() {
+            salida.push_str("\n--- Rangos de direcciones\n");
+            salida.push_str("  Nombre           Rango         Ejecuciones       Ciclos   % ciclos\n");
+            let mut rangos: Vec<&RangoPerfil> = self.rangos.iter().collect();
+            rangos.sort_by_key(|r| std::cmp::Reverse(r.contador.ciclos));
+            for rango in rangos {
+                salida.push_str(&format!("  {:<16} {:04X}-{:04X} {:>13} {:>12} {:>9.2}%\n"
+                    , rango.nombre, rango.inicio, rango.fin, rango.contador.ejecuciones, rango.contador.ciclos
+                    , self.porcentaje(rango.contador.ciclos, self.total.ciclos)));
+            }
+        }
+        salida
+    }
+}
+
+impl Default for Perfilador {
+    fn default() -> Self { Perfilador::new() }
+}
+
+//*****************************************************************************
+impl CPU {
+    pub fn habilitar_perfilador(&mut self) {
+        self.perfilador = Some(Box::new(Perfilador::new()));
+    }
+
+    pub fn deshabilitar_perfilador(&mut self) -> Option<Box<Perfilador>> {
+        self.perfilador.take()
+    }
+
+    pub fn agrega_rango_perfil(&mut self, nombre: &str, inicio: u16, fin: u16) -> Result<(), String> {
+        let perfilador = self.perfilador.as_mut().ok_or(String::from("El perfilador no está habilitado"))?;
+        if fin < inicio { return Err(format!("Rango '{}' vacío: 0x{:04X} es menor que 0x{:04X}", nombre, fin, inicio)); }
+        perfilador.agrega_rango(nombre, inicio, fin);
+        Ok(())
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proyectos::sim_cpu_pruebas::Aux;
+    use asm8080::asm8080;
+
+    #[test]
+    fn test_perfil_de_un_bucle() {
+        let mut cpu = CPU::new();
+        let mut aux = Aux { imp_contador_programa: 0, imp_instruccion: 0, imp_mnemonico: String::new() };
+        cpu.cargar_programa(&asm8080! {
+                    mvi a, 0
+                    mvi b, 1
+            bucle:  add b               // 0x0004
+                    inr b
+                    jmp bucle
+        });
+        cpu.habilitar_perfilador();
+        cpu.agrega_rango_perfil("bucle", 0x0004, 0x0008).unwrap();
+        for _ in 0..(2 + 3 * 10) { cpu.step_no_win(&mut aux); }
+
+        let perfilador = cpu.perfilador.as_ref().unwrap();
+        assert_eq!(perfilador.total.ejecuciones, 32);
+        assert_eq!(perfilador.total.ciclos, 7 + 7 + 10 * (4 + 5 + 10));
+        assert_eq!(perfilador.total.ciclos, cpu.ciclos);
+        assert_eq!(perfilador.por_opcode[0xC3], Contador { ejecuciones: 10, ciclos: 100 });
+        assert_eq!(perfilador.por_direccion[&0x0004].ejecuciones, 10);
+        assert_eq!(perfilador.rangos[0].contador, Contador { ejecuciones: 30, ciclos: 190 });
+
+        // El JMP es el punto más caliente y ADD/INR/JMP empatan en ejecuciones
+        assert_eq!(perfilador.puntos_calientes()[0].0, 0x0006);
+        let mezcla = perfilador.mezcla_instrucciones();
+        assert_eq!(mezcla.iter().map(|(o, _)| *o).collect::<Vec<u8>>(), vec![0x04, 0x80, 0xC3, 0x06, 0x3E]);
+
+        let informe = perfilador.informe(3);
+        assert!(informe.contains("Instrucciones: 32, Ciclos: 204"));
+        assert!(informe.contains("JMP addr"));
+        assert!(informe.contains("bucle"));
+    }
+
+    #[test]
+    fn test_sin_perfilador_no_se_registra() {
+        let mut cpu = CPU::new();
+        cpu.cargar_programa(&asm8080! { nop; nop });
+        cpu.ejecuta_paso();
+        assert!(cpu.perfilador.is_none());
+        assert_eq!(cpu.ciclos, 4);
+        assert!(cpu.agrega_rango_perfil("bucle", 0x0000, 0x0001).is_err());
+        cpu.habilitar_perfilador();
+        assert!(cpu.agrega_rango_perfil("bucle", 0x0001, 0x0000).is_err());
+        assert!(cpu.agrega_rango_perfil("bucle", 0x0000, 0x0001).is_ok());
+    }
+}
+
+//*****************************************************************************
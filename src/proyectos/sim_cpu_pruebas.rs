@@ -14,6 +14,7 @@
 use super::{sim_cpu_memoria::BancosMemoria, sim_cpu_registros::{self, CPU}};
 //use super::{sim_cpu_memoria::BancosMemoria, sim_cpu_registros::*};
 use colored::*;
+use asm8080::asm8080;
 //use std::io::{stdin, stdout, Write, Read};
 
 fn imprime_titulo(titulo: &String) {
@@ -30,10 +31,8 @@ pub struct Aux {
 impl CPU{
     pub fn step_no_win(&mut self, aux: &mut Aux) {
         aux.imp_contador_programa = self.contador_de_programa;
-        let instruccion = self.busca_instruccion();
-        aux.imp_instruccion = instruccion;
-        let (opcode, operandos) = self.decodifica_instruccion(instruccion);
-        self.ejecuta_instruccion(opcode, operandos);
+        let (opcode, operandos) = self.ejecuta_paso();
+        aux.imp_instruccion = opcode;
 
         /* (&self).info_registros()
         El paréntesis es necesario para asegurar que se tome la referencia de self antes de llamar al método
@@ -47,7 +46,18 @@ impl CPU{
     }
 
     pub fn run_no_win(&mut self, aux: &mut Aux) {
+         let mut reanuda = self.reanuda_en_ruptura();
          loop {
+            // Los puntos de ruptura se comprueban antes de ejecutar la instrucción del PC
+            if !reanuda {
+                let ruptura = self.comprueba_puntos_ruptura();
+                for mensaje in self.toma_mensajes_ruptura() { println!("{}", mensaje); }
+                if let Some(id) = ruptura {
+                    println!("Punto de ruptura #{} en 0x{:04X}", id, self.contador_de_programa);
+                    break;
+                }
+            }
+            reanuda = false;
             self.step_no_win(aux);
             println!("Contador: 0x{:04X}, Instruccion: {:02x}, Mnemonic: {},\tReg A: {:02x}, Reg B: {:02x}",
                 aux.imp_contador_programa,
@@ -59,7 +69,13 @@ impl CPU{
                 self.reg_b );
 
             if self.memoria.leer_memoria(self.contador_de_programa) == 0xFF { break; }
+            if !self.disparos_vigilancia().is_empty() {
+                for disparo in self.disparos_vigilancia() { println!("{}", disparo.descripcion()); }
+                break;
+            }
         } 
+        if let Some(perfilador) = self.perfilador.as_ref() { print!("{}", perfilador.informe(10)); }
+        if let Some(grafo) = self.grafo_llamadas.as_ref() { print!("{}", grafo.informe()); }
 
 /*
 // ***************
@@ -101,30 +117,31 @@ pub fn cpu_sim_0() {
     let mut aux = Aux {imp_contador_programa: 0x0, imp_instruccion: 0x0, imp_mnemonico: String::new()};
 
     //**************************************
-    let programa = vec![
-    0x00,               // NOP
-    0x3E, 0x04,         // Almacenar el valor 0x04 en el Registro A
-    0x06, 0x0a,         // Almacenar el valor 0x0a en el Registro B
-    0x04,               // Incrementa Registro B
-    0x80,               // Suma el contenido del Registro B al Registro A
-    0x00,               // NOP
-    0x3E, 0xf0,         // Almacenar el valor 0xf0 en el Registro A
-    0x06, 0x0f,         // Almacenar el valor 0x0f en el Registro B
-    0x80,               // Suma el contenido del Registro B al Registro A
-    0x00,               // NOP
-    0x3E, 0x3b,         // Almacenar el valor 0x3b en el Registro A
-    0x3C,               // Incrementa Registro A
-    0x32, 0x15, 0x00,   // Mueve el contenido de A a la dirección indicada por los dos bytes siguientes 
-    0x00, 0x00,         // <-- Se cambio el contenido y se convierte en 3C
-    0x3A, 0x0b, 0x00,   // Mueve el contenido (0x0f) de la dirección indicada (0x0b) en los dos bytes siguientes a A 
-    0x00, 0x00,
-    0x06, 0xff,         // Almacenar el valor 0xff en el Registro B
-    0x80,               // Suma el contenido del Registro B al Registro A
-    0x00, 0x00,
-    0xFF,
-    0xC3, 0x00, 0x00,   // Salta a la dirección 0x0000
-    0xFF, 0xFF,         // Marca fin de programa
-    ];
+    let programa = asm8080! {
+                nop
+                mvi a, 04h          // Almacenar el valor 0x04 en el Registro A
+                mvi b, 0ah          // Almacenar el valor 0x0a en el Registro B
+                inr b               // Incrementa Registro B
+                add b               // Suma el contenido del Registro B al Registro A
+                nop
+                mvi a, 0f0h         // Almacenar el valor 0xf0 en el Registro A
+        valor:  mvi b, 0fh          // Almacenar el valor 0x0f en el Registro B
+                add b               // Suma el contenido del Registro B al Registro A
+                nop
+                mvi a, 3bh          // Almacenar el valor 0x3b en el Registro A
+                inr a               // Incrementa Registro A
+                sta destino         // Mueve el contenido de A a 'destino' (0x0015)
+                nop
+        destino: nop                // <-- Se cambia el contenido y se convierte en 3C
+                lda valor + 1       // Mueve el contenido (0x0f) de 'valor + 1' (0x000B) a A
+                nop; nop
+                mvi b, 0ffh         // Almacenar el valor 0xff en el Registro B
+                add b               // Suma el contenido del Registro B al Registro A
+                nop; nop
+                db 0ffh             // Marca fin de programa
+                jmp 0               // Salta a la dirección 0x0000
+                db 0ffh, 0ffh
+    };
     cpu_reg.cargar_programa(&programa);
 
     cpu_reg.run_no_win(&mut aux);
@@ -0,0 +1,218 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Puntos de ruptura condicionales - Simulación CPU
+    Descripción:    Puntos de ruptura por dirección del PC con una condición opcional escrita
+                    en el lenguaje de sim_cpu_expresion, un contador de impactos y un modo de
+                    solo registro que genera un mensaje formateado en lugar de detenerse.
+    Referencias:
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+use super::sim_cpu_expresion::{Expresion, formatea_mensaje};
+
+//***************************************************************************** Notas
+/* Uso
+    let id = cpu.agrega_punto_ruptura(0x0006, Some("A == 0x3C && !Z"))?;
+    cpu.punto_ruptura_mut(id)?.cuenta = Some(3);                          // Para en el tercer impacto
+    cpu.agrega_registro_ruptura(0x0004, None, "A={A} B={B} [HL]={[HL]:d}")?;  // Solo registra
+
+La comprobación se hace antes de ejecutar la instrucción del PC, así que un punto en la
+dirección de inicio detiene la ejecución antes de la primera instrucción. Al reanudar, la primera
+instrucción es la del punto donde se detuvo y se ejecuta sin volver a comprobarla: run_no_win y
+run_traza lo saben con reanuda_en_ruptura(); el monitor, GDB y DAP siempre están detenidos en el
+PC actual y nunca comprueban la primera instrucción al continuar. Una condición que no se puede
+evaluar (memoria fuera del banco) detiene la ejecución con el error en 'mensajes'. Los mensajes
+de registro se guardan en 'mensajes' para que cada interfaz los muestre a su manera (run_no_win
+los imprime).
+*/
+
+pub struct PuntoRuptura {
+    pub id: usize,
+    pub direccion: u16,
+    pub texto_condicion: Option<String>,
+    pub condicion: Option<Expresion>,
+    pub cuenta: Option<u64>,            // Se detiene solo a partir del impacto número 'cuenta'
+    pub impactos: u64,                  // Veces que se ha llegado con la condición cumplida
+    pub mensaje: Option<String>,        // Modo registro: plantilla del mensaje (no se detiene)
+    pub activo: bool,
+}
+
+pub struct PuntosRuptura {
+    pub puntos: Vec<PuntoRuptura>,
+    pub mensajes: Vec<String>,
+    pub parado_en: Option<u16>,         // PC del último punto que detuvo la ejecución
+    siguiente_id: usize,
+}
+
+impl PuntosRuptura {
+    pub fn new() -> PuntosRuptura {
+        PuntosRuptura { puntos: Vec::new(), mensajes: Vec::new(), parado_en: None, siguiente_id: 1 }
+    }
+}
+
+impl Default for PuntosRuptura {
+    fn default() -> Self { PuntosRuptura::new() }
+}
+
+//*****************************************************************************
+impl CPU {
+    pub fn agrega_punto_ruptura(&mut self, direccion: u16, condicion: Option<&str>) -> Result<usize, String> {
+        let expresion = match condicion {
+            Some(texto) => Some(Expresion::analiza_con_simbolos(texto, self.simbolos.as_ref())?),
+            None => None,
+        };
+        let puntos = self.puntos_ruptura.get_or_insert_with(PuntosRuptura::new);
+        let id = puntos.siguiente_id;
+        puntos.siguiente_id += 1;
+        puntos.puntos.push(PuntoRuptura {
+            id,
+            direccion,
+            texto_condicion: condicion.map(|c| c.to_string()),
+            condicion: expresion,
+            cuenta: None,
+            impactos: 0,
+            mensaje: None,
+            activo: true,
+        });
+        Ok(id)
+    }
+
+    pub fn agrega_registro_ruptura(&mut self, direccion: u16, condicion: Option<&str>, mensaje: &str) -> Result<usize, String> {
+        // Se comprueba la plantilla ahora para no descubrir el error en plena ejecución
+        formatea_mensaje(mensaje, self)?;
+        let id = self.agrega_punto_ruptura(direccion, condicion)?;
+        self.punto_ruptura_mut(id)?.mensaje = Some(mensaje.to_string());
+        Ok(id)
+    }
+
+    pub fn punto_ruptura_mut(&mut self, id: usize) -> Result<&mut PuntoRuptura, String> {
+        self.puntos_ruptura.as_mut().and_then(|p| p.puntos.iter_mut().find(|p| p.id == id))
+            .ok_or(format!("No existe el punto de ruptura #{}", id))
+    }
+
+    pub fn elimina_punto_ruptura(&mut self, id: usize) -> Result<(), String> {
+        let puntos = self.puntos_ruptura.as_mut().ok_or(format!("No existe el punto de ruptura #{}", id))?;
+        let posicion = puntos.puntos.iter().position(|p| p.id == id).ok_or(format!("No existe el punto de ruptura #{}", id))?;
+        puntos.puntos.remove(posicion);
+        if puntos.puntos.is_empty() && puntos.mensajes.is_empty() { self.puntos_ruptura = None; }
+        Ok(())
+    }
+
+    // Comprueba los puntos de ruptura del PC actual antes de ejecutar su instrucción. Devuelve el
+    // id del punto que detiene la ejecución; los puntos en modo registro añaden su mensaje y no
+    // detienen.
+    pub fn comprueba_puntos_ruptura(&mut self) -> Option<usize> {
+        let pc = self.contador_de_programa;
+        let mut puntos = self.puntos_ruptura.take()?;
+        let mut detiene = None;
+        for punto in puntos.puntos.iter_mut().filter(|p| p.activo && p.direccion == pc) {
+            match punto.condicion.as_ref().map_or(Ok(true), |c| c.es_verdadera(self)) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    puntos.mensajes.push(format!("[#{} 0x{:04X}] Error en la condición: {}", punto.id, pc, e));
+                    if detiene.is_none() { detiene = Some(punto.id); }
+                    continue;
+                }
+            }
+            punto.impactos += 1;
+            if punto.cuenta.is_some_and(|cuenta| punto.impactos < cuenta) { continue; }
+            match punto.mensaje.as_ref() {
+                Some(plantilla) => {
+                    let texto = formatea_mensaje(plantilla, self).unwrap_or_else(|e| e);
+                    puntos.mensajes.push(format!("[#{} 0x{:04X}] {}", punto.id, pc, texto));
+                }
+                None => { if detiene.is_none() { detiene = Some(punto.id); } }
+            }
+        }
+        if detiene.is_some() { puntos.parado_en = Some(pc); }
+        self.puntos_ruptura = Some(puntos);
+        detiene
+    }
+
+    // Al empezar una ejecución: true si el PC es el del punto de ruptura donde se detuvo la
+    // anterior, y entonces su instrucción se ejecuta sin volver a comprobarla
+    pub fn reanuda_en_ruptura(&mut self) -> bool {
+        let pc = self.contador_de_programa;
+        self.puntos_ruptura.as_mut().and_then(|p| p.parado_en.take()) == Some(pc)
+    }
+
+    pub fn toma_mensajes_ruptura(&mut self) -> Vec<String> {
+        self.puntos_ruptura.as_mut().map(|p| std::mem::take(&mut p.mensajes)).unwrap_or_default()
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proyectos::sim_cpu_pruebas::Aux;
+    use asm8080::asm8080;
+
+    fn cpu_con_bucle() -> (CPU, Aux) {
+        let mut cpu = CPU::new();
+        cpu.cargar_programa(&asm8080! {
+                    mvi a, 0
+                    mvi b, 1
+            bucle:  add b               // 0x0004
+                    inr b
+                    jmp bucle
+        });
+        (cpu, Aux { imp_contador_programa: 0, imp_instruccion: 0, imp_mnemonico: String::new() })
+    }
+
+    #[test]
+    fn test_condicion_y_cuenta() {
+        let (mut cpu, mut aux) = cpu_con_bucle();
+        let id = cpu.agrega_punto_ruptura(0x0004, Some("B >= 3")).unwrap();
+        cpu.punto_ruptura_mut(id).unwrap().cuenta = Some(2);
+        cpu.run_no_win(&mut aux);
+        // B vale 3 en la primera vuelta que cumple la condición y 4 en la segunda
+        assert_eq!(cpu.contador_de_programa, 0x0004);
+        assert_eq!(cpu.reg_b, 4);
+        assert_eq!(cpu.reg_a, 1 + 2 + 3);
+        assert_eq!(cpu.punto_ruptura_mut(id).unwrap().impactos, 2);
+        // Al continuar se ejecuta la instrucción y se vuelve a parar en la siguiente vuelta
+        cpu.run_no_win(&mut aux);
+        assert_eq!(cpu.reg_b, 5);
+        assert!(cpu.agrega_punto_ruptura(0x0004, Some("B >=")).is_err());
+    }
+
+    #[test]
+    fn test_ruptura_en_el_inicio_y_error_de_condicion() {
+        let (mut cpu, mut aux) = cpu_con_bucle();
+        cpu.agrega_punto_ruptura(0x0000, None).unwrap();
+        cpu.run_no_win(&mut aux);
+        assert_eq!((cpu.contador_de_programa, cpu.reg_b), (0x0000, 0));
+        // Al reanudar se ejecuta la instrucción del punto y se para en la condición que falla
+        let id = cpu.agrega_punto_ruptura(0x0004, Some("[HL + 0x400] == 0")).unwrap();
+        cpu.run_no_win(&mut aux);
+        assert_eq!(cpu.contador_de_programa, 0x0004);
+        assert_eq!(cpu.punto_ruptura_mut(id).unwrap().impactos, 0);
+        assert!(cpu.toma_mensajes_ruptura().is_empty());   // run_no_win ya los ha mostrado
+        assert_eq!(cpu.comprueba_puntos_ruptura(), Some(id));
+        assert!(cpu.toma_mensajes_ruptura()[0].contains("fuera del banco"));
+    }
+
+    #[test]
+    fn test_modo_registro() {
+        let (mut cpu, mut aux) = cpu_con_bucle();
+        cpu.agrega_registro_ruptura(0x0006, Some("A > 2"), "A={A} B={B:d}").unwrap();
+        assert!(cpu.agrega_registro_ruptura(0x0006, None, "A={A").is_err());
+        for _ in 0..(2 + 3 * 3) {
+            cpu.step_no_win(&mut aux);
+            assert_eq!(cpu.comprueba_puntos_ruptura(), None);
+        }
+        assert_eq!(cpu.toma_mensajes_ruptura(), vec![
+            String::from("[#1 0x0006] A=0x03 B=3"),
+            String::from("[#1 0x0006] A=0x06 B=4"),
+        ]);
+        assert!(cpu.toma_mensajes_ruptura().is_empty());
+    }
+}
+
+//*****************************************************************************
@@ -9,7 +9,13 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
-use super::{sim_cpu_memoria::BancosMemoria, sim_cpu_memoria::Endianess};
+use super::{sim_cpu_memoria::BancosMemoria, sim_cpu_memoria::Endianess, sim_cpu_historial::Historial};
+use super::sim_cpu_perfilador::Perfilador;
+use super::sim_cpu_llamadas::GrafoLlamadas;
+use super::sim_cpu_vigilancia::Vigilancia;
+use super::sim_cpu_puntos_ruptura::PuntosRuptura;
+use super::sim_cpu_simbolos::TablaSimbolos;
+use super::sim_cpu_listado::ListadoFuente;
 
 /* Registro Flags (banderas)                
                                                     Bits    7	6	5	4	3	2	1	0
@@ -322,6 +328,15 @@ pub struct CPU {
     pub puntero_de_pila: u16,
     pub registro_instrucciones: u8,
     pub mnemonic: String,
+    pub ciclos: u64,                    // Ciclos de reloj acumulados desde el inicio
+    pub interrupciones: bool,           // Flip-flop INTE (EI / DI)
+    pub historial: Option<Historial>,   // Deltas para ejecución hacia atrás (None: desactivado)
+    pub perfilador: Option<Box<Perfilador>>,
+    pub grafo_llamadas: Option<Box<GrafoLlamadas>>,
+    pub vigilancia: Option<Vigilancia>, // Puntos de vigilancia de memoria (None: sin puntos)
+    pub puntos_ruptura: Option<PuntosRuptura>,
+    pub simbolos: Option<TablaSimbolos>, // Etiquetas para las vistas y el depurador (None: sin tabla)
+    pub listado: Option<ListadoFuente>, // Líneas de código fuente de un .PRN (None: sin listado)
 }
 
 impl CPU {
@@ -332,6 +347,9 @@ impl CPU {
                 //segmento_memoria: vec![vec![0; 16384]; 1],
                 banco_actual: 0,
                 endianess: Endianess::LittleEndian,
+                diario_escrituras: None,
+                cobertura: None,
+                diario_accesos: None,
             },
             flags: Flags { 
                 carry: false,
@@ -353,6 +371,15 @@ impl CPU {
             puntero_de_pila: 0,
             registro_instrucciones: 0,
             mnemonic: String::new(),
+            ciclos: 0,
+            interrupciones: false,
+            historial: None,
+            perfilador: None,
+            grafo_llamadas: None,
+            vigilancia: None,
+            puntos_ruptura: None,
+            simbolos: None,
+            listado: None,
         }
     }
 
@@ -747,6 +774,8 @@ mod tests {
         assert_eq!(registros.contador_de_programa, 0);
         assert_eq!(registros.puntero_de_pila, 0);
         assert_eq!(registros.registro_instrucciones, 0);
+        assert_eq!(registros.ciclos, 0);
+        assert_eq!(registros.interrupciones, false);
     }
 
     #[test]
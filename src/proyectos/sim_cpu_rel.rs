@@ -0,0 +1,580 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Cargador y enlazador de módulos REL de Microsoft - Simulación CPU
+    Descripción:    Lee el formato de objeto reubicable de M80/L80 (flujo de bits), resuelve
+                    los símbolos públicos y externos entre varios módulos, reubica los
+                    segmentos de programa y de datos en las direcciones elegidas y escribe la
+                    imagen enlazada en BancosMemoria junto con el mapa de símbolos.
+    Referencias:    Microsoft Utility Software Package Reference Manual (M80/L80), apéndice
+                    "Format of LINK Compatible Object Files"
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_memoria::BancosMemoria;
+use super::sim_cpu_hex::{genera_intel_hex, ImagenCarga};
+use std::collections::BTreeMap;
+
+//***************************************************************************** Notas
+/* Uso
+    let modulos = vec![
+        analiza_rel(&std::fs::read("MAIN.REL").unwrap())?,
+        analiza_rel(&std::fs::read("SUBS.REL").unwrap())?,
+    ];
+    let mut opciones = OpcionesEnlace::new();           // Programa en 0x0100, datos detrás
+    opciones.base_datos = Some(0x8000);
+    let enlazado = enlaza(&modulos, &opciones)?;
+    cpu.memoria.carga_enlazado(&enlazado, 0)?;          // Banco 0
+    cpu.contador_de_programa = enlazado.entrada.unwrap_or(0x0100);
+    println!("{}", enlazado.mapa());
+
+Formato (bits, el más significativo primero):
+    0 + 8 bits                      byte absoluto
+    1 + TT + 16 bits (bajo, alto)   palabra reubicable: TT 01 programa, 10 datos, 11 COMMON
+    1 00 + CCCC [A] [B]             elemento especial: A = TT + 16 bits, B = 3 bits de longitud
+                                    y hasta 7 caracteres de 8 bits
+Especiales: 0 símbolo de entrada (B), 1 selecciona COMMON (B), 2 nombre del programa (B),
+3 búsqueda en biblioteca (B), 4 extensión (B), 5 tamaño de COMMON (A B), 6 cadena de un externo
+(A B), 7 punto de entrada público (A B), 8 externo - desplazamiento (A), 9 externo +
+desplazamiento (A), 10 tamaño de datos (A), 11 contador de carga (A), 12 cadena de direcciones
+(A), 13 tamaño de programa (A), 14 fin de módulo (A, dirección de inicio) y 15 fin de archivo.
+
+Las referencias a un externo forman una cadena en el propio código: la dirección A apunta a la
+última, cada una contiene la dirección de la anterior y la primera contiene 0 absoluto. Al enlazar
+se recorre la cadena ya reubicada y se sustituye cada eslabón por el valor del símbolo (más el
+desplazamiento de 8/9 si lo había). Los bloques COMMON, la búsqueda en bibliotecas y los
+elementos de extensión no se soportan: COMMON es un error y los otros dos se ignoran.
+
+Cada módulo ocupa su tamaño de programa a partir de base_programa, uno detrás de otro, y lo mismo
+con los datos a partir de base_datos (o detrás del último módulo si es None). Si los datos caen
+dentro del programa el enlace es un error. El código absoluto (ASEG) se carga en su dirección y
+tampoco puede pisar otro módulo. El inicio es el del primer módulo que declara uno.
+
+enlaza_cmd escribe el resultado junto al primer módulo con su nombre: enlaza MAIN.REL SUBS.REL
+genera MAIN.hex y MAIN.sym.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Segmento {
+    Absoluto,
+    Programa,
+    Datos,
+}
+
+// Dirección dentro de un segmento del módulo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Posicion {
+    pub segmento: Segmento,
+    pub desplazamiento: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cadena {
+    Externo(String),                    // Se rellena con el valor del símbolo
+    Direccion(Posicion),                // Se rellena con una dirección del módulo
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuloRel {
+    pub nombre: String,
+    pub programa: Vec<u8>,
+    pub datos: Vec<u8>,
+    pub absoluto: BTreeMap<u16, u8>,
+    pub reubicaciones: Vec<(Posicion, Segmento)>,       // Palabra en la posición + base del segmento
+    pub publicos: Vec<(String, Posicion)>,
+    pub cadenas: Vec<(Posicion, Cadena)>,               // (cabeza de la cadena, valor)
+    pub desplazamientos: Vec<(Posicion, i32)>,          // Sumas tras resolver los externos (8/9)
+    pub inicio: Option<Posicion>,
+}
+
+//***************************************************************************** Lectura de bits
+struct LectorBits<'a> {
+    datos: &'a [u8],
+    bit: usize,
+}
+
+impl<'a> LectorBits<'a> {
+    fn lee(&mut self, n: usize) -> Result<u32, String> {
+        let mut valor = 0u32;
+        for _ in 0..n {
+            let byte = *self.datos.get(self.bit / 8).ok_or(format!("Archivo REL truncado (byte {})", self.bit / 8))?;
+            valor = (valor << 1) | ((byte >> (7 - self.bit % 8)) & 1) as u32;
+            self.bit += 1;
+        }
+        Ok(valor)
+    }
+
+    fn palabra(&mut self) -> Result<u16, String> {
+        let bajo = self.lee(8)? as u16;
+        Ok(bajo | (self.lee(8)? as u16) << 8)
+    }
+
+    fn campo_a(&mut self) -> Result<(u32, u16), String> {
+        Ok((self.lee(2)?, self.palabra()?))
+    }
+
+    fn campo_b(&mut self) -> Result<String, String> {
+        let longitud = self.lee(3)? as usize;
+        let longitud = if longitud == 0 { 8 } else { longitud };         // 0 equivale a 8 en L80
+        (0..longitud).map(|_| self.lee(8).map(|c| c as u8 as char)).collect()
+    }
+
+    fn alinea(&mut self) {
+        self.bit = self.bit.div_ceil(8) * 8;
+    }
+}
+
+fn segmento(tipo: u32, bit: usize) -> Result<Segmento, String> {
+    match tipo {
+        0 => Ok(Segmento::Absoluto),
+        1 => Ok(Segmento::Programa),
+        2 => Ok(Segmento::Datos),
+        _ => Err(format!("Bit {}: los bloques COMMON no están soportados", bit)),
+    }
+}
+
+//***************************************************************************** Análisis
+impl ModuloRel {
+    fn escribe(&mut self, posicion: Posicion, byte: u8) {
+        let indice = posicion.desplazamiento as usize;
+        let destino = match posicion.segmento {
+            Segmento::Absoluto => { self.absoluto.insert(posicion.desplazamiento, byte); return; }
+            Segmento::Programa => &mut self.programa,
+            Segmento::Datos => &mut self.datos,
+        };
+        if destino.len() <= indice { destino.resize(indice + 1, 0); }
+        destino[indice] = byte;
+    }
+}
+
+// Un módulo; el archivo puede tener varios, usar analiza_biblioteca
+fn analiza_modulo(lector: &mut LectorBits) -> Result<Option<ModuloRel>, String> {
+    let mut modulo = ModuloRel {
+        nombre: String::new(), programa: Vec::new(), datos: Vec::new(), absoluto: BTreeMap::new(),
+        reubicaciones: Vec::new(), publicos: Vec::new(), cadenas: Vec::new(), desplazamientos: Vec::new(), inicio: None,
+    };
+    let mut contador = Posicion { segmento: Segmento::Programa, desplazamiento: 0 };
+    let mut desplazamiento_externo: Option<i32> = None;
+    let avanza = |p: &mut Posicion| p.desplazamiento = p.desplazamiento.wrapping_add(1);
+    loop {
+        let bit = lector.bit;
+        if lector.lee(1)? == 0 {
+            let byte = lector.lee(8)? as u8;
+            if let Some(d) = desplazamiento_externo.take() { modulo.desplazamientos.push((contador, d)); }
+            modulo.escribe(contador, byte);
+            avanza(&mut contador);
+            continue;
+        }
+        let tipo = lector.lee(2)?;
+        if tipo != 0 {
+            let destino = segmento(tipo, bit)?;
+            let valor = lector.palabra()?;
+            if let Some(d) = desplazamiento_externo.take() { modulo.desplazamientos.push((contador, d)); }
+            modulo.reubicaciones.push((contador, destino));
+            for byte in valor.to_le_bytes() { modulo.escribe(contador, byte); avanza(&mut contador); }
+            continue;
+        }
+        let control = lector.lee(4)?;
+        let posicion = |(tipo, valor): (u32, u16)| segmento(tipo, bit).map(|s| Posicion { segmento: s, desplazamiento: valor });
+        match control {
+            0 | 3 | 4 => { lector.campo_b()?; }
+            1 | 5 => return Err(format!("Bit {}: los bloques COMMON no están soportados", bit)),
+            2 => modulo.nombre = lector.campo_b()?,
+            6 => {
+                let cabeza = posicion(lector.campo_a()?)?;
+                modulo.cadenas.push((cabeza, Cadena::Externo(lector.campo_b()?)));
+            }
+            7 => {
+                let direccion = posicion(lector.campo_a()?)?;
+                modulo.publicos.push((lector.campo_b()?, direccion));
+            }
+            8 | 9 => {
+                let (_, valor) = lector.campo_a()?;
+                desplazamiento_externo = Some(if control == 8 { -(valor as i32) } else { valor as i32 });
+            }
+            10 | 13 => {
+                // Tamaño de datos / programa: las zonas DS no llevan bytes en el archivo
+                let (_, valor) = lector.campo_a()?;
+                let destino = if control == 10 { &mut modulo.datos } else { &mut modulo.programa };
+                if destino.len() < valor as usize { destino.resize(valor as usize, 0); }
+            }
+            11 => contador = posicion(lector.campo_a()?)?,
+            12 => {
+                let cabeza = posicion(lector.campo_a()?)?;
+                modulo.cadenas.push((cabeza, Cadena::Direccion(contador)));
+            }
+            14 => {
+                let (tipo, valor) = lector.campo_a()?;
+                if tipo != 0 || valor != 0 { modulo.inicio = Some(posicion((tipo, valor))?); }
+                lector.alinea();
+                return Ok(Some(modulo));
+            }
+            _ => return Ok(None),                                       // 15: fin de archivo
+        }
+    }
+}
+
+pub fn analiza_rel(datos: &[u8]) -> Result<ModuloRel, String> {
+    let mut modulos = analiza_biblioteca(datos)?;
+    if modulos.len() != 1 { return Err(format!("Se esperaba un módulo y hay {}", modulos.len())); }
+    Ok(modulos.remove(0))
+}
+
+// Todos los módulos de un archivo (las bibliotecas .REL son módulos seguidos)
+pub fn analiza_biblioteca(datos: &[u8]) -> Result<Vec<ModuloRel>, String> {
+    let mut lector = LectorBits { datos, bit: 0 };
+    let mut modulos = Vec::new();
+    // Sin fin de archivo explícito: basta con llegar al final tras un fin de módulo
+    while lector.bit / 8 < datos.len() {
+        match analiza_modulo(&mut lector)? {
+            Some(modulo) => modulos.push(modulo),
+            None => break,
+        }
+    }
+    Ok(modulos)
+}
+
+//***************************************************************************** Enlace
+pub struct OpcionesEnlace {
+    pub base_programa: u16,
+    pub base_datos: Option<u16>,        // None: a continuación del programa
+}
+
+impl OpcionesEnlace {
+    pub fn new() -> OpcionesEnlace {
+        OpcionesEnlace { base_programa: 0x0100, base_datos: None }
+    }
+}
+
+impl Default for OpcionesEnlace {
+    fn default() -> Self { OpcionesEnlace::new() }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuloEnlazado {
+    pub nombre: String,
+    pub programa: (u16, u16),           // (base, tamaño)
+    pub datos: (u16, u16),
+}
+
+pub struct Enlazado {
+    pub bloques: Vec<(u16, Vec<u8>)>,
+    pub simbolos: BTreeMap<String, u16>,
+    pub modulos: Vec<ModuloEnlazado>,
+    pub entrada: Option<u16>,
+}
+
+pub fn enlaza(modulos: &[ModuloRel], opciones: &OpcionesEnlace) -> Result<Enlazado, String> {
+    // 1. Bases de cada módulo
+    let mut bases = Vec::new();
+    let mut siguiente = opciones.base_programa as u32;
+    for modulo in modulos {
+        bases.push((siguiente, 0u32));
+        siguiente += modulo.programa.len() as u32;
+    }
+    let inicio_datos = opciones.base_datos.map(|d| d as u32).unwrap_or(siguiente);
+    let mut siguiente_datos = inicio_datos;
+    for (i, modulo) in modulos.iter().enumerate() {
+        bases[i].1 = siguiente_datos;
+        siguiente_datos += modulo.datos.len() as u32;
+    }
+    if siguiente > 0x10000 || siguiente_datos > 0x10000 { return Err(String::from("El programa enlazado no cabe en 64K")); }
+    let base_programa = opciones.base_programa as u32;
+    if inicio_datos < siguiente && base_programa < siguiente_datos {
+        return Err(format!("Los datos (0x{:04X}-0x{:04X}) se solapan con el programa (0x{:04X}-0x{:04X})",
+            inicio_datos, siguiente_datos - 1, base_programa, siguiente - 1));
+    }
+    let absoluta = |i: usize, p: Posicion| -> u16 {
+        match p.segmento {
+            Segmento::Absoluto => p.desplazamiento,
+            Segmento::Programa => (bases[i].0 as u16).wrapping_add(p.desplazamiento),
+            Segmento::Datos => (bases[i].1 as u16).wrapping_add(p.desplazamiento),
+        }
+    };
+
+    // 2. Símbolos públicos
+    let mut simbolos: BTreeMap<String, u16> = BTreeMap::new();
+    let mut definido_en: BTreeMap<String, String> = BTreeMap::new();
+    for (i, modulo) in modulos.iter().enumerate() {
+        for (nombre, posicion) in &modulo.publicos {
+            if let Some(otro) = definido_en.get(nombre) {
+                return Err(format!("Símbolo '{}' definido en '{}' y en '{}'", nombre, otro, modulo.nombre));
+            }
+            simbolos.insert(nombre.clone(), absoluta(i, *posicion));
+            definido_en.insert(nombre.clone(), modulo.nombre.clone());
+        }
+    }
+
+    // 3. Imagen de 64K con los bytes y las reubicaciones
+    let mut memoria = vec![0u8; 0x10000];
+    let mut ocupado = vec![false; 0x10000];
+    for (i, modulo) in modulos.iter().enumerate() {
+        let segmentos = [(bases[i].0, &modulo.programa), (bases[i].1, &modulo.datos)];
+        for (base, bytes) in segmentos {
+            for (j, byte) in bytes.iter().enumerate() {
+                memoria[base as usize + j] = *byte;
+                ocupado[base as usize + j] = true;
+            }
+        }
+    }
+    // El código absoluto va después de colocar todos los segmentos, para detectar también los
+    // de los módulos siguientes
+    for modulo in modulos {
+        for (direccion, byte) in &modulo.absoluto {
+            if ocupado[*direccion as usize] { return Err(format!("'{}': el código absoluto en 0x{:04X} pisa otro módulo", modulo.nombre, direccion)); }
+            memoria[*direccion as usize] = *byte;
+            ocupado[*direccion as usize] = true;
+        }
+    }
+    let lee = |m: &Vec<u8>, d: u16| u16::from_le_bytes([m[d as usize], m[d.wrapping_add(1) as usize]]);
+    let escribe = |m: &mut Vec<u8>, d: u16, v: u16| {
+        let [bajo, alto] = v.to_le_bytes();
+        m[d as usize] = bajo;
+        m[d.wrapping_add(1) as usize] = alto;
+    };
+    for (i, modulo) in modulos.iter().enumerate() {
+        for (posicion, destino) in &modulo.reubicaciones {
+            let base = match destino { Segmento::Programa => bases[i].0 as u16, Segmento::Datos => bases[i].1 as u16, Segmento::Absoluto => 0 };
+            let direccion = absoluta(i, *posicion);
+            let valor = lee(&memoria, direccion).wrapping_add(base);
+            escribe(&mut memoria, direccion, valor);
+        }
+    }
+
+    // 4. Cadenas de externos y de direcciones
+    let mut sin_definir: Vec<String> = Vec::new();
+    for (i, modulo) in modulos.iter().enumerate() {
+        for (cabeza, cadena) in &modulo.cadenas {
+            let valor = match cadena {
+                Cadena::Externo(nombre) => match simbolos.get(nombre) {
+                    Some(valor) => *valor,
+                    None => {
+                        if !sin_definir.contains(nombre) { sin_definir.push(nombre.clone()); }
+                        continue;
+                    }
+                },
+                Cadena::Direccion(posicion) => absoluta(i, *posicion),
+            };
+            let mut eslabon = absoluta(i, *cabeza);
+            let mut pasos = 0;
+            while eslabon != 0 {
+                let anterior = lee(&memoria, eslabon);
+                escribe(&mut memoria, eslabon, valor);
+                eslabon = anterior;
+                pasos += 1;
+                if pasos > 0x8000 { return Err(format!("'{}': cadena de referencias circular", modulo.nombre)); }
+            }
+        }
+        for (posicion, desplazamiento) in &modulo.desplazamientos {
+            let direccion = absoluta(i, *posicion);
+            let valor = lee(&memoria, direccion).wrapping_add(*desplazamiento as u16);
+            escribe(&mut memoria, direccion, valor);
+        }
+    }
+    if !sin_definir.is_empty() { return Err(format!("Símbolos externos sin definir: {}", sin_definir.join(", "))); }
+
+    // 5. Bloques contiguos ocupados
+    let mut bloques: Vec<(u16, Vec<u8>)> = Vec::new();
+    for direccion in 0..0x10000usize {
+        if !ocupado[direccion] { continue; }
+        match bloques.last_mut() {
+            Some((inicio, bytes)) if *inicio as usize + bytes.len() == direccion => bytes.push(memoria[direccion]),
+            _ => bloques.push((direccion as u16, vec![memoria[direccion]])),
+        }
+    }
+    let entrada = modulos.iter().enumerate().find_map(|(i, m)| m.inicio.map(|p| absoluta(i, p)));
+    let resumen = modulos.iter().enumerate().map(|(i, m)| ModuloEnlazado {
+        nombre: m.nombre.clone(),
+        programa: (bases[i].0 as u16, m.programa.len() as u16),
+        datos: (bases[i].1 as u16, m.datos.len() as u16),
+    }).collect();
+    Ok(Enlazado { bloques, simbolos, modulos: resumen, entrada })
+}
+
+impl Enlazado {
+    // Módulos con sus segmentos y símbolos por dirección
+    pub fn mapa(&self) -> String {
+        let mut salida = String::from("Módulo    Programa         Datos\n");
+        for m in &self.modulos {
+            salida.push_str(&format!("{:<9} {:04X}-{:04X} ({:>4}) {:04X}-{:04X} ({:>4})\n", m.nombre
+                , m.programa.0, m.programa.0.wrapping_add(m.programa.1.max(1) - 1), m.programa.1
+                , m.datos.0, m.datos.0.wrapping_add(m.datos.1.max(1) - 1), m.datos.1));
+        }
+        if let Some(entrada) = self.entrada { salida.push_str(&format!("Inicio: {:04X}\n", entrada)); }
+        salida.push_str("\nSímbolos\n");
+        salida.push_str(&self.tabla_simbolos());
+        salida
+    }
+
+    // Formato .SYM (dirección y nombre), el mismo que el del ensamblador
+    pub fn tabla_simbolos(&self) -> String {
+        let mut simbolos: Vec<(&String, &u16)> = self.simbolos.iter().collect();
+        simbolos.sort_by_key(|(nombre, dir)| (**dir, (*nombre).clone()));
+        simbolos.iter().map(|(nombre, dir)| format!("{:04X} {}", dir, nombre)).collect::<Vec<String>>().join("\n")
+    }
+
+    pub fn imagen_carga(&self, banco: u8) -> ImagenCarga {
+        ImagenCarga {
+            bloques: self.bloques.iter().map(|(d, b)| (((banco as u32) << 16) | *d as u32, b.clone())).collect(),
+            inicio: self.entrada.map(|e| ((banco as u32) << 16) | e as u32),
+        }
+    }
+}
+
+impl BancosMemoria {
+    pub fn carga_enlazado(&mut self, enlazado: &Enlazado, banco: u8) -> Result<(), String> {
+        self.carga_imagen(&enlazado.imagen_carga(banco))
+    }
+}
+
+//*****************************************************************************
+// Enlaza los .REL dados (programa en 0x0100) y escribe <primero>.hex y <primero>.sym junto al
+// primer módulo (MAIN.REL -> MAIN.hex y MAIN.sym)
+pub fn enlaza_cmd(argumentos: &[String]) {
+    if argumentos.is_empty() {
+        println!("Uso: enlaza <modulo.rel> [modulo.rel ...]");
+        return;
+    }
+    let mut modulos = Vec::new();
+    for ruta in argumentos {
+        match std::fs::read(ruta).map_err(|e| format!("No se puede leer '{}': {}", ruta, e)).and_then(|d| analiza_biblioteca(&d)) {
+            Ok(leidos) => modulos.extend(leidos),
+            Err(error) => { println!("Error: {}", error); return; }
+        }
+    }
+    let enlazado = match enlaza(&modulos, &OpcionesEnlace::new()) {
+        Ok(enlazado) => enlazado,
+        Err(error) => { println!("Error: {}", error); return; }
+    };
+    println!("{}", enlazado.mapa());
+    let imagen = enlazado.imagen_carga(0);
+    let base = std::path::Path::new(&argumentos[0]).with_extension("");
+    let hex = genera_intel_hex(&imagen.bloques, imagen.inicio, 16);
+    let resultado = std::fs::write(base.with_extension("hex"), hex)
+        .and_then(|_| std::fs::write(base.with_extension("sym"), enlazado.tabla_simbolos() + "\n"));
+    if let Err(error) = resultado { println!("Error: {}", error); }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Genera el flujo de bits de un módulo como lo haría M80
+    struct EscritorBits {
+        bytes: Vec<u8>,
+        bit: usize,
+    }
+
+    impl EscritorBits {
+        fn new() -> EscritorBits { EscritorBits { bytes: Vec::new(), bit: 0 } }
+
+        fn bits(&mut self, valor: u32, n: usize) {
+            for i in (0..n).rev() {
+                if self.bit.is_multiple_of(8) { self.bytes.push(0); }
+                if (valor >> i) & 1 == 1 { *self.bytes.last_mut().unwrap() |= 0x80 >> (self.bit % 8); }
+                self.bit += 1;
+            }
+        }
+        fn palabra(&mut self, valor: u16) { self.bits((valor & 0xFF) as u32, 8); self.bits((valor >> 8) as u32, 8); }
+        fn abs(&mut self, bytes: &[u8]) { for b in bytes { self.bits(0, 1); self.bits(*b as u32, 8); } }
+        fn rel(&mut self, tipo: u32, valor: u16) { self.bits(1, 1); self.bits(tipo, 2); self.palabra(valor); }
+        fn especial(&mut self, control: u32, a: Option<(u32, u16)>, b: Option<&str>) {
+            self.bits(0b100, 3);
+            self.bits(control, 4);
+            if let Some((tipo, valor)) = a { self.bits(tipo, 2); self.palabra(valor); }
+            if let Some(nombre) = b {
+                self.bits(nombre.len() as u32, 3);
+                for c in nombre.bytes() { self.bits(c as u32, 8); }
+            }
+        }
+        fn fin(&mut self, inicio: Option<(u32, u16)>) {
+            self.especial(14, Some(inicio.unwrap_or((0, 0))), None);
+            self.bit = self.bit.div_ceil(8) * 8;
+        }
+    }
+
+    // MAIN: CALL SUB (externo) dos veces, LXI H,VAR (datos) y JMP a sí mismo; VAR en datos
+    // SUBS: SUB: INX H / RET; público también TABLA+offset desde MAIN
+    fn archivo_rel() -> Vec<u8> {
+        let mut w = EscritorBits::new();
+        w.especial(2, None, Some("MAIN"));
+        w.especial(7, Some((1, 0x0000)), Some("START"));
+        w.especial(10, Some((0, 0x0002)), None);                    // 2 bytes de datos
+        w.especial(13, Some((0, 0x000C)), None);                    // 12 bytes de programa
+        w.abs(&[0xCD]); w.abs(&[0x00, 0x00]);                       // 0000 CALL SUB (fin de cadena)
+        w.abs(&[0xCD]); w.rel(1, 0x0001);                           // 0003 CALL SUB (apunta a 0001)
+        w.abs(&[0x21]); w.rel(2, 0x0000);                           // 0006 LXI H,VAR
+        w.abs(&[0xC3]); w.rel(1, 0x0009);                           // 0009 JMP $
+        w.especial(11, Some((2, 0x0000)), None);
+        w.abs(&[0x34, 0x12]);                                       // VAR: DW 1234H
+        w.especial(6, Some((1, 0x0004)), Some("SUB"));              // Cadena: 0004 -> 0001 -> fin
+        w.fin(Some((1, 0x0000)));
+        w.especial(2, None, Some("SUBS"));
+        w.especial(7, Some((1, 0x0000)), Some("SUB"));
+        w.especial(13, Some((0, 0x0002)), None);
+        w.abs(&[0x23, 0xC9]);                                       // SUB: INX H / RET
+        w.fin(None);
+        w.especial(15, None, None);
+        w.bytes
+    }
+
+    #[test]
+    fn test_enlace_de_dos_modulos() {
+        let modulos = analiza_biblioteca(&archivo_rel()).unwrap();
+        assert_eq!(modulos.len(), 2);
+        assert_eq!(modulos[0].nombre, "MAIN");
+        assert_eq!(modulos[0].programa.len(), 12);
+        let mut opciones = OpcionesEnlace::new();
+        opciones.base_datos = Some(0x8000);
+        let enlazado = enlaza(&modulos, &opciones).unwrap();
+        assert_eq!(enlazado.simbolos["SUB"], 0x010C);
+        assert_eq!(enlazado.entrada, Some(0x0100));
+        assert_eq!(enlazado.bloques[0], (0x0100, vec![0xCD, 0x0C, 0x01, 0xCD, 0x0C, 0x01, 0x21, 0x00, 0x80, 0xC3, 0x09, 0x01, 0x23, 0xC9]));
+        assert_eq!(enlazado.bloques[1], (0x8000, vec![0x34, 0x12]));
+
+        let mut memoria = BancosMemoria::new();
+        memoria.segmento_memoria[0].resize(0x10000, 0);
+        memoria.carga_enlazado(&enlazado, 0).unwrap();
+        assert_eq!(memoria.leer_memoria(0x0104), 0x0C);
+        let mapa = enlazado.mapa();
+        assert!(mapa.contains("MAIN      0100-010B (  12) 8000-8001 (   2)"));
+        assert!(mapa.ends_with("0100 START\n010C SUB"));
+
+        // Datos detrás del programa si no se da base
+        let enlazado = enlaza(&modulos, &OpcionesEnlace::new()).unwrap();
+        assert_eq!(enlazado.modulos[0].datos, (0x010E, 2));
+    }
+
+    #[test]
+    fn test_errores_de_enlace() {
+        let modulos = analiza_biblioteca(&archivo_rel()).unwrap();
+        assert_eq!(enlaza(&modulos[..1], &OpcionesEnlace::new()).err().unwrap(), "Símbolos externos sin definir: SUB");
+        let duplicado = vec![modulos[1].clone(), modulos[1].clone()];
+        assert_eq!(enlaza(&duplicado, &OpcionesEnlace::new()).err().unwrap(), "Símbolo 'SUB' definido en 'SUBS' y en 'SUBS'");
+        let mut opciones = OpcionesEnlace { base_datos: Some(0x010D), ..Default::default() };
+        assert_eq!(enlaza(&modulos, &opciones).err().unwrap(), "Los datos (0x010D-0x010E) se solapan con el programa (0x0100-0x010D)");
+        opciones.base_datos = Some(0x00FE);
+        assert!(enlaza(&modulos, &opciones).is_ok());
+        // El ASEG de un módulo no puede quedar bajo el programa de uno posterior
+        let modulo = |nombre: &str, byte: u8| ModuloRel {
+            nombre: nombre.to_string(), programa: vec![byte; 4], datos: Vec::new(), absoluto: BTreeMap::new(),
+            reubicaciones: Vec::new(), publicos: Vec::new(), cadenas: Vec::new(), desplazamientos: Vec::new(), inicio: None,
+        };
+        let mut a = modulo("A", 0xAA);
+        a.absoluto.insert(0x0105, 0x77);
+        let b = modulo("B", 0xBB);
+        assert_eq!(enlaza(&[a.clone(), b.clone()], &OpcionesEnlace::new()).err().unwrap(), "'A': el código absoluto en 0x0105 pisa otro módulo");
+        a.absoluto = BTreeMap::from([(0x0108, 0x77)]);
+        let enlazado = enlaza(&[a, b], &OpcionesEnlace::new()).unwrap();
+        assert_eq!(enlazado.bloques, vec![(0x0100, vec![0xAA, 0xAA, 0xAA, 0xAA, 0xBB, 0xBB, 0xBB, 0xBB, 0x77])]);
+        assert!(analiza_rel(&archivo_rel()[..5]).err().unwrap().starts_with("Archivo REL truncado"));
+        let mut w = EscritorBits::new();
+        w.especial(1, None, Some("/COM/"));
+        assert!(analiza_rel(&w.bytes).err().unwrap().contains("COMMON"));
+    }
+}
+
+//*****************************************************************************
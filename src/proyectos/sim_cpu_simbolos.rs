@@ -0,0 +1,244 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Tabla de símbolos - Simulación CPU
+    Descripción:    Nombres de las direcciones del programa cargados de archivos .SYM / .MAP
+                    (M80/L80, ZMAC, z88dk, el ensamblador y el enlazador propios) o creados en
+                    código. Las vistas muestran 'etiqueta+desplazamiento' y el depurador acepta
+                    etiquetas en cualquier expresión donde acepta direcciones.
+    Referencias:
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+use super::sim_cpu_desensamblador::OpcionesDesensamblado;
+use std::collections::BTreeMap;
+
+//***************************************************************************** Notas
+/* Uso
+    cpu.cargar_simbolos("PROG.SYM")?;                   // o .MAP
+    // o en código, por ejemplo con el resultado del ensamblador o del enlazador:
+    let mut tabla = TablaSimbolos::new();
+    tabla.agrega_varios(programa.simbolos.iter().map(|(n, d)| (n.clone(), *d)));
+    cpu.simbolos = Some(tabla);
+
+    cpu.formatea_direccion(0x0106)      // "0106 <BUCLE+2>" ("0106" sin símbolo cercano)
+    monitor: 'b BUCLE+2', 'd MENSAJE 32', 'u SUB', 'b SUB if A == [CONTADOR]'
+
+Formatos que se leen (línea a línea; ';' empieza un comentario):
+    0100 START  010C SUB            pares dirección-nombre (.SYM de M80/L80 y del ensamblador)
+    START 0100  SUB 010C            pares nombre-dirección (mapas de L80 /M y similares)
+    START = $0100                   asignaciones de los .MAP (también 'EQU' y 'NOMBRE: EQU')
+Las direcciones pueden ser 0100, 0100H, $0100 o 0x0100. Si los dos elementos del par parecen una
+dirección (BEEF 0100) se toma el orden del .SYM. Las líneas que no encajan (cabeceras, resúmenes)
+se ignoran; un archivo sin ningún símbolo es un error.
+
+'nombre(dir)' busca la etiqueta anterior más cercana dentro de 'alcance' bytes; si una dirección
+tiene varios nombres se muestra el primero que se cargó. Las búsquedas por nombre no distinguen
+mayúsculas y minúsculas si no hay coincidencia exacta.
+*/
+
+pub struct TablaSimbolos {
+    pub nombres: BTreeMap<String, u16>,
+    direcciones: BTreeMap<u16, String>,         // Primer nombre de cada dirección
+    pub alcance: u16,                           // Desplazamiento máximo de 'etiqueta+n'
+}
+
+impl TablaSimbolos {
+    pub fn new() -> TablaSimbolos {
+        TablaSimbolos { nombres: BTreeMap::new(), direcciones: BTreeMap::new(), alcance: 0x1000 }
+    }
+
+    pub fn agrega(&mut self, nombre: &str, direccion: u16) {
+        // Un nombre redefinido deja su dirección anterior, que pasa a otro nombre si lo tiene
+        if let Some(anterior) = self.nombres.insert(nombre.to_string(), direccion) {
+            if anterior != direccion && self.direcciones.get(&anterior).is_some_and(|n| n == nombre) {
+                self.direcciones.remove(&anterior);
+                if let Some((otro, _)) = self.nombres.iter().find(|(_, d)| **d == anterior) {
+                    self.direcciones.insert(anterior, otro.clone());
+                }
+            }
+        }
+        self.direcciones.entry(direccion).or_insert(nombre.to_string());
+    }
+
+    pub fn agrega_varios<I: IntoIterator<Item = (String, u16)>>(&mut self, simbolos: I) {
+        for (nombre, direccion) in simbolos { self.agrega(&nombre, direccion); }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nombres.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nombres.is_empty()
+    }
+
+    //************************************* Búsquedas
+    pub fn busca(&self, nombre: &str) -> Option<u16> {
+        self.nombres.get(nombre).copied()
+            .or_else(|| self.nombres.iter().find(|(n, _)| n.eq_ignore_ascii_case(nombre)).map(|(_, d)| *d))
+    }
+
+    // ETIQUETA o ETIQUETA+n con la etiqueta anterior más cercana
+    pub fn nombre(&self, direccion: u16) -> Option<String> {
+        let (inicio, nombre) = self.direcciones.range(..=direccion).next_back()?;
+        match direccion - inicio {
+            0 => Some(nombre.clone()),
+            n if n <= self.alcance => Some(format!("{}+{}", nombre, n)),
+            _ => None,
+        }
+    }
+
+    // Solo la etiqueta exacta (operandos del desensamblado)
+    pub fn etiqueta(&self, direccion: u16) -> Option<&String> {
+        self.direcciones.get(&direccion)
+    }
+
+    // Formato .SYM ordenado por dirección, igual que el del ensamblador y el enlazador
+    pub fn tabla(&self) -> String {
+        let mut simbolos: Vec<(&String, &u16)> = self.nombres.iter().collect();
+        simbolos.sort_by_key(|(nombre, dir)| (**dir, (*nombre).clone()));
+        simbolos.iter().map(|(nombre, dir)| format!("{:04X} {}", dir, nombre)).collect::<Vec<String>>().join("\n")
+    }
+
+    //************************************* Lectura
+    pub fn analiza(texto: &str) -> Result<TablaSimbolos, String> {
+        let mut tabla = TablaSimbolos::new();
+        for linea in texto.lines() {
+            let linea = linea.split(';').next().unwrap_or("");
+            let tokens: Vec<&str> = linea.split_whitespace().collect();
+            if let Some((nombre, direccion)) = asignacion(&tokens) {
+                tabla.agrega(nombre, direccion);
+                continue;
+            }
+            if tokens.is_empty() || !tokens.len().is_multiple_of(2) { continue; }
+            let pares: Option<Vec<(&str, u16)>> = tokens.chunks(2).map(|par| {
+                match (direccion(par[0]), direccion(par[1])) {
+                    (Some(dir), _) if es_nombre(par[1]) => Some((par[1], dir)),
+                    (None, Some(dir)) if es_nombre(par[0]) => Some((par[0], dir)),
+                    _ => None,
+                }
+            }).collect();
+            for (nombre, direccion) in pares.unwrap_or_default() { tabla.agrega(nombre, direccion); }
+        }
+        if tabla.is_empty() { return Err(String::from("No se ha encontrado ningún símbolo")); }
+        Ok(tabla)
+    }
+
+    pub fn carga_archivo(ruta: &str) -> Result<TablaSimbolos, String> {
+        let texto = std::fs::read_to_string(ruta).map_err(|e| format!("No se puede leer '{}': {}", ruta, e))?;
+        TablaSimbolos::analiza(&texto).map_err(|e| format!("{}: {}", ruta, e))
+    }
+}
+
+impl Default for TablaSimbolos {
+    fn default() -> Self { TablaSimbolos::new() }
+}
+
+// NOMBRE = dir, NOMBRE EQU dir, NOMBRE: EQU dir
+fn asignacion<'a>(tokens: &[&'a str]) -> Option<(&'a str, u16)> {
+    match tokens {
+        [nombre, igual, valor] if *igual == "=" || igual.eq_ignore_ascii_case("EQU") => {
+            let nombre = nombre.strip_suffix(':').unwrap_or(nombre);
+            if es_nombre(nombre) { direccion(valor).map(|d| (nombre, d)) } else { None }
+        }
+        _ => None,
+    }
+}
+
+fn es_nombre(texto: &str) -> bool {
+    let mut caracteres = texto.chars();
+    matches!(caracteres.next(), Some(c) if c.is_ascii_alphabetic() || "_.?@".contains(c))
+        && caracteres.all(|c| c.is_ascii_alphanumeric() || "_.?@$".contains(c))
+}
+
+fn direccion(texto: &str) -> Option<u16> {
+    let (digitos, estricto) = if let Some(hex) = texto.strip_prefix('$').or(texto.strip_prefix("0x")).or(texto.strip_prefix("0X")) {
+        (hex, false)
+    } else if let Some(hex) = texto.strip_suffix('H').or(texto.strip_suffix('h')) {
+        (hex, false)
+    } else {
+        (texto, true)                   // Sin marca: solo 4 dígitos, como en los .SYM
+    };
+    if digitos.is_empty() || digitos.len() > 5 || (estricto && digitos.len() != 4) { return None; }
+    if !digitos.chars().all(|c| c.is_ascii_hexdigit()) { return None; }
+    u32::from_str_radix(digitos, 16).ok().and_then(|d| u16::try_from(d).ok())
+}
+
+//*****************************************************************************
+impl CPU {
+    pub fn cargar_simbolos(&mut self, ruta: &str) -> Result<usize, String> {
+        let tabla = TablaSimbolos::carga_archivo(ruta)?;
+        let cantidad = tabla.len();
+        self.simbolos = Some(tabla);
+        Ok(cantidad)
+    }
+
+    pub fn nombre_direccion(&self, direccion: u16) -> Option<String> {
+        self.simbolos.as_ref().and_then(|s| s.nombre(direccion))
+    }
+
+    // "0106 <BUCLE+2>" o "0106" si no hay símbolo
+    pub fn formatea_direccion(&self, direccion: u16) -> String {
+        match self.nombre_direccion(direccion) {
+            Some(nombre) => format!("{:04X} <{}>", direccion, nombre),
+            None => format!("{:04X}", direccion),
+        }
+    }
+
+    // Opciones del desensamblador con las etiquetas exactas de la tabla
+    pub fn opciones_desensamblado(&self) -> OpcionesDesensamblado {
+        let mut opciones = OpcionesDesensamblado::new();
+        if let Some(simbolos) = self.simbolos.as_ref() {
+            opciones.agrega_simbolos(simbolos.direcciones.iter().map(|(d, n)| (n.clone(), *d)));
+        }
+        opciones
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formatos_de_archivo() {
+        let tabla = TablaSimbolos::analiza("0100 START\t0108 BUCLE\n010C SUB\n").unwrap();
+        assert_eq!(tabla.busca("BUCLE"), Some(0x0108));
+        let mapa = "Módulo    Programa         Datos\nMAIN      0100-010B (  12) 8000-8001 (   2)\nInicio: 0100\n\n\
+                    START 0100H  SUB $010C\nVAR = 0x8000 ; variable\nbuffer: EQU 9000h\n";
+        let tabla = TablaSimbolos::analiza(mapa).unwrap();
+        assert_eq!(tabla.len(), 4);
+        assert_eq!(tabla.busca("var"), Some(0x8000));
+        assert_eq!(tabla.busca("BUFFER"), Some(0x9000));
+        assert_eq!(tabla.tabla(), "0100 START\n010C SUB\n8000 VAR\n9000 buffer");
+        assert!(TablaSimbolos::analiza("Sin símbolos\n").is_err());
+    }
+
+    #[test]
+    fn test_etiqueta_mas_desplazamiento() {
+        let mut cpu = CPU::new();
+        assert_eq!(cpu.formatea_direccion(0x0010), "0010");
+        let mut tabla = TablaSimbolos::new();
+        tabla.agrega_varios(vec![(String::from("INICIO"), 0x0000), (String::from("BUCLE"), 0x0008), (String::from("OTRO"), 0x0008)]);
+        tabla.alcance = 0x20;
+        cpu.simbolos = Some(tabla);
+        assert_eq!(cpu.formatea_direccion(0x0008), "0008 <BUCLE>");
+        assert_eq!(cpu.formatea_direccion(0x000A), "000A <BUCLE+2>");
+        assert_eq!(cpu.nombre_direccion(0x0100), None);
+        assert_eq!(cpu.opciones_desensamblado().simbolos.get(&0x0008), Some(&String::from("BUCLE")));
+        // Al redefinir un nombre su dirección anterior ya no se muestra con él
+        let tabla = cpu.simbolos.as_mut().unwrap();
+        tabla.agrega("BUCLE", 0x0100);
+        tabla.agrega("INICIO", 0x0040);
+        assert_eq!(tabla.etiqueta(0x0008), Some(&String::from("OTRO")));
+        assert_eq!(tabla.etiqueta(0x0000), None);
+        assert_eq!(tabla.nombre(0x0042), Some(String::from("INICIO+2")));
+        assert_eq!(tabla.nombre(0x0102), Some(String::from("BUCLE+2")));
+    }
+}
+
+//*****************************************************************************
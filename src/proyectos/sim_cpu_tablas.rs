@@ -0,0 +1,99 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Tablas del Intel 8080 - Simulación CPU
+    Descripción:    Ciclos de reloj, longitud y mnemónico de cada opcode. No dependen de ningún
+                    otro módulo del simulador para poder incluirse también en la macro asm8080!.
+    Referencias:
+
+***************************************************************************************/
+#![allow(dead_code)]
+
+//***************************************************************************** Ciclos de reloj
+/* Estados (ciclos de reloj) de cada opcode del Intel 8080
+Para los CALL y RET condicionales se indica el valor cuando la condición no se cumple
+(11 y 5); si se cumple se suman 6 ciclos más.
+*/
+pub const CICLOS_8080: [u8; 256] = [
+//  x0  x1  x2  x3  x4  x5  x6  x7  x8  x9  xA  xB  xC  xD  xE  xF
+     4, 10,  7,  5,  5,  5,  7,  4,  4, 10,  7,  5,  5,  5,  7,  4,   // 0x
+     4, 10,  7,  5,  5,  5,  7,  4,  4, 10,  7,  5,  5,  5,  7,  4,   // 1x
+     4, 10, 16,  5,  5,  5,  7,  4,  4, 10, 16,  5,  5,  5,  7,  4,   // 2x
+     4, 10, 13,  5, 10, 10, 10,  4,  4, 10, 13,  5,  5,  5,  7,  4,   // 3x
+     5,  5,  5,  5,  5,  5,  7,  5,  5,  5,  5,  5,  5,  5,  7,  5,   // 4x
+     5,  5,  5,  5,  5,  5,  7,  5,  5,  5,  5,  5,  5,  5,  7,  5,   // 5x
+     5,  5,  5,  5,  5,  5,  7,  5,  5,  5,  5,  5,  5,  5,  7,  5,   // 6x
+     7,  7,  7,  7,  7,  7,  7,  7,  5,  5,  5,  5,  5,  5,  7,  5,   // 7x
+     4,  4,  4,  4,  4,  4,  7,  4,  4,  4,  4,  4,  4,  4,  7,  4,   // 8x
+     4,  4,  4,  4,  4,  4,  7,  4,  4,  4,  4,  4,  4,  4,  7,  4,   // 9x
+     4,  4,  4,  4,  4,  4,  7,  4,  4,  4,  4,  4,  4,  4,  7,  4,   // Ax
+     4,  4,  4,  4,  4,  4,  7,  4,  4,  4,  4,  4,  4,  4,  7,  4,   // Bx
+     5, 10, 10, 10, 11, 11,  7, 11,  5, 10, 10, 10, 11, 17,  7, 11,   // Cx
+     5, 10, 10, 10, 11, 11,  7, 11,  5, 10, 10, 10, 11, 17,  7, 11,   // Dx
+     5, 10, 10, 18, 11, 11,  7, 11,  5,  5, 10,  4, 11, 17,  7, 11,   // Ex
+     5, 10, 10,  4, 11, 11,  7, 11,  5,  5, 10,  4, 11, 17,  7, 11,   // Fx
+];
+
+//***************************************************************************** Longitud de las instrucciones
+// Bytes que ocupa cada opcode del Intel 8080 (opcode + operandos)
+pub const LONGITUD_8080: [u8; 256] = [
+//  x0 x1 x2 x3 x4 x5 x6 x7 x8 x9 xA xB xC xD xE xF
+    1, 3, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,   // 0x
+    1, 3, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,   // 1x
+    1, 3, 3, 1, 1, 1, 2, 1, 1, 1, 3, 1, 1, 1, 2, 1,   // 2x
+    1, 3, 3, 1, 1, 1, 2, 1, 1, 1, 3, 1, 1, 1, 2, 1,   // 3x
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,   // 4x
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,   // 5x
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,   // 6x
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,   // 7x
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,   // 8x
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,   // 9x
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,   // Ax
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,   // Bx
+    1, 1, 3, 3, 3, 1, 2, 1, 1, 1, 3, 3, 3, 3, 2, 1,   // Cx
+    1, 1, 3, 2, 3, 1, 2, 1, 1, 1, 3, 2, 3, 3, 2, 1,   // Dx
+    1, 1, 3, 1, 3, 1, 2, 1, 1, 1, 3, 1, 3, 3, 2, 1,   // Ex
+    1, 1, 3, 1, 3, 1, 2, 1, 1, 1, 3, 1, 3, 3, 2, 1,   // Fx
+];
+
+//***************************************************************************** Mnemónicos
+/* Mnemónicos Intel de cada opcode del 8080
+Los operandos se indican con d8 (dato de 8 bits), d16 (dato de 16 bits) y addr (dirección). Los
+opcodes no documentados llevan '*' delante del mnemónico de la instrucción a la que equivalen.
+*/
+pub const MNEMONICOS_8080: [&str; 256] = [
+    "NOP", "LXI B,d16", "STAX B", "INX B", "INR B", "DCR B", "MVI B,d8", "RLC",                             // 00-07
+    "*NOP", "DAD B", "LDAX B", "DCX B", "INR C", "DCR C", "MVI C,d8", "RRC",                                // 08-0F
+    "*NOP", "LXI D,d16", "STAX D", "INX D", "INR D", "DCR D", "MVI D,d8", "RAL",                            // 10-17
+    "*NOP", "DAD D", "LDAX D", "DCX D", "INR E", "DCR E", "MVI E,d8", "RAR",                                // 18-1F
+    "*NOP", "LXI H,d16", "SHLD addr", "INX H", "INR H", "DCR H", "MVI H,d8", "DAA",                         // 20-27
+    "*NOP", "DAD H", "LHLD addr", "DCX H", "INR L", "DCR L", "MVI L,d8", "CMA",                             // 28-2F
+    "*NOP", "LXI SP,d16", "STA addr", "INX SP", "INR M", "DCR M", "MVI M,d8", "STC",                        // 30-37
+    "*NOP", "DAD SP", "LDA addr", "DCX SP", "INR A", "DCR A", "MVI A,d8", "CMC",                            // 38-3F
+    "MOV B,B", "MOV B,C", "MOV B,D", "MOV B,E", "MOV B,H", "MOV B,L", "MOV B,M", "MOV B,A",                 // 40-47
+    "MOV C,B", "MOV C,C", "MOV C,D", "MOV C,E", "MOV C,H", "MOV C,L", "MOV C,M", "MOV C,A",                 // 48-4F
+    "MOV D,B", "MOV D,C", "MOV D,D", "MOV D,E", "MOV D,H", "MOV D,L", "MOV D,M", "MOV D,A",                 // 50-57
+    "MOV E,B", "MOV E,C", "MOV E,D", "MOV E,E", "MOV E,H", "MOV E,L", "MOV E,M", "MOV E,A",                 // 58-5F
+    "MOV H,B", "MOV H,C", "MOV H,D", "MOV H,E", "MOV H,H", "MOV H,L", "MOV H,M", "MOV H,A",                 // 60-67
+    "MOV L,B", "MOV L,C", "MOV L,D", "MOV L,E", "MOV L,H", "MOV L,L", "MOV L,M", "MOV L,A",                 // 68-6F
+    "MOV M,B", "MOV M,C", "MOV M,D", "MOV M,E", "MOV M,H", "MOV M,L", "HLT", "MOV M,A",                     // 70-77
+    "MOV A,B", "MOV A,C", "MOV A,D", "MOV A,E", "MOV A,H", "MOV A,L", "MOV A,M", "MOV A,A",                 // 78-7F
+    "ADD B", "ADD C", "ADD D", "ADD E", "ADD H", "ADD L", "ADD M", "ADD A",                                 // 80-87
+    "ADC B", "ADC C", "ADC D", "ADC E", "ADC H", "ADC L", "ADC M", "ADC A",                                 // 88-8F
+    "SUB B", "SUB C", "SUB D", "SUB E", "SUB H", "SUB L", "SUB M", "SUB A",                                 // 90-97
+    "SBB B", "SBB C", "SBB D", "SBB E", "SBB H", "SBB L", "SBB M", "SBB A",                                 // 98-9F
+    "ANA B", "ANA C", "ANA D", "ANA E", "ANA H", "ANA L", "ANA M", "ANA A",                                 // A0-A7
+    "XRA B", "XRA C", "XRA D", "XRA E", "XRA H", "XRA L", "XRA M", "XRA A",                                 // A8-AF
+    "ORA B", "ORA C", "ORA D", "ORA E", "ORA H", "ORA L", "ORA M", "ORA A",                                 // B0-B7
+    "CMP B", "CMP C", "CMP D", "CMP E", "CMP H", "CMP L", "CMP M", "CMP A",                                 // B8-BF
+    "RNZ", "POP B", "JNZ addr", "JMP addr", "CNZ addr", "PUSH B", "ADI d8", "RST 0",                        // C0-C7
+    "RZ", "RET", "JZ addr", "*JMP addr", "CZ addr", "CALL addr", "ACI d8", "RST 1",                         // C8-CF
+    "RNC", "POP D", "JNC addr", "OUT d8", "CNC addr", "PUSH D", "SUI d8", "RST 2",                          // D0-D7
+    "RC", "*RET", "JC addr", "IN d8", "CC addr", "*CALL addr", "SBI d8", "RST 3",                           // D8-DF
+    "RPO", "POP H", "JPO addr", "XTHL", "CPO addr", "PUSH H", "ANI d8", "RST 4",                            // E0-E7
+    "RPE", "PCHL", "JPE addr", "XCHG", "CPE addr", "*CALL addr", "XRI d8", "RST 5",                         // E8-EF
+    "RP", "POP PSW", "JP addr", "DI", "CP addr", "PUSH PSW", "ORI d8", "RST 6",                             // F0-F7
+    "RM", "SPHL", "JM addr", "EI", "CM addr", "*CALL addr", "CPI d8", "RST 7",                              // F8-FF
+];
+
+//*****************************************************************************
@@ -0,0 +1,365 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Traza estructurada de ejecución - Simulación CPU
+    Descripción:    Un registro por instrucción con ciclos, PC, bytes del opcode,
+                    desensamblado, registros, SP y flags empaquetados, en texto de ancho
+                    fijo o CSV, escrito sobre cualquier destino que implemente Write.
+    Referencias:
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::{sim_cpu_opcode::LONGITUD_8080, sim_cpu_pruebas::Aux, sim_cpu_registros::CPU};
+use super::sim_cpu_desensamblador::desensambla_bytes;
+use std::io::Write;
+
+//***************************************************************************** Notas
+/* Formato de la traza
+Cada registro refleja el estado de la máquina ANTES de ejecutar la instrucción, igual que
+las trazas habituales de otros emuladores del 8080, de forma que se pueden comparar línea
+a línea.
+
+Texto de ancho fijo (una línea por instrucción):
+    CICLOS     PC   BYTES    DESENSAMBLADO     A  B  C  D  E  H  L  SP   F
+    0000000007 0002 06 0A    MVI B,0AH         04 00 00 00 00 00 00 0000 02
+
+CSV (con cabecera):
+    ciclos,pc,bytes,desensamblado,a,b,c,d,e,h,l,sp,flags,etiqueta
+    7,0002,06 0A,"MVI B,0AH",04,00,00,00,00,00,00,0000,02,
+
+El desensamblado es el de sim_cpu_desensamblador sobre los bytes de la instrucción, con las
+opciones de la CPU (cpu.opciones_desensamblado()). Con tabla de símbolos en la CPU los operandos
+de 16 bits con etiqueta exacta se escriben con su nombre ("CALL SUB") y la posición del PC
+("BUCLE+3") va en la columna 'etiqueta', la última: en el texto de ancho fijo se añade como
+" ; BUCLE+3" detrás de los flags, así que las columnas fijas no se mueven. lee_traza acepta
+también trazas sin esa columna. Un desensamblado de más de 17 caracteres ("CALL RUTINA_LARGA")
+se recorta en el texto de ancho fijo y termina en '~' ("CALL RUTINA_LARG~"); el CSV lo guarda
+completo.
+*/
+
+const ANCHO_DESENSAMBLADO: usize = 17;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormatoTraza {
+    TextoFijo,
+    Csv,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistroTraza {
+    pub ciclos: u64,
+    pub pc: u16,
+    pub bytes: Vec<u8>,
+    pub desensamblado: String,
+    pub registros: [u8; 7],     // A B C D E H L
+    pub sp: u16,
+    pub flags: u8,
+    pub etiqueta: String,       // ETIQUETA+n del PC (vacía sin tabla de símbolos)
+}
+
+impl RegistroTraza {
+    // Captura el estado actual de la CPU y los bytes de la instrucción en el PC
+    pub fn captura(cpu: &CPU) -> RegistroTraza {
+        let pc = cpu.contador_de_programa;
+        let opcode = cpu.memoria.leer_memoria(pc);
+        let bytes = (0..LONGITUD_8080[opcode as usize] as u16)
+            .map(|i| cpu.memoria.leer_memoria(pc.wrapping_add(i)))
+            .collect();
+        RegistroTraza {
+            ciclos: cpu.ciclos,
+            pc,
+            bytes,
+            desensamblado: String::new(),
+            registros: [cpu.reg_a, cpu.reg_b, cpu.reg_c, cpu.reg_d, cpu.reg_e, cpu.reg_h, cpu.reg_l],
+            sp: cpu.puntero_de_pila,
+            flags: cpu.flags.get_flags(),
+            etiqueta: cpu.nombre_direccion(pc).unwrap_or_default(),
+        }
+    }
+
+    fn bytes_hex(&self) -> String {
+        self.bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ")
+    }
+
+    // El desensamblado en su columna de 17 caracteres: si no cabe (operandos con etiquetas
+    // largas) se recorta y termina en '~' para no mover las columnas siguientes
+    fn desensamblado_fijo(&self) -> String {
+        if self.desensamblado.chars().count() <= ANCHO_DESENSAMBLADO { return self.desensamblado.clone(); }
+        let recortado: String = self.desensamblado.chars().take(ANCHO_DESENSAMBLADO - 1).collect();
+        format!("{}~", recortado)
+    }
+
+    pub fn a_texto(&self, formato: FormatoTraza) -> String {
+        let [a, b, c, d, e, h, l] = self.registros;
+        match formato {
+            FormatoTraza::TextoFijo => format!(
+                "{:010} {:04X} {:<8} {:<17} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:04X} {:02X}{}",
+                self.ciclos, self.pc, self.bytes_hex(), self.desensamblado_fijo(),
+                a, b, c, d, e, h, l, self.sp, self.flags,
+                if self.etiqueta.is_empty() { String::new() } else { format!(" ; {}", self.etiqueta) }),
+            FormatoTraza::Csv => format!(
+                "{},{:04X},{},\"{}\",{:02X},{:02X},{:02X},{:02X},{:02X},{:02X},{:02X},{:04X},{:02X},{}",
+                self.ciclos, self.pc, self.bytes_hex(), self.desensamblado.replace('"', "\"\""),
+                a, b, c, d, e, h, l, self.sp, self.flags, self.etiqueta),
+        }
+    }
+}
+
+//***************************************************************************** Lectura de trazas
+// Separa una línea CSV respetando los campos entre comillas
+fn separa_csv(linea: &str) -> Vec<String> {
+    let mut campos = Vec::new();
+    let mut actual = String::new();
+    let mut entre_comillas = false;
+    let mut caracteres = linea.chars().peekable();
+    while let Some(c) = caracteres.next() {
+        match c {
+            '"' if entre_comillas && caracteres.peek() == Some(&'"') => { actual.push('"'); caracteres.next(); }
+            '"' => entre_comillas = !entre_comillas,
+            ',' if !entre_comillas => campos.push(std::mem::take(&mut actual)),
+            _ => actual.push(c),
+        }
+    }
+    campos.push(actual);
+    campos
+}
+
+fn hex_u8(texto: &str) -> Result<u8, String> {
+    u8::from_str_radix(texto.trim(), 16).map_err(|_| format!("Valor hexadecimal no válido '{}'", texto))
+}
+
+fn hex_u16(texto: &str) -> Result<u16, String> {
+    u16::from_str_radix(texto.trim(), 16).map_err(|_| format!("Valor hexadecimal no válido '{}'", texto))
+}
+
+impl RegistroTraza {
+    pub fn desde_texto(linea: &str, formato: FormatoTraza) -> Result<RegistroTraza, String> {
+        let (ciclos, pc, bytes, desensamblado, resto, etiqueta): (String, String, Vec<u8>, String, Vec<String>, String) = match formato {
+            FormatoTraza::Csv => {
+                let campos = separa_csv(linea);
+                if campos.len() != 13 && campos.len() != 14 {
+                    return Err(format!("Se esperaban 13 o 14 campos y hay {}", campos.len()));
+                }
+                let bytes = campos[2].split_whitespace().map(hex_u8).collect::<Result<Vec<u8>, String>>()?;
+                let etiqueta = campos.get(13).cloned().unwrap_or_default();
+                (campos[0].clone(), campos[1].clone(), bytes, campos[3].clone(), campos[4..13].to_vec(), etiqueta)
+            }
+            FormatoTraza::TextoFijo => {
+                let (linea, etiqueta) = linea.split_once(" ; ").unwrap_or((linea, ""));
+                let tokens: Vec<&str> = linea.split_whitespace().collect();
+                if tokens.len() < 13 {
+                    return Err(format!("Línea de traza incompleta: '{}'", linea));
+                }
+                // Los 9 últimos campos son registros, SP y flags; la longitud del opcode indica
+                // cuántos tokens son bytes y el resto es el desensamblado
+                let opcode = hex_u8(tokens[2])?;
+                let longitud = LONGITUD_8080[opcode as usize] as usize;
+                let fin_bytes = 2 + longitud;
+                if fin_bytes > tokens.len() - 9 {
+                    return Err(format!("Línea de traza incompleta: '{}'", linea));
+                }
+                let bytes = tokens[2..fin_bytes].iter().map(|t| hex_u8(t)).collect::<Result<Vec<u8>, String>>()?;
+                let desensamblado = tokens[fin_bytes..tokens.len() - 9].join(" ");
+                let resto = tokens[tokens.len() - 9..].iter().map(|t| t.to_string()).collect();
+                (tokens[0].to_string(), tokens[1].to_string(), bytes, desensamblado, resto, etiqueta.trim().to_string())
+            }
+        };
+        Ok(RegistroTraza {
+            ciclos: ciclos.trim().parse().map_err(|_| format!("Ciclos no válidos '{}'", ciclos))?,
+            pc: hex_u16(&pc)?,
+            bytes,
+            desensamblado,
+            registros: [hex_u8(&resto[0])?, hex_u8(&resto[1])?, hex_u8(&resto[2])?, hex_u8(&resto[3])?,
+                        hex_u8(&resto[4])?, hex_u8(&resto[5])?, hex_u8(&resto[6])?],
+            sp: hex_u16(&resto[7])?,
+            flags: hex_u8(&resto[8])?,
+            etiqueta,
+        })
+    }
+}
+
+// Lee una traza completa. El formato se detecta por la cabecera o por la primera línea.
+pub fn lee_traza(texto: &str) -> Result<Vec<RegistroTraza>, String> {
+    let mut registros = Vec::new();
+    let mut formato: Option<FormatoTraza> = None;
+    for (num, linea) in texto.lines().enumerate() {
+        let linea = linea.trim_end();
+        if linea.trim().is_empty() { continue; }
+        if linea == EscritorTraza::<Vec<u8>>::cabecera(FormatoTraza::TextoFijo) {
+            formato = Some(FormatoTraza::TextoFijo);
+            continue;
+        }
+        if linea == EscritorTraza::<Vec<u8>>::cabecera(FormatoTraza::Csv) {
+            formato = Some(FormatoTraza::Csv);
+            continue;
+        }
+        let formato_linea = *formato.get_or_insert(
+            if linea.contains(',') && linea.split(',').count() >= 13 { FormatoTraza::Csv } else { FormatoTraza::TextoFijo });
+        registros.push(RegistroTraza::desde_texto(linea, formato_linea)
+            .map_err(|e| format!("Línea {}: {}", num + 1, e))?);
+    }
+    Ok(registros)
+}
+
+//***************************************************************************** Escritor
+pub struct EscritorTraza<W: Write> {
+    pub destino: W,
+    pub formato: FormatoTraza,
+    cabecera_escrita: bool,
+}
+
+impl<W: Write> EscritorTraza<W> {
+    pub fn new(destino: W, formato: FormatoTraza) -> EscritorTraza<W> {
+        EscritorTraza { destino, formato, cabecera_escrita: false }
+    }
+
+    pub fn cabecera(formato: FormatoTraza) -> &'static str {
+        match formato {
+            FormatoTraza::TextoFijo => "CICLOS     PC   BYTES    DESENSAMBLADO     A  B  C  D  E  H  L  SP   F",
+            FormatoTraza::Csv => "ciclos,pc,bytes,desensamblado,a,b,c,d,e,h,l,sp,flags,etiqueta",
+        }
+    }
+
+    pub fn escribe(&mut self, registro: &RegistroTraza) -> Result<(), String> {
+        if !self.cabecera_escrita {
+            writeln!(self.destino, "{}", EscritorTraza::<W>::cabecera(self.formato)).map_err(|e| e.to_string())?;
+            self.cabecera_escrita = true;
+        }
+        writeln!(self.destino, "{}", registro.a_texto(self.formato)).map_err(|e| e.to_string())
+    }
+}
+
+//*****************************************************************************
+impl CPU {
+    // Ejecuta una instrucción con step_no_win y escribe su registro de traza
+    // El desensamblado sale de los bytes capturados antes de ejecutar (no de 'mnemonic'), así que
+    // también es correcto para las instrucciones que se modifican a sí mismas
+    pub fn step_traza<W: Write>(&mut self, aux: &mut Aux, escritor: &mut EscritorTraza<W>) -> Result<(), String> {
+        let mut registro = RegistroTraza::captura(self);
+        registro.desensamblado = desensambla_bytes(registro.pc, &registro.bytes, &self.opciones_desensamblado()).texto();
+        self.step_no_win(aux);
+        escritor.escribe(&registro)
+    }
+
+    // Igual que run_no_win pero escribe la traza en lugar de la línea informativa
+    pub fn run_traza<W: Write>(&mut self, aux: &mut Aux, escritor: &mut EscritorTraza<W>, max_instrucciones: usize) -> Result<usize, String> {
+        let mut ejecutadas = 0;
+        let reanuda = self.reanuda_en_ruptura();
+        while ejecutadas < max_instrucciones {
+            if !(reanuda && ejecutadas == 0) && self.comprueba_puntos_ruptura().is_some() { break; }
+            self.step_traza(aux, escritor)?;
+            ejecutadas += 1;
+            if self.memoria.leer_memoria(self.contador_de_programa) == 0xFF { break; }
+            if !self.disparos_vigilancia().is_empty() { break; }
+        }
+        Ok(ejecutadas)
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proyectos::sim_cpu_simbolos::TablaSimbolos;
+    use asm8080::asm8080;
+
+    fn traza(formato: FormatoTraza) -> String {
+        let mut cpu = CPU::new();
+        let mut aux = Aux { imp_contador_programa: 0, imp_instruccion: 0, imp_mnemonico: String::new() };
+        cpu.cargar_programa(&asm8080! {
+            mvi a, 4
+            mvi b, 0ah
+            add b
+            sta 15h
+            db 0ffh
+        });
+        let mut escritor = EscritorTraza::new(Vec::new(), formato);
+        assert_eq!(cpu.run_traza(&mut aux, &mut escritor, 100), Ok(4));
+        String::from_utf8(escritor.destino).unwrap()
+    }
+
+    #[test]
+    fn test_traza_texto_fijo() {
+        let salida = traza(FormatoTraza::TextoFijo);
+        let lineas: Vec<&str> = salida.lines().collect();
+        assert_eq!(lineas.len(), 5);
+        assert_eq!(lineas[0], EscritorTraza::<Vec<u8>>::cabecera(FormatoTraza::TextoFijo));
+        assert_eq!(lineas[1], "0000000000 0000 3E 04    MVI A,04H         00 00 00 00 00 00 00 0000 02");
+        assert_eq!(lineas[3], "0000000014 0004 80       ADD B             04 0A 00 00 00 00 00 0000 02");
+        assert_eq!(lineas[4], "0000000018 0005 32 15 00 STA 0015H         0E 0A 00 00 00 00 00 0000 02");
+        // Todas las líneas de datos tienen el mismo ancho
+        assert!(lineas[1..].iter().all(|l| l.len() == lineas[1].len()));
+    }
+
+    #[test]
+    fn test_traza_csv() {
+        let salida = traza(FormatoTraza::Csv);
+        let lineas: Vec<&str> = salida.lines().collect();
+        assert_eq!(lineas[0], "ciclos,pc,bytes,desensamblado,a,b,c,d,e,h,l,sp,flags,etiqueta");
+        assert_eq!(lineas[2], "7,0002,06 0A,\"MVI B,0AH\",04,00,00,00,00,00,00,0000,02,");
+    }
+
+    #[test]
+    fn test_lectura_de_trazas() {
+        for formato in [FormatoTraza::TextoFijo, FormatoTraza::Csv] {
+            let registros = lee_traza(&traza(formato)).unwrap();
+            assert_eq!(registros.len(), 4);
+            assert_eq!(registros[1].pc, 0x0002);
+            assert_eq!(registros[1].desensamblado, "MVI B,0AH");
+            assert_eq!(registros[3].bytes, vec![0x32, 0x15, 0x00]);
+            assert_eq!(registros[3].ciclos, 18);
+            assert_eq!(registros[3].registros[0], 0x0E);
+            assert_eq!(registros[3].flags, 0x02);
+        }
+        assert!(lee_traza("0000000000 0000 3E").unwrap_err().starts_with("Línea 1"));
+    }
+
+    #[test]
+    fn test_traza_con_simbolos() {
+        let mut cpu = CPU::new();
+        let mut aux = Aux { imp_contador_programa: 0, imp_instruccion: 0, imp_mnemonico: String::new() };
+        cpu.cargar_programa(&asm8080! { mvi a, 4; sta 15h; db 0ffh });
+        let mut tabla = TablaSimbolos::new();
+        tabla.agrega("INICIO", 0x0000);
+        tabla.agrega("VAR", 0x0015);
+        cpu.simbolos = Some(tabla);
+        let mut escritor = EscritorTraza::new(Vec::new(), FormatoTraza::TextoFijo);
+        assert_eq!(cpu.run_traza(&mut aux, &mut escritor, 100), Ok(2));
+        let salida = String::from_utf8(escritor.destino).unwrap();
+        assert!(salida.contains(" 0000 3E 04    MVI A,04H         00 00 00 00 00 00 00 0000 02 ; INICIO\n"));
+        assert!(salida.contains(" 0002 32 15 00 STA VAR           04 00 00 00 00 00 00 0000 02 ; INICIO+2\n"));
+        let registros = lee_traza(&salida).unwrap();
+        assert_eq!((registros[1].desensamblado.as_str(), registros[1].etiqueta.as_str()), ("STA VAR", "INICIO+2"));
+        // En CSV la etiqueta es la última columna
+        let mut csv = EscritorTraza::new(Vec::new(), FormatoTraza::Csv);
+        csv.escribe(&registros[1]).unwrap();
+        let csv = String::from_utf8(csv.destino).unwrap();
+        assert!(csv.ends_with(",\"STA VAR\",04,00,00,00,00,00,00,0000,02,INICIO+2\n"));
+        assert_eq!(lee_traza(&csv).unwrap()[0], registros[1]);
+        // Un operando con una etiqueta larga se recorta sin mover las columnas siguientes
+        let mut largo = registros[1].clone();
+        largo.desensamblado = String::from("STA VARIABLE_MUY_LARGA");
+        let texto = largo.a_texto(FormatoTraza::TextoFijo);
+        assert!(texto.contains(" 0002 32 15 00 STA VARIABLE_MUY~ 04 00 "));
+        assert_eq!(texto.len(), registros[1].a_texto(FormatoTraza::TextoFijo).len());
+        assert!(largo.a_texto(FormatoTraza::Csv).contains("\"STA VARIABLE_MUY_LARGA\""));
+    }
+
+    #[test]
+    fn test_desensamblado_de_los_bytes() {
+        // 0x08 no está implementado y no cambia 'mnemonic': la traza no repite el MVI anterior
+        let mut cpu = CPU::new();
+        let mut aux = Aux { imp_contador_programa: 0, imp_instruccion: 0, imp_mnemonico: String::new() };
+        cpu.cargar_programa(&asm8080! { mvi a, 4; db 08h });      // 0x08: NOP no documentado
+        let mut escritor = EscritorTraza::new(Vec::new(), FormatoTraza::Csv);
+        cpu.step_traza(&mut aux, &mut escritor).unwrap();
+        cpu.step_traza(&mut aux, &mut escritor).unwrap();
+        let registros = lee_traza(&String::from_utf8(escritor.destino).unwrap()).unwrap();
+        assert_eq!(cpu.mnemonic, "MVI A,d8");
+        assert_eq!(registros[1].desensamblado, "*NOP");
+    }
+}
+
+//*****************************************************************************
@@ -0,0 +1,240 @@
+/***************************************************************************************
+    José Juan Ojeda Granados
+    Fecha:          19-10-2026
+    Titulo:         Puntos de vigilancia de memoria (watchpoints) - Simulación CPU
+    Descripción:    Vigila una dirección o un rango de direcciones en lectura, escritura o
+                    cualquier acceso, con una condición opcional sobre el valor. La ejecución
+                    se detiene después de la instrucción que provoca el acceso y se informa
+                    del PC, el valor anterior y el valor nuevo.
+    Referencias:
+
+***************************************************************************************/
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+use super::sim_cpu_registros::CPU;
+
+//***************************************************************************** Notas
+/* Uso
+    // ¿Quién escribe 0x3C en 0x0015? (programa de cpu_generica_0)
+    cpu.agrega_vigilancia(0x0015, 0x0015, ModoVigilancia::Escritura, Some(CondicionValor::Igual(0x3C)));
+    cpu.run_no_win(&mut aux);           // Se detiene tras el STA 0x0015 e imprime el disparo
+
+Las lecturas vigiladas son las de datos (LDA, LDAX...), no la búsqueda de instrucciones. Mientras
+hay puntos de vigilancia la memoria anota los accesos de cada instrucción en diario_accesos y,
+al terminar la instrucción, se comparan con los puntos activos.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModoVigilancia {
+    Lectura,
+    Escritura,
+    Acceso,                             // Lectura o escritura
+}
+
+// Condición sobre el valor leído o sobre el valor nuevo escrito
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CondicionValor {
+    Igual(u8),
+    Distinto(u8),
+    Mayor(u8),
+    Menor(u8),
+    Cambia,                             // Solo escrituras que modifican el valor anterior
+}
+
+impl CondicionValor {
+    pub fn cumple(&self, anterior: u8, valor: u8) -> bool {
+        match *self {
+            CondicionValor::Igual(v) => valor == v,
+            CondicionValor::Distinto(v) => valor != v,
+            CondicionValor::Mayor(v) => valor > v,
+            CondicionValor::Menor(v) => valor < v,
+            CondicionValor::Cambia => valor != anterior,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccesoMemoria {
+    pub banco: u8,
+    pub direccion: u16,
+    pub escritura: bool,
+    pub anterior: u8,                   // En las lecturas coincide con valor
+    pub valor: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct PuntoVigilancia {
+    pub id: usize,
+    pub inicio: u16,
+    pub fin: u16,                       // Incluido
+    pub modo: ModoVigilancia,
+    pub condicion: Option<CondicionValor>,
+    pub activo: bool,
+    pub disparos: u64,
+}
+
+impl PuntoVigilancia {
+    pub fn coincide(&self, acceso: &AccesoMemoria) -> bool {
+        let modo = match self.modo {
+            ModoVigilancia::Lectura => !acceso.escritura,
+            ModoVigilancia::Escritura => acceso.escritura,
+            ModoVigilancia::Acceso => true,
+        };
+        self.activo && modo
+            && self.inicio <= acceso.direccion && acceso.direccion <= self.fin
+            && self.condicion.is_none_or(|c| c.cumple(acceso.anterior, acceso.valor))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisparoVigilancia {
+    pub id: usize,
+    pub pc: u16,                        // Dirección de la instrucción que hizo el acceso
+    pub mnemonic: String,
+    pub acceso: AccesoMemoria,
+}
+
+impl DisparoVigilancia {
+    pub fn descripcion(&self) -> String {
+        let tipo = if self.acceso.escritura { "Escritura" } else { "Lectura" };
+        format!("Vigilancia #{}: {} en 0x{:04X} (banco {}) por PC=0x{:04X} ({}): anterior 0x{:02X}, nuevo 0x{:02X}"
+            , self.id, tipo, self.acceso.direccion, self.acceso.banco, self.pc, self.mnemonic
+            , self.acceso.anterior, self.acceso.valor)
+    }
+}
+
+pub struct Vigilancia {
+    pub puntos: Vec<PuntoVigilancia>,
+    pub disparos: Vec<DisparoVigilancia>,   // Disparos de la última instrucción ejecutada
+    siguiente_id: usize,
+}
+
+impl Vigilancia {
+    pub fn new() -> Vigilancia {
+        Vigilancia { puntos: Vec::new(), disparos: Vec::new(), siguiente_id: 1 }
+    }
+}
+
+impl Default for Vigilancia {
+    fn default() -> Self { Vigilancia::new() }
+}
+
+//*****************************************************************************
+impl CPU {
+    pub fn agrega_vigilancia(&mut self, inicio: u16, fin: u16, modo: ModoVigilancia, condicion: Option<CondicionValor>) -> Result<usize, String> {
+        if fin < inicio {
+            return Err(format!("Rango de vigilancia no válido: 0x{:04X}-0x{:04X}", inicio, fin));
+        }
+        let vigilancia = self.vigilancia.get_or_insert_with(Vigilancia::new);
+        let id = vigilancia.siguiente_id;
+        vigilancia.siguiente_id += 1;
+        vigilancia.puntos.push(PuntoVigilancia { id, inicio, fin, modo, condicion, activo: true, disparos: 0 });
+        Ok(id)
+    }
+
+    pub fn elimina_vigilancia(&mut self, id: usize) -> Result<(), String> {
+        let vigilancia = self.vigilancia.as_mut().ok_or(format!("No existe la vigilancia #{}", id))?;
+        let posicion = vigilancia.puntos.iter().position(|p| p.id == id).ok_or(format!("No existe la vigilancia #{}", id))?;
+        vigilancia.puntos.remove(posicion);
+        if vigilancia.puntos.is_empty() { self.vigilancia = None; }
+        Ok(())
+    }
+
+    pub fn activa_vigilancia(&mut self, id: usize, activo: bool) -> Result<(), String> {
+        let punto = self.vigilancia.as_mut().and_then(|v| v.puntos.iter_mut().find(|p| p.id == id))
+            .ok_or(format!("No existe la vigilancia #{}", id))?;
+        punto.activo = activo;
+        Ok(())
+    }
+
+    // Se llama antes de ejecutar una instrucción: activa el diario de accesos si hay vigilancias
+    pub fn inicia_vigilancia(&mut self) {
+        if let Some(vigilancia) = self.vigilancia.as_mut() {
+            vigilancia.disparos.clear();
+            self.memoria.diario_accesos = Some(Vec::new());
+        }
+    }
+
+    // Se llama después de ejecutar la instrucción que empezó en 'pc'
+    pub fn comprueba_vigilancia(&mut self, pc: u16) {
+        let accesos = match self.memoria.diario_accesos.take() {
+            Some(accesos) => accesos,
+            None => return,
+        };
+        let mnemonic = self.mnemonic.clone();
+        if let Some(vigilancia) = self.vigilancia.as_mut() {
+            for acceso in accesos.iter() {
+                for punto in vigilancia.puntos.iter_mut().filter(|p| p.coincide(acceso)) {
+                    punto.disparos += 1;
+                    vigilancia.disparos.push(DisparoVigilancia { id: punto.id, pc, mnemonic: mnemonic.clone(), acceso: *acceso });
+                }
+            }
+        }
+    }
+
+    // Disparos provocados por la última instrucción (vacío si no hay que detenerse)
+    pub fn disparos_vigilancia(&self) -> &[DisparoVigilancia] {
+        self.vigilancia.as_ref().map(|v| v.disparos.as_slice()).unwrap_or(&[])
+    }
+}
+
+//***************************************************************************** Test
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proyectos::sim_cpu_pruebas::Aux;
+    use asm8080::asm8080;
+
+    fn cpu_con_programa() -> (CPU, Aux) {
+        let mut cpu = CPU::new();
+        cpu.cargar_programa(&asm8080! {
+                    mvi a, 3bh
+                    inr a
+                    sta dato
+                    lda 3
+                    sta dato
+                    nop
+                    db 0ffh
+            dato:   db 0                // 0x000E
+        });
+        (cpu, Aux { imp_contador_programa: 0, imp_instruccion: 0, imp_mnemonico: String::new() })
+    }
+
+    #[test]
+    fn test_vigilancia_escritura_con_condicion() {
+        let (mut cpu, mut aux) = cpu_con_programa();
+        cpu.agrega_vigilancia(0x000E, 0x000E, ModoVigilancia::Escritura, Some(CondicionValor::Igual(0x3C))).unwrap();
+        cpu.run_no_win(&mut aux);
+        // Se detiene justo después del primer STA
+        assert_eq!(cpu.contador_de_programa, 0x0006);
+        let disparos = cpu.disparos_vigilancia();
+        assert_eq!(disparos.len(), 1);
+        assert_eq!(disparos[0].pc, 0x0003);
+        assert_eq!(disparos[0].acceso, AccesoMemoria { banco: 0, direccion: 0x000E, escritura: true, anterior: 0x00, valor: 0x3C });
+        assert!(disparos[0].descripcion().contains("PC=0x0003 (STA addr): anterior 0x00, nuevo 0x3C"));
+
+        // El segundo STA escribe 0x32: no cumple la condición y el programa termina
+        cpu.run_no_win(&mut aux);
+        assert_eq!(cpu.contador_de_programa, 0x000D);
+        assert!(cpu.disparos_vigilancia().is_empty());
+    }
+
+    #[test]
+    fn test_vigilancia_lectura_en_rango() {
+        let (mut cpu, mut aux) = cpu_con_programa();
+        let id = cpu.agrega_vigilancia(0x0000, 0x0005, ModoVigilancia::Lectura, None).unwrap();
+        for _ in 0..4 { cpu.step_no_win(&mut aux); }
+        // La búsqueda de instrucciones no cuenta, solo la lectura del LDA
+        let punto = &cpu.vigilancia.as_ref().unwrap().puntos[0];
+        assert_eq!(punto.disparos, 1);
+        assert_eq!(cpu.reg_a, 0x32);
+
+        assert!(cpu.agrega_vigilancia(0x0010, 0x0001, ModoVigilancia::Acceso, None).is_err());
+        assert!(cpu.elimina_vigilancia(id).is_ok());
+        assert!(cpu.vigilancia.is_none());
+        assert!(cpu.elimina_vigilancia(id).is_err());
+    }
+}
+
+//*****************************************************************************